@@ -30,11 +30,18 @@ func (p Position) IsValid() bool {
 	return p.Line > 0 && p.Column > 0
 }
 
+// Span is a range of byte offsets into the source of a file.
+type Span struct {
+	Start int `json:"start"` // Start is the offset of the first byte of the node
+	End   int `json:"end"`   // End is the offset just past the last byte of the node
+}
+
 // SourceLocation represents the location of a node in the AST
 type SourceLocation struct {
 	File   string   `json:"file,omitempty"`
 	Start  Position `json:"start"`            // Start is the location in the source the node starts
 	End    Position `json:"end"`              // End is the location in the source the node ends
+	Span   *Span    `json:"span,omitempty"`   // Span is the optional byte range of the node in the source
 	Source string   `json:"source,omitempty"` // Source is optional raw source
 }
 
@@ -25,6 +25,9 @@ func (l SourceLocation) FromBuf(buf *fbast.SourceLocation) *SourceLocation {
 	l.File = string(buf.File())
 	l.Start.FromBuf(buf.Start(nil))
 	l.End.FromBuf(buf.End(nil))
+	if span := buf.Span(nil); span != nil {
+		l.Span = &Span{Start: int(span.Start()), End: int(span.End())}
+	}
 	l.Source = string(buf.Source())
 	return &l
 }
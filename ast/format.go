@@ -14,7 +14,7 @@ import (
 // - In a function call (or object definition), if the arguments (or properties) are more than 3,
 //	they are split into multiple lines.
 func Format(n Node) string {
-	f := &formatter{new(strings.Builder), 0}
+	f := &formatter{Builder: new(strings.Builder)}
 	f.formatNode(n)
 	return f.get()
 }
@@ -22,6 +22,10 @@ func Format(n Node) string {
 type formatter struct {
 	*strings.Builder
 	indentation int
+	// src is the raw source of the file being formatted, if known,
+	// which starts at the byte offset base.
+	src  string
+	base int
 }
 
 func (f *formatter) get() string {
@@ -238,6 +242,11 @@ func (f *formatter) formatPackage(n *Package) {
 func (f *formatter) formatFile(n *File, includePkg bool) {
 	sep := '\n'
 
+	f.src, f.base = "", 0
+	if n.Loc != nil && n.Loc.Span != nil {
+		f.src, f.base = n.Loc.Source, n.Loc.Span.Start
+	}
+
 	if includePkg && n.Package != nil && n.Package.Name != nil && n.Package.Name.Name != "" {
 		f.writeIndent()
 		f.formatNode(n.Package)
@@ -612,12 +621,28 @@ func (f *formatter) formatStringLiteral(n *StringLiteral) {
 		f.writeString(n.Loc.Source)
 		return
 	}
+	if src, ok := f.source(n.Loc); ok {
+		f.writeString(src)
+		return
+	}
 	// Write out escaped string value
 	f.writeRune('"')
 	f.writeString(escapeStr(n.Value))
 	f.writeRune('"')
 }
 
+// source slices the raw source of a node out of the source of its file.
+func (f *formatter) source(l *SourceLocation) (string, bool) {
+	if f.src == "" || l == nil || l.Span == nil {
+		return "", false
+	}
+	start, end := l.Span.Start-f.base, l.Span.End-f.base
+	if start < 0 || end > len(f.src) || start >= end {
+		return "", false
+	}
+	return f.src[start:end], true
+}
+
 func escapeStr(s string) string {
 	if !strings.ContainsAny(s, `"\`) {
 		return s
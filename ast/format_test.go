@@ -468,6 +468,31 @@ func TestFormat_Raw(t *testing.T) {
 			},
 			script: "\"foo \\\\ \\\" \r\n\"",
 		},
+		{
+			name: "string literal sliced from file source",
+			node: &ast.File{
+				BaseNode: ast.BaseNode{
+					Loc: &ast.SourceLocation{
+						Span:   &ast.Span{Start: 0, End: 10},
+						Source: `x = "\x41"`,
+					},
+				},
+				Body: []ast.Statement{
+					&ast.VariableAssignment{
+						ID: &ast.Identifier{Name: "x"},
+						Init: &ast.StringLiteral{
+							BaseNode: ast.BaseNode{
+								Loc: &ast.SourceLocation{
+									Span: &ast.Span{Start: 4, End: 10},
+								},
+							},
+							Value: "A",
+						},
+					},
+				},
+			},
+			script: `x = "\x41"`,
+		},
 		{
 			name: "package multiple files",
 			node: &ast.Package{
@@ -68,8 +68,21 @@ func (rcv *SourceLocation) Source() []byte {
 	return nil
 }
 
+func (rcv *SourceLocation) Span(obj *Span) *Span {
+	o := flatbuffers.UOffsetT(rcv._tab.Offset(12))
+	if o != 0 {
+		x := o + rcv._tab.Pos
+		if obj == nil {
+			obj = new(Span)
+		}
+		obj.Init(rcv._tab.Bytes, x)
+		return obj
+	}
+	return nil
+}
+
 func SourceLocationStart(builder *flatbuffers.Builder) {
-	builder.StartObject(4)
+	builder.StartObject(5)
 }
 func SourceLocationAddFile(builder *flatbuffers.Builder, file flatbuffers.UOffsetT) {
 	builder.PrependUOffsetTSlot(0, flatbuffers.UOffsetT(file), 0)
@@ -83,6 +96,9 @@ func SourceLocationAddEnd(builder *flatbuffers.Builder, end flatbuffers.UOffsetT
 func SourceLocationAddSource(builder *flatbuffers.Builder, source flatbuffers.UOffsetT) {
 	builder.PrependUOffsetTSlot(3, flatbuffers.UOffsetT(source), 0)
 }
+func SourceLocationAddSpan(builder *flatbuffers.Builder, span flatbuffers.UOffsetT) {
+	builder.PrependStructSlot(4, flatbuffers.UOffsetT(span), 0)
+}
 func SourceLocationEnd(builder *flatbuffers.Builder) flatbuffers.UOffsetT {
 	return builder.EndObject()
 }
@@ -0,0 +1,41 @@
+// Code generated by the FlatBuffers compiler. DO NOT EDIT.
+
+package fbast
+
+import (
+	flatbuffers "github.com/google/flatbuffers/go"
+)
+
+type Span struct {
+	_tab flatbuffers.Struct
+}
+
+func (rcv *Span) Init(buf []byte, i flatbuffers.UOffsetT) {
+	rcv._tab.Bytes = buf
+	rcv._tab.Pos = i
+}
+
+func (rcv *Span) Table() flatbuffers.Table {
+	return rcv._tab.Table
+}
+
+func (rcv *Span) Start() uint32 {
+	return rcv._tab.GetUint32(rcv._tab.Pos + flatbuffers.UOffsetT(0))
+}
+func (rcv *Span) MutateStart(n uint32) bool {
+	return rcv._tab.MutateUint32(rcv._tab.Pos+flatbuffers.UOffsetT(0), n)
+}
+
+func (rcv *Span) End() uint32 {
+	return rcv._tab.GetUint32(rcv._tab.Pos + flatbuffers.UOffsetT(4))
+}
+func (rcv *Span) MutateEnd(n uint32) bool {
+	return rcv._tab.MutateUint32(rcv._tab.Pos+flatbuffers.UOffsetT(4), n)
+}
+
+func CreateSpan(builder *flatbuffers.Builder, start uint32, end uint32) flatbuffers.UOffsetT {
+	builder.Prep(4, 8)
+	builder.PrependUint32(end)
+	builder.PrependUint32(start)
+	return builder.Offset()
+}
@@ -162,7 +162,7 @@ func TestCompileAndEval(t *testing.T) {
 			want: values.NewString("n = 10"),
 		},
 		{
-			name: "interpolated string expression error",
+			name: "interpolated integer expression",
 			// f = (r) => "n = ${r.n}"
 			fn: &semantic.FunctionExpression{
 				Block: &semantic.FunctionBlock{
@@ -198,6 +198,45 @@ func TestCompileAndEval(t *testing.T) {
 					"n": values.NewInt(10),
 				}),
 			}),
+			want: values.NewString("n = 10"),
+		},
+		{
+			name: "interpolated string expression error",
+			// f = (r) => "n = ${r.n}"
+			fn: &semantic.FunctionExpression{
+				Block: &semantic.FunctionBlock{
+					Parameters: &semantic.FunctionParameters{
+						List: []*semantic.FunctionParameter{
+							{Key: &semantic.Identifier{Name: "r"}},
+						},
+					},
+					Body: &semantic.StringExpression{
+						Parts: []semantic.StringExpressionPart{
+							&semantic.TextPart{
+								Value: "n = ",
+							},
+							&semantic.InterpolatedPart{
+								Expression: &semantic.MemberExpression{
+									Object: &semantic.IdentifierExpression{
+										Name: "r",
+									},
+									Property: "n",
+								},
+							},
+						},
+					},
+				},
+			},
+			inType: semantic.NewObjectType(map[string]semantic.Type{
+				"r": semantic.NewObjectType(map[string]semantic.Type{
+					"n": semantic.Regexp,
+				}),
+			}),
+			input: values.NewObjectWithValues(map[string]values.Value{
+				"r": values.NewObjectWithValues(map[string]values.Value{
+					"n": values.NewRegexp(regexp.MustCompile("a")),
+				}),
+			}),
 			wantErr: true,
 		},
 		{
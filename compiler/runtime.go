@@ -198,7 +198,15 @@ func (*interpolatedEvaluator) Type() semantic.Type {
 }
 
 func (e *interpolatedEvaluator) Eval(ctx context.Context, scope Scope) (values.Value, error) {
-	return e.s.Eval(ctx, scope)
+	v, err := e.s.Eval(ctx, scope)
+	if err != nil {
+		return nil, err
+	}
+	s, err := values.Stringify(v)
+	if err != nil {
+		return nil, err
+	}
+	return values.NewString(s), nil
 }
 
 type objEvaluator struct {
@@ -500,7 +500,15 @@ func (itrp *Interpreter) doStringPart(ctx context.Context, part semantic.StringE
 	case *semantic.TextPart:
 		return values.NewString(p.Value), nil
 	case *semantic.InterpolatedPart:
-		return itrp.doExpression(ctx, p.Expression, scope)
+		v, err := itrp.doExpression(ctx, p.Expression, scope)
+		if err != nil {
+			return nil, err
+		}
+		s, err := values.Stringify(v)
+		if err != nil {
+			return nil, err
+		}
+		return values.NewString(s), nil
 	}
 	return nil, errors.New(codes.Internal, "expecting interpolated string part")
 }
@@ -138,11 +138,20 @@ func TestEval(t *testing.T) {
 			},
 		},
 		{
-			name: "string interpolation error",
+			name: "string interpolation of basic types",
 			query: `
 				a = 1
-				b = 2
-				"a + b = ${a + b}"`,
+				b = 2.5
+				"a + 1 = ${a + 1}, b = ${b}, ${true} at ${2020-01-01T00:00:00Z} for ${1h5m}"`,
+			want: []values.Value{
+				values.NewString("a + 1 = 2, b = 2.5, true at 2020-01-01T00:00:00.000000000Z for 1h5m"),
+			},
+		},
+		{
+			name: "string interpolation error",
+			query: `
+				r = /a/
+				"r = ${r}"`,
 			wantErr: true,
 		},
 		{
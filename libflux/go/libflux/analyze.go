@@ -68,3 +68,25 @@ func Analyze(astPkg *ASTPkg) (*SemanticPkg, error) {
 	runtime.SetFinalizer(p, free)
 	return p, nil
 }
+
+// AnalyzeWithLimits is like Analyze, but returns an error naming the limit
+// once analysis breaks one of the given limits. Analyze enforces the
+// default limits.
+func AnalyzeWithLimits(astPkg *ASTPkg, limits Limits) (*SemanticPkg, error) {
+	var semPkg *C.struct_flux_semantic_pkg_t
+	defer func() {
+		astPkg.ptr = nil
+	}()
+	climits := limits.c()
+	if err := C.flux_analyze_with_limits(astPkg.ptr, &climits, &semPkg); err != nil {
+		defer C.flux_free(unsafe.Pointer(err))
+		cstr := C.flux_error_str(err)
+		defer C.flux_free(unsafe.Pointer(cstr))
+
+		str := C.GoString(cstr)
+		return nil, errors.New(str)
+	}
+	p := &SemanticPkg{ptr: semPkg}
+	runtime.SetFinalizer(p, free)
+	return p, nil
+}
@@ -27,7 +27,7 @@ func TestAnalyze(t *testing.T) {
 		{
 			name: "failure",
 			flx:  `x = "foo" + 10`,
-			err:  errors.New("cannot unify string with int"),
+			err:  errors.New("error at @1:5-1:15: expected string but found int"),
 		},
 	}
 	for _, tc := range tcs {
@@ -82,3 +82,155 @@ func Parse(s string) *ASTPkg {
 	runtime.SetFinalizer(p, free)
 	return p
 }
+
+// ParseWithEncoding is like Parse, but counts the columns of source
+// positions in the named encoding, "utf-8" or "utf-16".
+func ParseWithEncoding(s, encoding string) (*ASTPkg, error) {
+	cstr := C.CString(s)
+	defer C.free(unsafe.Pointer(cstr))
+	cenc := C.CString(encoding)
+	defer C.free(unsafe.Pointer(cenc))
+
+	var ptr *C.struct_flux_ast_pkg_t
+	if err := C.flux_parse_with_encoding(cstr, cenc, &ptr); err != nil {
+		defer C.flux_free(unsafe.Pointer(err))
+		cerr := C.flux_error_str(err)
+		defer C.flux_free(unsafe.Pointer(cerr))
+
+		str := C.GoString(cerr)
+		return nil, errors.New(str)
+	}
+	p := &ASTPkg{ptr: ptr}
+	runtime.SetFinalizer(p, free)
+	return p, nil
+}
+
+// Limits bounds the work done to parse and analyze a query that may come
+// from an untrusted user. A limit that is zero takes its default: MaxDepth
+// defaults to 128, and the other limits are not enforced.
+type Limits struct {
+	// MaxSourceLen is the largest source accepted, in bytes.
+	MaxSourceLen uint64
+	// MaxDepth is how deeply expressions may nest. A chain of operators,
+	// such as a + b + c, nests one level for every operator.
+	MaxDepth uint64
+	// MaxTokens is the most tokens the source may contain.
+	MaxTokens uint64
+	// MaxTypeVars is the most type variables inference may create.
+	MaxTypeVars uint64
+	// MaxInferenceSteps is the most unification steps inference may take.
+	MaxInferenceSteps uint64
+}
+
+func (l Limits) c() C.struct_flux_limits_t {
+	return C.struct_flux_limits_t{
+		max_source_len:      C.uint64_t(l.MaxSourceLen),
+		max_depth:           C.uint64_t(l.MaxDepth),
+		max_tokens:          C.uint64_t(l.MaxTokens),
+		max_type_vars:       C.uint64_t(l.MaxTypeVars),
+		max_inference_steps: C.uint64_t(l.MaxInferenceSteps),
+	}
+}
+
+// ParseWithLimits is like Parse, but returns an error naming the limit
+// once the source breaks one of the given limits.
+func ParseWithLimits(s string, limits Limits) (*ASTPkg, error) {
+	cstr := C.CString(s)
+	defer C.free(unsafe.Pointer(cstr))
+	climits := limits.c()
+
+	var ptr *C.struct_flux_ast_pkg_t
+	if err := C.flux_parse_with_limits(cstr, &climits, &ptr); err != nil {
+		defer C.flux_free(unsafe.Pointer(err))
+		cerr := C.flux_error_str(err)
+		defer C.flux_free(unsafe.Pointer(cerr))
+
+		str := C.GoString(cerr)
+		return nil, errors.New(str)
+	}
+	p := &ASTPkg{ptr: ptr}
+	runtime.SetFinalizer(p, free)
+	return p, nil
+}
+
+// Tokenize returns the tokens of a string as json, comments included.
+func Tokenize(s string) ([]byte, error) {
+	cstr := C.CString(s)
+	defer C.free(unsafe.Pointer(cstr))
+
+	var buf C.struct_flux_buffer_t
+	if err := C.flux_tokenize(cstr, &buf); err != nil {
+		defer C.flux_free(unsafe.Pointer(err))
+		cerr := C.flux_error_str(err)
+		defer C.flux_free(unsafe.Pointer(cerr))
+
+		str := C.GoString(cerr)
+		return nil, errors.New(str)
+	}
+	defer C.flux_free(buf.data)
+
+	data := C.GoBytes(buf.data, C.int(buf.len))
+	return data, nil
+}
+
+// ParseExpression will take a string holding a single expression and
+// return the JSON of its AST.
+func ParseExpression(s string) ([]byte, error) {
+	cstr := C.CString(s)
+	defer C.free(unsafe.Pointer(cstr))
+
+	var buf C.struct_flux_buffer_t
+	if err := C.flux_parse_expression(cstr, &buf); err != nil {
+		defer C.flux_free(unsafe.Pointer(err))
+		cerr := C.flux_error_str(err)
+		defer C.flux_free(unsafe.Pointer(cerr))
+
+		str := C.GoString(cerr)
+		return nil, errors.New(str)
+	}
+	defer C.flux_free(buf.data)
+
+	data := C.GoBytes(buf.data, C.int(buf.len))
+	return data, nil
+}
+
+// ParseStatement will take a string holding a single statement and
+// return the JSON of its AST.
+func ParseStatement(s string) ([]byte, error) {
+	cstr := C.CString(s)
+	defer C.free(unsafe.Pointer(cstr))
+
+	var buf C.struct_flux_buffer_t
+	if err := C.flux_parse_statement(cstr, &buf); err != nil {
+		defer C.flux_free(unsafe.Pointer(err))
+		cerr := C.flux_error_str(err)
+		defer C.flux_free(unsafe.Pointer(cerr))
+
+		str := C.GoString(cerr)
+		return nil, errors.New(str)
+	}
+	defer C.flux_free(buf.data)
+
+	data := C.GoBytes(buf.data, C.int(buf.len))
+	return data, nil
+}
+
+// ParseType will take a type expression and return its canonical text,
+// or an error locating the point at which it is invalid.
+func ParseType(s string) (string, error) {
+	cstr := C.CString(s)
+	defer C.free(unsafe.Pointer(cstr))
+
+	var buf C.struct_flux_buffer_t
+	if err := C.flux_parse_type(cstr, &buf); err != nil {
+		defer C.flux_free(unsafe.Pointer(err))
+		cerr := C.flux_error_str(err)
+		defer C.flux_free(unsafe.Pointer(cerr))
+
+		str := C.GoString(cerr)
+		return "", errors.New(str)
+	}
+	defer C.flux_free(buf.data)
+
+	return C.GoStringN((*C.char)(buf.data), C.int(buf.len)), nil
+}
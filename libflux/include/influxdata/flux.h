@@ -2,6 +2,7 @@
 #define _INFLUXDATA_FLUX_H
 
 #include <stddef.h>
+#include <stdint.h>
 
 #ifdef __cplusplus
 extern "C" {
@@ -16,6 +17,27 @@ struct flux_buffer_t {
 	size_t len;
 };
 
+// flux_limits_t bounds the work done to parse and analyze a query that may
+// come from an untrusted user. A field that is zero takes its default:
+// max_depth defaults to 128, and the other limits are not enforced.
+struct flux_limits_t {
+	// max_source_len is the largest source accepted, in bytes.
+	uint64_t max_source_len;
+
+	// max_depth is how deeply expressions may nest. A chain of operators,
+	// such as a + b + c, nests one level for every operator.
+	uint64_t max_depth;
+
+	// max_tokens is the most tokens the source may contain.
+	uint64_t max_tokens;
+
+	// max_type_vars is the most type variables inference may create.
+	uint64_t max_type_vars;
+
+	// max_inference_steps is the most unification steps inference may take.
+	uint64_t max_inference_steps;
+};
+
 // flux_error_t represents a flux error.
 struct flux_error_t;
 
@@ -30,9 +52,51 @@ void flux_free(void *);
 struct flux_ast_pkg_t;
 
 // flux_parse will take in a string and return the AST representation
-// of the query.
+// of the query. Invalid UTF-8, like any other error, is reported on the
+// file within the returned package.
 struct flux_ast_pkg_t *flux_parse(const char *);
 
+// flux_parse_with_encoding is like flux_parse, but counts the columns of
+// source positions in the named encoding, "utf-8" or "utf-16". It populates
+// the third pointer argument with the AST, or returns an error if the encoding
+// is unknown. The error must be freed using flux_free if it is non-null.
+struct flux_error_t *flux_parse_with_encoding(const char *, const char *, struct flux_ast_pkg_t **);
+
+// flux_parse_with_limits is like flux_parse, but stops once the source breaks
+// one of the given limits. A null pointer takes the default limits. It
+// populates the third pointer argument with the AST, or returns an error
+// naming the limit that was exceeded. The error must be freed using
+// flux_free if it is non-null.
+struct flux_error_t *flux_parse_with_limits(const char *, const struct flux_limits_t *, struct flux_ast_pkg_t **);
+
+// flux_tokenize will take in a string and fill in the given buffer with
+// a json array of its tokens, comments included. Each token has a kind,
+// a byte span and start and end positions. If successful, memory will be
+// allocated for the data within the buffer and it is the caller's
+// responsibility to free this data. If an error happens it will be
+// returned. The error must be freed using flux_free if it is non-null.
+struct flux_error_t *flux_tokenize(const char *, struct flux_buffer_t *);
+
+// flux_parse_expression will take in a string holding a single expression
+// and fill in the given buffer with the json of its AST. Input left over
+// after the expression is reported as an error on the expression node.
+// If successful, memory will be allocated for the data within the buffer
+// and it is the caller's responsibility to free this data. If an error
+// happens it will be returned. The error must be freed using flux_free if
+// it is non-null.
+struct flux_error_t *flux_parse_expression(const char *, struct flux_buffer_t *);
+
+// flux_parse_statement is like flux_parse_expression, but parses a single
+// statement.
+struct flux_error_t *flux_parse_statement(const char *, struct flux_buffer_t *);
+
+// flux_parse_type will take in a type expression, such as
+// "forall [t0] (x: t0) => t0", and fill in the given buffer with its
+// canonical text. If the type expression is invalid, an error with its
+// location will be returned. The error must be freed using flux_free if it
+// is non-null.
+struct flux_error_t *flux_parse_type(const char *, struct flux_buffer_t *);
+
 // flux_ast_marshal_json will marshal json and fill in the given buffer
 // with the data. If successful, memory will be allocated for the data
 // within the buffer and it is the caller's responsibility to free this
@@ -62,6 +126,11 @@ struct flux_semantic_pkg_t;
 // flux_ast_pkg_t* argument, so it should not be reused after calling this function.
 struct flux_error_t *flux_analyze(struct flux_ast_pkg_t *, struct flux_semantic_pkg_t **);
 
+// flux_analyze_with_limits is like flux_analyze, but stops once analysis
+// breaks one of the given limits, returning an error that names it. A null
+// pointer takes the default limits, which flux_analyze also enforces.
+struct flux_error_t *flux_analyze_with_limits(struct flux_ast_pkg_t *, const struct flux_limits_t *, struct flux_semantic_pkg_t **);
+
 // flux_semantic_marshal_fb will marshal the given semantic graph as a flatbuffer into
 // the given buffer. If successful, memory will be allocated for the data
 // within the buffer and it is the caller's responsibility to free this
@@ -0,0 +1,564 @@
+//! Functions to build an AST from code, without writing out every node.
+//!
+//! Nodes are built with empty locations; `FileBuilder::build` then gives
+//! every node of the file a synthetic location that carries only the file name.
+//! Literals are built so that formatting them gives back the same value when
+//! the output is parsed, so the result can be printed with `formatter`:
+//!
+//! ```
+//! use flux::args;
+//! use flux::ast::build::{self, call, func, member, param, pipe, string};
+//! use flux::ast::Operator;
+//! use flux::formatter::Formatter;
+//!
+//! let file = build::file("query.flux")
+//!     .var(
+//!         "data",
+//!         pipe(call("from", args![bucket => "my \"bucket\""]))
+//!             .then(call("range", args![start => build::duration(-1, "h")]))
+//!             .then(call(
+//!                 "filter",
+//!                 args![fn => func(
+//!                     vec![param("r")],
+//!                     build::binary(
+//!                         Operator::EqualOperator,
+//!                         member(build::ident("r"), "_measurement"),
+//!                         string("cpu"),
+//!                     ),
+//!                 )],
+//!             )),
+//!     )
+//!     .build();
+//!
+//! let mut f = Formatter::new(0);
+//! f.format_file(&file, true);
+//! assert!(f.output().0.starts_with("data = from(bucket: \"my \\\"bucket\\\"\")"));
+//! ```
+#[cfg(test)]
+mod tests;
+
+use crate::ast::walk_mut::{walk_mut, NodeMut};
+use crate::ast::*;
+
+use chrono::FixedOffset;
+
+/// args builds the properties of a call from `key => value` pairs,
+/// converting every value with `IntoExpr`.
+#[macro_export]
+macro_rules! args {
+    ($($key:ident => $value:expr),* $(,)?) => {
+        vec![$($crate::ast::build::property(stringify!($key), $value)),*]
+    };
+}
+
+/// IntoExpr converts a value to the expression that builds it.
+/// Strings become string literals; use `ident` and `path` to refer to names.
+pub trait IntoExpr {
+    fn into_expr(self) -> Expression;
+}
+
+impl IntoExpr for Expression {
+    fn into_expr(self) -> Expression {
+        self
+    }
+}
+
+impl IntoExpr for CallExpr {
+    fn into_expr(self) -> Expression {
+        Expression::Call(Box::new(self))
+    }
+}
+
+impl IntoExpr for &str {
+    fn into_expr(self) -> Expression {
+        string(self)
+    }
+}
+
+impl IntoExpr for String {
+    fn into_expr(self) -> Expression {
+        string(&self)
+    }
+}
+
+impl IntoExpr for i32 {
+    fn into_expr(self) -> Expression {
+        int(self.into())
+    }
+}
+
+impl IntoExpr for i64 {
+    fn into_expr(self) -> Expression {
+        int(self)
+    }
+}
+
+impl IntoExpr for f64 {
+    fn into_expr(self) -> Expression {
+        float(self)
+    }
+}
+
+impl IntoExpr for bool {
+    fn into_expr(self) -> Expression {
+        boolean(self)
+    }
+}
+
+impl<T: IntoExpr> IntoExpr for Vec<T> {
+    fn into_expr(self) -> Expression {
+        array(self.into_iter().map(IntoExpr::into_expr).collect())
+    }
+}
+
+/// Pipe builds a chain of calls joined by the pipe forward operator `|>`.
+#[derive(Debug, Clone)]
+pub struct Pipe {
+    expr: Expression,
+}
+
+/// pipe starts a chain of calls from the expression that is piped into the first call.
+pub fn pipe(source: impl IntoExpr) -> Pipe {
+    Pipe {
+        expr: source.into_expr(),
+    }
+}
+
+impl Pipe {
+    /// then pipes the chain built so far into another call.
+    pub fn then(self, call: CallExpr) -> Pipe {
+        Pipe {
+            expr: Expression::PipeExpr(Box::new(PipeExpr {
+                base: BaseNode::default(),
+                argument: self.expr,
+                call,
+            })),
+        }
+    }
+}
+
+impl IntoExpr for Pipe {
+    fn into_expr(self) -> Expression {
+        self.expr
+    }
+}
+
+// is_identifier reports whether a name can be written as an identifier,
+// following the scanner's rules.
+fn is_identifier(name: &str) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "and", "or", "not", "empty", "in", "import", "package", "return", "option", "builtin",
+        "test", "if", "then", "else", "exists",
+    ];
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_alphanumeric() || c == '_') && !KEYWORDS.contains(&name)
+}
+
+fn identifier(name: &str) -> Identifier {
+    Identifier {
+        base: BaseNode::default(),
+        name: name.to_string(),
+    }
+}
+
+fn string_lit(value: &str) -> StringLit {
+    StringLit {
+        base: BaseNode::default(),
+        value: value.to_string(),
+    }
+}
+
+// property_key uses an identifier for the key when it can be written as one,
+// and a string literal otherwise.
+fn property_key(key: &str) -> PropertyKey {
+    if is_identifier(key) {
+        PropertyKey::Identifier(identifier(key))
+    } else {
+        PropertyKey::StringLit(string_lit(key))
+    }
+}
+
+fn negate(argument: Expression) -> Expression {
+    unary(Operator::SubtractionOperator, argument)
+}
+
+/// ident refers to a name. The name must be a valid identifier.
+pub fn ident(name: &str) -> Expression {
+    Expression::Identifier(identifier(name))
+}
+
+/// path refers to a name inside of a package or record, such as `strings.toUpper`.
+pub fn path(name: &str) -> Expression {
+    let mut parts = name.split('.');
+    let mut expr = ident(parts.next().unwrap_or_default());
+    for part in parts {
+        expr = member(expr, part);
+    }
+    expr
+}
+
+/// string builds a string literal; the value is escaped when it is formatted.
+pub fn string(value: &str) -> Expression {
+    Expression::StringLit(string_lit(value))
+}
+
+/// regex builds a regular expression literal; slashes are escaped when it is formatted.
+pub fn regex(expr: &str) -> Expression {
+    Expression::Regexp(RegexpLit {
+        base: BaseNode::default(),
+        value: expr.to_string(),
+    })
+}
+
+/// int builds an integer literal. Negative values are negated literals,
+/// as the parser would produce them.
+pub fn int(value: i64) -> Expression {
+    if value < 0 && value != i64::MIN {
+        return negate(int(-value));
+    }
+    Expression::Integer(IntegerLit {
+        base: BaseNode::default(),
+        value,
+    })
+}
+
+/// float builds a float literal. The value must be finite.
+pub fn float(value: f64) -> Expression {
+    if value.is_sign_negative() && value != 0.0 {
+        return negate(float(-value));
+    }
+    Expression::Float(FloatLit {
+        base: BaseNode::default(),
+        value,
+    })
+}
+
+/// boolean refers to `true` or `false`, which are identifiers like in the parser.
+pub fn boolean(value: bool) -> Expression {
+    ident(if value { "true" } else { "false" })
+}
+
+/// duration builds a duration literal of a single unit, such as `duration(-5, "m")`.
+pub fn duration(magnitude: i64, unit: &str) -> Expression {
+    if magnitude < 0 {
+        return negate(duration(-magnitude, unit));
+    }
+    Expression::Duration(DurationLit {
+        base: BaseNode::default(),
+        values: vec![Duration {
+            magnitude,
+            unit: unit.to_string(),
+        }],
+    })
+}
+
+/// time builds a date time literal.
+pub fn time(value: chrono::DateTime<FixedOffset>) -> Expression {
+    Expression::DateTime(DateTimeLit {
+        base: BaseNode::default(),
+        value,
+    })
+}
+
+/// array builds an array of expressions.
+pub fn array(elements: Vec<Expression>) -> Expression {
+    Expression::Array(Box::new(ArrayExpr {
+        base: BaseNode::default(),
+        elements,
+    }))
+}
+
+/// property builds a `key: value` pair. Keys that are not identifiers are quoted.
+pub fn property(key: &str, value: impl IntoExpr) -> Property {
+    Property {
+        base: BaseNode::default(),
+        key: property_key(key),
+        value: Some(value.into_expr()),
+    }
+}
+
+/// object builds a record from its properties.
+pub fn object(properties: Vec<Property>) -> Expression {
+    Expression::Object(Box::new(ObjectExpr {
+        base: BaseNode::default(),
+        with: None,
+        properties,
+    }))
+}
+
+/// object_with builds a record that extends the record named `with`.
+pub fn object_with(with: &str, properties: Vec<Property>) -> Expression {
+    Expression::Object(Box::new(ObjectExpr {
+        base: BaseNode::default(),
+        with: Some(identifier(with)),
+        properties,
+    }))
+}
+
+/// member accesses a property of a record. Names that are not identifiers
+/// are accessed with brackets, as in `r["my field"]`.
+pub fn member(object: impl IntoExpr, property: &str) -> Expression {
+    Expression::Member(Box::new(MemberExpr {
+        base: BaseNode::default(),
+        object: object.into_expr(),
+        property: property_key(property),
+    }))
+}
+
+/// index accesses an element of an array.
+pub fn index(array: impl IntoExpr, index: impl IntoExpr) -> Expression {
+    Expression::Index(Box::new(IndexExpr {
+        base: BaseNode::default(),
+        array: array.into_expr(),
+        index: index.into_expr(),
+    }))
+}
+
+/// binary applies an arithmetic or comparison operator.
+pub fn binary(operator: Operator, left: impl IntoExpr, right: impl IntoExpr) -> Expression {
+    Expression::Binary(Box::new(BinaryExpr {
+        base: BaseNode::default(),
+        operator,
+        left: left.into_expr(),
+        right: right.into_expr(),
+    }))
+}
+
+/// logical applies `and` or `or`.
+pub fn logical(operator: LogicalOperator, left: impl IntoExpr, right: impl IntoExpr) -> Expression {
+    Expression::Logical(Box::new(LogicalExpr {
+        base: BaseNode::default(),
+        operator,
+        left: left.into_expr(),
+        right: right.into_expr(),
+    }))
+}
+
+/// unary applies a prefix operator such as `not` or `-`.
+pub fn unary(operator: Operator, argument: impl IntoExpr) -> Expression {
+    Expression::Unary(Box::new(UnaryExpr {
+        base: BaseNode::default(),
+        operator,
+        argument: argument.into_expr(),
+    }))
+}
+
+/// conditional builds `if test then consequent else alternate`.
+pub fn conditional(
+    test: impl IntoExpr,
+    consequent: impl IntoExpr,
+    alternate: impl IntoExpr,
+) -> Expression {
+    Expression::Conditional(Box::new(ConditionalExpr {
+        base: BaseNode::default(),
+        test: test.into_expr(),
+        consequent: consequent.into_expr(),
+        alternate: alternate.into_expr(),
+    }))
+}
+
+/// call calls the function at `callee`, a name or a `path`, with named arguments.
+pub fn call(callee: &str, args: Vec<Property>) -> CallExpr {
+    call_expr(path(callee), args)
+}
+
+/// call_expr calls the function that an expression evaluates to.
+pub fn call_expr(callee: impl IntoExpr, args: Vec<Property>) -> CallExpr {
+    let arguments = if args.is_empty() {
+        Vec::new()
+    } else {
+        vec![object(args)]
+    };
+    CallExpr {
+        base: BaseNode::default(),
+        callee: callee.into_expr(),
+        arguments,
+    }
+}
+
+/// param declares a function parameter without a default.
+pub fn param(name: &str) -> Property {
+    Property {
+        base: BaseNode::default(),
+        key: PropertyKey::Identifier(identifier(name)),
+        value: None,
+    }
+}
+
+/// param_default declares a function parameter with a default value.
+pub fn param_default(name: &str, default: impl IntoExpr) -> Property {
+    Property {
+        base: BaseNode::default(),
+        key: PropertyKey::Identifier(identifier(name)),
+        value: Some(default.into_expr()),
+    }
+}
+
+/// pipe_param declares the parameter that receives piped data, as in `(tables=<-)`.
+pub fn pipe_param(name: &str) -> Property {
+    param_default(
+        name,
+        Expression::PipeLit(PipeLit {
+            base: BaseNode::default(),
+        }),
+    )
+}
+
+/// func builds a function whose body is a single expression.
+pub fn func(params: Vec<Property>, body: impl IntoExpr) -> Expression {
+    Expression::Function(Box::new(FunctionExpr {
+        base: BaseNode::default(),
+        params,
+        body: FunctionBody::Expr(body.into_expr()),
+    }))
+}
+
+/// func_block builds a function whose body is a block of statements,
+/// which should end with `ret`.
+pub fn func_block(params: Vec<Property>, body: Vec<Statement>) -> Expression {
+    Expression::Function(Box::new(FunctionExpr {
+        base: BaseNode::default(),
+        params,
+        body: FunctionBody::Block(Block {
+            base: BaseNode::default(),
+            body,
+        }),
+    }))
+}
+
+/// expr builds an expression statement.
+pub fn expr(expression: impl IntoExpr) -> Statement {
+    Statement::Expr(ExprStmt {
+        base: BaseNode::default(),
+        expression: expression.into_expr(),
+    })
+}
+
+/// var builds a variable assignment.
+pub fn var(name: &str, init: impl IntoExpr) -> Statement {
+    Statement::Variable(Box::new(variable_assgn(name, init)))
+}
+
+/// ret builds a return statement.
+pub fn ret(argument: impl IntoExpr) -> Statement {
+    Statement::Return(ReturnStmt {
+        base: BaseNode::default(),
+        argument: argument.into_expr(),
+    })
+}
+
+/// option builds an option statement. A dotted name such as `now.x` assigns
+/// to a member of an option.
+pub fn option(name: &str, init: impl IntoExpr) -> Statement {
+    let assignment = match path(name) {
+        Expression::Member(member) => Assignment::Member(Box::new(MemberAssgn {
+            base: BaseNode::default(),
+            member: *member,
+            init: init.into_expr(),
+        })),
+        _ => Assignment::Variable(Box::new(variable_assgn(name, init))),
+    };
+    Statement::Option(Box::new(OptionStmt {
+        base: BaseNode::default(),
+        assignment,
+    }))
+}
+
+fn variable_assgn(name: &str, init: impl IntoExpr) -> VariableAssgn {
+    VariableAssgn {
+        base: BaseNode::default(),
+        id: identifier(name),
+        init: init.into_expr(),
+    }
+}
+
+/// FileBuilder builds a file statement by statement.
+#[derive(Debug, Clone)]
+pub struct FileBuilder {
+    file: File,
+}
+
+/// file starts building a file with the given name.
+pub fn file(name: &str) -> FileBuilder {
+    FileBuilder {
+        file: File {
+            base: BaseNode::default(),
+            name: name.to_string(),
+            metadata: String::new(),
+            package: None,
+            imports: Vec::new(),
+            body: Vec::new(),
+        },
+    }
+}
+
+impl FileBuilder {
+    /// package sets the package clause of the file.
+    pub fn package(mut self, name: &str) -> Self {
+        self.file.package = Some(PackageClause {
+            base: BaseNode::default(),
+            name: identifier(name),
+        });
+        self
+    }
+
+    /// import adds an import of a package.
+    pub fn import(mut self, path: &str) -> Self {
+        self.file.imports.push(ImportDeclaration {
+            base: BaseNode::default(),
+            alias: None,
+            path: string_lit(path),
+        });
+        self
+    }
+
+    /// import_as adds an import of a package under another name.
+    pub fn import_as(mut self, alias: &str, path: &str) -> Self {
+        self.file.imports.push(ImportDeclaration {
+            base: BaseNode::default(),
+            alias: Some(identifier(alias)),
+            path: string_lit(path),
+        });
+        self
+    }
+
+    /// stmt adds a statement to the body of the file.
+    pub fn stmt(mut self, stmt: Statement) -> Self {
+        self.file.body.push(stmt);
+        self
+    }
+
+    /// expr adds an expression statement.
+    pub fn expr(self, expression: impl IntoExpr) -> Self {
+        self.stmt(expr(expression))
+    }
+
+    /// var adds a variable assignment.
+    pub fn var(self, name: &str, init: impl IntoExpr) -> Self {
+        self.stmt(var(name, init))
+    }
+
+    /// option adds an option statement.
+    pub fn option(self, name: &str, init: impl IntoExpr) -> Self {
+        self.stmt(option(name, init))
+    }
+
+    /// build returns the file, after giving every node a location in it.
+    pub fn build(mut self) -> File {
+        let name = self.file.name.clone();
+        walk_mut(
+            &mut |n: &mut NodeMut| {
+                n.base_mut().location = SourceLocation {
+                    file: Some(name.clone()),
+                    ..SourceLocation::default()
+                }
+            },
+            &mut NodeMut::File(&mut self.file),
+        );
+        self.file
+    }
+}
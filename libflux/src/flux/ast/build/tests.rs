@@ -0,0 +1,249 @@
+use super::*;
+use crate::args;
+use crate::ast::check;
+use crate::ast::walk::{create_visitor, walk, Node};
+use crate::formatter::Formatter;
+use crate::parser::parse_string;
+
+fn format(file: &File) -> String {
+    let mut fmt = Formatter::new(0);
+    fmt.format_file(file, true);
+    fmt.output().0.to_string()
+}
+
+// reparse formats a built file and parses it back, checking that the
+// output is valid Flux that formats the same way.
+fn reparse(file: &File) -> File {
+    let src = format(file);
+    let parsed = parse_string("reparse", &src);
+    assert_eq!(
+        Vec::<String>::new(),
+        check::check(Node::File(&parsed))
+            .into_iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>(),
+        "{}",
+        src
+    );
+    assert_eq!(src, format(&parsed));
+    parsed
+}
+
+// literals collects the values of the string and regex literals of a file.
+fn literals(file: &File) -> Vec<String> {
+    let mut values = Vec::new();
+    walk(
+        &create_visitor(&mut |n| match *n {
+            Node::StringLit(l) => values.push(l.value.clone()),
+            Node::RegexpLit(l) => values.push(l.value.clone()),
+            _ => {}
+        }),
+        Node::File(file),
+    );
+    values
+}
+
+#[test]
+fn pipeline() {
+    let file = file("query.flux")
+        .var(
+            "data",
+            pipe(call("from", args![bucket => "telegraf/autogen"]))
+                .then(call(
+                    "range",
+                    args![start => duration(-1, "h"), stop => ident("now")],
+                ))
+                .then(call(
+                    "filter",
+                    args![fn => func(
+                        vec![param("r")],
+                        logical(
+                            LogicalOperator::AndOperator,
+                            binary(
+                                Operator::EqualOperator,
+                                member(ident("r"), "_measurement"),
+                                "cpu",
+                            ),
+                            binary(
+                                Operator::RegexpMatchOperator,
+                                member(ident("r"), "host"),
+                                regex("^server/[0-9]+$"),
+                            ),
+                        ),
+                    )],
+                ))
+                .then(call("yield", vec![])),
+        )
+        .build();
+    assert_eq!(
+        r#"data = from(bucket: "telegraf/autogen")
+	|> range(start: -1h, stop: now)
+	|> filter(fn: (r) =>
+		(r._measurement == "cpu" and r.host =~ /^server\/[0-9]+$/))
+	|> yield()"#,
+        format(&file)
+    );
+    reparse(&file);
+}
+
+#[test]
+fn file_header() {
+    let file = file("header.flux")
+        .import("strings")
+        .import_as("h", "http/requests")
+        .option("now", func(vec![], call("system.time", vec![])))
+        .option("task.every", duration(5, "m"))
+        .expr(call("strings.toUpper", args![v => "a"]))
+        .build();
+    assert_eq!(
+        r#"import "strings"
+import h "http/requests"
+
+option now = () =>
+	(system.time())
+option task.every = 5m
+
+strings.toUpper(v: "a")"#,
+        format(&file)
+    );
+    reparse(&file);
+}
+
+#[test]
+fn string_escaping() {
+    let values = vec![
+        "plain",
+        "quote \" inside",
+        r"back\slash",
+        r#"\" both"#,
+        "${interpolation}",
+        "$ and {} alone",
+        "trailing $",
+        "new\nline and\ttab",
+        "ünïcødé",
+        "",
+    ];
+    let file = file("strings.flux").var("strings", values.clone()).build();
+    let parsed = reparse(&file);
+    assert_eq!(values, literals(&parsed));
+}
+
+#[test]
+fn regex_escaping() {
+    let values = [
+        r"a/b",
+        r"^/path/to/\w+$",
+        r"escaped \/ slash",
+        r"escaped \\/ backslash",
+        r"\d+\.\d*",
+        "",
+    ];
+    let file = file("regex.flux")
+        .var(
+            "regexes",
+            values.iter().map(|v| regex(v)).collect::<Vec<_>>(),
+        )
+        .build();
+    let parsed = reparse(&file);
+    assert_eq!(
+        vec![
+            "a/b",
+            "^/path/to/\\w+$",
+            // an escaped slash is the same as a slash.
+            "escaped / slash",
+            r"escaped \\/ backslash",
+            r"\d+\.\d*",
+            "(?:)",
+        ],
+        literals(&parsed)
+    );
+}
+
+#[test]
+fn keys() {
+    let file = file("keys.flux")
+        .expr(object(vec![
+            property("a", 1),
+            property("my key", 2),
+            property("and", 3),
+        ]))
+        .expr(object(args![_x9 => 4]))
+        .expr(object_with("r", args![b => true]))
+        .expr(member(ident("r"), "my field"))
+        .var("i", index(vec![1, 2], 0))
+        .build();
+    assert_eq!(
+        r#"{a: 1, "my key": 2, "and": 3}
+{_x9: 4}
+{r with b: true}
+r["my field"]
+
+i = [1, 2][0]"#,
+        format(&file)
+    );
+    reparse(&file);
+}
+
+#[test]
+fn numbers() {
+    let file = file("numbers.flux")
+        .var("a", vec![int(-3), int(0), int(42)])
+        .var("b", vec![float(-1.5), float(0.0), float(2.0)])
+        .var(
+            "c",
+            conditional(boolean(true), duration(-90, "s"), duration(2, "d")),
+        )
+        .build();
+    assert_eq!(
+        "a = [-3, 0, 42]\nb = [-1.5, 0.0, 2.0]\nc = if true then -90s else 2d",
+        format(&file)
+    );
+    reparse(&file);
+}
+
+#[test]
+fn functions() {
+    let file = file("functions.flux")
+        .var(
+            "f",
+            func_block(
+                vec![pipe_param("tables"), param_default("n", 2), param("fn")],
+                vec![
+                    var("x", binary(Operator::MultiplicationOperator, ident("n"), 2)),
+                    ret(pipe(ident("tables")).then(call("map", args![fn => ident("fn")]))),
+                ],
+            ),
+        )
+        .expr(unary(Operator::NotOperator, call_expr(ident("f"), vec![])))
+        .build();
+    assert_eq!(
+        r#"f = (tables=<-, n=2, fn) => {
+	x = n * 2
+
+	return tables
+		|> map(fn: fn)
+}
+
+not f()"#,
+        format(&file)
+    );
+    reparse(&file);
+}
+
+#[test]
+fn synthetic_locations() {
+    let file = file("loc.flux")
+        .var("a", pipe("b").then(call("c", args![d => 1.0])))
+        .build();
+    let mut count = 0;
+    walk(
+        &create_visitor(&mut |n| {
+            count += 1;
+            let loc = &n.base().location;
+            assert_eq!(Some("loc.flux".to_string()), loc.file, "{}", n);
+            assert!(!loc.is_valid(), "{}", n);
+        }),
+        Node::File(&file),
+    );
+    assert_eq!(11, count);
+}
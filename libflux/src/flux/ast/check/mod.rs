@@ -1,10 +1,21 @@
-use crate::ast::{walk, PropertyKey, SourceLocation};
+use crate::ast::{
+    walk, Duration, Expression, ObjectExpr, Position, Property, PropertyKey, SourceLocation, Span,
+};
+use crate::limits::{self, Limit};
+use regex_syntax::ast::parse::Parser;
+use regex_syntax::hir::translate::Translator;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::rc::Rc;
 
 // check() inspects an AST node and returns a list of found AST errors plus
 // any errors existed before ast.check() is performed.
 pub fn check(node: walk::Node) -> Vec<Error> {
     let mut errors = vec![];
+    // The objects that are the arguments of a call, which are checked as
+    // arguments rather than as records.
+    let mut arguments: HashSet<*const ObjectExpr> = HashSet::new();
     walk::walk(
         &walk::create_visitor(&mut |n| {
             // collect any errors we found prior to ast.check().
@@ -50,6 +61,40 @@ pub fn check(node: walk::Node) -> Vec<Error> {
                             message: String::from("cannot mix implicit and explicit properties"),
                         })
                     }
+                    if !arguments.contains(&(n as *const ObjectExpr)) {
+                        errors.extend(duplicates(&n.properties, "record key"));
+                    }
+                }
+                walk::Node::CallExpr(n) => {
+                    for arg in &n.arguments {
+                        if let Expression::Object(obj) = arg {
+                            arguments.insert(obj.as_ref() as *const ObjectExpr);
+                            errors.extend(duplicates(&obj.properties, "argument"));
+                        }
+                    }
+                }
+                walk::Node::FunctionExpr(n) => {
+                    errors.extend(duplicates(&n.params, "parameter"));
+                }
+                walk::Node::RegexpLit(n) => {
+                    if let Err((msg, start, end)) = compile_regex(&n.value) {
+                        errors.push(Error {
+                            location: within(
+                                &n.base.location,
+                                regex_offset(&n.value, start),
+                                regex_offset(&n.value, end),
+                            ),
+                            message: format!("invalid regular expression: {}", msg),
+                        })
+                    }
+                }
+                walk::Node::DurationLit(n) => {
+                    for (msg, start, end) in check_duration(&n.values) {
+                        errors.push(Error {
+                            location: within(&n.base.location, start, end),
+                            message: msg,
+                        })
+                    }
                 }
                 _ => {}
             }
@@ -59,6 +104,199 @@ pub fn check(node: walk::Node) -> Vec<Error> {
     errors
 }
 
+// check_depth returns an error if expressions in an AST node nest more
+// deeply than max_depth. An AST built by the parser under the same limit
+// always passes; the check is for ASTs that come from elsewhere, such as a
+// deserialized package. The walk stops descending at the limit, so it does
+// not itself recurse past it.
+pub fn check_depth(node: walk::Node, max_depth: Option<u64>) -> Result<(), limits::Error> {
+    let max = match max_depth {
+        Some(max) => max,
+        None => return Ok(()),
+    };
+    let v = DepthVisitor {
+        max,
+        depth: Rc::new(Cell::new(0)),
+        exceeded: Rc::new(Cell::new(false)),
+    };
+    walk::walk(&v, node);
+    if v.exceeded.get() {
+        return Err(limits::Error {
+            limit: Limit::Depth,
+            max,
+        });
+    }
+    Ok(())
+}
+
+#[derive(Clone)]
+struct DepthVisitor {
+    max: u64,
+    depth: Rc<Cell<u64>>,
+    exceeded: Rc<Cell<bool>>,
+}
+
+impl DepthVisitor {
+    // levels returns how many levels of nesting a node adds, which is one
+    // for an expression and none for anything else.
+    fn levels(node: &walk::Node) -> u64 {
+        match node {
+            walk::Node::Package(_)
+            | walk::Node::File(_)
+            | walk::Node::PackageClause(_)
+            | walk::Node::ImportDeclaration(_)
+            | walk::Node::ExprStmt(_)
+            | walk::Node::OptionStmt(_)
+            | walk::Node::ReturnStmt(_)
+            | walk::Node::BadStmt(_)
+            | walk::Node::TestStmt(_)
+            | walk::Node::BuiltinStmt(_)
+            | walk::Node::Block(_)
+            | walk::Node::Property(_)
+            | walk::Node::TextPart(_)
+            | walk::Node::InterpolatedPart(_)
+            | walk::Node::VariableAssgn(_)
+            | walk::Node::MemberAssgn(_) => 0,
+            _ => 1,
+        }
+    }
+}
+
+impl<'a> walk::Visitor<'a> for DepthVisitor {
+    fn visit(&self, node: Rc<walk::Node<'a>>) -> Option<Self> {
+        let depth = self.depth.get() + DepthVisitor::levels(&node);
+        self.depth.set(depth);
+        if depth > self.max {
+            self.exceeded.set(true);
+            return None;
+        }
+        Some(self.clone())
+    }
+
+    fn done(&self, node: Rc<walk::Node<'a>>) {
+        self.depth.set(self.depth.get() - DepthVisitor::levels(&node));
+    }
+}
+
+// duplicates returns an error for each property whose key is that of an
+// earlier property. The error is at the later key and names where the
+// earlier one is.
+fn duplicates(props: &[Property], what: &str) -> Vec<Error> {
+    let mut errors = vec![];
+    let mut seen: HashMap<&str, &SourceLocation> = HashMap::new();
+    for p in props {
+        let location = &p.key.base().location;
+        match seen.get(p.key.name()) {
+            Some(first) => errors.push(Error {
+                location: location.clone(),
+                message: format!(
+                    "duplicate {} {}, first given at {}:{}",
+                    what,
+                    p.key.name(),
+                    first.start.line,
+                    first.start.column
+                ),
+            }),
+            None => {
+                seen.insert(p.key.name(), location);
+            }
+        }
+    }
+    errors
+}
+
+// compile_regex compiles a regular expression the way the regex crate
+// does, returning the error and the byte range of the expression it is in.
+fn compile_regex(expr: &str) -> Result<(), (String, usize, usize)> {
+    // An empty span points between two characters, so it is widened to
+    // the character after it.
+    let range = |span: &regex_syntax::ast::Span| {
+        let (start, end) = (span.start.offset, span.end.offset);
+        match expr[start..].chars().next() {
+            Some(c) if start == end => (start, end + c.len_utf8()),
+            _ => (start, end),
+        }
+    };
+    let ast = Parser::new().parse(expr).map_err(|e| {
+        let (start, end) = range(e.span());
+        (e.kind().to_string(), start, end)
+    })?;
+    Translator::new().translate(expr, &ast).map_err(|e| {
+        let (start, end) = range(e.span());
+        (e.kind().to_string(), start, end)
+    })?;
+    if let Err(regex::Error::CompiledTooBig(_)) = regex::Regex::new(expr) {
+        return Err((String::from("compiled too big"), 0, expr.len()));
+    }
+    Ok(())
+}
+
+// regex_offset maps a byte offset within the value of a regular expression
+// to an offset within its literal, which starts with a slash and writes
+// each slash of the value escaped.
+fn regex_offset(expr: &str, offset: usize) -> usize {
+    1 + offset + expr[..offset].matches('/').count()
+}
+
+// The units of a duration, from the largest to the smallest.
+const DURATION_UNITS: [&str; 10] = ["y", "mo", "w", "d", "h", "m", "s", "ms", "us", "ns"];
+
+// check_duration checks that the units of a duration are given from the
+// largest to the smallest, each at most once. It returns an error for each
+// part that is not, with the byte range of the part within the literal.
+fn check_duration(values: &[Duration]) -> Vec<(String, usize, usize)> {
+    let mut errors = vec![];
+    let mut prev: Option<(usize, &str)> = None;
+    let mut start = 0;
+    for d in values {
+        let end = start + d.magnitude.to_string().len() + d.unit.len();
+        if let Some(i) = DURATION_UNITS.iter().position(|u| *u == d.unit) {
+            match prev {
+                Some((j, _)) if i == j => {
+                    errors.push((format!("duration unit {} is repeated", d.unit), start, end))
+                }
+                Some((j, unit)) if i < j => errors.push((
+                    format!("duration unit {} must come before {}", d.unit, unit),
+                    start,
+                    end,
+                )),
+                _ => prev = Some((i, &d.unit)),
+            }
+        }
+        start = end;
+    }
+    errors
+}
+
+// within returns the location of a byte range within a node on a single
+// line, such as a literal.
+fn within(loc: &SourceLocation, start: usize, end: usize) -> SourceLocation {
+    if !loc.is_valid() {
+        return loc.clone();
+    }
+    let (start, end) = (start as u32, end as u32);
+    SourceLocation {
+        file: loc.file.clone(),
+        start: Position {
+            line: loc.start.line,
+            column: loc.start.column + start,
+        },
+        end: Position {
+            line: loc.start.line,
+            column: loc.start.column + end,
+        },
+        span: Span {
+            start: loc.span.start + start,
+            end: loc.span.start + end,
+        },
+        source: loc
+            .source
+            .as_ref()
+            .and_then(|s| s.get(start as usize..end as usize))
+            .map(String::from),
+    }
+}
+
 #[derive(Debug, PartialEq)] // derive std::fmt::Debug on AppError
 pub struct Error {
     pub location: SourceLocation,
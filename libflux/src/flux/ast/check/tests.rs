@@ -1,7 +1,7 @@
 use super::*;
 use crate::ast::Expression::Integer;
 use crate::ast::Statement::Variable;
-use crate::ast::{BaseNode, File, Identifier, IntegerLit, Position, VariableAssgn};
+use crate::ast::{BaseNode, File, Identifier, IntegerLit, Position, Span, VariableAssgn};
 use crate::parser::parse_string;
 
 #[test]
@@ -16,7 +16,8 @@ fn test_object_check() {
                 line: 2,
                 column: 14,
             },
-            source: Some(String::from("{c: 2, a}")),
+            span: Span { start: 10, end: 19 },
+            source: None,
         },
         message: String::from("cannot mix implicit and explicit properties"),
     }];
@@ -32,7 +33,8 @@ fn test_bad_expr() {
             file: Some(String::from("bad_expr_test")),
             start: Position { line: 3, column: 2 },
             end: Position { line: 3, column: 3 },
-            source: Some(String::from("=")),
+            span: Span { start: 12, end: 13 },
+            source: None,
         },
         message: String::from("invalid statement: ="),
     }];
@@ -54,7 +56,8 @@ fn test_check_collect_existing_error() {
                 file: Some(String::from("test_check_collect_existing_error")),
                 start: Position { line: 1, column: 1 },
                 end: Position { line: 3, column: 6 },
-                source: Some(String::from("a = 1\nb=2\nc=a+b")),
+                span: Span { start: 0, end: 15 },
+                source: None,
             },
             errors: vec![String::from("error 1")],
         },
@@ -68,7 +71,8 @@ fn test_check_collect_existing_error() {
                     file: Some(String::from("test_check_collect_existing_error")),
                     start: Position { line: 1, column: 1 },
                     end: Position { line: 1, column: 6 },
-                    source: Some(String::from("a = 1")),
+                    span: Span { start: 0, end: 5 },
+                    source: None,
                 },
                 errors: vec![],
             },
@@ -78,7 +82,8 @@ fn test_check_collect_existing_error() {
                         file: Some(String::from("test_check_collect_existing_error")),
                         start: Position { line: 1, column: 1 },
                         end: Position { line: 1, column: 2 },
-                        source: Some(String::from("a")),
+                        span: Span { start: 0, end: 1 },
+                        source: None,
                     },
                     errors: vec![],
                 },
@@ -90,7 +95,8 @@ fn test_check_collect_existing_error() {
                         file: Some(String::from("test_check_collect_existing_error")),
                         start: Position { line: 1, column: 5 },
                         end: Position { line: 1, column: 6 },
-                        source: Some(String::from("1")),
+                        span: Span { start: 4, end: 5 },
+                        source: None,
                     },
                     errors: vec![String::from("error 2"), String::from("error 3")],
                 },
@@ -104,3 +110,137 @@ fn test_check_collect_existing_error() {
         assert_eq!(err.message, format!("error {}", i + 1));
     }
 }
+
+fn check_messages(src: &str) -> Vec<String> {
+    let file = parse_string("", src);
+    check(walk::Node::File(&file))
+        .iter()
+        .map(|e| e.to_string())
+        .collect()
+}
+
+#[test]
+fn test_check_regex() {
+    assert!(check_messages(r#"a = /^a.*\/b[c-e]+$/"#).is_empty());
+    assert_eq!(
+        check_messages("a = /[a-/"),
+        vec!["error at @1:6-1:7: invalid regular expression: unclosed character class"]
+    );
+    assert_eq!(
+        check_messages("a = /x*/ =~ /*/"),
+        vec!["error at @1:14-1:15: invalid regular expression: repetition operator missing expression"]
+    );
+    // The offset of an error counts the escape of each slash before it.
+    assert_eq!(
+        check_messages(r#"a = /\/\/(a/"#),
+        vec!["error at @1:10-1:11: invalid regular expression: unclosed group"]
+    );
+    assert_eq!(
+        check_messages(r#"a = /\pX/"#),
+        vec!["error at @1:6-1:9: invalid regular expression: Unicode property not found"]
+    );
+}
+
+#[test]
+fn test_check_duration() {
+    assert!(check_messages("a = 1y2mo3w4d5h6m7s8ms9us10ns").is_empty());
+    assert_eq!(
+        check_messages("a = 1mo1mo"),
+        vec!["error at @1:8-1:11: duration unit mo is repeated"]
+    );
+    assert_eq!(
+        check_messages("a = 10ns1mo\nb = 1h30m2h5s"),
+        vec![
+            "error at @1:9-1:12: duration unit mo must come before ns",
+            "error at @2:10-2:12: duration unit h must come before m",
+        ]
+    );
+}
+
+// Every regex and duration literal of the standard library checks, and
+// no key of the standard library is given twice.
+#[test]
+fn test_check_stdlib() {
+    for entry in walkdir::WalkDir::new("../../../stdlib") {
+        let entry = entry.unwrap();
+        let path = entry.path().display().to_string();
+        if !path.ends_with(".flux") {
+            continue;
+        }
+        let src = std::fs::read_to_string(&path).unwrap();
+        let file = parse_string(&path, &src);
+        let errs: Vec<String> = check(walk::Node::File(&file))
+            .iter()
+            .map(|e| e.to_string())
+            .filter(|e| {
+                e.contains("regular expression")
+                    || e.contains("duration")
+                    || e.contains("duplicate")
+            })
+            .collect();
+        assert!(errs.is_empty(), "{}", errs.join("\n"));
+    }
+}
+
+#[test]
+fn test_check_duplicates() {
+    assert!(check_messages("f = (a, b) => ({a: a, b: b})\nx = f(a: 1, b: 2)").is_empty());
+    assert_eq!(
+        check_messages("x = {a: 1, b: 2, \"a\": 3}"),
+        vec!["error at @1:18-1:21: duplicate record key a, first given at 1:6"]
+    );
+    assert_eq!(
+        check_messages("f = (a, b, a=1) => a"),
+        vec!["error at @1:12-1:13: duplicate parameter a, first given at 1:6"]
+    );
+    assert_eq!(
+        check_messages("x = f(x: 1, y: {x: 1}, x: 2)"),
+        vec!["error at @1:24-1:25: duplicate argument x, first given at 1:7"]
+    );
+    assert_eq!(
+        check_messages("x = {r with a: 1, a: 2, a: 3}"),
+        vec![
+            "error at @1:19-1:20: duplicate record key a, first given at 1:13",
+            "error at @1:25-1:26: duplicate record key a, first given at 1:13",
+        ]
+    );
+}
+
+#[test]
+fn test_check_depth() {
+    use crate::limits::{self, Limit, Limits};
+    use crate::parser::parse_string_with_limits;
+
+    let unlimited = Limits {
+        max_depth: None,
+        ..Limits::default()
+    };
+    let file = parse_string_with_limits("", "a = f(x: [1 + 2 * 3])", unlimited).unwrap();
+    let depth = |max| check_depth(walk::Node::File(&file), max);
+    assert_eq!(depth(None), Ok(()));
+    assert_eq!(depth(Some(6)), Ok(()));
+    assert_eq!(
+        depth(Some(5)),
+        Err(limits::Error {
+            limit: Limit::Depth,
+            max: 5
+        })
+    );
+
+    // An AST the parser accepts passes the check with the same limit.
+    let limits = Limits {
+        max_depth: Some(10),
+        ..Limits::default()
+    };
+    for src in &[
+        "a = ((((1))))",
+        "a = 1 + 2 + 3 + 4 + 5 + 6 + 7",
+        "a = b.c.d |> e()",
+    ] {
+        let file = parse_string_with_limits("", src, limits).unwrap();
+        assert_eq!(
+            check_depth(walk::Node::File(&file), limits.max_depth),
+            Ok(())
+        );
+    }
+}
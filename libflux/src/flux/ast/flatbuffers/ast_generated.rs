@@ -706,6 +706,65 @@ pub mod fbast {
         }
     }
 
+    // struct Span, aligned to 4
+    #[repr(C, align(4))]
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Span {
+        start_: u32,
+        end_: u32,
+    } // pub struct Span
+    impl flatbuffers::SafeSliceAccess for Span {}
+    impl<'a> flatbuffers::Follow<'a> for Span {
+        type Inner = &'a Span;
+        #[inline]
+        fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+            <&'a Span>::follow(buf, loc)
+        }
+    }
+    impl<'a> flatbuffers::Follow<'a> for &'a Span {
+        type Inner = &'a Span;
+        #[inline]
+        fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+            flatbuffers::follow_cast_ref::<Span>(buf, loc)
+        }
+    }
+    impl<'b> flatbuffers::Push for Span {
+        type Output = Span;
+        #[inline]
+        fn push(&self, dst: &mut [u8], _rest: &[u8]) {
+            let src = unsafe {
+                ::std::slice::from_raw_parts(self as *const Span as *const u8, Self::size())
+            };
+            dst.copy_from_slice(src);
+        }
+    }
+    impl<'b> flatbuffers::Push for &'b Span {
+        type Output = Span;
+
+        #[inline]
+        fn push(&self, dst: &mut [u8], _rest: &[u8]) {
+            let src = unsafe {
+                ::std::slice::from_raw_parts(*self as *const Span as *const u8, Self::size())
+            };
+            dst.copy_from_slice(src);
+        }
+    }
+
+    impl Span {
+        pub fn new<'a>(_start: u32, _end: u32) -> Self {
+            Span {
+                start_: _start.to_little_endian(),
+                end_: _end.to_little_endian(),
+            }
+        }
+        pub fn start<'a>(&'a self) -> u32 {
+            self.start_.from_little_endian()
+        }
+        pub fn end<'a>(&'a self) -> u32 {
+            self.end_.from_little_endian()
+        }
+    }
+
     pub enum SourceLocationOffset {}
     #[derive(Copy, Clone, Debug, PartialEq)]
 
@@ -734,6 +793,9 @@ pub mod fbast {
             args: &'args SourceLocationArgs<'args>,
         ) -> flatbuffers::WIPOffset<SourceLocation<'bldr>> {
             let mut builder = SourceLocationBuilder::new(_fbb);
+            if let Some(x) = args.span {
+                builder.add_span(x);
+            }
             if let Some(x) = args.source {
                 builder.add_source(x);
             }
@@ -753,6 +815,7 @@ pub mod fbast {
         pub const VT_START: flatbuffers::VOffsetT = 6;
         pub const VT_END: flatbuffers::VOffsetT = 8;
         pub const VT_SOURCE: flatbuffers::VOffsetT = 10;
+        pub const VT_SPAN: flatbuffers::VOffsetT = 12;
 
         #[inline]
         pub fn file(&self) -> Option<&'a str> {
@@ -772,6 +835,10 @@ pub mod fbast {
             self._tab
                 .get::<flatbuffers::ForwardsUOffset<&str>>(SourceLocation::VT_SOURCE, None)
         }
+        #[inline]
+        pub fn span(&self) -> Option<&'a Span> {
+            self._tab.get::<Span>(SourceLocation::VT_SPAN, None)
+        }
     }
 
     pub struct SourceLocationArgs<'a> {
@@ -779,6 +846,7 @@ pub mod fbast {
         pub start: Option<&'a Position>,
         pub end: Option<&'a Position>,
         pub source: Option<flatbuffers::WIPOffset<&'a str>>,
+        pub span: Option<&'a Span>,
     }
     impl<'a> Default for SourceLocationArgs<'a> {
         #[inline]
@@ -788,6 +856,7 @@ pub mod fbast {
                 start: None,
                 end: None,
                 source: None,
+                span: None,
             }
         }
     }
@@ -817,6 +886,11 @@ pub mod fbast {
                 .push_slot_always::<flatbuffers::WIPOffset<_>>(SourceLocation::VT_SOURCE, source);
         }
         #[inline]
+        pub fn add_span(&mut self, span: &'b Span) {
+            self.fbb_
+                .push_slot_always::<&Span>(SourceLocation::VT_SPAN, span);
+        }
+        #[inline]
         pub fn new(
             _fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>,
         ) -> SourceLocationBuilder<'a, 'b> {
@@ -885,6 +885,7 @@ impl<'a> SerializingVisitorState<'a> {
                     loc.end.column as i32,
                 )),
                 source,
+                span: Some(&fbast::Span::new(loc.span.start, loc.span.end)),
             },
         ))
     }
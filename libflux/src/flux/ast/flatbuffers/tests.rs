@@ -906,6 +906,21 @@ fn compare_loc(
     compare_pos(&ast_loc.start, &fb_loc.start())?;
     compare_pos(&ast_loc.end, &fb_loc.end())?;
     compare_opt_strings("source location source", &ast_loc.source, &fb_loc.source())?;
+    compare_span(&ast_loc.span, &fb_loc.span())?;
+    Ok(())
+}
+
+fn compare_span(ast_span: &ast::Span, fb_span: &Option<&fbast::Span>) -> Result<(), String> {
+    let fb_span = unwrap_or_fail("span", fb_span)?;
+    if ast_span.start != fb_span.start() || ast_span.end != fb_span.end() {
+        return Err(format!(
+            "ast span is {}..{}, fb is {}..{}",
+            ast_span.start,
+            ast_span.end,
+            fb_span.start(),
+            fb_span.end()
+        ));
+    }
     Ok(())
 }
 
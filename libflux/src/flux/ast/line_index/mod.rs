@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ast::{Position, SourceLocation, Span};
+
+// PositionEncoding is the unit in which the columns of a position count.
+// The scanner counts UTF-8 bytes; editors speaking LSP, and JavaScript
+// strings, count UTF-16 code units.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PositionEncoding {
+    Utf8,
+    Utf16,
+}
+
+impl Default for PositionEncoding {
+    fn default() -> Self {
+        PositionEncoding::Utf8
+    }
+}
+
+impl FromStr for PositionEncoding {
+    type Err = String;
+
+    // from_str accepts the encoding names used by LSP.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "utf-8" => Ok(PositionEncoding::Utf8),
+            "utf-16" => Ok(PositionEncoding::Utf16),
+            _ => Err(format!("unknown position encoding {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for PositionEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            PositionEncoding::Utf8 => "utf-8",
+            PositionEncoding::Utf16 => "utf-16",
+        })
+    }
+}
+
+// LineIndex maps between byte offsets into a source file and the line and
+// column positions used by the AST. Columns count UTF-8 bytes from the start
+// of the line, as the scanner does, unless an encoding is given.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineIndex {
+    // The byte offset at which each line starts.
+    starts: Vec<u32>,
+    // The multi-byte characters of each line that has any, by line index.
+    wide: HashMap<u32, Vec<WideChar>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct WideChar {
+    // The byte column at which the character starts, from 0.
+    start: u32,
+    // The length of the character in bytes.
+    len: u32,
+}
+
+impl WideChar {
+    fn len_utf16(&self) -> u32 {
+        if self.len == 4 {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+impl LineIndex {
+    pub fn new(src: &str) -> LineIndex {
+        let mut starts = vec![0];
+        let mut wide: HashMap<u32, Vec<WideChar>> = HashMap::new();
+        for (i, c) in src.char_indices() {
+            let i = i as u32;
+            if c == '\n' {
+                starts.push(i + 1);
+            } else if !c.is_ascii() {
+                let line = starts.len() as u32 - 1;
+                wide.entry(line).or_default().push(WideChar {
+                    start: i - starts[line as usize],
+                    len: c.len_utf8() as u32,
+                });
+            }
+        }
+        LineIndex { starts, wide }
+    }
+
+    // line returns the line, starting from 1, that contains an offset.
+    pub fn line(&self, offset: u32) -> u32 {
+        match self.starts.binary_search(&offset) {
+            Ok(i) => i as u32 + 1,
+            Err(i) => i as u32,
+        }
+    }
+
+    pub fn position(&self, offset: u32) -> Position {
+        self.position_in(offset, PositionEncoding::Utf8)
+    }
+
+    // position_in returns the position of an offset with its column counted
+    // in the given encoding.
+    pub fn position_in(&self, offset: u32, encoding: PositionEncoding) -> Position {
+        let line = self.line(offset);
+        let mut column = offset - self.starts[line as usize - 1];
+        if encoding == PositionEncoding::Utf16 {
+            for c in self.wide_chars(line) {
+                if c.start >= column {
+                    break;
+                }
+                column -= c.len - c.len_utf16();
+            }
+        }
+        Position {
+            line,
+            column: column + 1,
+        }
+    }
+
+    pub fn offset(&self, pos: &Position) -> u32 {
+        self.offset_in(pos, PositionEncoding::Utf8)
+    }
+
+    // offset_in returns the offset of a position whose column is counted in
+    // the given encoding.
+    pub fn offset_in(&self, pos: &Position, encoding: PositionEncoding) -> u32 {
+        let mut column = pos.column - 1;
+        if encoding == PositionEncoding::Utf16 {
+            for c in self.wide_chars(pos.line) {
+                if c.start >= column {
+                    break;
+                }
+                column += c.len - c.len_utf16();
+            }
+        }
+        self.starts[pos.line as usize - 1] + column
+    }
+
+    // convert re-counts the column of a position in another encoding.
+    pub fn convert(
+        &self,
+        pos: &Position,
+        from: PositionEncoding,
+        to: PositionEncoding,
+    ) -> Position {
+        if from == to {
+            return pos.clone();
+        }
+        self.position_in(self.offset_in(pos, from), to)
+    }
+
+    pub fn location(&self, file: Option<String>, span: Span) -> SourceLocation {
+        self.location_in(file, span, PositionEncoding::Utf8)
+    }
+
+    // location_in computes the source location of a span, with its columns
+    // counted in the given encoding.
+    pub fn location_in(
+        &self,
+        file: Option<String>,
+        span: Span,
+        encoding: PositionEncoding,
+    ) -> SourceLocation {
+        SourceLocation {
+            file,
+            start: self.position_in(span.start, encoding),
+            end: self.position_in(span.end, encoding),
+            span,
+            source: None,
+        }
+    }
+
+    fn wide_chars(&self, line: u32) -> &[WideChar] {
+        match self.wide.get(&(line - 1)) {
+            Some(chars) => chars,
+            None => &[],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,78 @@
+use super::*;
+
+#[test]
+fn positions_and_offsets() {
+    let src = "a = 1\n\nb = \"é\"\nc";
+    let index = LineIndex::new(src);
+    let cases = vec![
+        (0, 1, 1),
+        (4, 1, 5),
+        (5, 1, 6),
+        (6, 2, 1),
+        (7, 3, 1),
+        (11, 3, 5),
+        // é is two bytes long, so the closing quote is three columns on.
+        (14, 3, 8),
+        (15, 3, 9),
+        (16, 4, 1),
+        (17, 4, 2),
+    ];
+    for (offset, line, column) in cases {
+        let pos = Position { line, column };
+        assert_eq!(index.position(offset), pos, "offset {}", offset);
+        assert_eq!(index.offset(&pos), offset, "position {:?}", pos);
+    }
+}
+
+#[test]
+fn location_of_span() {
+    let src = "x = 1\ny = x +\n    2\n";
+    let index = LineIndex::new(src);
+    let span = Span { start: 6, end: 19 };
+    let loc = index.location(Some(String::from("a.flux")), span);
+    assert_eq!(loc.to_string(), "a.flux@2:1-3:6");
+    assert_eq!(loc.text(src), "y = x +\n    2");
+}
+
+#[test]
+fn utf16_columns() {
+    // é is one UTF-16 code unit and 😀 is two, a surrogate pair.
+    let src = "x = \"é😀\" + y\nz";
+    let index = LineIndex::new(src);
+    let cases = vec![
+        // offset, UTF-8 column, UTF-16 column
+        (0, 1, 1),
+        (5, 6, 6),
+        (7, 8, 7),
+        (11, 12, 9),
+        (14, 15, 12),
+        (15, 16, 13),
+        (16, 17, 14),
+        (17, 1, 1),
+    ];
+    for (offset, utf8, utf16) in cases {
+        let line = index.line(offset);
+        let pos8 = Position { line, column: utf8 };
+        let pos16 = Position {
+            line,
+            column: utf16,
+        };
+        assert_eq!(index.position_in(offset, PositionEncoding::Utf8), pos8);
+        assert_eq!(index.position_in(offset, PositionEncoding::Utf16), pos16);
+        assert_eq!(index.offset_in(&pos16, PositionEncoding::Utf16), offset);
+        assert_eq!(
+            index.convert(&pos8, PositionEncoding::Utf8, PositionEncoding::Utf16),
+            pos16
+        );
+    }
+}
+
+#[test]
+fn encoding_names() {
+    assert_eq!("utf-16".parse(), Ok(PositionEncoding::Utf16));
+    assert_eq!(PositionEncoding::Utf8.to_string(), "utf-8");
+    assert_eq!(
+        "utf-32".parse::<PositionEncoding>(),
+        Err(String::from("unknown position encoding \"utf-32\""))
+    );
+}
@@ -1,8 +1,13 @@
 #![allow(missing_docs)]
+pub mod build;
 pub mod check;
 
 pub mod flatbuffers;
+pub mod line_index;
 pub mod walk;
+pub mod walk_mut;
+
+pub use line_index::{LineIndex, PositionEncoding};
 
 use crate::scanner;
 use std::collections::HashMap;
@@ -61,19 +66,36 @@ impl Default for Position {
     }
 }
 
+// Span is a range of byte offsets into the source of a file.
+#[derive(Debug, Default, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Span {
+    pub start: u32,
+    pub end: u32,
+}
+
 // SourceLocation represents the location of a node in the AST
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 pub struct SourceLocation {
-    pub file: Option<String>,   // File is the optional file name.
-    pub start: Position,        // Start is the location in the source the node starts.
-    pub end: Position,          // End is the location in the source the node ends.
-    pub source: Option<String>, // Source is optional raw source.
+    pub file: Option<String>, // File is the optional file name.
+    pub start: Position,      // Start is the location in the source the node starts.
+    pub end: Position,        // End is the location in the source the node ends.
+    #[serde(default)]
+    pub span: Span, // Span is the byte range of the node in the source.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub source: Option<String>, // Source is optional raw source, see Parser::with_source.
 }
 
 impl SourceLocation {
     pub fn is_valid(&self) -> bool {
         self.start.is_valid() && self.end.is_valid()
     }
+
+    // text returns the raw source of the node from the source of its file.
+    pub fn text<'a>(&self, src: &'a str) -> &'a str {
+        src.get(self.span.start as usize..self.span.end as usize)
+            .unwrap_or("")
+    }
 }
 
 impl fmt::Display for SourceLocation {
@@ -184,6 +206,36 @@ impl Expression {
             Expression::Paren(wrapped) => &wrapped.base,
         }
     }
+
+    // `base_mut` returns the BaseNode for an Expression, to be modified.
+    pub fn base_mut(&mut self) -> &mut BaseNode {
+        match self {
+            Expression::Identifier(wrapped) => &mut wrapped.base,
+            Expression::Array(wrapped) => &mut wrapped.base,
+            Expression::Function(wrapped) => &mut wrapped.base,
+            Expression::Logical(wrapped) => &mut wrapped.base,
+            Expression::Object(wrapped) => &mut wrapped.base,
+            Expression::Member(wrapped) => &mut wrapped.base,
+            Expression::Index(wrapped) => &mut wrapped.base,
+            Expression::Binary(wrapped) => &mut wrapped.base,
+            Expression::Unary(wrapped) => &mut wrapped.base,
+            Expression::PipeExpr(wrapped) => &mut wrapped.base,
+            Expression::Call(wrapped) => &mut wrapped.base,
+            Expression::Conditional(wrapped) => &mut wrapped.base,
+            Expression::Integer(wrapped) => &mut wrapped.base,
+            Expression::Float(wrapped) => &mut wrapped.base,
+            Expression::StringLit(wrapped) => &mut wrapped.base,
+            Expression::Duration(wrapped) => &mut wrapped.base,
+            Expression::Uint(wrapped) => &mut wrapped.base,
+            Expression::Boolean(wrapped) => &mut wrapped.base,
+            Expression::DateTime(wrapped) => &mut wrapped.base,
+            Expression::Regexp(wrapped) => &mut wrapped.base,
+            Expression::PipeLit(wrapped) => &mut wrapped.base,
+            Expression::Bad(wrapped) => &mut wrapped.base,
+            Expression::StringExpr(wrapped) => &mut wrapped.base,
+            Expression::Paren(wrapped) => &mut wrapped.base,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -219,6 +271,19 @@ impl Statement {
         }
     }
 
+    // `base_mut` returns the BaseNode for a Statement, to be modified.
+    pub fn base_mut(&mut self) -> &mut BaseNode {
+        match self {
+            Statement::Expr(wrapped) => &mut wrapped.base,
+            Statement::Variable(wrapped) => &mut wrapped.base,
+            Statement::Option(wrapped) => &mut wrapped.base,
+            Statement::Return(wrapped) => &mut wrapped.base,
+            Statement::Bad(wrapped) => &mut wrapped.base,
+            Statement::Test(wrapped) => &mut wrapped.base,
+            Statement::Builtin(wrapped) => &mut wrapped.base,
+        }
+    }
+
     // returns a integer based type value.
     pub fn typ(&self) -> i8 {
         match self {
@@ -268,6 +333,14 @@ impl PropertyKey {
             PropertyKey::StringLit(wrapped) => &wrapped.base,
         }
     }
+
+    // `name` returns the label a PropertyKey stands for.
+    pub fn name(&self) -> &str {
+        match self {
+            PropertyKey::Identifier(id) => &id.name,
+            PropertyKey::StringLit(lit) => &lit.value,
+        }
+    }
 }
 
 // This matches the grammar, and not ast.go:
@@ -321,6 +394,14 @@ impl BaseNode {
     }
 }
 
+// Comment is a line comment of the source, as kept by a parser made with
+// Parser::with_comments. Its text starts with the // and has no newline.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    pub text: String,
+    pub location: SourceLocation,
+}
+
 // Package represents a complete package source tree
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -370,7 +451,7 @@ pub struct File {
 }
 
 impl File {
-    fn get_package(self: &File) -> &str {
+    pub fn get_package(self: &File) -> &str {
         match &self.package {
             Some(pkg_clause) => pkg_clause.name.name.as_str(),
             None => DEFAULT_PACKAGE_NAME,
@@ -1373,6 +1373,7 @@ fn test_object_expression_with_source_locations_and_errors() {
                     line: 1,
                     column: 13,
                 },
+                span: Span { start: 0, end: 12 },
                 source: Some("{a: \"hello\"}".to_string()),
             },
             errors: vec![],
@@ -1387,6 +1388,7 @@ fn test_object_expression_with_source_locations_and_errors() {
                         line: 1,
                         column: 12,
                     },
+                    span: Span { start: 1, end: 11 },
                     source: Some("a: \"hello\"".to_string()),
                 },
                 errors: vec!["an error".to_string()],
@@ -1397,6 +1399,7 @@ fn test_object_expression_with_source_locations_and_errors() {
                         file: Some("foo.flux".to_string()),
                         start: Position { line: 1, column: 2 },
                         end: Position { line: 1, column: 3 },
+                        span: Span { start: 1, end: 2 },
                         source: Some("a".to_string()),
                     },
                     errors: vec![],
@@ -1412,6 +1415,7 @@ fn test_object_expression_with_source_locations_and_errors() {
                             line: 1,
                             column: 12,
                         },
+                        span: Span { start: 4, end: 11 },
                         source: Some("\"hello\"".to_string()),
                     },
                     errors: vec!["an error".to_string(), "another error".to_string()],
@@ -1423,7 +1427,7 @@ fn test_object_expression_with_source_locations_and_errors() {
     let serialized = serde_json::to_string(&n).unwrap();
     assert_eq!(
         serialized,
-        r#"{"type":"ObjectExpression","location":{"file":"foo.flux","start":{"line":1,"column":1},"end":{"line":1,"column":13},"source":"{a: \"hello\"}"},"properties":[{"type":"Property","location":{"file":"foo.flux","start":{"line":1,"column":2},"end":{"line":1,"column":12},"source":"a: \"hello\""},"errors":[{"msg":"an error"}],"key":{"type":"Identifier","location":{"file":"foo.flux","start":{"line":1,"column":2},"end":{"line":1,"column":3},"source":"a"},"name":"a"},"value":{"type":"StringLiteral","location":{"file":"foo.flux","start":{"line":1,"column":5},"end":{"line":1,"column":12},"source":"\"hello\""},"errors":[{"msg":"an error"},{"msg":"another error"}],"value":"hello"}}]}"#
+        r#"{"type":"ObjectExpression","location":{"file":"foo.flux","start":{"line":1,"column":1},"end":{"line":1,"column":13},"span":{"start":0,"end":12},"source":"{a: \"hello\"}"},"properties":[{"type":"Property","location":{"file":"foo.flux","start":{"line":1,"column":2},"end":{"line":1,"column":12},"span":{"start":1,"end":11},"source":"a: \"hello\""},"errors":[{"msg":"an error"}],"key":{"type":"Identifier","location":{"file":"foo.flux","start":{"line":1,"column":2},"end":{"line":1,"column":3},"span":{"start":1,"end":2},"source":"a"},"name":"a"},"value":{"type":"StringLiteral","location":{"file":"foo.flux","start":{"line":1,"column":5},"end":{"line":1,"column":12},"span":{"start":4,"end":11},"source":"\"hello\""},"errors":[{"msg":"an error"},{"msg":"another error"}],"value":"hello"}}]}"#
     );
     // TODO(affo): leaving proper error deserialization for the future.
     // let deserialized: ObjectExpr = serde_json::from_str(serialized.as_str()).unwrap();
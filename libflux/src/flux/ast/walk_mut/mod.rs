@@ -0,0 +1,540 @@
+#[cfg(test)]
+mod tests;
+
+use crate::ast::*;
+use std::mem;
+
+/// NodeMut represents any structure that can appear in the AST.
+/// It also enables mutability of the wrapped AST node.
+#[derive(Debug)]
+pub enum NodeMut<'a> {
+    Package(&'a mut Package),
+    File(&'a mut File),
+    PackageClause(&'a mut PackageClause),
+    ImportDeclaration(&'a mut ImportDeclaration),
+
+    // Expressions
+    Identifier(&'a mut Identifier),
+
+    ArrayExpr(&'a mut ArrayExpr),
+    FunctionExpr(&'a mut FunctionExpr),
+    LogicalExpr(&'a mut LogicalExpr),
+    ObjectExpr(&'a mut ObjectExpr),
+    MemberExpr(&'a mut MemberExpr),
+    IndexExpr(&'a mut IndexExpr),
+    BinaryExpr(&'a mut BinaryExpr),
+    UnaryExpr(&'a mut UnaryExpr),
+    PipeExpr(&'a mut PipeExpr),
+    CallExpr(&'a mut CallExpr),
+    ConditionalExpr(&'a mut ConditionalExpr),
+    StringExpr(&'a mut StringExpr),
+    ParenExpr(&'a mut ParenExpr),
+
+    IntegerLit(&'a mut IntegerLit),
+    FloatLit(&'a mut FloatLit),
+    StringLit(&'a mut StringLit),
+    DurationLit(&'a mut DurationLit),
+    UintLit(&'a mut UintLit),
+    BooleanLit(&'a mut BooleanLit),
+    DateTimeLit(&'a mut DateTimeLit),
+    RegexpLit(&'a mut RegexpLit),
+    PipeLit(&'a mut PipeLit),
+
+    BadExpr(&'a mut BadExpr),
+
+    // Statements
+    ExprStmt(&'a mut ExprStmt),
+    OptionStmt(&'a mut OptionStmt),
+    ReturnStmt(&'a mut ReturnStmt),
+    BadStmt(&'a mut BadStmt),
+    TestStmt(&'a mut TestStmt),
+    BuiltinStmt(&'a mut BuiltinStmt),
+
+    // FunctionBlock
+    Block(&'a mut Block),
+
+    // Property
+    Property(&'a mut Property),
+
+    // StringExprPart
+    TextPart(&'a mut TextPart),
+    InterpolatedPart(&'a mut InterpolatedPart),
+
+    // Assignment
+    VariableAssgn(&'a mut VariableAssgn),
+    MemberAssgn(&'a mut MemberAssgn),
+}
+
+impl<'a> fmt::Display for NodeMut<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NodeMut::Package(_) => write!(f, "Package"),
+            NodeMut::File(_) => write!(f, "File"),
+            NodeMut::PackageClause(_) => write!(f, "PackageClause"),
+            NodeMut::ImportDeclaration(_) => write!(f, "ImportDeclaration"),
+            NodeMut::Identifier(_) => write!(f, "Identifier"),
+            NodeMut::ArrayExpr(_) => write!(f, "ArrayExpr"),
+            NodeMut::FunctionExpr(_) => write!(f, "FunctionExpr"),
+            NodeMut::LogicalExpr(_) => write!(f, "LogicalExpr"),
+            NodeMut::ObjectExpr(_) => write!(f, "ObjectExpr"),
+            NodeMut::MemberExpr(_) => write!(f, "MemberExpr"),
+            NodeMut::IndexExpr(_) => write!(f, "IndexExpr"),
+            NodeMut::BinaryExpr(_) => write!(f, "BinaryExpr"),
+            NodeMut::UnaryExpr(_) => write!(f, "UnaryExpr"),
+            NodeMut::PipeExpr(_) => write!(f, "PipeExpr"),
+            NodeMut::CallExpr(_) => write!(f, "CallExpr"),
+            NodeMut::ConditionalExpr(_) => write!(f, "ConditionalExpr"),
+            NodeMut::StringExpr(_) => write!(f, "StringExpr"),
+            NodeMut::ParenExpr(_) => write!(f, "ParenExpr"),
+            NodeMut::IntegerLit(_) => write!(f, "IntegerLit"),
+            NodeMut::FloatLit(_) => write!(f, "FloatLit"),
+            NodeMut::StringLit(_) => write!(f, "StringLit"),
+            NodeMut::DurationLit(_) => write!(f, "DurationLit"),
+            NodeMut::UintLit(_) => write!(f, "UintLit"),
+            NodeMut::BooleanLit(_) => write!(f, "BooleanLit"),
+            NodeMut::DateTimeLit(_) => write!(f, "DateTimeLit"),
+            NodeMut::RegexpLit(_) => write!(f, "RegexpLit"),
+            NodeMut::PipeLit(_) => write!(f, "PipeLit"),
+            NodeMut::BadExpr(_) => write!(f, "BadExpr"),
+            NodeMut::ExprStmt(_) => write!(f, "ExprStmt"),
+            NodeMut::OptionStmt(_) => write!(f, "OptionStmt"),
+            NodeMut::ReturnStmt(_) => write!(f, "ReturnStmt"),
+            NodeMut::BadStmt(_) => write!(f, "BadStmt"),
+            NodeMut::TestStmt(_) => write!(f, "TestStmt"),
+            NodeMut::BuiltinStmt(_) => write!(f, "BuiltinStmt"),
+            NodeMut::Block(_) => write!(f, "Block"),
+            NodeMut::Property(_) => write!(f, "Property"),
+            NodeMut::TextPart(_) => write!(f, "TextPart"),
+            NodeMut::InterpolatedPart(_) => write!(f, "InterpolatedPart"),
+            NodeMut::VariableAssgn(_) => write!(f, "VariableAssgn"),
+            NodeMut::MemberAssgn(_) => write!(f, "MemberAssgn"),
+        }
+    }
+}
+
+impl<'a> NodeMut<'a> {
+    pub fn base(&self) -> &BaseNode {
+        match self {
+            NodeMut::Package(n) => &n.base,
+            NodeMut::File(n) => &n.base,
+            NodeMut::PackageClause(n) => &n.base,
+            NodeMut::ImportDeclaration(n) => &n.base,
+            NodeMut::Identifier(n) => &n.base,
+            NodeMut::ArrayExpr(n) => &n.base,
+            NodeMut::FunctionExpr(n) => &n.base,
+            NodeMut::LogicalExpr(n) => &n.base,
+            NodeMut::ObjectExpr(n) => &n.base,
+            NodeMut::MemberExpr(n) => &n.base,
+            NodeMut::IndexExpr(n) => &n.base,
+            NodeMut::BinaryExpr(n) => &n.base,
+            NodeMut::UnaryExpr(n) => &n.base,
+            NodeMut::PipeExpr(n) => &n.base,
+            NodeMut::CallExpr(n) => &n.base,
+            NodeMut::ConditionalExpr(n) => &n.base,
+            NodeMut::StringExpr(n) => &n.base,
+            NodeMut::ParenExpr(n) => &n.base,
+            NodeMut::IntegerLit(n) => &n.base,
+            NodeMut::FloatLit(n) => &n.base,
+            NodeMut::StringLit(n) => &n.base,
+            NodeMut::DurationLit(n) => &n.base,
+            NodeMut::UintLit(n) => &n.base,
+            NodeMut::BooleanLit(n) => &n.base,
+            NodeMut::DateTimeLit(n) => &n.base,
+            NodeMut::RegexpLit(n) => &n.base,
+            NodeMut::PipeLit(n) => &n.base,
+            NodeMut::BadExpr(n) => &n.base,
+            NodeMut::ExprStmt(n) => &n.base,
+            NodeMut::OptionStmt(n) => &n.base,
+            NodeMut::ReturnStmt(n) => &n.base,
+            NodeMut::BadStmt(n) => &n.base,
+            NodeMut::TestStmt(n) => &n.base,
+            NodeMut::BuiltinStmt(n) => &n.base,
+            NodeMut::Block(n) => &n.base,
+            NodeMut::Property(n) => &n.base,
+            NodeMut::TextPart(n) => &n.base,
+            NodeMut::InterpolatedPart(n) => &n.base,
+            NodeMut::VariableAssgn(n) => &n.base,
+            NodeMut::MemberAssgn(n) => &n.base,
+        }
+    }
+
+    pub fn base_mut(&mut self) -> &mut BaseNode {
+        match self {
+            NodeMut::Package(n) => &mut n.base,
+            NodeMut::File(n) => &mut n.base,
+            NodeMut::PackageClause(n) => &mut n.base,
+            NodeMut::ImportDeclaration(n) => &mut n.base,
+            NodeMut::Identifier(n) => &mut n.base,
+            NodeMut::ArrayExpr(n) => &mut n.base,
+            NodeMut::FunctionExpr(n) => &mut n.base,
+            NodeMut::LogicalExpr(n) => &mut n.base,
+            NodeMut::ObjectExpr(n) => &mut n.base,
+            NodeMut::MemberExpr(n) => &mut n.base,
+            NodeMut::IndexExpr(n) => &mut n.base,
+            NodeMut::BinaryExpr(n) => &mut n.base,
+            NodeMut::UnaryExpr(n) => &mut n.base,
+            NodeMut::PipeExpr(n) => &mut n.base,
+            NodeMut::CallExpr(n) => &mut n.base,
+            NodeMut::ConditionalExpr(n) => &mut n.base,
+            NodeMut::StringExpr(n) => &mut n.base,
+            NodeMut::ParenExpr(n) => &mut n.base,
+            NodeMut::IntegerLit(n) => &mut n.base,
+            NodeMut::FloatLit(n) => &mut n.base,
+            NodeMut::StringLit(n) => &mut n.base,
+            NodeMut::DurationLit(n) => &mut n.base,
+            NodeMut::UintLit(n) => &mut n.base,
+            NodeMut::BooleanLit(n) => &mut n.base,
+            NodeMut::DateTimeLit(n) => &mut n.base,
+            NodeMut::RegexpLit(n) => &mut n.base,
+            NodeMut::PipeLit(n) => &mut n.base,
+            NodeMut::BadExpr(n) => &mut n.base,
+            NodeMut::ExprStmt(n) => &mut n.base,
+            NodeMut::OptionStmt(n) => &mut n.base,
+            NodeMut::ReturnStmt(n) => &mut n.base,
+            NodeMut::BadStmt(n) => &mut n.base,
+            NodeMut::TestStmt(n) => &mut n.base,
+            NodeMut::BuiltinStmt(n) => &mut n.base,
+            NodeMut::Block(n) => &mut n.base,
+            NodeMut::Property(n) => &mut n.base,
+            NodeMut::TextPart(n) => &mut n.base,
+            NodeMut::InterpolatedPart(n) => &mut n.base,
+            NodeMut::VariableAssgn(n) => &mut n.base,
+            NodeMut::MemberAssgn(n) => &mut n.base,
+        }
+    }
+
+    /// invalidate_location replaces the location of this node with an invalid one,
+    /// keeping only the file name. Use it on nodes whose source text no longer
+    /// matches what they represent.
+    pub fn invalidate_location(&mut self) {
+        invalidate_location(self.base_mut());
+    }
+}
+
+impl<'a> NodeMut<'a> {
+    pub fn from_expr(expr: &'a mut Expression) -> NodeMut<'a> {
+        match expr {
+            Expression::Identifier(e) => NodeMut::Identifier(e),
+            Expression::Array(e) => NodeMut::ArrayExpr(e),
+            Expression::Function(e) => NodeMut::FunctionExpr(e),
+            Expression::Logical(e) => NodeMut::LogicalExpr(e),
+            Expression::Object(e) => NodeMut::ObjectExpr(e),
+            Expression::Member(e) => NodeMut::MemberExpr(e),
+            Expression::Index(e) => NodeMut::IndexExpr(e),
+            Expression::Binary(e) => NodeMut::BinaryExpr(e),
+            Expression::Unary(e) => NodeMut::UnaryExpr(e),
+            Expression::PipeExpr(e) => NodeMut::PipeExpr(e),
+            Expression::Call(e) => NodeMut::CallExpr(e),
+            Expression::Conditional(e) => NodeMut::ConditionalExpr(e),
+            Expression::StringExpr(e) => NodeMut::StringExpr(e),
+            Expression::Paren(e) => NodeMut::ParenExpr(e),
+            Expression::Integer(e) => NodeMut::IntegerLit(e),
+            Expression::Float(e) => NodeMut::FloatLit(e),
+            Expression::StringLit(e) => NodeMut::StringLit(e),
+            Expression::Duration(e) => NodeMut::DurationLit(e),
+            Expression::Uint(e) => NodeMut::UintLit(e),
+            Expression::Boolean(e) => NodeMut::BooleanLit(e),
+            Expression::DateTime(e) => NodeMut::DateTimeLit(e),
+            Expression::Regexp(e) => NodeMut::RegexpLit(e),
+            Expression::PipeLit(e) => NodeMut::PipeLit(e),
+            Expression::Bad(e) => NodeMut::BadExpr(e),
+        }
+    }
+    pub fn from_stmt(stmt: &'a mut Statement) -> NodeMut<'a> {
+        match stmt {
+            Statement::Expr(s) => NodeMut::ExprStmt(s),
+            Statement::Variable(s) => NodeMut::VariableAssgn(s),
+            Statement::Option(s) => NodeMut::OptionStmt(s),
+            Statement::Return(s) => NodeMut::ReturnStmt(s),
+            Statement::Bad(s) => NodeMut::BadStmt(s),
+            Statement::Test(s) => NodeMut::TestStmt(s),
+            Statement::Builtin(s) => NodeMut::BuiltinStmt(s),
+        }
+    }
+    fn from_property_key(pk: &'a mut PropertyKey) -> NodeMut<'a> {
+        match pk {
+            PropertyKey::Identifier(i) => NodeMut::Identifier(i),
+            PropertyKey::StringLit(s) => NodeMut::StringLit(s),
+        }
+    }
+    fn from_string_expr_part(sp: &'a mut StringExprPart) -> NodeMut<'a> {
+        match sp {
+            StringExprPart::Text(t) => NodeMut::TextPart(t),
+            StringExprPart::Interpolated(e) => NodeMut::InterpolatedPart(e),
+        }
+    }
+    fn from_assignment(a: &'a mut Assignment) -> NodeMut<'a> {
+        match a {
+            Assignment::Variable(v) => NodeMut::VariableAssgn(v),
+            Assignment::Member(m) => NodeMut::MemberAssgn(m),
+        }
+    }
+}
+
+/// VisitorMut is used by `walk_mut` to recursively visit an AST and mutate it.
+/// One can implement VisitorMut or use a `FnMut(&mut NodeMut)`.
+///
+/// Besides mutating nodes while visiting them, a VisitorMut can rewrite the
+/// tree: every place that holds an `Expression` or a `Statement` is offered to
+/// `rewrite_expr` or `rewrite_stmt` once its node has been walked, and a
+/// returned node takes its place.
+///
+/// # Examples
+///
+/// A VisitorMut that replaces every integer literal with a float literal:
+///
+/// ```
+/// use flux::ast::walk_mut::{walk_mut, NodeMut, VisitorMut};
+/// use flux::ast::{BaseNode, Expression, FloatLit};
+///
+/// struct IntToFloat;
+///
+/// impl VisitorMut for IntToFloat {
+///     fn visit(&mut self, _: &mut NodeMut) -> bool {
+///         true
+///     }
+///     fn rewrite_expr(&mut self, expr: &mut Expression) -> Option<Expression> {
+///         match expr {
+///             Expression::Integer(lit) => Some(Expression::Float(FloatLit {
+///                 base: BaseNode::default(),
+///                 value: lit.value as f64,
+///             })),
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// let mut file = flux::parser::parse_string("", "a = 1 + 2");
+/// walk_mut(&mut IntToFloat, &mut NodeMut::File(&mut file));
+/// ```
+pub trait VisitorMut: Sized {
+    /// Visit is called for a node.
+    /// When the VisitorMut is used in function `walk_mut`, the boolean value returned
+    /// is used to continue (true) or stop (false) walking.
+    fn visit(&mut self, node: &mut NodeMut) -> bool;
+    /// Done is called for a node once it has been visited along with all of its children.
+    /// The default is to do nothing.
+    fn done(&mut self, _: &mut NodeMut) {}
+    /// rewrite_expr is called for an expression after `done`. If it returns an
+    /// expression, that expression replaces the visited one and its location is
+    /// invalidated. The replacement is not walked.
+    /// The default is to keep the expression.
+    fn rewrite_expr(&mut self, _: &mut Expression) -> Option<Expression> {
+        None
+    }
+    /// rewrite_stmt is the counterpart of `rewrite_expr` for statements.
+    fn rewrite_stmt(&mut self, _: &mut Statement) -> Option<Statement> {
+        None
+    }
+}
+
+/// `walk_mut` recursively visits children of a node given a VisitorMut.
+/// Nodes are visited in depth-first order.
+pub fn walk_mut<T>(v: &mut T, node: &mut NodeMut)
+where
+    T: VisitorMut,
+{
+    if v.visit(node) {
+        match node {
+            NodeMut::Package(n) => {
+                for file in n.files.iter_mut() {
+                    walk_mut(v, &mut NodeMut::File(file));
+                }
+            }
+            NodeMut::File(n) => {
+                if let Some(pkg) = n.package.as_mut() {
+                    walk_mut(v, &mut NodeMut::PackageClause(pkg));
+                }
+                for imp in n.imports.iter_mut() {
+                    walk_mut(v, &mut NodeMut::ImportDeclaration(imp));
+                }
+                for stmt in n.body.iter_mut() {
+                    walk_stmt(v, stmt);
+                }
+            }
+            NodeMut::PackageClause(n) => {
+                walk_mut(v, &mut NodeMut::Identifier(&mut n.name));
+            }
+            NodeMut::ImportDeclaration(n) => {
+                if let Some(alias) = n.alias.as_mut() {
+                    walk_mut(v, &mut NodeMut::Identifier(alias));
+                }
+                walk_mut(v, &mut NodeMut::StringLit(&mut n.path));
+            }
+            NodeMut::Identifier(_) => {}
+            NodeMut::ArrayExpr(n) => {
+                for element in n.elements.iter_mut() {
+                    walk_expr(v, element);
+                }
+            }
+            NodeMut::FunctionExpr(n) => {
+                for param in n.params.iter_mut() {
+                    walk_mut(v, &mut NodeMut::Property(param));
+                }
+                match &mut n.body {
+                    FunctionBody::Block(b) => walk_mut(v, &mut NodeMut::Block(b)),
+                    FunctionBody::Expr(e) => walk_expr(v, e),
+                }
+            }
+            NodeMut::LogicalExpr(n) => {
+                walk_expr(v, &mut n.left);
+                walk_expr(v, &mut n.right);
+            }
+            NodeMut::ObjectExpr(n) => {
+                if let Some(i) = n.with.as_mut() {
+                    walk_mut(v, &mut NodeMut::Identifier(i));
+                }
+                for prop in n.properties.iter_mut() {
+                    walk_mut(v, &mut NodeMut::Property(prop));
+                }
+            }
+            NodeMut::MemberExpr(n) => {
+                walk_expr(v, &mut n.object);
+                walk_mut(v, &mut NodeMut::from_property_key(&mut n.property));
+            }
+            NodeMut::IndexExpr(n) => {
+                walk_expr(v, &mut n.array);
+                walk_expr(v, &mut n.index);
+            }
+            NodeMut::BinaryExpr(n) => {
+                walk_expr(v, &mut n.left);
+                walk_expr(v, &mut n.right);
+            }
+            NodeMut::UnaryExpr(n) => {
+                walk_expr(v, &mut n.argument);
+            }
+            NodeMut::PipeExpr(n) => {
+                walk_expr(v, &mut n.argument);
+                walk_mut(v, &mut NodeMut::CallExpr(&mut n.call));
+            }
+            NodeMut::CallExpr(n) => {
+                walk_expr(v, &mut n.callee);
+                for arg in n.arguments.iter_mut() {
+                    walk_expr(v, arg);
+                }
+            }
+            NodeMut::ConditionalExpr(n) => {
+                walk_expr(v, &mut n.test);
+                walk_expr(v, &mut n.consequent);
+                walk_expr(v, &mut n.alternate);
+            }
+            NodeMut::StringExpr(n) => {
+                for part in n.parts.iter_mut() {
+                    walk_mut(v, &mut NodeMut::from_string_expr_part(part));
+                }
+            }
+            NodeMut::ParenExpr(n) => {
+                walk_expr(v, &mut n.expression);
+            }
+            NodeMut::IntegerLit(_) => {}
+            NodeMut::FloatLit(_) => {}
+            NodeMut::StringLit(_) => {}
+            NodeMut::DurationLit(_) => {}
+            NodeMut::UintLit(_) => {}
+            NodeMut::BooleanLit(_) => {}
+            NodeMut::DateTimeLit(_) => {}
+            NodeMut::RegexpLit(_) => {}
+            NodeMut::PipeLit(_) => {}
+            NodeMut::BadExpr(n) => {
+                if let Some(e) = n.expression.as_mut() {
+                    walk_expr(v, e);
+                }
+            }
+            NodeMut::ExprStmt(n) => {
+                walk_expr(v, &mut n.expression);
+            }
+            NodeMut::OptionStmt(n) => {
+                walk_mut(v, &mut NodeMut::from_assignment(&mut n.assignment));
+            }
+            NodeMut::ReturnStmt(n) => {
+                walk_expr(v, &mut n.argument);
+            }
+            NodeMut::BadStmt(_) => {}
+            NodeMut::TestStmt(n) => {
+                walk_mut(v, &mut NodeMut::VariableAssgn(&mut n.assignment));
+            }
+            NodeMut::BuiltinStmt(n) => {
+                walk_mut(v, &mut NodeMut::Identifier(&mut n.id));
+            }
+            NodeMut::Block(n) => {
+                for s in n.body.iter_mut() {
+                    walk_stmt(v, s);
+                }
+            }
+            NodeMut::Property(n) => {
+                walk_mut(v, &mut NodeMut::from_property_key(&mut n.key));
+                if let Some(e) = n.value.as_mut() {
+                    walk_expr(v, e);
+                }
+            }
+            NodeMut::TextPart(_) => {}
+            NodeMut::InterpolatedPart(n) => {
+                walk_expr(v, &mut n.expression);
+            }
+            NodeMut::VariableAssgn(n) => {
+                walk_mut(v, &mut NodeMut::Identifier(&mut n.id));
+                walk_expr(v, &mut n.init);
+            }
+            NodeMut::MemberAssgn(n) => {
+                walk_mut(v, &mut NodeMut::MemberExpr(&mut n.member));
+                walk_expr(v, &mut n.init);
+            }
+        }
+    }
+    v.done(node);
+}
+
+fn walk_expr<T: VisitorMut>(v: &mut T, expr: &mut Expression) {
+    walk_mut(v, &mut NodeMut::from_expr(expr));
+    if let Some(new) = v.rewrite_expr(expr) {
+        replace_expr(expr, new);
+    }
+}
+
+fn walk_stmt<T: VisitorMut>(v: &mut T, stmt: &mut Statement) {
+    walk_mut(v, &mut NodeMut::from_stmt(stmt));
+    if let Some(new) = v.rewrite_stmt(stmt) {
+        replace_stmt(stmt, new);
+    }
+}
+
+/// Implementation of VisitorMut for a mutable closure.
+/// We need Higher-Rank Trait Bounds (`for<'a> ...`) here for compiling.
+/// See https://doc.rust-lang.org/nomicon/hrtb.html.
+impl<F> VisitorMut for F
+where
+    F: for<'a> FnMut(&mut NodeMut<'a>),
+{
+    fn visit(&mut self, node: &mut NodeMut) -> bool {
+        self(node);
+        true
+    }
+}
+
+/// replace_expr puts `new` in place of the expression `expr` and returns the old one.
+/// The location of `new` is invalidated, since it no longer describes source text;
+/// the locations of its children are left alone so that nodes moved out of the
+/// old expression keep theirs.
+pub fn replace_expr(expr: &mut Expression, mut new: Expression) -> Expression {
+    invalidate_location(new.base_mut());
+    mem::replace(expr, new)
+}
+
+/// replace_stmt puts `new` in place of the statement `stmt` and returns the old one.
+/// Locations are invalidated as in `replace_expr`.
+pub fn replace_stmt(stmt: &mut Statement, mut new: Statement) -> Statement {
+    invalidate_location(new.base_mut());
+    mem::replace(stmt, new)
+}
+
+/// invalidate_locations invalidates the location of a node and of all its children.
+pub fn invalidate_locations(node: &mut NodeMut) {
+    walk_mut(&mut |n: &mut NodeMut| n.invalidate_location(), node);
+}
+
+fn invalidate_location(base: &mut BaseNode) {
+    base.location = SourceLocation {
+        file: base.location.file.take(),
+        ..SourceLocation::default()
+    };
+}
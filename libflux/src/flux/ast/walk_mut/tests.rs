@@ -0,0 +1,316 @@
+use super::*;
+use crate::ast::walk::{create_visitor, walk, Node};
+use crate::formatter::Formatter;
+use crate::parser::parse_string;
+
+fn test_walk(source: &str, want: Vec<&str>) {
+    let mut file = parse_string("test_walk", source);
+    let mut nodes = Vec::new();
+    walk_mut(
+        &mut |n: &mut NodeMut| nodes.push(format!("{}", n)),
+        &mut NodeMut::File(&mut file),
+    );
+    assert_eq!(want, nodes);
+}
+
+fn format(file: &File) -> String {
+    let mut fmt = Formatter::new(0);
+    fmt.format_file(file, true);
+    fmt.output().0.to_string()
+}
+
+fn locations(file: &File) -> Vec<(String, SourceLocation)> {
+    let mut locs = Vec::new();
+    walk(
+        &create_visitor(&mut |n| locs.push((format!("{}", n), n.base().location.clone()))),
+        Node::File(file),
+    );
+    locs
+}
+
+#[test]
+fn test_file() {
+    test_walk("", vec!["File"])
+}
+#[test]
+fn test_import_declaration() {
+    test_walk(
+        "import b \"a\"",
+        vec!["File", "ImportDeclaration", "Identifier", "StringLit"],
+    )
+}
+#[test]
+fn test_function_expr() {
+    test_walk(
+        "(a=1) => { return a }",
+        vec![
+            "File",
+            "ExprStmt",
+            "FunctionExpr",
+            "Property",
+            "Identifier",
+            "IntegerLit",
+            "Block",
+            "ReturnStmt",
+            "Identifier",
+        ],
+    )
+}
+#[test]
+fn test_object_expr() {
+    test_walk(
+        "{r with a:1, \"b\":x.y}",
+        vec![
+            "File",
+            "ExprStmt",
+            "ObjectExpr",
+            "Identifier",
+            "Property",
+            "Identifier",
+            "IntegerLit",
+            "Property",
+            "StringLit",
+            "MemberExpr",
+            "Identifier",
+            "Identifier",
+        ],
+    )
+}
+#[test]
+fn test_pipe_expr() {
+    test_walk(
+        "a |> b(c: <-)",
+        vec![
+            "File",
+            "ExprStmt",
+            "PipeExpr",
+            "Identifier",
+            "CallExpr",
+            "Identifier",
+            "ObjectExpr",
+            "Property",
+            "Identifier",
+            "PipeLit",
+        ],
+    )
+}
+#[test]
+fn test_string_expr() {
+    test_walk(
+        "\"a ${b.c}\"",
+        vec![
+            "File",
+            "ExprStmt",
+            "StringExpr",
+            "TextPart",
+            "InterpolatedPart",
+            "MemberExpr",
+            "Identifier",
+            "Identifier",
+        ],
+    )
+}
+#[test]
+fn test_statements() {
+    test_walk(
+        "option a.b = 1\ntest t = () => ({})\nbuiltin c",
+        vec![
+            "File",
+            "OptionStmt",
+            "MemberAssgn",
+            "MemberExpr",
+            "Identifier",
+            "Identifier",
+            "IntegerLit",
+            "TestStmt",
+            "VariableAssgn",
+            "Identifier",
+            "FunctionExpr",
+            "ParenExpr",
+            "ObjectExpr",
+            "BuiltinStmt",
+            "Identifier",
+        ],
+    )
+}
+
+// The mutable walk must visit the same nodes, in the same order, as `walk`.
+#[test]
+fn test_same_order_as_walk() {
+    let source = r#"
+package foo
+import "csv"
+option now = () => 2018-05-22T19:53:00Z
+a = from(bucket: "b") |> range(start: -1h) |> filter(fn: (r) => r._value > 1.0 and r.tag =~ /x/)
+b = if a then {a with c: 1u} else [1, 2][0]
+f = (t=<-, g) => { x = not g
+ return t |> map(fn: (r) => ({r with s: "v: ${r._value}"})) }
+"#;
+    let mut file = parse_string("test", source);
+    let want: Vec<String> = locations(&file).into_iter().map(|(n, _)| n).collect();
+    let mut got = Vec::new();
+    walk_mut(
+        &mut |n: &mut NodeMut| got.push(format!("{}", n)),
+        &mut NodeMut::File(&mut file),
+    );
+    assert_eq!(want, got);
+}
+
+#[test]
+fn test_visit_stops_walk() {
+    struct NoFunctions(Vec<String>);
+    impl VisitorMut for NoFunctions {
+        fn visit(&mut self, node: &mut NodeMut) -> bool {
+            self.0.push(format!("{}", node));
+            if let NodeMut::FunctionExpr(_) = node {
+                return false;
+            }
+            true
+        }
+    }
+    let mut file = parse_string("test", "f = (x) => x + 1");
+    let mut v = NoFunctions(Vec::new());
+    walk_mut(&mut v, &mut NodeMut::File(&mut file));
+    assert_eq!(
+        vec!["File", "VariableAssgn", "Identifier", "FunctionExpr"],
+        v.0
+    );
+}
+
+#[test]
+fn test_mutate_nodes() {
+    let mut file = parse_string("test", "a = b + c\nd = a(b: b)");
+    walk_mut(
+        &mut |n: &mut NodeMut| {
+            if let NodeMut::Identifier(id) = n {
+                if id.name == "b" {
+                    id.name = "z".to_string();
+                }
+            }
+        },
+        &mut NodeMut::File(&mut file),
+    );
+    assert_eq!("a = z + c\nd = a(z: z)", format(&file));
+}
+
+// ConstantFolder folds additions of integer literals, children first.
+struct ConstantFolder;
+
+impl VisitorMut for ConstantFolder {
+    fn visit(&mut self, _: &mut NodeMut) -> bool {
+        true
+    }
+    fn rewrite_expr(&mut self, expr: &mut Expression) -> Option<Expression> {
+        if let Expression::Binary(bin) = expr {
+            if let (Operator::AdditionOperator, Expression::Integer(l), Expression::Integer(r)) =
+                (&bin.operator, &bin.left, &bin.right)
+            {
+                return Some(Expression::Integer(IntegerLit {
+                    base: BaseNode::default(),
+                    value: l.value + r.value,
+                }));
+            }
+        }
+        None
+    }
+}
+
+#[test]
+fn test_rewrite_expr() {
+    let mut file = parse_string("test", "a = 1 + 2 + 3\nb = [a + 1, 4 + 5]");
+    walk_mut(&mut ConstantFolder, &mut NodeMut::File(&mut file));
+    assert_eq!("a = 6\nb = [a + 1, 9]", format(&file));
+}
+
+#[test]
+fn test_rewrite_stmt() {
+    // DropTests replaces test statements with bad statements.
+    struct DropTests;
+    impl VisitorMut for DropTests {
+        fn visit(&mut self, node: &mut NodeMut) -> bool {
+            if let NodeMut::TestStmt(_) = node {
+                return false;
+            }
+            true
+        }
+        fn rewrite_stmt(&mut self, stmt: &mut Statement) -> Option<Statement> {
+            match stmt {
+                Statement::Test(t) => Some(Statement::Bad(BadStmt {
+                    base: t.base.clone(),
+                    text: "test removed".to_string(),
+                })),
+                _ => None,
+            }
+        }
+    }
+    let mut file = parse_string("test", "a = 1\ntest t = () => ({})");
+    walk_mut(&mut DropTests, &mut NodeMut::File(&mut file));
+    match &file.body[1] {
+        Statement::Bad(s) => {
+            assert_eq!("test removed", s.text);
+            assert!(!s.base.location.is_valid());
+            assert_eq!(Some("test".to_string()), s.base.location.file);
+        }
+        s => panic!("expected a bad statement, got {:?}", s),
+    }
+}
+
+#[test]
+fn test_replace_expr_keeps_moved_locations() {
+    let mut file = parse_string("test", "a = x");
+    let stmt_loc = file.body[0].base().location.clone();
+    if let Statement::Variable(v) = &mut file.body[0] {
+        let x_loc = v.init.base().location.clone();
+        assert!(x_loc.is_valid());
+        // wrap `x` into `-x`, moving the original identifier.
+        let placeholder = Expression::Bad(Box::new(BadExpr {
+            base: BaseNode::default(),
+            text: String::new(),
+            expression: None,
+        }));
+        let x = replace_expr(&mut v.init, placeholder);
+        let old = replace_expr(
+            &mut v.init,
+            Expression::Unary(Box::new(UnaryExpr {
+                base: x.base().clone(),
+                operator: Operator::SubtractionOperator,
+                argument: x,
+            })),
+        );
+        if let Expression::Bad(_) = old {
+        } else {
+            panic!("expected the placeholder, got {:?}", old);
+        }
+        match &v.init {
+            Expression::Unary(u) => {
+                assert!(!u.base.location.is_valid());
+                assert_eq!(x_loc, u.argument.base().location);
+            }
+            e => panic!("expected a unary expression, got {:?}", e),
+        }
+    }
+    // the statement itself is untouched.
+    assert_eq!(stmt_loc, file.body[0].base().location);
+    assert_eq!("a = -x", format(&file));
+}
+
+#[test]
+fn test_replace_stmt() {
+    let mut file = parse_string("test", "a = 1\nb = 2");
+    let new = parse_string("", "c = 3").body.remove(0);
+    let old = replace_stmt(&mut file.body[1], new);
+    assert!(old.base().location.is_valid());
+    assert!(!file.body[1].base().location.is_valid());
+    assert_eq!("a = 1\nc = 3", format(&file));
+}
+
+#[test]
+fn test_invalidate_locations() {
+    let mut file = parse_string("test", "a = from(bucket: \"b\") |> range(start: -1h)");
+    assert!(locations(&file).iter().all(|(_, l)| l.is_valid()));
+    invalidate_locations(&mut NodeMut::File(&mut file));
+    for (node, loc) in locations(&file) {
+        assert!(!loc.is_valid(), "{} has a valid location", node);
+        assert_eq!(Some("test".to_string()), loc.file, "{}", node);
+    }
+}
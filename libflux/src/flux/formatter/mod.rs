@@ -1,5 +1,6 @@
 #![allow(missing_docs)]
 use crate::ast::{self, walk::Node};
+use crate::parser::strconv;
 use crate::Error;
 
 use chrono::SecondsFormat;
@@ -545,22 +546,7 @@ impl Formatter {
         }
 
         // Write out escaped string value
-        self.write_rune('"');
-        let mut escaped: String;
-        if n.value.contains("\"\\") {
-            escaped = n.value.clone()
-        } else {
-            escaped = String::with_capacity(n.value.len() * 2);
-            for r in n.value.chars() {
-                if r == '"' || r == '\\' {
-                    escaped.push('\\')
-                }
-                escaped.push(r)
-            }
-        }
-
-        self.write_string(&escaped);
-        self.write_rune('"');
+        self.write_string(&strconv::format_string(&n.value));
     }
 
     fn format_boolean_literal(&mut self, n: &ast::BooleanLit) {
@@ -631,9 +617,7 @@ impl Formatter {
     }
 
     fn format_regexp_literal(&mut self, n: &ast::RegexpLit) {
-        self.write_rune('/');
-        self.write_string(&n.value.replace("/", "\\/"));
-        self.write_rune('/')
+        self.write_string(&strconv::format_regex(&n.value))
     }
 }
 
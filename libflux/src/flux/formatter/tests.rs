@@ -7,7 +7,7 @@ use std::str;
 use pretty_assertions::assert_eq;
 
 fn format_helper(golden: &str) {
-    let file = Parser::new(golden).parse_file("".to_string());
+    let file = Parser::with_source(golden).parse_file("".to_string());
     let mut fmt = Formatter::new(golden.len());
     fmt.format_file(&file, true);
     let (ouput, _) = fmt.output();
@@ -120,6 +120,18 @@ with multiple lines""#,
     format_helper(r#""\xe6\x97\xa5\xe6\x9c\xac\xe8\xaa\x9e""#); // with byte
 }
 
+// fluxc parses with the raw source kept. Without it, a string literal is
+// written from its value.
+#[test]
+fn str_lit_without_source() {
+    let src = r#"x = "\x41 \" \\""#;
+    let file = Parser::new(src).parse_file("".to_string());
+    let mut fmt = Formatter::new(src.len());
+    fmt.format_file(&file, true);
+    let (output, _) = fmt.output();
+    assert_eq!(r#"x = "A \" \\""#, output);
+}
+
 #[test]
 fn package_import() {
     format_helper(
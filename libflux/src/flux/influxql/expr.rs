@@ -0,0 +1,209 @@
+use chrono::Duration;
+
+// SelectStatement is a parsed InfluxQL `SELECT` statement. Clauses that were
+// not given hold their zero value.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SelectStatement {
+    pub fields: Vec<Field>,
+    pub sources: Vec<Measurement>,
+    pub condition: Option<Expr>,
+    pub dimensions: Vec<Dimension>,
+    pub fill: Fill,
+    // order_desc is set by `ORDER BY time DESC`.
+    pub order_desc: bool,
+    pub limit: i64,
+    pub offset: i64,
+    pub slimit: i64,
+    pub soffset: i64,
+    // location is the time zone given with `tz('...')`.
+    pub location: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Field {
+    pub expr: Expr,
+    pub alias: Option<String>,
+}
+
+impl Field {
+    // name returns the name of the column this field is output as, following
+    // the InfluxQL rules: the alias, else the function name, else the field.
+    pub fn name(&self) -> String {
+        if let Some(alias) = &self.alias {
+            return alias.clone();
+        }
+        fn expr_name(e: &Expr) -> String {
+            match e {
+                Expr::VarRef(v) => v.name.clone(),
+                Expr::Call(c) => c.name.clone(),
+                Expr::Paren(e) => expr_name(e),
+                Expr::Binary(b) => {
+                    let lhs = expr_name(&b.lhs);
+                    let rhs = expr_name(&b.rhs);
+                    match (lhs.is_empty(), rhs.is_empty()) {
+                        (false, false) => format!("{}_{}", lhs, rhs),
+                        (false, true) => lhs,
+                        _ => rhs,
+                    }
+                }
+                _ => String::new(),
+            }
+        }
+        expr_name(&self.expr)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Measurement {
+    pub database: Option<String>,
+    pub retention_policy: Option<String>,
+    pub name: MeasurementName,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum MeasurementName {
+    Name(String),
+    Regex(String),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Dimension {
+    // Time is `time(interval[, offset])`.
+    Time {
+        interval: Duration,
+        offset: Duration,
+    },
+    Tag(String),
+    Regex(String),
+    Wildcard,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Fill {
+    Null,
+    None,
+    Previous,
+    Linear,
+    Integer(i64),
+    Number(f64),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DataType {
+    Float,
+    Integer,
+    Unsigned,
+    String,
+    Boolean,
+    Field,
+    Tag,
+}
+
+impl DataType {
+    pub fn from_name(name: &str) -> Option<DataType> {
+        match name.to_lowercase().as_str() {
+            "float" => Some(DataType::Float),
+            "integer" => Some(DataType::Integer),
+            "unsigned" => Some(DataType::Unsigned),
+            "string" => Some(DataType::String),
+            "boolean" => Some(DataType::Boolean),
+            "field" => Some(DataType::Field),
+            "tag" => Some(DataType::Tag),
+            _ => None,
+        }
+    }
+}
+
+// VarRef refers to a field or tag, optionally with a `::type` cast.
+#[derive(Debug, PartialEq, Clone)]
+pub struct VarRef {
+    pub name: String,
+    pub data_type: Option<DataType>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expr {
+    VarRef(VarRef),
+    Call(Call),
+    Wildcard,
+    Integer(i64),
+    Number(f64),
+    String(String),
+    Boolean(bool),
+    Duration(Duration),
+    Regex(String),
+    Paren(Box<Expr>),
+    Binary(Box<BinaryExpr>),
+}
+
+impl Expr {
+    // refers_to_time reports whether the expression mentions the `time`
+    // column anywhere.
+    pub fn refers_to_time(&self) -> bool {
+        match self {
+            Expr::VarRef(v) => v.name.eq_ignore_ascii_case("time"),
+            Expr::Paren(e) => e.refers_to_time(),
+            Expr::Binary(b) => b.lhs.refers_to_time() || b.rhs.refers_to_time(),
+            Expr::Call(c) => c.args.iter().any(Expr::refers_to_time),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Call {
+    pub name: String,
+    pub args: Vec<Expr>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    Eq,
+    Neq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    EqRegex,
+    NeqRegex,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Eq
+            | BinaryOp::Neq
+            | BinaryOp::Lt
+            | BinaryOp::Lte
+            | BinaryOp::Gt
+            | BinaryOp::Gte
+            | BinaryOp::EqRegex
+            | BinaryOp::NeqRegex => 4,
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::BitwiseOr | BinaryOp::BitwiseXor => 5,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod | BinaryOp::BitwiseAnd => 6,
+        }
+    }
+
+    pub fn is_comparison(self) -> bool {
+        self.precedence() == 4
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BinaryExpr {
+    pub op: BinaryOp,
+    pub lhs: Expr,
+    pub rhs: Expr,
+}
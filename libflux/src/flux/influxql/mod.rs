@@ -0,0 +1,52 @@
+#![allow(missing_docs)]
+//! Transpilation of InfluxQL queries into Flux.
+//!
+//! InfluxQL `SELECT` statements are parsed into
+//! [`SelectStatement`](expr::SelectStatement)s and then lowered by a
+//! [`Transpiler`] into an `ast::Package` with one
+//! `from |> range |> filter |> aggregateWindow` pipeline per selected field.
+pub mod expr;
+pub mod parser;
+mod transpile;
+
+pub use parser::parse;
+pub use transpile::Transpiler;
+
+use std::fmt;
+
+use crate::ast;
+
+#[derive(Debug, PartialEq)]
+pub struct Error {
+    pub msg: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl From<String> for Error {
+    fn from(msg: String) -> Error {
+        Error { msg }
+    }
+}
+
+impl From<&str> for Error {
+    fn from(msg: &str) -> Error {
+        Error {
+            msg: String::from(msg),
+        }
+    }
+}
+
+impl Transpiler {
+    /// Parses an InfluxQL query and transpiles it into a Flux package.
+    pub fn transpile_str(&self, query: &str) -> Result<ast::Package, Error> {
+        self.transpile(&parse(query)?)
+    }
+}
+
+#[cfg(test)]
+mod tests;
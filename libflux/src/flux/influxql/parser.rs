@@ -0,0 +1,993 @@
+use crate::influxql::expr::*;
+use crate::influxql::Error;
+
+use chrono::Duration;
+use regex::Regex;
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    // quoted is true for double quoted identifiers, which are never keywords.
+    Ident { name: String, quoted: bool },
+    Integer(i64),
+    Number(f64),
+    Duration(Duration),
+    Str(String),
+    Regex(String),
+    LeftParen,
+    RightParen,
+    Comma,
+    Dot,
+    DoubleColon,
+    Semicolon,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    Eq,
+    Neq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    EqRegex,
+    NeqRegex,
+    EOF,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Ident {
+                name,
+                quoted: false,
+            } => name.clone(),
+            Token::Ident { name, quoted: true } => format!("{:?}", name),
+            Token::Integer(n) => n.to_string(),
+            Token::Number(n) => n.to_string(),
+            Token::Duration(_) => "duration".to_string(),
+            Token::Str(s) => format!("'{}'", s),
+            Token::Regex(r) => format!("/{}/", r),
+            Token::LeftParen => "(".to_string(),
+            Token::RightParen => ")".to_string(),
+            Token::Comma => ",".to_string(),
+            Token::Dot => ".".to_string(),
+            Token::DoubleColon => "::".to_string(),
+            Token::Semicolon => ";".to_string(),
+            Token::Add => "+".to_string(),
+            Token::Sub => "-".to_string(),
+            Token::Mul => "*".to_string(),
+            Token::Div => "/".to_string(),
+            Token::Mod => "%".to_string(),
+            Token::BitwiseAnd => "&".to_string(),
+            Token::BitwiseOr => "|".to_string(),
+            Token::BitwiseXor => "^".to_string(),
+            Token::Eq => "=".to_string(),
+            Token::Neq => "!=".to_string(),
+            Token::Lt => "<".to_string(),
+            Token::Lte => "<=".to_string(),
+            Token::Gt => ">".to_string(),
+            Token::Gte => ">=".to_string(),
+            Token::EqRegex => "=~".to_string(),
+            Token::NeqRegex => "!~".to_string(),
+            Token::EOF => "EOF".to_string(),
+        }
+    }
+
+    fn is_keyword(&self, kw: &str) -> bool {
+        match self {
+            Token::Ident {
+                name,
+                quoted: false,
+            } => name.eq_ignore_ascii_case(kw),
+            _ => false,
+        }
+    }
+}
+
+fn error_at(pos: usize, msg: String) -> Error {
+    Error::from(format!("parse error at char {}: {}", pos + 1, msg))
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+// Lexer splits an InfluxQL query into tokens. Every token is paired with the
+// (char based) offset it starts at, for error reporting.
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Lexer {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn peek_at(&self, n: usize) -> Option<char> {
+        self.chars.get(self.pos + n).cloned()
+    }
+
+    fn tokens(mut self) -> Result<Vec<(Token, usize)>, Error> {
+        let mut tokens: Vec<(Token, usize)> = Vec::new();
+        loop {
+            self.skip_whitespace()?;
+            let start = self.pos;
+            let c = match self.peek() {
+                Some(c) => c,
+                None => {
+                    tokens.push((Token::EOF, start));
+                    return Ok(tokens);
+                }
+            };
+            // A slash starts a regex rather than a division wherever the
+            // grammar expects a regex operand.
+            let regex_allowed = match tokens.last() {
+                Some((t, _)) => {
+                    *t == Token::EqRegex
+                        || *t == Token::NeqRegex
+                        || *t == Token::Comma
+                        || *t == Token::Dot
+                        || t.is_keyword("from")
+                        || t.is_keyword("by")
+                }
+                None => false,
+            };
+            let tok = if c == '/' && regex_allowed {
+                self.regex()?
+            } else if c.is_ascii_digit() || (c == '.' && self.peek_number_after_dot()) {
+                self.number()?
+            } else if is_ident_start(c) {
+                self.ident()
+            } else if c == '"' {
+                self.quoted_ident()?
+            } else if c == '\'' {
+                self.string()?
+            } else {
+                self.operator(c)?
+            };
+            tokens.push((tok, start));
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), Error> {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.pos += 1;
+            } else if c == '-' && self.peek_at(1) == Some('-') {
+                while let Some(c) = self.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+            } else if c == '/' && self.peek_at(1) == Some('*') {
+                let start = self.pos;
+                self.pos += 2;
+                loop {
+                    match (self.peek(), self.peek_at(1)) {
+                        (Some('*'), Some('/')) => {
+                            self.pos += 2;
+                            break;
+                        }
+                        (Some(_), _) => self.pos += 1,
+                        (None, _) => {
+                            return Err(error_at(start, "unterminated comment".to_string()))
+                        }
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn peek_number_after_dot(&self) -> bool {
+        match self.peek_at(1) {
+            Some(c) => c.is_ascii_digit(),
+            None => false,
+        }
+    }
+
+    fn ident(&mut self) -> Token {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !is_ident_char(c) {
+                break;
+            }
+            self.pos += 1;
+        }
+        Token::Ident {
+            name: self.chars[start..self.pos].iter().collect(),
+            quoted: false,
+        }
+    }
+
+    fn digits(&mut self) -> String {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_digit() {
+                break;
+            }
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    // duration_unit returns the unit starting at the current position and
+    // its length, if a valid duration unit follows.
+    fn duration_unit(&self) -> Option<(Duration, usize)> {
+        let (unit, len) = match (self.peek(), self.peek_at(1)) {
+            (Some('n'), Some('s')) => (Duration::nanoseconds(1), 2),
+            (Some('m'), Some('s')) => (Duration::milliseconds(1), 2),
+            (Some('u'), _) | (Some('µ'), _) => (Duration::microseconds(1), 1),
+            (Some('s'), _) => (Duration::seconds(1), 1),
+            (Some('m'), _) => (Duration::minutes(1), 1),
+            (Some('h'), _) => (Duration::hours(1), 1),
+            (Some('d'), _) => (Duration::days(1), 1),
+            (Some('w'), _) => (Duration::weeks(1), 1),
+            _ => return None,
+        };
+        match self.peek_at(len) {
+            Some(c) if is_ident_char(c) && !c.is_ascii_digit() => None,
+            _ => Some((unit, len)),
+        }
+    }
+
+    fn number(&mut self) -> Result<Token, Error> {
+        let start = self.pos;
+        let int_part = self.digits();
+        if self.peek() == Some('.') && self.peek_number_after_dot() {
+            self.pos += 1;
+            let frac = self.digits();
+            let text = format!("{}.{}", int_part, frac);
+            return match text.parse::<f64>() {
+                Ok(n) => Ok(Token::Number(n)),
+                Err(_) => Err(error_at(
+                    start,
+                    format!("unable to parse number {:?}", text),
+                )),
+            };
+        }
+        if self.duration_unit().is_some() {
+            return self.duration(start, int_part);
+        }
+        match int_part.parse::<i64>() {
+            Ok(n) => Ok(Token::Integer(n)),
+            Err(_) => Err(error_at(
+                start,
+                format!("unable to parse integer {:?}", int_part),
+            )),
+        }
+    }
+
+    // duration lexes a sequence of magnitude/unit pairs like `1h30m`.
+    fn duration(&mut self, start: usize, first: String) -> Result<Token, Error> {
+        let mut total = Duration::zero();
+        let mut magnitude = first;
+        loop {
+            let (unit, len) = match self.duration_unit() {
+                Some(u) => u,
+                None => return Err(error_at(start, "invalid duration".to_string())),
+            };
+            self.pos += len;
+            let d = magnitude
+                .parse::<i64>()
+                .ok()
+                .and_then(|n| unit.num_nanoseconds().and_then(|u| u.checked_mul(n)))
+                .and_then(|n| total.checked_add(&Duration::nanoseconds(n)));
+            total = match d {
+                Some(d) if d.num_nanoseconds().is_some() => d,
+                _ => return Err(error_at(start, "overflowed duration".to_string())),
+            };
+            match self.peek() {
+                Some(c) if c.is_ascii_digit() => magnitude = self.digits(),
+                _ => return Ok(Token::Duration(total)),
+            }
+        }
+    }
+
+    // quoted reads the body of a quoted string or identifier up to the
+    // closing quote, handling backslash escapes.
+    fn quoted(&mut self, quote: char) -> Result<String, Error> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut value = String::new();
+        loop {
+            let c = match self.peek() {
+                Some(c) => c,
+                None => return Err(error_at(start, "unterminated quoted string".to_string())),
+            };
+            self.pos += 1;
+            if c == quote {
+                return Ok(value);
+            }
+            match c {
+                '\\' => {
+                    let c = match self.peek() {
+                        Some(c) => c,
+                        None => {
+                            return Err(error_at(start, "unterminated quoted string".to_string()))
+                        }
+                    };
+                    self.pos += 1;
+                    match c {
+                        'n' => value.push('\n'),
+                        '\\' | '\'' | '"' => value.push(c),
+                        _ => {
+                            return Err(error_at(
+                                self.pos - 2,
+                                format!("bad escape sequence {:?}", format!("\\{}", c)),
+                            ))
+                        }
+                    }
+                }
+                '\n' => return Err(error_at(start, "unterminated quoted string".to_string())),
+                _ => value.push(c),
+            }
+        }
+    }
+
+    fn quoted_ident(&mut self) -> Result<Token, Error> {
+        Ok(Token::Ident {
+            name: self.quoted('"')?,
+            quoted: true,
+        })
+    }
+
+    fn string(&mut self) -> Result<Token, Error> {
+        Ok(Token::Str(self.quoted('\'')?))
+    }
+
+    // regex reads a regex literal. Only `\/` is unescaped; other escapes are
+    // left for the regex engine.
+    fn regex(&mut self) -> Result<Token, Error> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut value = String::new();
+        loop {
+            let c = match self.peek() {
+                Some(c) => c,
+                None => return Err(error_at(start, "unterminated regex".to_string())),
+            };
+            self.pos += 1;
+            match c {
+                '/' => return Ok(Token::Regex(value)),
+                '\\' if self.peek() == Some('/') => {
+                    self.pos += 1;
+                    value.push('/');
+                }
+                '\n' => return Err(error_at(start, "unterminated regex".to_string())),
+                _ => value.push(c),
+            }
+        }
+    }
+
+    fn operator(&mut self, c: char) -> Result<Token, Error> {
+        let next = self.peek_at(1);
+        let (tok, len) = match (c, next) {
+            ('=', Some('~')) => (Token::EqRegex, 2),
+            ('=', _) => (Token::Eq, 1),
+            ('!', Some('=')) => (Token::Neq, 2),
+            ('!', Some('~')) => (Token::NeqRegex, 2),
+            ('<', Some('>')) => (Token::Neq, 2),
+            ('<', Some('=')) => (Token::Lte, 2),
+            ('<', _) => (Token::Lt, 1),
+            ('>', Some('=')) => (Token::Gte, 2),
+            ('>', _) => (Token::Gt, 1),
+            (':', Some(':')) => (Token::DoubleColon, 2),
+            ('(', _) => (Token::LeftParen, 1),
+            (')', _) => (Token::RightParen, 1),
+            (',', _) => (Token::Comma, 1),
+            ('.', _) => (Token::Dot, 1),
+            (';', _) => (Token::Semicolon, 1),
+            ('+', _) => (Token::Add, 1),
+            ('-', _) => (Token::Sub, 1),
+            ('*', _) => (Token::Mul, 1),
+            ('/', _) => (Token::Div, 1),
+            ('%', _) => (Token::Mod, 1),
+            ('&', _) => (Token::BitwiseAnd, 1),
+            ('|', _) => (Token::BitwiseOr, 1),
+            ('^', _) => (Token::BitwiseXor, 1),
+            _ => return Err(error_at(self.pos, format!("unexpected character {:?}", c))),
+        };
+        self.pos += len;
+        Ok(tok)
+    }
+}
+
+// Keywords that cannot be used as unquoted identifiers.
+const KEYWORDS: &[&str] = &[
+    "all",
+    "alter",
+    "and",
+    "any",
+    "as",
+    "asc",
+    "begin",
+    "by",
+    "create",
+    "delete",
+    "desc",
+    "drop",
+    "duration",
+    "end",
+    "every",
+    "explain",
+    "field",
+    "for",
+    "from",
+    "grant",
+    "group",
+    "in",
+    "inf",
+    "insert",
+    "into",
+    "key",
+    "limit",
+    "measurement",
+    "name",
+    "not",
+    "offset",
+    "on",
+    "or",
+    "order",
+    "revoke",
+    "select",
+    "series",
+    "show",
+    "slimit",
+    "soffset",
+    "tag",
+    "to",
+    "values",
+    "where",
+    "with",
+    "write",
+];
+
+fn is_keyword(name: &str) -> bool {
+    KEYWORDS.contains(&name.to_lowercase().as_str())
+}
+
+// Parser is a recursive descent parser for InfluxQL statements.
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    cur: usize,
+}
+
+/// Parses an InfluxQL query consisting of one or more `SELECT` statements
+/// separated by semicolons.
+pub fn parse(src: &str) -> Result<Vec<SelectStatement>, Error> {
+    let lexer = Lexer {
+        chars: src.chars().collect(),
+        pos: 0,
+    };
+    let mut p = Parser {
+        tokens: lexer.tokens()?,
+        cur: 0,
+    };
+    let mut statements = Vec::new();
+    loop {
+        while *p.peek() == Token::Semicolon {
+            p.next();
+        }
+        if *p.peek() == Token::EOF {
+            break;
+        }
+        statements.push(p.parse_select_statement()?);
+        match p.peek() {
+            Token::Semicolon | Token::EOF => (),
+            _ => return Err(p.unexpected("EOF")),
+        }
+    }
+    if statements.is_empty() {
+        return Err(p.unexpected("SELECT"));
+    }
+    Ok(statements)
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.cur].0
+    }
+
+    fn next(&mut self) -> Token {
+        let t = self.tokens[self.cur].0.clone();
+        if self.cur < self.tokens.len() - 1 {
+            self.cur += 1;
+        }
+        t
+    }
+
+    fn pos(&self) -> usize {
+        self.tokens[self.cur].1
+    }
+
+    fn error(&self, msg: String) -> Error {
+        error_at(self.pos(), msg)
+    }
+
+    fn unexpected(&self, expected: &str) -> Error {
+        self.error(format!(
+            "found {}, expected {}",
+            self.peek().describe(),
+            expected
+        ))
+    }
+
+    fn expect(&mut self, t: Token) -> Result<(), Error> {
+        if *self.peek() == t {
+            self.next();
+            Ok(())
+        } else {
+            Err(self.unexpected(&t.describe()))
+        }
+    }
+
+    fn peek_keyword(&self, kw: &str) -> bool {
+        self.peek().is_keyword(kw)
+    }
+
+    fn expect_keyword(&mut self, kw: &str) -> Result<(), Error> {
+        if self.peek_keyword(kw) {
+            self.next();
+            Ok(())
+        } else {
+            Err(self.unexpected(&kw.to_uppercase()))
+        }
+    }
+
+    fn parse_ident(&mut self) -> Result<String, Error> {
+        match self.peek().clone() {
+            Token::Ident { name, quoted } if quoted || !is_keyword(&name) => {
+                self.next();
+                Ok(name)
+            }
+            _ => Err(self.unexpected("identifier")),
+        }
+    }
+
+    fn parse_integer(&mut self) -> Result<i64, Error> {
+        match *self.peek() {
+            Token::Integer(n) => {
+                self.next();
+                Ok(n)
+            }
+            _ => Err(self.unexpected("integer")),
+        }
+    }
+
+    fn parse_select_statement(&mut self) -> Result<SelectStatement, Error> {
+        if !self.peek_keyword("select") {
+            return Err(self.unexpected("SELECT"));
+        }
+        self.next();
+        let fields = self.parse_fields()?;
+        if self.peek_keyword("into") {
+            return Err(self.error("SELECT INTO is not supported".to_string()));
+        }
+        self.expect_keyword("from")?;
+        let sources = self.parse_sources()?;
+        let condition = if self.peek_keyword("where") {
+            self.next();
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        let dimensions = if self.peek_keyword("group") {
+            self.next();
+            self.expect_keyword("by")?;
+            self.parse_dimensions()?
+        } else {
+            Vec::new()
+        };
+        let fill = if self.peek_keyword("fill") {
+            self.parse_fill()?
+        } else {
+            Fill::Null
+        };
+        let mut order_desc = false;
+        if self.peek_keyword("order") {
+            self.next();
+            self.expect_keyword("by")?;
+            order_desc = self.parse_order()?;
+        }
+        let mut stmt = SelectStatement {
+            fields,
+            sources,
+            condition,
+            dimensions,
+            fill,
+            order_desc,
+            limit: 0,
+            offset: 0,
+            slimit: 0,
+            soffset: 0,
+            location: None,
+        };
+        for (kw, value) in &mut [
+            ("limit", &mut stmt.limit),
+            ("offset", &mut stmt.offset),
+            ("slimit", &mut stmt.slimit),
+            ("soffset", &mut stmt.soffset),
+        ] {
+            if self.peek_keyword(kw) {
+                self.next();
+                **value = self.parse_integer()?;
+            }
+        }
+        if self.peek_keyword("tz") {
+            self.next();
+            self.expect(Token::LeftParen)?;
+            match self.next() {
+                Token::Str(s) => stmt.location = Some(s),
+                _ => {
+                    self.cur -= 1;
+                    return Err(self.unexpected("string"));
+                }
+            }
+            self.expect(Token::RightParen)?;
+        }
+        Ok(stmt)
+    }
+
+    fn parse_fields(&mut self) -> Result<Vec<Field>, Error> {
+        let mut fields = Vec::new();
+        loop {
+            let expr = self.parse_expr()?;
+            let alias = if self.peek_keyword("as") {
+                self.next();
+                Some(self.parse_ident()?)
+            } else {
+                None
+            };
+            fields.push(Field { expr, alias });
+            if *self.peek() != Token::Comma {
+                return Ok(fields);
+            }
+            self.next();
+        }
+    }
+
+    fn parse_sources(&mut self) -> Result<Vec<Measurement>, Error> {
+        let mut sources = vec![self.parse_measurement()?];
+        while *self.peek() == Token::Comma {
+            self.next();
+            sources.push(self.parse_measurement()?);
+        }
+        Ok(sources)
+    }
+
+    // parse_measurement parses `m`, `rp.m`, `db.rp.m` or `db..m`, where the
+    // measurement may also be a regex.
+    fn parse_measurement(&mut self) -> Result<Measurement, Error> {
+        let mut segments: Vec<Option<String>> = Vec::new();
+        let mut regex = None;
+        loop {
+            match self.peek().clone() {
+                Token::Regex(r) => {
+                    self.next();
+                    regex = Some(r);
+                    break;
+                }
+                Token::Dot if !segments.is_empty() => {
+                    // An empty segment, as the retention policy in `db..m`.
+                    segments.push(None);
+                }
+                _ => segments.push(Some(self.parse_ident()?)),
+            }
+            if *self.peek() != Token::Dot || segments.len() == 3 {
+                break;
+            }
+            self.next();
+        }
+        let name = match regex {
+            Some(r) => {
+                validate_regex(self.pos(), &r)?;
+                MeasurementName::Regex(r)
+            }
+            None => match segments.pop() {
+                Some(Some(name)) => MeasurementName::Name(name),
+                _ => return Err(self.unexpected("identifier")),
+            },
+        };
+        let (database, retention_policy) = match segments.len() {
+            0 => (None, None),
+            1 => (None, segments.remove(0)),
+            2 => (segments.remove(0), segments.remove(0)),
+            _ => return Err(self.unexpected("identifier")),
+        };
+        Ok(Measurement {
+            database,
+            retention_policy,
+            name,
+        })
+    }
+
+    fn parse_dimensions(&mut self) -> Result<Vec<Dimension>, Error> {
+        let mut dims = Vec::new();
+        loop {
+            dims.push(self.parse_dimension()?);
+            if *self.peek() != Token::Comma {
+                return Ok(dims);
+            }
+            self.next();
+        }
+    }
+
+    fn parse_dimension(&mut self) -> Result<Dimension, Error> {
+        let pos = self.pos();
+        match self.peek().clone() {
+            Token::Mul => {
+                self.next();
+                Ok(Dimension::Wildcard)
+            }
+            Token::Regex(r) => {
+                self.next();
+                validate_regex(pos, &r)?;
+                Ok(Dimension::Regex(r))
+            }
+            Token::Ident {
+                ref name,
+                quoted: false,
+            } if name.eq_ignore_ascii_case("time") => {
+                self.next();
+                self.expect(Token::LeftParen)?;
+                let interval = match self.parse_expr()? {
+                    Expr::Duration(d) if d > Duration::zero() => d,
+                    _ => {
+                        return Err(error_at(
+                            pos,
+                            "time dimension must have a positive duration argument".to_string(),
+                        ))
+                    }
+                };
+                let offset = if *self.peek() == Token::Comma {
+                    self.next();
+                    match self.parse_expr()? {
+                        Expr::Duration(d) => d,
+                        _ => {
+                            return Err(error_at(
+                                pos,
+                                "time dimension offset must be a duration".to_string(),
+                            ))
+                        }
+                    }
+                } else {
+                    Duration::zero()
+                };
+                self.expect(Token::RightParen)?;
+                Ok(Dimension::Time { interval, offset })
+            }
+            _ => Ok(Dimension::Tag(self.parse_ident()?)),
+        }
+    }
+
+    fn parse_fill(&mut self) -> Result<Fill, Error> {
+        self.next();
+        self.expect(Token::LeftParen)?;
+        let negative = if *self.peek() == Token::Sub {
+            self.next();
+            true
+        } else {
+            false
+        };
+        let fill = match self.peek().clone() {
+            Token::Integer(n) => Fill::Integer(if negative { -n } else { n }),
+            Token::Number(n) => Fill::Number(if negative { -n } else { n }),
+            Token::Ident {
+                ref name,
+                quoted: false,
+            } if !negative => match name.to_lowercase().as_str() {
+                "null" => Fill::Null,
+                "none" => Fill::None,
+                "previous" => Fill::Previous,
+                "linear" => Fill::Linear,
+                _ => return Err(self.unexpected("fill option")),
+            },
+            _ => return Err(self.unexpected("fill option")),
+        };
+        self.next();
+        self.expect(Token::RightParen)?;
+        Ok(fill)
+    }
+
+    // parse_order parses the sort fields of an ORDER BY clause and returns
+    // whether the order is descending. Only the time column can be sorted.
+    fn parse_order(&mut self) -> Result<bool, Error> {
+        let pos = self.pos();
+        if self.peek_keyword("time") {
+            self.next();
+        } else if !self.peek_keyword("asc") && !self.peek_keyword("desc") {
+            return Err(error_at(
+                pos,
+                "only ORDER BY time supported at this time".to_string(),
+            ));
+        }
+        let desc = if self.peek_keyword("desc") {
+            self.next();
+            true
+        } else {
+            if self.peek_keyword("asc") {
+                self.next();
+            }
+            false
+        };
+        if *self.peek() == Token::Comma {
+            return Err(self.error("only ORDER BY time supported at this time".to_string()));
+        }
+        Ok(desc)
+    }
+
+    fn peek_binary_op(&self) -> Option<BinaryOp> {
+        match self.peek() {
+            Token::Add => Some(BinaryOp::Add),
+            Token::Sub => Some(BinaryOp::Sub),
+            Token::Mul => Some(BinaryOp::Mul),
+            Token::Div => Some(BinaryOp::Div),
+            Token::Mod => Some(BinaryOp::Mod),
+            Token::BitwiseAnd => Some(BinaryOp::BitwiseAnd),
+            Token::BitwiseOr => Some(BinaryOp::BitwiseOr),
+            Token::BitwiseXor => Some(BinaryOp::BitwiseXor),
+            Token::Eq => Some(BinaryOp::Eq),
+            Token::Neq => Some(BinaryOp::Neq),
+            Token::Lt => Some(BinaryOp::Lt),
+            Token::Lte => Some(BinaryOp::Lte),
+            Token::Gt => Some(BinaryOp::Gt),
+            Token::Gte => Some(BinaryOp::Gte),
+            Token::EqRegex => Some(BinaryOp::EqRegex),
+            Token::NeqRegex => Some(BinaryOp::NeqRegex),
+            t if t.is_keyword("and") => Some(BinaryOp::And),
+            t if t.is_keyword("or") => Some(BinaryOp::Or),
+            _ => None,
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, Error> {
+        self.parse_binary(0)
+    }
+
+    // parse_binary parses binary expressions whose operators bind at least
+    // as tightly as min_prec, using precedence climbing. All operators are
+    // left associative.
+    fn parse_binary(&mut self, min_prec: u8) -> Result<Expr, Error> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            let op = match self.peek_binary_op() {
+                Some(op) if op.precedence() >= min_prec => op,
+                _ => return Ok(lhs),
+            };
+            self.next();
+            let rhs = self.parse_binary(op.precedence() + 1)?;
+            if (op == BinaryOp::EqRegex || op == BinaryOp::NeqRegex)
+                && match rhs {
+                    Expr::Regex(_) => false,
+                    _ => true,
+                }
+            {
+                return Err(self.error(format!(
+                    "regex operator {:?} requires a regex operand",
+                    if op == BinaryOp::EqRegex { "=~" } else { "!~" }
+                )));
+            }
+            lhs = Expr::Binary(Box::new(BinaryExpr { op, lhs, rhs }));
+        }
+    }
+
+    // parse_unary folds a leading sign into the number or duration literal
+    // that follows it.
+    fn parse_unary(&mut self) -> Result<Expr, Error> {
+        let negative = match self.peek() {
+            Token::Sub => true,
+            Token::Add => false,
+            _ => return self.parse_primary(),
+        };
+        self.next();
+        match self.next() {
+            Token::Integer(n) => Ok(Expr::Integer(if negative { -n } else { n })),
+            Token::Number(n) => Ok(Expr::Number(if negative { -n } else { n })),
+            Token::Duration(d) => Ok(Expr::Duration(if negative { -d } else { d })),
+            _ => {
+                self.cur -= 1;
+                Err(self.unexpected("number or duration"))
+            }
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, Error> {
+        let pos = self.pos();
+        match self.next() {
+            Token::Integer(n) => Ok(Expr::Integer(n)),
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::Duration(d) => Ok(Expr::Duration(d)),
+            Token::Str(s) => Ok(Expr::String(s)),
+            Token::Regex(r) => {
+                validate_regex(pos, &r)?;
+                Ok(Expr::Regex(r))
+            }
+            Token::Mul => Ok(Expr::Wildcard),
+            Token::LeftParen => {
+                let e = self.parse_expr()?;
+                self.expect(Token::RightParen)?;
+                Ok(Expr::Paren(Box::new(e)))
+            }
+            Token::Ident { name, quoted } => {
+                if !quoted {
+                    if name.eq_ignore_ascii_case("true") {
+                        return Ok(Expr::Boolean(true));
+                    } else if name.eq_ignore_ascii_case("false") {
+                        return Ok(Expr::Boolean(false));
+                    }
+                }
+                if !quoted && *self.peek() == Token::LeftParen {
+                    return self.parse_call(name.to_lowercase());
+                }
+                if !quoted && is_keyword(&name) {
+                    self.cur -= 1;
+                    return Err(self.unexpected("identifier"));
+                }
+                self.parse_var_ref(name)
+            }
+            _ => {
+                self.cur -= 1;
+                Err(self.unexpected("identifier, string, number, bool"))
+            }
+        }
+    }
+
+    fn parse_call(&mut self, name: String) -> Result<Expr, Error> {
+        self.expect(Token::LeftParen)?;
+        let mut args = Vec::new();
+        if *self.peek() != Token::RightParen {
+            loop {
+                args.push(self.parse_expr()?);
+                if *self.peek() != Token::Comma {
+                    break;
+                }
+                self.next();
+            }
+        }
+        self.expect(Token::RightParen)?;
+        Ok(Expr::Call(Call { name, args }))
+    }
+
+    fn parse_var_ref(&mut self, name: String) -> Result<Expr, Error> {
+        let data_type = if *self.peek() == Token::DoubleColon {
+            self.next();
+            let pos = self.pos();
+            match self.next() {
+                Token::Ident { name, .. } => match DataType::from_name(&name) {
+                    Some(t) => Some(t),
+                    None => return Err(error_at(pos, format!("unknown data type {:?}", name))),
+                },
+                _ => {
+                    self.cur -= 1;
+                    return Err(self.unexpected("data type"));
+                }
+            }
+        } else {
+            None
+        };
+        Ok(Expr::VarRef(VarRef { name, data_type }))
+    }
+}
+
+fn validate_regex(pos: usize, value: &str) -> Result<(), Error> {
+    match Regex::new(value) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(error_at(
+            pos,
+            format!("invalid regular expression {:?}: {}", value, e),
+        )),
+    }
+}
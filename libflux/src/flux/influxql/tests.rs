@@ -0,0 +1,265 @@
+use super::expr::*;
+use super::*;
+use crate::formatter::Formatter;
+
+use chrono::{DateTime, Duration};
+use std::fs;
+use std::path::Path;
+
+// This gives us a colorful diff.
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+fn transpiler() -> Transpiler {
+    Transpiler {
+        database: "telegraf".to_string(),
+        retention_policy: "autogen".to_string(),
+        now: DateTime::parse_from_rfc3339("2019-11-01T12:00:00Z").unwrap(),
+    }
+}
+
+fn format(pkg: &ast::Package) -> String {
+    let mut fmt = Formatter::new(1024);
+    fmt.format_file(&pkg.files[0], false);
+    let (output, err) = fmt.output();
+    assert_eq!("", err);
+    output.to_string()
+}
+
+fn parse_error(query: &str) -> String {
+    match parse(query) {
+        Ok(stmts) => panic!("expected {:?} to fail, got {:?}", query, stmts),
+        Err(e) => e.msg,
+    }
+}
+
+fn transpile_error(query: &str) -> String {
+    match transpiler().transpile_str(query) {
+        Ok(_) => panic!("expected {:?} to fail to transpile", query),
+        Err(e) => e.msg,
+    }
+}
+
+// Every `<name>.influxql` file in testdata is transpiled and compared with
+// the Flux in `<name>.flux`.
+#[test]
+fn transpile_golden_files() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("influxql/testdata");
+    let mut count = 0;
+    for entry in fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "influxql") {
+            continue;
+        }
+        let query = fs::read_to_string(&path).unwrap();
+        let want = fs::read_to_string(path.with_extension("flux")).unwrap();
+        let pkg = match transpiler().transpile_str(&query) {
+            Ok(pkg) => pkg,
+            Err(e) => panic!("{}: {}", path.display(), e),
+        };
+        assert_eq!(
+            want.trim_end(),
+            format(&pkg).trim_end(),
+            "{}",
+            path.display()
+        );
+        count += 1;
+    }
+    assert!(count > 0, "no test cases found in {}", dir.display());
+}
+
+#[test]
+fn parse_select_statement() {
+    let stmts = parse(
+        r#"select mean("value") as m from db.rp."cpu", /^mem/ where host = 'a' and time > now() - 1h group by time(5m, 1m), host fill(-1) order by time desc limit 5 offset 2"#,
+    )
+    .unwrap();
+    let var = |name: &str| {
+        Expr::VarRef(VarRef {
+            name: name.to_string(),
+            data_type: None,
+        })
+    };
+    let binary = |op, lhs, rhs| Expr::Binary(Box::new(BinaryExpr { op, lhs, rhs }));
+    assert_eq!(
+        stmts,
+        vec![SelectStatement {
+            fields: vec![Field {
+                expr: Expr::Call(Call {
+                    name: "mean".to_string(),
+                    args: vec![var("value")],
+                }),
+                alias: Some("m".to_string()),
+            }],
+            sources: vec![
+                Measurement {
+                    database: Some("db".to_string()),
+                    retention_policy: Some("rp".to_string()),
+                    name: MeasurementName::Name("cpu".to_string()),
+                },
+                Measurement {
+                    database: None,
+                    retention_policy: None,
+                    name: MeasurementName::Regex("^mem".to_string()),
+                },
+            ],
+            condition: Some(binary(
+                BinaryOp::And,
+                binary(BinaryOp::Eq, var("host"), Expr::String("a".to_string())),
+                binary(
+                    BinaryOp::Gt,
+                    var("time"),
+                    binary(
+                        BinaryOp::Sub,
+                        Expr::Call(Call {
+                            name: "now".to_string(),
+                            args: vec![],
+                        }),
+                        Expr::Duration(Duration::hours(1)),
+                    ),
+                ),
+            )),
+            dimensions: vec![
+                Dimension::Time {
+                    interval: Duration::minutes(5),
+                    offset: Duration::minutes(1),
+                },
+                Dimension::Tag("host".to_string()),
+            ],
+            fill: Fill::Integer(-1),
+            order_desc: true,
+            limit: 5,
+            offset: 2,
+            slimit: 0,
+            soffset: 0,
+            location: None,
+        }]
+    );
+}
+
+#[test]
+fn parse_measurements() {
+    let measurement = |query: &str| parse(query).unwrap().remove(0).sources.remove(0);
+    let name = |s: &str| MeasurementName::Name(s.to_string());
+    assert_eq!(
+        measurement("SELECT a FROM db..m"),
+        Measurement {
+            database: Some("db".to_string()),
+            retention_policy: None,
+            name: name("m"),
+        }
+    );
+    assert_eq!(
+        measurement("SELECT a FROM rp.m"),
+        Measurement {
+            database: None,
+            retention_policy: Some("rp".to_string()),
+            name: name("m"),
+        }
+    );
+    assert_eq!(
+        measurement(r"SELECT a FROM db.rp./a\/b/"),
+        Measurement {
+            database: Some("db".to_string()),
+            retention_policy: Some("rp".to_string()),
+            name: MeasurementName::Regex("a/b".to_string()),
+        }
+    );
+}
+
+#[test]
+fn parse_errors() {
+    for (query, want) in &[
+        ("", "parse error at char 1: found EOF, expected SELECT"),
+        (
+            "SHOW DATABASES",
+            "parse error at char 1: found SHOW, expected SELECT",
+        ),
+        (
+            "SELECT a",
+            "parse error at char 9: found EOF, expected FROM",
+        ),
+        (
+            "SELECT a FROM b WHERE c = 'd",
+            "parse error at char 27: unterminated quoted string",
+        ),
+        (
+            "SELECT a FROM b GROUP BY time(0s)",
+            "parse error at char 26: time dimension must have a positive duration argument",
+        ),
+        (
+            "SELECT a FROM b fill(always)",
+            "parse error at char 22: found always, expected fill option",
+        ),
+        (
+            "SELECT a FROM b ORDER BY host",
+            "parse error at char 26: only ORDER BY time supported at this time",
+        ),
+        (
+            "SELECT a FROM b WHERE c =~ 'd'",
+            r#"parse error at char 31: regex operator "=~" requires a regex operand"#,
+        ),
+        (
+            "SELECT a::color FROM b",
+            r#"parse error at char 11: unknown data type "color""#,
+        ),
+        (
+            "SELECT a INTO c FROM b",
+            "parse error at char 10: SELECT INTO is not supported",
+        ),
+    ] {
+        assert_eq!(parse_error(query), *want, "query: {}", query);
+    }
+}
+
+#[test]
+fn transpile_errors() {
+    for (query, want) in &[
+        (
+            "SELECT mean(a), b FROM m",
+            "mixing aggregate and non-aggregate queries is not supported",
+        ),
+        (
+            "SELECT a FROM m GROUP BY time(1m)",
+            "GROUP BY requires at least one aggregate function",
+        ),
+        (
+            "SELECT mean(a) FROM m GROUP BY time(1m) fill(linear)",
+            "fill(linear) is not supported yet",
+        ),
+        (
+            "SELECT derivative(a) FROM m",
+            "function derivative() is not supported yet",
+        ),
+        (
+            "SELECT mean(a, b) FROM m",
+            "invalid number of arguments for mean, expected 1, got 2",
+        ),
+        (
+            "SELECT top(a, 3) FROM m GROUP BY time(1m)",
+            "top() and bottom() with GROUP BY time() are not supported yet",
+        ),
+        (
+            "SELECT a FROM m WHERE time > now() - 1h OR host = 'x'",
+            "time conditions must be combined with other conditions using AND",
+        ),
+        (
+            "SELECT a FROM m WHERE b > 1",
+            "conditions on fields other than the selected one are not supported yet: b",
+        ),
+        (
+            "SELECT a FROM m WHERE time > 'yesterday'",
+            r#"invalid time "yesterday""#,
+        ),
+        (
+            "SELECT a FROM db1..m, db2..m",
+            "measurements from different databases or retention policies are not supported yet",
+        ),
+        (
+            "SELECT a FROM m tz('Europe/Berlin')",
+            "tz() is not supported yet",
+        ),
+    ] {
+        assert_eq!(transpile_error(query), *want, "query: {}", query);
+    }
+}
@@ -0,0 +1,1097 @@
+use crate::ast::{self, BaseNode, Expression, LogicalOperator, Operator, PropertyKey, Statement};
+use crate::influxql::expr::{
+    BinaryOp, Call, DataType, Dimension, Expr, Field, Fill, Measurement, MeasurementName,
+    SelectStatement, VarRef,
+};
+use crate::influxql::Error;
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+// The earliest time InfluxDB can store, used when a query has no lower time
+// bound.
+fn min_time() -> DateTime<FixedOffset> {
+    Utc.timestamp(-9_223_372_037, 145_224_194).into()
+}
+
+/// A Transpiler converts InfluxQL `SELECT` statements into Flux.
+///
+/// Every selected field becomes its own pipeline that reads the field from
+/// the bucket `<database>/<retention policy>`, restricts it to the time range
+/// and condition of the statement, groups it like the statement and applies
+/// the aggregate, if any. Fields of one statement are then joined into rows
+/// by `pivot()` and each statement yields its result under its index.
+#[derive(Debug, Clone)]
+pub struct Transpiler {
+    /// The database of measurements that do not name one.
+    pub database: String,
+    /// The retention policy of measurements that do not name one.
+    pub retention_policy: String,
+    /// The time `now()` evaluates to. It is also the upper time bound of
+    /// statements whose condition has none.
+    pub now: DateTime<FixedOffset>,
+}
+
+// Aggregate is an InfluxQL aggregate or selector function applied to a field.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Aggregate {
+    Count,
+    Distinct,
+    Sum,
+    Mean,
+    Median,
+    Mode,
+    Spread,
+    Stddev,
+    First,
+    Last,
+    Min,
+    Max,
+    Percentile(f64),
+    Top(i64),
+    Bottom(i64),
+}
+
+impl Aggregate {
+    // is_selector reports whether the function returns points of the input
+    // with their own timestamps, rather than computing a new value.
+    fn is_selector(self) -> bool {
+        match self {
+            Aggregate::First
+            | Aggregate::Last
+            | Aggregate::Min
+            | Aggregate::Max
+            | Aggregate::Percentile(_)
+            | Aggregate::Top(_)
+            | Aggregate::Bottom(_) => true,
+            _ => false,
+        }
+    }
+
+    // yields_float reports whether the result is a float regardless of the
+    // type of the field.
+    fn yields_float(self) -> bool {
+        match self {
+            Aggregate::Mean | Aggregate::Median | Aggregate::Stddev => true,
+            _ => false,
+        }
+    }
+
+    // call builds the Flux call computing the aggregate. The column argument
+    // is passed through when the call is wrapped for aggregateWindow().
+    fn call(self, column: Option<Expression>) -> ast::CallExpr {
+        let (name, mut args) = match self {
+            Aggregate::Count => ("count", vec![]),
+            Aggregate::Distinct => ("distinct", vec![]),
+            Aggregate::Sum => ("sum", vec![]),
+            Aggregate::Mean => ("mean", vec![]),
+            Aggregate::Median => ("median", vec![("method", string("exact_mean"))]),
+            Aggregate::Mode => ("mode", vec![]),
+            Aggregate::Spread => ("spread", vec![]),
+            Aggregate::Stddev => ("stddev", vec![]),
+            Aggregate::First => ("first", vec![]),
+            Aggregate::Last => ("last", vec![]),
+            Aggregate::Min => ("min", vec![]),
+            Aggregate::Max => ("max", vec![]),
+            // InfluxQL percentiles select the point at the nearest rank.
+            Aggregate::Percentile(q) => (
+                "quantile",
+                vec![("q", float(q)), ("method", string("exact_selector"))],
+            ),
+            Aggregate::Top(n) => ("top", vec![("n", integer(n))]),
+            Aggregate::Bottom(n) => ("bottom", vec![("n", integer(n))]),
+        };
+        if let Some(column) = column {
+            args.push(("column", column));
+        }
+        call(name, args)
+    }
+
+    // function returns the aggregate as a function value that can be passed
+    // to aggregateWindow().
+    fn function(self) -> Expression {
+        let plain = self.call(None);
+        if let ast::CallExpr {
+            callee: Expression::Identifier(id),
+            arguments,
+            ..
+        } = &plain
+        {
+            if arguments.is_empty() {
+                return Expression::Identifier(id.clone());
+            }
+        }
+        // (column, tables=<-) => tables |> f(column: column, ...)
+        Expression::Function(Box::new(ast::FunctionExpr {
+            base: BaseNode::default(),
+            params: vec![
+                ast::Property {
+                    base: BaseNode::default(),
+                    key: PropertyKey::Identifier(identifier("column")),
+                    value: None,
+                },
+                ast::Property {
+                    base: BaseNode::default(),
+                    key: PropertyKey::Identifier(identifier("tables")),
+                    value: Some(Expression::PipeLit(ast::PipeLit {
+                        base: BaseNode::default(),
+                    })),
+                },
+            ],
+            body: ast::FunctionBody::Expr(pipeline(
+                Expression::Identifier(identifier("tables")),
+                vec![self.call(Some(Expression::Identifier(identifier("column"))))],
+            )),
+        }))
+    }
+}
+
+// Selection is what a field reads before any math is applied to it: a field
+// (or all fields for a wildcard) and optionally an aggregate over it.
+#[derive(Debug, Clone)]
+struct Selection {
+    field: Option<String>,
+    aggregate: Option<Aggregate>,
+}
+
+// FieldPlan describes how a single field of the field list is computed.
+struct FieldPlan {
+    selection: Selection,
+    // value computes the output value from `r._value`, if the field applies
+    // math to the selection.
+    value: Option<Expression>,
+    name: String,
+}
+
+// TimeRange collects the time bounds of a condition. The stop is exclusive.
+#[derive(Default)]
+struct TimeRange {
+    start: Option<DateTime<FixedOffset>>,
+    stop: Option<DateTime<FixedOffset>>,
+}
+
+impl TimeRange {
+    fn restrict_start(&mut self, t: DateTime<FixedOffset>) {
+        match self.start {
+            Some(start) if start >= t => (),
+            _ => self.start = Some(t),
+        }
+    }
+
+    fn restrict_stop(&mut self, t: DateTime<FixedOffset>) {
+        match self.stop {
+            Some(stop) if stop <= t => (),
+            _ => self.stop = Some(t),
+        }
+    }
+}
+
+// Grouping is the GROUP BY clause of a statement.
+#[derive(Default)]
+struct Grouping {
+    interval: Option<(Duration, Duration)>,
+    tags: Vec<String>,
+    wildcard: bool,
+}
+
+impl Transpiler {
+    /// Converts InfluxQL statements into a Flux package consisting of a
+    /// single file. The result of each statement is yielded under its index.
+    pub fn transpile(&self, statements: &[SelectStatement]) -> Result<ast::Package, Error> {
+        let mut body = Vec::with_capacity(statements.len());
+        for (i, stmt) in statements.iter().enumerate() {
+            let expression = pipeline(
+                self.transpile_select(stmt)?,
+                vec![call("yield", vec![("name", string(&i.to_string()))])],
+            );
+            body.push(Statement::Expr(ast::ExprStmt {
+                base: BaseNode::default(),
+                expression,
+            }));
+        }
+        let file = ast::File {
+            base: BaseNode::default(),
+            name: String::new(),
+            metadata: String::new(),
+            package: None,
+            imports: Vec::new(),
+            body,
+        };
+        Ok(ast::Package::from(file))
+    }
+
+    fn transpile_select(&self, stmt: &SelectStatement) -> Result<Expression, Error> {
+        if stmt.location.is_some() {
+            return Err("tz() is not supported yet".into());
+        }
+        if stmt.slimit != 0 || stmt.soffset != 0 {
+            return Err("SLIMIT and SOFFSET are not supported yet".into());
+        }
+        if stmt.offset != 0 && stmt.limit == 0 {
+            return Err("OFFSET without LIMIT is not supported yet".into());
+        }
+        if stmt.fill == Fill::Linear {
+            return Err("fill(linear) is not supported yet".into());
+        }
+
+        let grouping = grouping(&stmt.dimensions)?;
+        let plans = stmt
+            .fields
+            .iter()
+            .map(field_plan)
+            .collect::<Result<Vec<_>, Error>>()?;
+        let aggregated = plans.iter().any(|p| p.selection.aggregate.is_some());
+        if aggregated && plans.iter().any(|p| p.selection.aggregate.is_none()) {
+            return Err("mixing aggregate and non-aggregate queries is not supported".into());
+        }
+        if !aggregated && grouping.interval.is_some() {
+            return Err("GROUP BY requires at least one aggregate function".into());
+        }
+        if plans.len() > 1 && plans.iter().any(|p| p.selection.field.is_none()) {
+            return Err("wildcards cannot be combined with other fields yet".into());
+        }
+
+        let (bucket, measurement) = self.sources(&stmt.sources)?;
+        let mut range = TimeRange::default();
+        let condition = match &stmt.condition {
+            Some(cond) => self.split_condition(cond, &mut range)?,
+            None => None,
+        };
+        let range_call = call(
+            "range",
+            vec![
+                ("start", datetime(range.start.unwrap_or_else(min_time))),
+                ("stop", datetime(range.stop.unwrap_or(self.now))),
+            ],
+        );
+
+        let mut tables = Vec::with_capacity(plans.len());
+        for plan in &plans {
+            let mut calls = vec![range_call.clone()];
+            let mut predicate = measurement.clone();
+            if let Some(field) = &plan.selection.field {
+                predicate = logical(
+                    LogicalOperator::AndOperator,
+                    predicate,
+                    binary(
+                        Operator::EqualOperator,
+                        member("r", "_field"),
+                        string(field),
+                    ),
+                );
+            }
+            if let Some(cond) = &condition {
+                let field = plan.selection.field.as_ref().map(String::as_str);
+                predicate = logical(
+                    LogicalOperator::AndOperator,
+                    predicate,
+                    field_condition(cond, field)?,
+                );
+            }
+            calls.push(call("filter", vec![("fn", function(predicate))]));
+            calls.extend(self.field_calls(plan, &grouping, &stmt.fill)?);
+            tables.push(pipeline(bucket_call(&bucket), calls));
+        }
+
+        let mut calls = Vec::new();
+        let mut result = if tables.len() == 1 {
+            tables.remove(0)
+        } else {
+            calls.push(pivot_call());
+            Expression::Call(Box::new(call(
+                "union",
+                vec![(
+                    "tables",
+                    Expression::Array(Box::new(ast::ArrayExpr {
+                        base: BaseNode::default(),
+                        elements: tables,
+                    })),
+                )],
+            )))
+        };
+        // Merging series loses the time order of raw points, while windowed
+        // aggregates are already sorted.
+        if !aggregated || stmt.order_desc {
+            let mut args = vec![("columns", column_list(&["_time"]))];
+            if stmt.order_desc {
+                args.push(("desc", boolean(true)));
+            }
+            calls.push(call("sort", args));
+        }
+        if stmt.limit != 0 {
+            let mut args = vec![("n", integer(stmt.limit))];
+            if stmt.offset != 0 {
+                args.push(("offset", integer(stmt.offset)));
+            }
+            calls.push(call("limit", args));
+        }
+        if !calls.is_empty() {
+            result = pipeline(result, calls);
+        }
+        Ok(result)
+    }
+
+    // field_calls builds the calls that group and aggregate a field once it
+    // has been filtered. The `_field` column is renamed after the output
+    // column, which also names the column the field is pivoted into.
+    fn field_calls(
+        &self,
+        plan: &FieldPlan,
+        grouping: &Grouping,
+        fill: &Fill,
+    ) -> Result<Vec<ast::CallExpr>, Error> {
+        let mut calls = Vec::new();
+        let wildcard = plan.selection.field.is_none();
+        if wildcard {
+            calls.push(pivot_call());
+        }
+        if !grouping.wildcard {
+            let mut columns = vec!["_start", "_stop", "_measurement"];
+            if !wildcard {
+                columns.push("_field");
+            }
+            columns.extend(grouping.tags.iter().map(String::as_str));
+            calls.push(call(
+                "group",
+                vec![("columns", column_list(&columns)), ("mode", string("by"))],
+            ));
+        }
+        if let Some(agg) = plan.selection.aggregate {
+            match grouping.interval {
+                Some((every, offset)) => {
+                    calls.extend(self.window_calls(agg, every, offset, fill)?);
+                }
+                None => {
+                    calls.push(agg.call(None));
+                    if !agg.is_selector() {
+                        calls.push(call(
+                            "duplicate",
+                            vec![("column", string("_start")), ("as", string("_time"))],
+                        ));
+                    }
+                }
+            }
+        }
+        if let Some(value) = &plan.value {
+            calls.push(call(
+                "map",
+                vec![(
+                    "fn",
+                    function(with_r(vec![property("_value", value.clone())])),
+                )],
+            ));
+        }
+        if !wildcard && Some(&plan.name) != plan.selection.field.as_ref() {
+            calls.push(call(
+                "set",
+                vec![("key", string("_field")), ("value", string(&plan.name))],
+            ));
+        }
+        Ok(calls)
+    }
+
+    // window_calls aggregates a field per GROUP BY time() interval. InfluxQL
+    // labels each window with its start time.
+    fn window_calls(
+        &self,
+        agg: Aggregate,
+        every: Duration,
+        offset: Duration,
+        fill: &Fill,
+    ) -> Result<Vec<ast::CallExpr>, Error> {
+        match agg {
+            Aggregate::Top(_) | Aggregate::Bottom(_) => {
+                return Err("top() and bottom() with GROUP BY time() are not supported yet".into())
+            }
+            _ => (),
+        }
+        let create_empty = *fill != Fill::None;
+        let mut calls = if offset == Duration::zero() {
+            let mut args = vec![
+                ("every", duration(every)),
+                ("fn", agg.function()),
+                ("timeSrc", string("_start")),
+            ];
+            if !create_empty {
+                args.push(("createEmpty", boolean(false)));
+            }
+            vec![call("aggregateWindow", args)]
+        } else {
+            // aggregateWindow() cannot shift its windows, so spell it out.
+            let offset = nanos(offset) % nanos(every);
+            let offset = if offset < 0 {
+                offset + nanos(every)
+            } else {
+                offset
+            };
+            vec![
+                call(
+                    "window",
+                    vec![
+                        ("every", duration(every)),
+                        ("offset", duration(Duration::nanoseconds(offset))),
+                        ("createEmpty", boolean(create_empty)),
+                    ],
+                ),
+                agg.call(None),
+                call(
+                    "duplicate",
+                    vec![("column", string("_start")), ("as", string("_time"))],
+                ),
+                call(
+                    "window",
+                    vec![("every", Expression::Identifier(identifier("inf")))],
+                ),
+            ]
+        };
+        match fill {
+            Fill::Previous => calls.push(call("fill", vec![("usePrevious", boolean(true))])),
+            Fill::Integer(n) if agg.yields_float() => {
+                calls.push(call("fill", vec![("value", float(*n as f64))]))
+            }
+            Fill::Integer(n) => calls.push(call("fill", vec![("value", integer(*n))])),
+            Fill::Number(n) => calls.push(call("fill", vec![("value", float(*n))])),
+            Fill::Null | Fill::None | Fill::Linear => (),
+        }
+        Ok(calls)
+    }
+
+    // sources returns the bucket to read from and a predicate matching the
+    // measurements of the FROM clause.
+    fn sources(&self, sources: &[Measurement]) -> Result<(String, Expression), Error> {
+        let mut bucket: Option<String> = None;
+        let mut predicate: Option<Expression> = None;
+        for m in sources {
+            let db = m.database.as_ref().unwrap_or(&self.database);
+            if db.is_empty() {
+                return Err("database name required".into());
+            }
+            let rp = match &m.retention_policy {
+                Some(rp) => rp,
+                None => &self.retention_policy,
+            };
+            let b = format!("{}/{}", db, rp);
+            match &bucket {
+                Some(bucket) if *bucket != b => {
+                    return Err(
+                        "measurements from different databases or retention policies are not supported yet"
+                            .into(),
+                    )
+                }
+                _ => bucket = Some(b),
+            }
+            let p = match &m.name {
+                MeasurementName::Name(name) => binary(
+                    Operator::EqualOperator,
+                    member("r", "_measurement"),
+                    string(name),
+                ),
+                MeasurementName::Regex(re) => binary(
+                    Operator::RegexpMatchOperator,
+                    member("r", "_measurement"),
+                    regex(re),
+                ),
+            };
+            predicate = Some(match predicate {
+                Some(left) => logical(LogicalOperator::OrOperator, left, p),
+                None => p,
+            });
+        }
+        match (bucket, predicate) {
+            (Some(bucket), Some(predicate)) => Ok((bucket, predicate)),
+            _ => Err("at least one measurement is required".into()),
+        }
+    }
+
+    // split_condition moves the time bounds of a condition into range and
+    // returns what remains of the condition. Time bounds must be combined
+    // with the rest of the condition by AND.
+    fn split_condition(&self, cond: &Expr, range: &mut TimeRange) -> Result<Option<Expr>, Error> {
+        match cond {
+            Expr::Paren(e) => self.split_condition(e, range),
+            Expr::Binary(b) if b.op == BinaryOp::And => {
+                let lhs = self.split_condition(&b.lhs, range)?;
+                let rhs = self.split_condition(&b.rhs, range)?;
+                Ok(match (lhs, rhs) {
+                    (Some(lhs), Some(rhs)) => {
+                        Some(Expr::Binary(Box::new(crate::influxql::expr::BinaryExpr {
+                            op: BinaryOp::And,
+                            lhs,
+                            rhs,
+                        })))
+                    }
+                    (Some(e), None) | (None, Some(e)) => Some(e),
+                    (None, None) => None,
+                })
+            }
+            _ if !cond.refers_to_time() => Ok(Some(cond.clone())),
+            Expr::Binary(b) if b.op.is_comparison() => {
+                let (op, value) = if is_time_ref(&b.lhs) {
+                    (b.op, &b.rhs)
+                } else if is_time_ref(&b.rhs) {
+                    (flip(b.op), &b.lhs)
+                } else {
+                    return Err("invalid time comparison".into());
+                };
+                let t = self.time_value(value)?;
+                let next = |t: DateTime<FixedOffset>| -> Result<_, Error> {
+                    add(t, Duration::nanoseconds(1))
+                };
+                match op {
+                    BinaryOp::Gt => range.restrict_start(next(t)?),
+                    BinaryOp::Gte => range.restrict_start(t),
+                    BinaryOp::Lt => range.restrict_stop(t),
+                    BinaryOp::Lte => range.restrict_stop(next(t)?),
+                    BinaryOp::Eq => {
+                        range.restrict_start(t);
+                        range.restrict_stop(next(t)?);
+                    }
+                    _ => return Err("invalid operator in time comparison".into()),
+                }
+                Ok(None)
+            }
+            _ => Err("time conditions must be combined with other conditions using AND".into()),
+        }
+    }
+
+    // time_value evaluates the operand of a time comparison.
+    fn time_value(&self, e: &Expr) -> Result<DateTime<FixedOffset>, Error> {
+        match e {
+            Expr::Paren(e) => self.time_value(e),
+            Expr::Call(Call { name, args }) if name == "now" && args.is_empty() => Ok(self.now),
+            Expr::String(s) => parse_time(s),
+            Expr::Integer(n) => add(epoch(), Duration::nanoseconds(*n)),
+            Expr::Duration(d) => add(epoch(), *d),
+            Expr::Binary(b) if b.op == BinaryOp::Add || b.op == BinaryOp::Sub => {
+                let t = self.time_value(&b.lhs)?;
+                let d = match &b.rhs {
+                    Expr::Duration(d) => *d,
+                    Expr::Integer(n) => Duration::nanoseconds(*n),
+                    _ => return Err("time expressions may only add or subtract durations".into()),
+                };
+                add(t, if b.op == BinaryOp::Add { d } else { -d })
+            }
+            _ => Err("invalid time expression".into()),
+        }
+    }
+}
+
+fn grouping(dimensions: &[Dimension]) -> Result<Grouping, Error> {
+    let mut g = Grouping::default();
+    for dim in dimensions {
+        match dim {
+            Dimension::Time { interval, offset } => {
+                if g.interval.is_some() {
+                    return Err("multiple time dimensions not allowed".into());
+                }
+                g.interval = Some((*interval, *offset));
+            }
+            Dimension::Tag(tag) => g.tags.push(tag.clone()),
+            Dimension::Wildcard => g.wildcard = true,
+            Dimension::Regex(_) => {
+                return Err("GROUP BY with a regex is not supported yet".into());
+            }
+        }
+    }
+    Ok(g)
+}
+
+fn field_plan(field: &Field) -> Result<FieldPlan, Error> {
+    let mut selection = None;
+    let value = match &field.expr {
+        Expr::VarRef(_) | Expr::Call(_) | Expr::Wildcard => {
+            selection = Some(select(&field.expr)?);
+            None
+        }
+        e => Some(field_math(e, &mut selection)?),
+    };
+    match selection {
+        Some(selection) => Ok(FieldPlan {
+            selection,
+            value,
+            name: field.name(),
+        }),
+        None => Err("field must contain at least one variable".into()),
+    }
+}
+
+// field_math converts arithmetic in the field list into an expression over
+// `r._value`. Values are converted to floats so that integer fields and
+// literals can be mixed freely.
+fn field_math(e: &Expr, selection: &mut Option<Selection>) -> Result<Expression, Error> {
+    match e {
+        Expr::VarRef(_) | Expr::Call(_) => {
+            if selection.is_some() {
+                return Err("binary expressions between fields are not supported yet".into());
+            }
+            *selection = Some(select(e)?);
+            Ok(Expression::Call(Box::new(call(
+                "float",
+                vec![("v", member("r", "_value"))],
+            ))))
+        }
+        Expr::Integer(n) => Ok(float(*n as f64)),
+        Expr::Number(n) => Ok(float(*n)),
+        Expr::Paren(e) => field_math(e, selection),
+        Expr::Binary(b) => {
+            let op = match b.op {
+                BinaryOp::Add => Operator::AdditionOperator,
+                BinaryOp::Sub => Operator::SubtractionOperator,
+                BinaryOp::Mul => Operator::MultiplicationOperator,
+                BinaryOp::Div => Operator::DivisionOperator,
+                BinaryOp::Mod => Operator::ModuloOperator,
+                BinaryOp::BitwiseAnd | BinaryOp::BitwiseOr | BinaryOp::BitwiseXor => {
+                    return Err("bitwise operators are not supported yet".into())
+                }
+                _ => return Err("invalid operator in field list".into()),
+            };
+            let lhs = field_math(&b.lhs, selection)?;
+            let rhs = field_math(&b.rhs, selection)?;
+            Ok(binary(op, lhs, rhs))
+        }
+        Expr::Wildcard => Err("wildcards cannot be used in expressions".into()),
+        _ => Err("invalid expression in field list".into()),
+    }
+}
+
+fn select(e: &Expr) -> Result<Selection, Error> {
+    match e {
+        Expr::VarRef(v) => Ok(Selection {
+            field: Some(v.name.clone()),
+            aggregate: None,
+        }),
+        Expr::Wildcard => Ok(Selection {
+            field: None,
+            aggregate: None,
+        }),
+        Expr::Call(c) => {
+            let field = match c.args.first() {
+                Some(Expr::VarRef(v)) => v.name.clone(),
+                Some(Expr::Wildcard) => {
+                    return Err(format!("wildcards in {}() are not supported yet", c.name).into())
+                }
+                Some(Expr::Call(inner)) => {
+                    return Err(format!(
+                        "nested function calls like {}({}()) are not supported yet",
+                        c.name, inner.name
+                    )
+                    .into())
+                }
+                _ => return Err(format!("expected field argument in {}()", c.name).into()),
+            };
+            Ok(Selection {
+                field: Some(field),
+                aggregate: Some(aggregate(c)?),
+            })
+        }
+        _ => Err("invalid expression in field list".into()),
+    }
+}
+
+fn aggregate(c: &Call) -> Result<Aggregate, Error> {
+    let expect_args = |n: usize| -> Result<(), Error> {
+        if c.args.len() != n {
+            return Err(format!(
+                "invalid number of arguments for {}, expected {}, got {}",
+                c.name,
+                n,
+                c.args.len()
+            )
+            .into());
+        }
+        Ok(())
+    };
+    let agg = match c.name.as_str() {
+        "count" => Aggregate::Count,
+        "distinct" => Aggregate::Distinct,
+        "sum" => Aggregate::Sum,
+        "mean" => Aggregate::Mean,
+        "median" => Aggregate::Median,
+        "mode" => Aggregate::Mode,
+        "spread" => Aggregate::Spread,
+        "stddev" => Aggregate::Stddev,
+        "first" => Aggregate::First,
+        "last" => Aggregate::Last,
+        "min" => Aggregate::Min,
+        "max" => Aggregate::Max,
+        "percentile" => {
+            expect_args(2)?;
+            let p = match c.args[1] {
+                Expr::Integer(n) => n as f64,
+                Expr::Number(n) => n,
+                _ => return Err("expected float argument in percentile()".into()),
+            };
+            if !(0.0..=100.0).contains(&p) {
+                return Err("percentile must be between 0 and 100".into());
+            }
+            return Ok(Aggregate::Percentile(p / 100.0));
+        }
+        "top" | "bottom" => {
+            if c.args.len() > 2 {
+                return Err(format!("{}() with tag arguments is not supported yet", c.name).into());
+            }
+            expect_args(2)?;
+            let n = match c.args[1] {
+                Expr::Integer(n) if n > 0 => n,
+                _ => return Err(format!("{}() requires a positive integer limit", c.name).into()),
+            };
+            return Ok(if c.name == "top" {
+                Aggregate::Top(n)
+            } else {
+                Aggregate::Bottom(n)
+            });
+        }
+        _ => return Err(format!("function {}() is not supported yet", c.name).into()),
+    };
+    expect_args(1)?;
+    Ok(agg)
+}
+
+// field_condition converts the non-time part of a WHERE clause into a Flux
+// predicate. Variables compared to strings or regexes are tags; variables
+// compared to numbers or booleans are fields, which can only be the field
+// being selected.
+fn field_condition(cond: &Expr, field: Option<&str>) -> Result<Expression, Error> {
+    match cond {
+        Expr::Paren(e) => field_condition(e, field),
+        Expr::Binary(b) if b.op == BinaryOp::And || b.op == BinaryOp::Or => {
+            let op = if b.op == BinaryOp::And {
+                LogicalOperator::AndOperator
+            } else {
+                LogicalOperator::OrOperator
+            };
+            Ok(logical(
+                op,
+                field_condition(&b.lhs, field)?,
+                field_condition(&b.rhs, field)?,
+            ))
+        }
+        Expr::Binary(b) if b.op.is_comparison() => {
+            let op = match b.op {
+                BinaryOp::Eq => Operator::EqualOperator,
+                BinaryOp::Neq => Operator::NotEqualOperator,
+                BinaryOp::Lt => Operator::LessThanOperator,
+                BinaryOp::Lte => Operator::LessThanEqualOperator,
+                BinaryOp::Gt => Operator::GreaterThanOperator,
+                BinaryOp::Gte => Operator::GreaterThanEqualOperator,
+                BinaryOp::EqRegex => Operator::RegexpMatchOperator,
+                _ => Operator::NotRegexpMatchOperator,
+            };
+            let lhs = operand(&b.lhs, &b.rhs, field)?;
+            let rhs = operand(&b.rhs, &b.lhs, field)?;
+            Ok(binary(op, lhs, rhs))
+        }
+        _ => Err("invalid condition, expected a comparison".into()),
+    }
+}
+
+fn operand(e: &Expr, other: &Expr, field: Option<&str>) -> Result<Expression, Error> {
+    match e {
+        Expr::VarRef(v) => {
+            if is_field_ref(v, other) {
+                match field {
+                    Some(f) if f == v.name => Ok(member("r", "_value")),
+                    _ => Err(format!(
+                        "conditions on fields other than the selected one are not supported yet: {}",
+                        v.name
+                    )
+                    .into()),
+                }
+            } else {
+                Ok(member("r", &v.name))
+            }
+        }
+        Expr::String(s) => Ok(string(s)),
+        Expr::Integer(n) => Ok(integer(*n)),
+        Expr::Number(n) => Ok(float(*n)),
+        Expr::Boolean(b) => Ok(boolean(*b)),
+        Expr::Regex(r) => Ok(regex(r)),
+        Expr::Paren(e) => operand(e, other, field),
+        _ => Err("invalid operand in condition".into()),
+    }
+}
+
+fn is_field_ref(v: &VarRef, other: &Expr) -> bool {
+    match v.data_type {
+        Some(DataType::Tag) => false,
+        Some(DataType::String) | None => match other {
+            Expr::Integer(_) | Expr::Number(_) | Expr::Boolean(_) => true,
+            _ => v.data_type.is_some(),
+        },
+        Some(_) => true,
+    }
+}
+
+fn is_time_ref(e: &Expr) -> bool {
+    match e {
+        Expr::VarRef(v) => v.name.eq_ignore_ascii_case("time"),
+        Expr::Paren(e) => is_time_ref(e),
+        _ => false,
+    }
+}
+
+// flip returns the operator that gives the same result when the operands
+// of a comparison are swapped.
+fn flip(op: BinaryOp) -> BinaryOp {
+    match op {
+        BinaryOp::Lt => BinaryOp::Gt,
+        BinaryOp::Lte => BinaryOp::Gte,
+        BinaryOp::Gt => BinaryOp::Lt,
+        BinaryOp::Gte => BinaryOp::Lte,
+        op => op,
+    }
+}
+
+fn epoch() -> DateTime<FixedOffset> {
+    Utc.timestamp(0, 0).into()
+}
+
+// parse_time parses a time string as accepted by InfluxQL: RFC3339, or a
+// UTC date with an optional time separated by a space.
+fn parse_time(s: &str) -> Result<DateTime<FixedOffset>, Error> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Ok(t);
+    }
+    if let Ok(t) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f") {
+        return Ok(DateTime::<Utc>::from_utc(t, Utc).into());
+    }
+    if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(DateTime::<Utc>::from_utc(d.and_hms(0, 0, 0), Utc).into());
+    }
+    Err(format!("invalid time {:?}", s).into())
+}
+
+fn add(t: DateTime<FixedOffset>, d: Duration) -> Result<DateTime<FixedOffset>, Error> {
+    match t.checked_add_signed(d) {
+        Some(t) => Ok(t),
+        None => Err(format!("time {} plus {} is out of range", t.to_rfc3339(), d).into()),
+    }
+}
+
+fn nanos(d: Duration) -> i64 {
+    // Durations are range checked when they are parsed.
+    d.num_nanoseconds().unwrap_or(std::i64::MAX)
+}
+
+// is_identifier reports whether a column name can be written as a plain
+// Flux identifier, rather than a string literal.
+fn is_identifier(name: &str) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "and", "builtin", "else", "empty", "exists", "if", "import", "in", "not", "option", "or",
+        "package", "return", "test", "then",
+    ];
+    let mut chars = name.chars();
+    let start = match chars.next() {
+        Some(c) => c.is_ascii_alphabetic() || c == '_',
+        None => false,
+    };
+    start && chars.all(|c| c.is_ascii_alphanumeric() || c == '_') && !KEYWORDS.contains(&name)
+}
+
+fn identifier(name: &str) -> ast::Identifier {
+    ast::Identifier {
+        base: BaseNode::default(),
+        name: name.to_string(),
+    }
+}
+
+fn property_key(name: &str) -> PropertyKey {
+    if is_identifier(name) {
+        PropertyKey::Identifier(identifier(name))
+    } else {
+        PropertyKey::StringLit(ast::StringLit {
+            base: BaseNode::default(),
+            value: name.to_string(),
+        })
+    }
+}
+
+fn property(key: &str, value: Expression) -> ast::Property {
+    ast::Property {
+        base: BaseNode::default(),
+        key: property_key(key),
+        value: Some(value),
+    }
+}
+
+fn member(object: &str, property: &str) -> Expression {
+    Expression::Member(Box::new(ast::MemberExpr {
+        base: BaseNode::default(),
+        object: Expression::Identifier(identifier(object)),
+        property: property_key(property),
+    }))
+}
+
+fn string(v: &str) -> Expression {
+    Expression::StringLit(ast::StringLit {
+        base: BaseNode::default(),
+        value: v.to_string(),
+    })
+}
+
+fn boolean(v: bool) -> Expression {
+    Expression::Boolean(ast::BooleanLit {
+        base: BaseNode::default(),
+        value: v,
+    })
+}
+
+fn integer(v: i64) -> Expression {
+    Expression::Integer(ast::IntegerLit {
+        base: BaseNode::default(),
+        value: v,
+    })
+}
+
+fn float(v: f64) -> Expression {
+    Expression::Float(ast::FloatLit {
+        base: BaseNode::default(),
+        value: v,
+    })
+}
+
+fn regex(v: &str) -> Expression {
+    Expression::Regexp(ast::RegexpLit {
+        base: BaseNode::default(),
+        value: v.to_string(),
+    })
+}
+
+fn nanoseconds(n: i64) -> Expression {
+    Expression::Duration(ast::DurationLit {
+        base: BaseNode::default(),
+        values: vec![ast::Duration {
+            magnitude: n,
+            unit: "ns".to_string(),
+        }],
+    })
+}
+
+// duration builds a duration literal from the largest units that divide it,
+// e.g. `1h30m` rather than `5400000000000ns`.
+fn duration(d: Duration) -> Expression {
+    const UNITS: &[(&str, i64)] = &[
+        ("w", 7 * 24 * 3_600_000_000_000),
+        ("d", 24 * 3_600_000_000_000),
+        ("h", 3_600_000_000_000),
+        ("m", 60_000_000_000),
+        ("s", 1_000_000_000),
+        ("ms", 1_000_000),
+        ("us", 1_000),
+        ("ns", 1),
+    ];
+    let mut n = nanos(d);
+    if n <= 0 {
+        return nanoseconds(n);
+    }
+    let mut values = Vec::new();
+    for (unit, size) in UNITS {
+        if n >= *size {
+            values.push(ast::Duration {
+                magnitude: n / size,
+                unit: unit.to_string(),
+            });
+            n %= size;
+        }
+    }
+    Expression::Duration(ast::DurationLit {
+        base: BaseNode::default(),
+        values,
+    })
+}
+
+fn datetime(t: DateTime<FixedOffset>) -> Expression {
+    Expression::DateTime(ast::DateTimeLit {
+        base: BaseNode::default(),
+        value: t,
+    })
+}
+
+fn column_list<S: AsRef<str>>(cols: &[S]) -> Expression {
+    Expression::Array(Box::new(ast::ArrayExpr {
+        base: BaseNode::default(),
+        elements: cols.iter().map(|c| string(c.as_ref())).collect(),
+    }))
+}
+
+fn binary(operator: Operator, left: Expression, right: Expression) -> Expression {
+    Expression::Binary(Box::new(ast::BinaryExpr {
+        base: BaseNode::default(),
+        operator,
+        left,
+        right,
+    }))
+}
+
+fn logical(operator: LogicalOperator, left: Expression, right: Expression) -> Expression {
+    Expression::Logical(Box::new(ast::LogicalExpr {
+        base: BaseNode::default(),
+        operator,
+        left,
+        right,
+    }))
+}
+
+fn call(name: &str, args: Vec<(&str, Expression)>) -> ast::CallExpr {
+    let arguments = if args.is_empty() {
+        Vec::new()
+    } else {
+        vec![Expression::Object(Box::new(ast::ObjectExpr {
+            base: BaseNode::default(),
+            with: None,
+            properties: args.into_iter().map(|(k, v)| property(k, v)).collect(),
+        }))]
+    };
+    ast::CallExpr {
+        base: BaseNode::default(),
+        callee: Expression::Identifier(identifier(name)),
+        arguments,
+    }
+}
+
+fn bucket_call(bucket: &str) -> Expression {
+    Expression::Call(Box::new(call("from", vec![("bucket", string(bucket))])))
+}
+
+// pivot_call turns the fields of a row into columns.
+fn pivot_call() -> ast::CallExpr {
+    call(
+        "pivot",
+        vec![
+            ("rowKey", column_list(&["_time"])),
+            ("columnKey", column_list(&["_field"])),
+            ("valueColumn", string("_value")),
+        ],
+    )
+}
+
+fn pipeline(arg: Expression, calls: Vec<ast::CallExpr>) -> Expression {
+    calls.into_iter().fold(arg, |argument, call| {
+        Expression::PipeExpr(Box::new(ast::PipeExpr {
+            base: BaseNode::default(),
+            argument,
+            call,
+        }))
+    })
+}
+
+// function builds `(r) => <body>`.
+fn function(body: Expression) -> Expression {
+    Expression::Function(Box::new(ast::FunctionExpr {
+        base: BaseNode::default(),
+        params: vec![ast::Property {
+            base: BaseNode::default(),
+            key: PropertyKey::Identifier(identifier("r")),
+            value: None,
+        }],
+        body: ast::FunctionBody::Expr(body),
+    }))
+}
+
+// with_r builds `{r with <properties>}`.
+fn with_r(properties: Vec<ast::Property>) -> Expression {
+    Expression::Object(Box::new(ast::ObjectExpr {
+        base: BaseNode::default(),
+        with: Some(identifier("r")),
+        properties,
+    }))
+}
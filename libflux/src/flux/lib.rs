@@ -9,18 +9,24 @@ extern crate serde_aux;
 
 pub mod ast;
 pub mod formatter;
+pub mod influxql;
+pub mod limits;
 pub mod parser;
+pub mod promql;
 pub mod scanner;
 pub mod semantic;
 
+use std::any::Any;
 use std::error;
 use std::ffi::*;
 use std::fmt;
 use std::os::raw::{c_char, c_void};
+use std::panic;
 
 use parser::Parser;
 
 pub use ast::DEFAULT_PACKAGE_NAME;
+pub use limits::Limits;
 
 #[allow(non_camel_case_types, missing_docs)]
 pub mod ctypes {
@@ -40,6 +46,14 @@ pub struct ErrorHandle {
 #[derive(Debug)]
 pub struct Error {
     msg: String,
+    limit: Option<limits::Error>,
+}
+
+impl Error {
+    /// Returns the limit that was exceeded, if that is the cause of the error.
+    pub fn limit(&self) -> Option<&limits::Error> {
+        self.limit.as_ref()
+    }
 }
 
 impl fmt::Display for Error {
@@ -56,7 +70,7 @@ impl error::Error for Error {
 
 impl From<String> for Error {
     fn from(msg: String) -> Self {
-        Error { msg }
+        Error { msg, limit: None }
     }
 }
 
@@ -64,13 +78,17 @@ impl From<&str> for Error {
     fn from(msg: &str) -> Self {
         Error {
             msg: String::from(msg),
+            limit: None,
         }
     }
 }
 
 impl From<semantic::nodes::Error> for Error {
     fn from(sn_err: semantic::nodes::Error) -> Self {
-        Error { msg: sn_err.msg }
+        Error {
+            msg: sn_err.msg,
+            limit: None,
+        }
     }
 }
 
@@ -78,6 +96,16 @@ impl From<semantic::check::Error> for Error {
     fn from(err: semantic::check::Error) -> Self {
         Error {
             msg: format!("{}", err),
+            limit: None,
+        }
+    }
+}
+
+impl From<limits::Error> for Error {
+    fn from(err: limits::Error) -> Self {
+        Error {
+            msg: err.to_string(),
+            limit: Some(err),
         }
     }
 }
@@ -91,6 +119,139 @@ pub struct flux_buffer_t {
     pub len: usize,
 }
 
+/// Limits on parsing and analysis, passed in from C. A field that is zero
+/// takes its default: `max_depth` defaults to `Limits::DEFAULT_MAX_DEPTH`,
+/// and the other limits are not enforced.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct flux_limits_t {
+    /// The largest source accepted, in bytes.
+    pub max_source_len: u64,
+    /// How deeply expressions may nest.
+    pub max_depth: u64,
+    /// The most tokens the source may contain.
+    pub max_tokens: u64,
+    /// The most type variables inference may create.
+    pub max_type_vars: u64,
+    /// The most unification steps inference may take.
+    pub max_inference_steps: u64,
+}
+
+impl From<&flux_limits_t> for Limits {
+    fn from(limits: &flux_limits_t) -> Self {
+        let or = |max: u64, default: Option<u64>| if max == 0 { default } else { Some(max) };
+        let default = Limits::default();
+        Limits {
+            max_source_len: or(limits.max_source_len, default.max_source_len),
+            max_depth: or(limits.max_depth, default.max_depth),
+            max_tokens: or(limits.max_tokens, default.max_tokens),
+            max_type_vars: or(limits.max_type_vars, default.max_type_vars),
+            max_inference_steps: or(limits.max_inference_steps, default.max_inference_steps),
+        }
+    }
+}
+
+/// Reads limits passed in from C, where a null pointer takes the defaults.
+///
+/// # Safety
+///
+/// The pointer must be null or point to a valid `flux_limits_t`.
+pub unsafe fn read_limits(limits: *const flux_limits_t) -> Limits {
+    match limits.as_ref() {
+        Some(limits) => Limits::from(limits),
+        None => Limits::default(),
+    }
+}
+
+/// Runs the body of an exported function, turning a panic into an error
+/// so that it does not unwind into the caller. A stack overflow aborts the
+/// process instead, and cannot be caught; the parser and analyzer bound
+/// their recursion by the depth limit so that it does not happen.
+pub fn catch_panic<F>(f: F) -> *mut flux_error_t
+where
+    F: FnOnce() -> *mut flux_error_t,
+{
+    match panic::catch_unwind(panic::AssertUnwindSafe(f)) {
+        Ok(err) => err,
+        Err(payload) => {
+            let errh = ErrorHandle {
+                err: Box::new(Error::from(panic_message(payload))),
+            };
+            Box::into_raw(Box::new(errh)) as *mut flux_error_t
+        }
+    }
+}
+
+/// Runs the body of an exported function that reports failure through an
+/// error handle. An error returned by the body, or a panic within it, is
+/// handed to the caller as an error handle, and success as a null pointer.
+pub fn catch_errors<F>(f: F) -> *mut flux_error_t
+where
+    F: FnOnce() -> Result<(), Box<dyn error::Error>>,
+{
+    catch_panic(|| match f() {
+        Ok(()) => std::ptr::null_mut(),
+        Err(err) => {
+            let errh = ErrorHandle { err };
+            Box::into_raw(Box::new(errh)) as *mut flux_error_t
+        }
+    })
+}
+
+// source_to_buffer runs the body of an exported function that takes a
+// source string from C and writes its result to a buffer.
+unsafe fn source_to_buffer<F>(
+    cstr: *const c_char,
+    buf: *mut flux_buffer_t,
+    f: F,
+) -> *mut flux_error_t
+where
+    F: FnOnce(&str) -> Result<Vec<u8>, Box<dyn error::Error>>,
+{
+    catch_errors(|| {
+        let s = read_source(cstr)?;
+        write_buffer(buf, f(&s)?);
+        Ok(())
+    })
+}
+
+// write_buffer hands the ownership of data to the caller through a buffer.
+unsafe fn write_buffer(buf: *mut flux_buffer_t, data: Vec<u8>) {
+    let buffer = &mut *buf; // Unsafe
+    buffer.len = data.len();
+    buffer.data = Box::into_raw(data.into_boxed_slice()) as *mut u8;
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    let msg = if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        String::from("unknown cause")
+    };
+    format!("internal error: {}", msg)
+}
+
+// read_source reads a source string passed in from C, which must be valid
+// UTF-8.
+unsafe fn read_source(cstr: *const c_char) -> Result<String, Error> {
+    let buf = CStr::from_ptr(cstr).to_bytes(); // Unsafe
+    String::from_utf8(buf.to_vec()).map_err(|err| {
+        Error::from(format!(
+            "invalid UTF-8 in source at byte {}",
+            err.utf8_error().valid_up_to()
+        ))
+    })
+}
+
+// error_file returns an empty file that reports the given error.
+fn error_file(msg: String) -> ast::File {
+    let mut file = parser::parse_string("", "");
+    file.base.errors.push(msg);
+    file
+}
+
 /// # Safety
 ///
 /// This function is unsafe because it dereferences a raw pointer passed
@@ -98,13 +259,123 @@ pub struct flux_buffer_t {
 /// could occur.
 #[no_mangle]
 pub unsafe extern "C" fn flux_parse(cstr: *mut c_char) -> *mut flux_ast_pkg_t {
-    let buf = CStr::from_ptr(cstr).to_bytes(); // Unsafe
-    let s = String::from_utf8(buf.to_vec()).unwrap();
-    let mut p = Parser::new(&s);
-    let pkg: ast::Package = p.parse_file(String::from("")).into();
+    // Errors are reported on the file of the package, as parse errors are.
+    let file = panic::catch_unwind(|| match read_source(cstr) {
+        Ok(s) => Parser::new(&s).parse_file(String::from("")),
+        Err(err) => error_file(err.to_string()),
+    })
+    .unwrap_or_else(|payload| error_file(panic_message(payload)));
+    let pkg: ast::Package = file.into();
     Box::into_raw(Box::new(pkg)) as *mut flux_ast_pkg_t
 }
 
+/// # Safety
+///
+/// This function is unsafe because it dereferences raw pointers passed
+/// in as parameters. For example, if that pointer is NULL, undefined behavior
+/// could occur.
+#[no_mangle]
+pub unsafe extern "C" fn flux_parse_with_encoding(
+    cstr: *mut c_char,
+    encoding: *mut c_char,
+    out_pkg: *mut *const flux_ast_pkg_t,
+) -> *mut flux_error_t {
+    catch_errors(|| {
+        let s = read_source(cstr)?;
+        let encoding = CStr::from_ptr(encoding).to_string_lossy(); // Unsafe
+        let encoding: ast::PositionEncoding = encoding.parse().map_err(Error::from)?;
+        let mut p = Parser::with_encoding(&s, encoding);
+        let pkg: ast::Package = p.parse_file(String::from("")).into();
+        *out_pkg = Box::into_raw(Box::new(pkg)) as *const flux_ast_pkg_t;
+        Ok(())
+    })
+}
+
+/// # Safety
+///
+/// This function is unsafe because it dereferences raw pointers passed
+/// in as parameters. For example, if that pointer is NULL, undefined behavior
+/// could occur.
+#[no_mangle]
+pub unsafe extern "C" fn flux_parse_with_limits(
+    cstr: *mut c_char,
+    limits: *const flux_limits_t,
+    out_pkg: *mut *const flux_ast_pkg_t,
+) -> *mut flux_error_t {
+    catch_errors(|| {
+        let s = read_source(cstr)?;
+        let mut p = Parser::with_limits(&s, read_limits(limits));
+        let file = p.parse_file(String::from(""));
+        if let Some(err) = p.exceeded() {
+            return Err(Error::from(*err).into());
+        }
+        let pkg: ast::Package = file.into();
+        *out_pkg = Box::into_raw(Box::new(pkg)) as *const flux_ast_pkg_t;
+        Ok(())
+    })
+}
+
+/// # Safety
+///
+/// This function is unsafe because it dereferences raw pointers passed
+/// in as parameters. For example, if that pointer is NULL, undefined behavior
+/// could occur.
+#[no_mangle]
+pub unsafe extern "C" fn flux_tokenize(
+    cstr: *mut c_char,
+    buf: *mut flux_buffer_t,
+) -> *mut flux_error_t {
+    source_to_buffer(cstr, buf, |s| Ok(serde_json::to_vec(&parser::tokenize(s))?))
+}
+
+/// # Safety
+///
+/// This function is unsafe because it dereferences raw pointers passed
+/// in as parameters. For example, if that pointer is NULL, undefined behavior
+/// could occur.
+#[no_mangle]
+pub unsafe extern "C" fn flux_parse_expression(
+    cstr: *mut c_char,
+    buf: *mut flux_buffer_t,
+) -> *mut flux_error_t {
+    source_to_buffer(cstr, buf, |s| {
+        Ok(serde_json::to_vec(&parser::parse_expression(s))?)
+    })
+}
+
+/// # Safety
+///
+/// This function is unsafe because it dereferences raw pointers passed
+/// in as parameters. For example, if that pointer is NULL, undefined behavior
+/// could occur.
+#[no_mangle]
+pub unsafe extern "C" fn flux_parse_statement(
+    cstr: *mut c_char,
+    buf: *mut flux_buffer_t,
+) -> *mut flux_error_t {
+    source_to_buffer(cstr, buf, |s| {
+        Ok(serde_json::to_vec(&parser::parse_statement(s))?)
+    })
+}
+
+/// # Safety
+///
+/// This function is unsafe because it dereferences raw pointers passed
+/// in as parameters. For example, if that pointer is NULL, undefined behavior
+/// could occur.
+#[no_mangle]
+pub unsafe extern "C" fn flux_parse_type(
+    cstr: *mut c_char,
+    buf: *mut flux_buffer_t,
+) -> *mut flux_error_t {
+    source_to_buffer(cstr, buf, |s| {
+        let ty = semantic::parser::parse_located(s).map_err(|err| Error::from(err.to_string()))?;
+        Ok(semantic::printer::Printer::default()
+            .polytype(&ty)
+            .into_bytes())
+    })
+}
+
 /// # Safety
 ///
 /// This function is unsafe because it dereferences raw pointers passed
@@ -115,19 +386,11 @@ pub unsafe extern "C" fn flux_ast_marshal_json(
     ast_pkg: *mut flux_ast_pkg_t,
     buf: *mut flux_buffer_t,
 ) -> *mut flux_error_t {
-    let ast_pkg = &*(ast_pkg as *mut ast::Package) as &ast::Package; // Unsafe
-    let data = match serde_json::to_vec(ast_pkg) {
-        Ok(v) => v,
-        Err(err) => {
-            let errh = ErrorHandle { err: Box::new(err) };
-            return Box::into_raw(Box::new(errh)) as *mut flux_error_t;
-        }
-    };
-
-    let buffer = &mut *buf; // Unsafe
-    buffer.len = data.len();
-    buffer.data = Box::into_raw(data.into_boxed_slice()) as *mut u8;
-    std::ptr::null_mut()
+    catch_errors(|| {
+        let ast_pkg = &*(ast_pkg as *mut ast::Package) as &ast::Package; // Unsafe
+        write_buffer(buf, serde_json::to_vec(ast_pkg)?);
+        Ok(())
+    })
 }
 
 /// # Safety
@@ -140,22 +403,14 @@ pub unsafe extern "C" fn flux_ast_marshal_fb(
     ast: *mut flux_ast_pkg_t,
     buf: *mut flux_buffer_t,
 ) -> *mut flux_error_t {
-    let pkg = &*(ast as *mut ast::Package) as &ast::Package; // Unsafe
-    let (mut vec, offset) = match ast::flatbuffers::serialize(&pkg) {
-        Ok(vec_offset) => vec_offset,
-        Err(err) => {
-            let err: Error = err.into();
-            let errh = ErrorHandle { err: Box::new(err) };
-            return Box::into_raw(Box::new(errh)) as *mut flux_error_t;
-        }
-    };
+    catch_errors(|| {
+        let pkg = &*(ast as *mut ast::Package) as &ast::Package; // Unsafe
+        let (mut vec, offset) = ast::flatbuffers::serialize(&pkg).map_err(Error::from)?;
 
-    // Note, split_off() does a copy: https://github.com/influxdata/flux/issues/2194
-    let data = vec.split_off(offset);
-    let buffer = &mut *buf; // Unsafe
-    buffer.len = data.len();
-    buffer.data = Box::into_raw(data.into_boxed_slice()) as *mut u8;
-    std::ptr::null_mut()
+        // Note, split_off() does a copy: https://github.com/influxdata/flux/issues/2194
+        write_buffer(buf, vec.split_off(offset));
+        Ok(())
+    })
 }
 
 /// # Safety
@@ -168,22 +423,14 @@ pub unsafe extern "C" fn flux_semantic_marshal_fb(
     ast: *mut flux_semantic_pkg_t,
     buf: *mut flux_buffer_t,
 ) -> *mut flux_error_t {
-    let pkg = &*(ast as *mut semantic::nodes::Package) as &semantic::nodes::Package; // Unsafe
-    let (mut vec, offset) = match semantic::flatbuffers::serialize(&pkg) {
-        Ok(vec_offset) => vec_offset,
-        Err(err) => {
-            let err: Error = err.into();
-            let errh = ErrorHandle { err: Box::new(err) };
-            return Box::into_raw(Box::new(errh)) as *mut flux_error_t;
-        }
-    };
+    catch_errors(|| {
+        let pkg = &*(ast as *mut semantic::nodes::Package) as &semantic::nodes::Package; // Unsafe
+        let (mut vec, offset) = semantic::flatbuffers::serialize(&pkg).map_err(Error::from)?;
 
-    // Note, split_off() does a copy: https://github.com/influxdata/flux/issues/2194
-    let data = vec.split_off(offset);
-    let buffer = &mut *buf; // Unsafe
-    buffer.len = data.len();
-    buffer.data = Box::into_raw(data.into_boxed_slice()) as *mut u8;
-    std::ptr::null_mut()
+        // Note, split_off() does a copy: https://github.com/influxdata/flux/issues/2194
+        write_buffer(buf, vec.split_off(offset));
+        Ok(())
+    })
 }
 
 /// # Safety
@@ -193,7 +440,7 @@ pub unsafe extern "C" fn flux_semantic_marshal_fb(
 #[no_mangle]
 pub unsafe extern "C" fn flux_error_str(err: *mut flux_error_t) -> *mut c_char {
     let e = &*(err as *mut ErrorHandle); // Unsafe
-    let s = CString::new(format!("{}", e.err)).unwrap();
+    let s = CString::new(format!("{}", e.err).replace('\0', "")).unwrap_or_default();
     s.into_raw()
 }
 
@@ -0,0 +1,98 @@
+//! Limits on the resources used to parse and analyze a query, for callers
+//! that accept Flux from untrusted users.
+use std::error;
+use std::fmt;
+
+/// Limits bounds the work done for a single query. A limit that is `None`
+/// is not enforced. The default enforces only `max_depth`, as the parser and
+/// analyzer recurse over nested expressions and would otherwise overflow the
+/// stack on deeply nested input.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    /// The largest source accepted, in bytes.
+    pub max_source_len: Option<u64>,
+    /// How deeply expressions may nest. A chain of operators, such as
+    /// `a + b + c` or `f() |> g()`, nests one level for every operator.
+    pub max_depth: Option<u64>,
+    /// The most tokens the source may contain.
+    pub max_tokens: Option<u64>,
+    /// The most type variables inference may create.
+    pub max_type_vars: Option<u64>,
+    /// The most unification steps inference may take.
+    pub max_inference_steps: Option<u64>,
+}
+
+impl Limits {
+    /// The depth allowed by default. It leaves room for the deepest
+    /// expressions of the standard library, and is shallow enough to be
+    /// parsed, analyzed and serialized within a stack of 8 MiB.
+    pub const DEFAULT_MAX_DEPTH: u64 = 128;
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_source_len: None,
+            max_depth: Some(Limits::DEFAULT_MAX_DEPTH),
+            max_tokens: None,
+            max_type_vars: None,
+            max_inference_steps: None,
+        }
+    }
+}
+
+/// Limit names one of the bounds of `Limits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// `max_source_len`
+    SourceLen,
+    /// `max_depth`
+    Depth,
+    /// `max_tokens`
+    Tokens,
+    /// `max_type_vars`
+    TypeVars,
+    /// `max_inference_steps`
+    InferenceSteps,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Limit::SourceLen => "source length",
+            Limit::Depth => "nesting depth",
+            Limit::Tokens => "token count",
+            Limit::TypeVars => "type variable count",
+            Limit::InferenceSteps => "inference step count",
+        })
+    }
+}
+
+/// Error reports that a query exceeded one of its limits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Error {
+    /// The limit that was exceeded.
+    pub limit: Limit,
+    /// The maximum that was set for it.
+    pub max: u64,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} limit of {} exceeded", self.limit, self.max)
+    }
+}
+
+impl error::Error for Error {}
+
+impl Limit {
+    /// check returns an error if a count has gone past the maximum set for
+    /// this limit, if any.
+    pub fn check(self, max: Option<u64>, count: u64) -> Result<(), Error> {
+        match max {
+            Some(max) if count > max => Err(Error { limit: self, max }),
+            _ => Ok(()),
+        }
+    }
+}
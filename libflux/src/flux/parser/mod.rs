@@ -3,21 +3,68 @@ use std::collections::HashMap;
 use std::ffi::CString;
 use std::str;
 
+use chrono::{FixedOffset, TimeZone};
+
 use crate::ast;
 use crate::ast::*;
+use crate::limits::{self, Limit, Limits};
 use crate::scanner;
 use crate::scanner::*;
 
 use wasm_bindgen::prelude::*;
 
-mod strconv;
+pub(crate) mod strconv;
 
 #[wasm_bindgen]
-pub fn parse(s: &str) -> JsValue {
+pub fn parse(s: &str) -> Result<JsValue, JsValue> {
     let mut p = Parser::new(s);
     let file = p.parse_file(String::from(""));
 
-    JsValue::from_serde(&file).unwrap()
+    to_js(&file)
+}
+
+// Parses a string of source code like parse, and also copies the raw source
+// of every node into its location.
+#[wasm_bindgen]
+pub fn parse_with_source(s: &str) -> Result<JsValue, JsValue> {
+    let mut p = Parser::with_source(s);
+    let file = p.parse_file(String::from(""));
+
+    to_js(&file)
+}
+
+// to_js converts a value for JavaScript, failing with an exception rather
+// than a panic.
+fn to_js<T: serde::Serialize>(v: &T) -> Result<JsValue, JsValue> {
+    JsValue::from_serde(v).map_err(|e| JsValue::from(e.to_string()))
+}
+
+// Parses a string of source code, counting the columns of positions in the
+// named encoding, "utf-8" or "utf-16". JavaScript strings index by UTF-16
+// code units.
+#[wasm_bindgen]
+pub fn parse_with_encoding(s: &str, encoding: &str) -> Result<JsValue, JsValue> {
+    let encoding: PositionEncoding = encoding.parse().map_err(|e: String| JsValue::from(e))?;
+    let mut p = Parser::with_encoding(s, encoding);
+    let file = p.parse_file(String::from(""));
+
+    to_js(&file)
+}
+
+// Parses a string of source code, unless it exceeds one of the given limits.
+// The limits are an object with the fields of Limits, such as
+// {max_depth: 64}; a field left out takes its default.
+#[wasm_bindgen]
+pub fn parse_with_limits(s: &str, limits: JsValue) -> Result<JsValue, JsValue> {
+    let limits: Limits = limits
+        .into_serde()
+        .map_err(|e| JsValue::from(e.to_string()))?;
+    let mut p = Parser::with_limits(s, limits);
+    let file = p.parse_file(String::from(""));
+    match p.exceeded() {
+        Some(err) => Err(JsValue::from(err.to_string())),
+        None => to_js(&file),
+    }
 }
 
 // Parses a string of source code.
@@ -27,6 +74,62 @@ pub fn parse_string(name: &str, s: &str) -> File {
     p.parse_file(String::from(name))
 }
 
+// Parses a string of source code, unless it exceeds one of the given limits.
+pub fn parse_string_with_limits(
+    name: &str,
+    s: &str,
+    limits: Limits,
+) -> Result<File, limits::Error> {
+    let mut p = Parser::with_limits(s, limits);
+    let file = p.parse_file(String::from(name));
+    match p.exceeded() {
+        Some(err) => Err(*err),
+        None => Ok(file),
+    }
+}
+
+// Parses a string of source code as a single expression. Input left over
+// after the expression is reported as an error on it.
+pub fn parse_expression(s: &str) -> Expression {
+    Parser::new(s).parse_single_expression()
+}
+
+// Parses a string of source code as a single statement. Input left over
+// after the statement is reported as an error on it.
+pub fn parse_statement(s: &str) -> Statement {
+    Parser::new(s).parse_single_statement()
+}
+
+#[wasm_bindgen(js_name = parse_expression)]
+pub fn parse_expression_js(s: &str) -> Result<JsValue, JsValue> {
+    to_js(&parse_expression(s))
+}
+
+#[wasm_bindgen(js_name = parse_statement)]
+pub fn parse_statement_js(s: &str) -> Result<JsValue, JsValue> {
+    to_js(&parse_statement(s))
+}
+
+// Lexeme is a token of the source, as returned by tokenize. Its kind is the
+// name of the token, such as IDENT, REGEX or COMMENT.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Lexeme {
+    pub kind: &'static str,
+    pub span: Span,
+    pub start: ast::Position,
+    pub end: ast::Position,
+}
+
+// Returns every token of a string of source code, comments included.
+pub fn tokenize(s: &str) -> Vec<Lexeme> {
+    Parser::new(s).tokenize()
+}
+
+#[wasm_bindgen(js_name = tokenize)]
+pub fn tokenize_js(s: &str) -> Result<JsValue, JsValue> {
+    to_js(&tokenize(s))
+}
+
 // TODO uncomment when we get back to the Go build side.
 //#[no_mangle]
 //pub fn go_parse(s: *const c_char) {
@@ -94,7 +197,7 @@ fn format_token(t: TOK) -> &'static str {
         TOK_QUOTE => "QUOTE",
         TOK_STRINGEXPR => "STRINGEXPR",
         TOK_TEXT => "TEXT",
-        _ => panic!("unknown token {}", t),
+        _ => "UNKNOWN",
     }
 }
 
@@ -108,20 +211,143 @@ pub struct Parser {
 
     fname: String,
     source: String,
+    // keep_source copies the raw source of every node into its location.
+    keep_source: bool,
+    // lines is set when positions are counted in an encoding other than the
+    // scanner's UTF-8 bytes.
+    lines: Option<LineIndex>,
+    encoding: PositionEncoding,
+    // tokens records every token scanned, when it is set.
+    tokens: Option<Vec<Token>>,
+
+    limits: Limits,
+    // depth counts the expressions being parsed that enclose the current one,
+    // and the links of the chains of operators within them.
+    depth: u64,
+    // links counts the links of chains within the current expression, and
+    // height is the height of the tallest expression nested within it.
+    links: u64,
+    height: u64,
+    scanned: u64,
+    // exceeded records the first limit that was exceeded. After that the
+    // parser reads eof in place of the rest of the input.
+    exceeded: Option<limits::Error>,
+    // input_errs holds the errors about the input as a whole, which are
+    // reported on the file.
+    input_errs: Vec<String>,
+    eof: Option<Token>,
 }
 
 impl Parser {
     pub fn new(src: &str) -> Parser {
-        let cdata = CString::new(src).expect("CString::new failed");
+        // The scanner reads a NUL-terminated string, so the source is cut
+        // short at any NUL byte within it.
+        let (cdata, errs) = match CString::new(src) {
+            Ok(cdata) => (cdata, Vec::new()),
+            Err(err) => {
+                let at = err.nul_position();
+                let mut bytes = err.into_vec();
+                bytes.truncate(at);
+                (
+                    CString::new(bytes).unwrap_or_default(),
+                    vec![format!("unexpected NUL byte at offset {}", at)],
+                )
+            }
+        };
         let s = Scanner::new(cdata);
         Parser {
             s,
             t: None,
             errs: Vec::new(),
+            input_errs: errs,
             blocks: HashMap::new(),
             fname: "".to_string(),
             source: src.to_string(),
+            keep_source: false,
+            lines: None,
+            encoding: PositionEncoding::Utf8,
+            tokens: None,
+            limits: Limits::default(),
+            depth: 0,
+            links: 0,
+            height: 0,
+            scanned: 0,
+            exceeded: None,
+            eof: None,
+        }
+    }
+
+    // with_limits returns a parser that stops once the source breaks one of
+    // the given limits, reporting the limit as an error.
+    pub fn with_limits(src: &str, limits: Limits) -> Parser {
+        if let Err(err) = Limit::SourceLen.check(limits.max_source_len, src.len() as u64) {
+            let mut p = Parser::new("");
+            let eof = p.s.scan();
+            p.exceed(err, &eof);
+            return p;
+        }
+        let mut p = Parser::new(src);
+        p.limits = limits;
+        p
+    }
+
+    // exceeded returns the limit the source has exceeded, if any.
+    pub fn exceeded(&self) -> Option<&limits::Error> {
+        self.exceeded.as_ref()
+    }
+
+    // with_encoding returns a parser that counts the columns of positions in
+    // the given encoding. Spans are byte offsets in any encoding.
+    pub fn with_encoding(src: &str, encoding: PositionEncoding) -> Parser {
+        let mut p = Parser::new(src);
+        if encoding != PositionEncoding::Utf8 {
+            p.lines = Some(LineIndex::new(src));
+            p.encoding = encoding;
         }
+        p
+    }
+
+    // with_source returns a parser that also stores the raw source of every
+    // node in its location, for consumers that need the text itself rather
+    // than its span.
+    pub fn with_source(src: &str) -> Parser {
+        let mut p = Parser::new(src);
+        p.keep_source();
+        p
+    }
+
+    // keep_source makes the parser store the raw source of every node in its
+    // location, as with_source does.
+    pub fn keep_source(&mut self) {
+        self.keep_source = true;
+    }
+
+    // with_comments returns a parser that keeps the comments it skips over,
+    // to be taken with take_comments once the source is parsed.
+    pub fn with_comments(src: &str) -> Parser {
+        let mut p = Parser::new(src);
+        p.s.keep_comments();
+        p
+    }
+
+    // take_comments returns the comments skipped over since the last call,
+    // in the order they appear. It returns none unless the parser was made
+    // with with_comments.
+    pub fn take_comments(&mut self) -> Vec<ast::Comment> {
+        self.s
+            .take_comments()
+            .into_iter()
+            .map(|t| {
+                let t = self.encode(t);
+                ast::Comment {
+                    text: t.lit.trim_end().to_string(),
+                    location: self.source_location(
+                        &ast::Position::from(&t.start_pos),
+                        &ast::Position::from(&t.end_pos),
+                    ),
+                }
+            })
+            .collect()
     }
 
     // scan will read the next token from the Scanner. If peek has been used,
@@ -132,7 +358,7 @@ impl Parser {
                 self.t = None;
                 t
             }
-            None => self.s.scan(),
+            None => self.next(Scanner::scan),
         }
     }
 
@@ -142,29 +368,124 @@ impl Parser {
         match self.t.clone() {
             Some(t) => t,
             None => {
-                let t = self.s.scan();
+                let t = self.next(Scanner::scan);
                 self.t = Some(t.clone());
                 t
             }
         }
     }
 
+    // next reads a token using the given scan method, or eof once a limit
+    // has been exceeded.
+    fn next(&mut self, scan: fn(&mut Scanner) -> Token) -> Token {
+        if let Some(eof) = &self.eof {
+            return eof.clone();
+        }
+        let t = scan(&mut self.s);
+        let t = self.scanned(t);
+        self.scanned += 1;
+        if let Err(err) = Limit::Tokens.check(self.limits.max_tokens, self.scanned) {
+            self.exceed(err, &t);
+            return self.eof.clone().unwrap_or(t);
+        }
+        t
+    }
+
     // peek_with_regex is the same as peek, except that the scan step will allow scanning regexp tokens.
     fn peek_with_regex(&mut self) -> Token {
         if let Some(Token { tok: TOK_DIV, .. }) = &self.t {
             self.t = None;
             self.s.unread();
+            // The token is scanned again as a regex.
+            self.scanned -= 1;
+            if let Some(tokens) = &mut self.tokens {
+                tokens.pop();
+            }
         }
         match self.t.clone() {
             Some(t) => t,
             None => {
-                let t = self.s.scan_with_regex();
+                let t = self.next(Scanner::scan_with_regex);
                 self.t = Some(t.clone());
                 t
             }
         }
     }
 
+    // exceed records that a limit has been exceeded and ends the input at
+    // the start of the given token.
+    fn exceed(&mut self, err: limits::Error, at: &Token) {
+        if self.exceeded.is_some() {
+            return;
+        }
+        self.input_errs.push(err.to_string());
+        self.exceeded = Some(err);
+        // A token that was peeked at is dropped, so that it is read as eof
+        // like the rest of the input.
+        self.t = None;
+        self.eof = Some(Token {
+            tok: TOK_EOF,
+            lit: String::new(),
+            start_offset: at.start_offset,
+            end_offset: at.start_offset,
+            start_pos: at.start_pos.clone(),
+            end_pos: at.start_pos.clone(),
+        });
+    }
+
+    // scanned takes a token fresh from the scanner. It re-counts the columns
+    // of its positions in the parser's encoding and records it when
+    // tokenizing.
+    fn scanned(&mut self, t: Token) -> Token {
+        let t = self.encode(t);
+        if let Some(tokens) = &mut self.tokens {
+            tokens.push(t.clone());
+        }
+        t
+    }
+
+    fn encode(&self, mut t: Token) -> Token {
+        if let Some(lines) = &self.lines {
+            let start = lines.position_in(t.start_offset, self.encoding);
+            let end = lines.position_in(t.end_offset, self.encoding);
+            t.start_pos = scanner::Position::from(&start);
+            t.end_pos = scanner::Position::from(&end);
+        }
+        t
+    }
+
+    // tokenize returns every token of the source, comments included, in the
+    // order they appear. The source is parsed to scan each token in the mode
+    // the parser would, so that regular expressions and string expressions
+    // are told apart as they are in the AST.
+    pub fn tokenize(mut self) -> Vec<Lexeme> {
+        self.tokens = Some(Vec::new());
+        self.s.keep_comments();
+        self.parse_file(String::new());
+        let comments: Vec<Token> = self
+            .s
+            .take_comments()
+            .into_iter()
+            .map(|t| self.encode(t))
+            .collect();
+        let mut tokens = self.tokens.take().unwrap_or_default();
+        tokens.extend(comments);
+        tokens.retain(|t| t.tok != TOK_EOF);
+        tokens.sort_by_key(|t| t.start_offset);
+        tokens
+            .iter()
+            .map(|t| Lexeme {
+                kind: format_token(t.tok),
+                span: Span {
+                    start: t.start_offset,
+                    end: t.end_offset,
+                },
+                start: ast::Position::from(&t.start_pos),
+                end: ast::Position::from(&t.end_pos),
+            })
+            .collect()
+    }
+
     // consume will consume a token that has been retrieve using peek.
     // This will panic if a token has not been buffered with peek.
     fn consume(&mut self) {
@@ -183,8 +504,10 @@ impl Parser {
             match t.tok {
                 tok if tok == exp => return t,
                 TOK_EOF => {
-                    self.errs
-                        .push(format!("expected {}, got EOF", format_token(exp)));
+                    if self.exceeded.is_none() {
+                        self.errs
+                            .push(format!("expected {}, got EOF", format_token(exp)));
+                    }
                     return t;
                 }
                 _ => {
@@ -263,6 +586,11 @@ impl Parser {
         // TODO(jsternberg): Return NoPos when the positioning code
         // is prepared for that.
 
+        // Input cut off at a limit is only reported as the limit.
+        if self.exceeded.is_some() {
+            return tok;
+        }
+
         // Append an error to the current node.
         self.errs.push(format!(
             "expected {}, got {}",
@@ -312,16 +640,68 @@ impl Parser {
         if !start.is_valid() || !end.is_valid() {
             return SourceLocation::default();
         }
-        let s_off = self.s.offset(&scanner::Position::from(start)) as usize;
-        let e_off = self.s.offset(&scanner::Position::from(end)) as usize;
+        let span = match &self.lines {
+            Some(lines) => Span {
+                start: lines.offset_in(start, self.encoding),
+                end: lines.offset_in(end, self.encoding),
+            },
+            None => Span {
+                start: self.s.offset(&scanner::Position::from(start)),
+                end: self.s.offset(&scanner::Position::from(end)),
+            },
+        };
         SourceLocation {
             file: Some(self.fname.clone()),
             start: start.clone(),
             end: end.clone(),
-            source: Some(self.source[s_off..e_off].to_string()),
+            span,
+            source: if self.keep_source {
+                Some(self.source[span.start as usize..span.end as usize].to_string())
+            } else {
+                None
+            },
         }
     }
 
+    // parse_single_expression parses all of the input as one expression.
+    pub fn parse_single_expression(&mut self) -> Expression {
+        let mut expr = self.parse_expression();
+        self.finish("expression", expr.base_mut());
+        expr
+    }
+
+    // parse_single_statement parses all of the input as one statement.
+    pub fn parse_single_statement(&mut self) -> Statement {
+        let mut stmt = self.parse_statement();
+        self.finish("statement", stmt.base_mut());
+        stmt
+    }
+
+    // finish reports any input left over after a node as an error on it,
+    // along with the errors that no node has taken yet.
+    fn finish(&mut self, what: &str, base: &mut BaseNode) {
+        let start = self.peek();
+        if start.tok != TOK_EOF {
+            let mut end = start.clone();
+            loop {
+                let t = self.scan();
+                if t.tok == TOK_EOF {
+                    break;
+                }
+                end = t;
+            }
+            let loc = self.source_location(
+                &ast::Position::from(&start.start_pos),
+                &ast::Position::from(&end.end_pos),
+            );
+            let text = &self.source[loc.span.start as usize..loc.span.end as usize];
+            let msg = format!("unexpected input after {} {}: {}", what, loc, text);
+            self.errs.push(msg);
+        }
+        base.errors.append(&mut self.errs);
+        base.errors.extend(self.input_errs.iter().cloned());
+    }
+
     const METADATA: &'static str = "parser-type=rust";
 
     pub fn parse_file(&mut self, fname: String) -> File {
@@ -343,7 +723,7 @@ impl Parser {
         File {
             base: BaseNode {
                 location: self.source_location(&ast::Position::from(&t.start_pos), &end),
-                errors: vec![],
+                errors: self.input_errs.clone(),
             },
             name: self.fname.clone(),
             metadata: String::from(Self::METADATA),
@@ -434,14 +814,6 @@ impl Parser {
     fn parse_option_assignment_suffix(&mut self, id: Identifier) -> Assignment {
         let t = self.peek();
         match t.tok {
-            TOK_ASSIGN => {
-                let init = self.parse_assign_statement();
-                Assignment::Variable(Box::new(VariableAssgn {
-                    base: self.base_node_from_others(&id.base, init.base()),
-                    id,
-                    init,
-                }))
-            }
             TOK_DOT => {
                 self.consume();
                 let prop = self.parse_identifier();
@@ -456,7 +828,16 @@ impl Parser {
                     init,
                 }))
             }
-            _ => panic!("invalid option assignment suffix"),
+            // Anything other than a member assignment is read as a variable
+            // assignment, which reports a missing "=".
+            _ => {
+                let init = self.parse_assign_statement();
+                Assignment::Variable(Box::new(VariableAssgn {
+                    base: self.base_node_from_others(&id.base, init.base()),
+                    id,
+                    init,
+                }))
+            }
         }
     }
     fn parse_builtin_statement(&mut self) -> Statement {
@@ -493,7 +874,8 @@ impl Parser {
                 }))
             }
             _ => {
-                let expr = self.parse_expression_suffix(Expression::Identifier(id));
+                let expr =
+                    self.parse_nested(|p| p.parse_expression_suffix(Expression::Identifier(id)));
                 Statement::Expr(ExprStmt {
                     base: self.base_node(expr.base().location.clone()),
                     expression: expr,
@@ -531,7 +913,62 @@ impl Parser {
         }
     }
     fn parse_expression(&mut self) -> Expression {
-        self.parse_conditional_expression()
+        self.parse_nested(Parser::parse_conditional_expression)
+    }
+    // parse_nested parses an expression one level deeper than the current
+    // one, unless that is deeper than the parser allows.
+    //
+    // Besides bounding the recursion of the parser, the depth bounds the
+    // height of the tree that is returned, which later passes recurse over.
+    // Chains of operators, such as `a + b + c` or `f().g()`, are parsed in a
+    // loop but nest their left-hand side one level deeper for every link, so
+    // links count towards the depth until the expression that holds them is
+    // done. The height of a nested expression is the number of its links and
+    // the height of the tallest expression nested within it.
+    fn parse_nested<F>(&mut self, parse: F) -> Expression
+    where
+        F: FnOnce(&mut Parser) -> Expression,
+    {
+        if let Err(err) = Limit::Depth.check(self.limits.max_depth, self.depth + 1) {
+            let t = self.peek();
+            self.exceed(err, &t);
+            return Expression::Bad(Box::new(BadExpr {
+                base: BaseNode {
+                    location: self.source_location(
+                        &ast::Position::from(&t.start_pos),
+                        &ast::Position::from(&t.start_pos),
+                    ),
+                    errors: vec![],
+                },
+                text: err.to_string(),
+                expression: None,
+            }));
+        }
+        let (links, height) = (self.links, self.height);
+        self.depth += 1;
+        self.links = 0;
+        self.height = 0;
+        let expr = parse(self);
+        let nested = self.links + self.height + 1;
+        self.depth -= self.links + 1;
+        self.links = links;
+        self.height = height.max(nested);
+        self.check_height();
+        expr
+    }
+    // link counts another link of a chain of operators.
+    fn link(&mut self) {
+        self.links += 1;
+        self.depth += 1;
+        self.check_height();
+    }
+    // check_height checks the height the current expression reaches, from
+    // the top of the tree, against the depth allowed.
+    fn check_height(&mut self) {
+        if let Err(err) = Limit::Depth.check(self.limits.max_depth, self.depth + self.height) {
+            let t = self.peek();
+            self.exceed(err, &t);
+        }
     }
     // From GoDoc:
     // parseExpressionWhile will continue to parse expressions until
@@ -554,6 +991,10 @@ impl Parser {
         } {
             let e = self.parse_expression();
             if let Expression::Bad(_) = e {
+                // Input cut off at a limit is only reported as the limit.
+                if self.exceeded.is_some() {
+                    break;
+                }
                 // We got a BadExpression, push the error and consume the token.
                 // TODO(jsternberg): We should pretend the token is
                 //  an operator and create a binary expression. For now, skip past it.
@@ -574,6 +1015,7 @@ impl Parser {
                         left: ex,
                         right: e,
                     })));
+                    self.link();
                 }
                 None => {
                     expr = Some(e);
@@ -645,6 +1087,7 @@ impl Parser {
                         left: res,
                         right: rhs,
                     }));
+                    self.link();
                 }
                 None => break,
             };
@@ -677,6 +1120,7 @@ impl Parser {
                         left: res,
                         right: rhs,
                     }));
+                    self.link();
                 }
                 None => break,
             };
@@ -697,7 +1141,7 @@ impl Parser {
         let op = self.parse_logical_unary_operator();
         match op {
             Some(op) => {
-                let expr = self.parse_logical_unary_expression();
+                let expr = self.parse_nested(Parser::parse_logical_unary_expression);
                 Expression::Unary(Box::new(UnaryExpr {
                     base: self.base_node_from_other_end(&t, expr.base()),
                     operator: op,
@@ -738,6 +1182,7 @@ impl Parser {
                         left: res,
                         right: rhs,
                     }));
+                    self.link();
                 }
                 None => break,
             };
@@ -780,6 +1225,7 @@ impl Parser {
                         left: res,
                         right: rhs,
                     }));
+                    self.link();
                 }
                 None => break,
             };
@@ -816,6 +1262,7 @@ impl Parser {
                         left: res,
                         right: rhs,
                     }));
+                    self.link();
                 }
                 None => break,
             };
@@ -876,6 +1323,7 @@ impl Parser {
                     }));
                 }
             }
+            self.link();
         }
         res
     }
@@ -891,7 +1339,7 @@ impl Parser {
         let t = self.peek();
         let op = self.parse_additive_operator();
         if let Some(op) = op {
-            let expr = self.parse_unary_expression();
+            let expr = self.parse_nested(Parser::parse_unary_expression);
             return Expression::Unary(Box::new(UnaryExpr {
                 base: self.base_node_from_other_end(&t, expr.base()),
                 operator: op,
@@ -901,14 +1349,8 @@ impl Parser {
         self.parse_postfix_expression()
     }
     fn parse_postfix_expression(&mut self) -> Expression {
-        let mut expr = self.parse_primary_expression();
-        loop {
-            let po = self.parse_postfix_operator(expr);
-            match po {
-                Ok(e) => expr = e,
-                Err(e) => return e,
-            }
-        }
+        let expr = self.parse_primary_expression();
+        self.parse_postfix_operator_suffix(expr)
     }
     fn parse_postfix_operator_suffix(&mut self, mut expr: Expression) -> Expression {
         loop {
@@ -917,6 +1359,7 @@ impl Parser {
                 Ok(e) => expr = e,
                 Err(e) => return e,
             }
+            self.link();
         }
     }
     // parse_postfix_operator parses a postfix operator (membership, function call, indexing).
@@ -1008,7 +1451,7 @@ impl Parser {
             TOK_PIPE_RECEIVE => Expression::PipeLit(self.parse_pipe_literal()),
             TOK_LBRACK => Expression::Array(Box::new(self.parse_array_literal())),
             TOK_LBRACE => Expression::Object(Box::new(self.parse_object_literal())),
-            TOK_LPAREN => self.parse_paren_expression(),
+            TOK_LPAREN => self.parse_nested(Parser::parse_paren_expression),
             // We got a bad token, do not consume it, but use it in the message.
             // Other methods will match BadExpr and consume the token if needed.
             _ => Expression::Bad(Box::new(BadExpr {
@@ -1033,12 +1476,19 @@ impl Parser {
         let start = self.expect(TOK_QUOTE);
         let mut parts = Vec::new();
         loop {
-            let t = self.s.scan_string_expr();
+            let t = self.next(Scanner::scan_string_expr);
             match t.tok {
                 TOK_TEXT => {
+                    let value = match strconv::parse_text(t.lit.as_str()) {
+                        Ok(value) => value,
+                        Err(err) => {
+                            self.errs.push(err);
+                            String::new()
+                        }
+                    };
                     parts.push(StringExprPart::Text(TextPart {
                         base: self.base_node_from_token(&t),
-                        value: strconv::parse_text(t.lit.as_str()).unwrap(),
+                        value,
                     }));
                 }
                 TOK_STRINGEXPR => {
@@ -1105,9 +1555,19 @@ impl Parser {
     }
     fn parse_float_literal(&mut self) -> FloatLit {
         let t = self.expect(TOK_FLOAT);
-        FloatLit {
-            base: self.base_node_from_token(&t),
-            value: (&t.lit).parse::<f64>().unwrap(),
+        match (&t.lit).parse::<f64>() {
+            Err(_e) => {
+                self.errs
+                    .push(format!("invalid float literal \"{}\"", t.lit));
+                FloatLit {
+                    base: self.base_node_from_token(&t),
+                    value: 0.0,
+                }
+            }
+            Ok(v) => FloatLit {
+                base: self.base_node_from_token(&t),
+                value: v,
+            },
         }
     }
     fn parse_string_literal(&mut self) -> StringLit {
@@ -1145,18 +1605,34 @@ impl Parser {
     }
     fn parse_time_literal(&mut self) -> DateTimeLit {
         let t = self.expect(TOK_TIME);
-        let value = strconv::parse_time(t.lit.as_str()).unwrap();
-        DateTimeLit {
-            base: self.base_node_from_token(&t),
-            value,
+        match strconv::parse_time(t.lit.as_str()) {
+            Ok(value) => DateTimeLit {
+                base: self.base_node_from_token(&t),
+                value,
+            },
+            Err(err) => {
+                self.errs.push(err);
+                DateTimeLit {
+                    base: self.base_node_from_token(&t),
+                    value: FixedOffset::east(0).timestamp(0, 0),
+                }
+            }
         }
     }
     fn parse_duration_literal(&mut self) -> DurationLit {
         let t = self.expect(TOK_DURATION);
-        let values = strconv::parse_duration(t.lit.as_str()).unwrap();
-        DurationLit {
-            base: self.base_node_from_token(&t),
-            values,
+        match strconv::parse_duration(t.lit.as_str()) {
+            Ok(values) => DurationLit {
+                base: self.base_node_from_token(&t),
+                values,
+            },
+            Err(err) => {
+                self.errs.push(err);
+                DurationLit {
+                    base: self.base_node_from_token(&t),
+                    values: Vec::new(),
+                }
+            }
         }
     }
     fn parse_pipe_literal(&mut self) -> PipeLit {
@@ -1278,6 +1754,9 @@ impl Parser {
                 while self.more() {
                     let rhs = self.parse_expression();
                     if let Expression::Bad(_) = rhs {
+                        if self.exceeded.is_some() {
+                            break;
+                        }
                         let invalid_t = self.scan();
                         let loc = self.source_location(
                             &ast::Position::from(&invalid_t.start_pos),
@@ -1293,6 +1772,7 @@ impl Parser {
                         left: expr,
                         right: rhs,
                     }));
+                    self.link();
                 }
                 let rparen = self.close(TOK_RPAREN);
                 Expression::Paren(Box::new(ParenExpr {
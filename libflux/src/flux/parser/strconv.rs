@@ -5,7 +5,6 @@ use std::iter::Peekable;
 use std::str::{CharIndices, Chars};
 
 use crate::ast;
-use regex::Regex;
 
 pub fn parse_string(lit: &str) -> Result<String, String> {
     if lit.len() < 2 || !lit.starts_with('"') || !lit.ends_with('"') {
@@ -35,6 +34,24 @@ pub fn parse_text(lit: &str) -> Result<String, String> {
     }
 }
 
+// format_string returns the literal for a string value, the inverse of `parse_string`.
+pub fn format_string(value: &str) -> String {
+    let mut lit = String::with_capacity(value.len() + 2);
+    lit.push('"');
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' | '"' => lit.push('\\'),
+            // only `${` starts an interpolation, a lone `$` is fine.
+            '$' if chars.peek() == Some(&'{') => lit.push('\\'),
+            _ => {}
+        }
+        lit.push(c);
+    }
+    lit.push('"');
+    lit
+}
+
 fn push_unescaped(s: &mut Vec<u8>, chars: &mut CharIndices) -> Result<(), String> {
     match chars.next() {
         Some((_, c)) => match c {
@@ -88,19 +105,39 @@ pub fn parse_regex(lit: &str) -> Result<String, String> {
         return Err(String::from("regexp literal must end with a slash"));
     }
 
+    // The expression is compiled by ast::check, which can point to the
+    // error within the literal.
     let expr = &lit[1..lit.len() - 1];
-    let expr = expr.replace("\\/", "/");
-    match Regex::new(expr.as_str()) {
-        Ok(_) => Ok(expr),
-        Err(e) => match e {
-            regex::Error::Syntax(msg) => {
-                // removes newlines, 4 spaces tabs, and the pointer to the error in the regexp.
-                Err(msg.replace("\n", "").replace("    ", " ").replace("^", ""))
-            }
-            regex::Error::CompiledTooBig(_) => Err("compiled too big".to_string()),
-            _ => Err("bad regexp".to_string()),
-        },
+    Ok(expr.replace("\\/", "/"))
+}
+
+// format_regex returns the literal for a regular expression, the inverse of `parse_regex`.
+// Slashes are escaped, and an escaped slash in the expression is written the same way
+// since `\/` and `/` match the same text.
+// The empty expression is written as an empty group since `//` starts a comment.
+pub fn format_regex(expr: &str) -> String {
+    if expr.is_empty() {
+        return "/(?:)/".to_string();
+    }
+    let mut lit = String::with_capacity(expr.len() + 2);
+    lit.push('/');
+    let mut chars = expr.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('/') => lit.push_str("\\/"),
+                Some(c) => {
+                    lit.push('\\');
+                    lit.push(c);
+                }
+                None => lit.push('\\'),
+            },
+            '/' => lit.push_str("\\/"),
+            _ => lit.push(c),
+        }
     }
+    lit.push('/');
+    lit
 }
 
 pub fn parse_time(lit: &str) -> Result<DateTime<FixedOffset>, String> {
@@ -0,0 +1,293 @@
+use chrono::Duration;
+
+// ValueType is the type a PromQL expression evaluates to.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ValueType {
+    Scalar,
+    Vector,
+    Matrix,
+    String,
+}
+
+impl ValueType {
+    pub fn name(self) -> &'static str {
+        match self {
+            ValueType::Scalar => "scalar",
+            ValueType::Vector => "instant vector",
+            ValueType::Matrix => "range vector",
+            ValueType::String => "string",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expr {
+    Number(f64),
+    String(String),
+    Paren(Box<Expr>),
+    Unary(Box<UnaryExpr>),
+    Binary(Box<BinaryExpr>),
+    Aggregate(Box<AggregateExpr>),
+    Call(Call),
+    VectorSelector(VectorSelector),
+    MatrixSelector(MatrixSelector),
+    Subquery(Box<SubqueryExpr>),
+}
+
+impl Expr {
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Expr::Number(_) => ValueType::Scalar,
+            Expr::String(_) => ValueType::String,
+            Expr::Paren(e) => e.value_type(),
+            Expr::Unary(e) => e.expr.value_type(),
+            Expr::Binary(e) => {
+                if e.lhs.value_type() == ValueType::Scalar
+                    && e.rhs.value_type() == ValueType::Scalar
+                {
+                    ValueType::Scalar
+                } else {
+                    ValueType::Vector
+                }
+            }
+            Expr::Aggregate(_) => ValueType::Vector,
+            Expr::Call(c) => c.func.return_type,
+            Expr::VectorSelector(_) => ValueType::Vector,
+            Expr::MatrixSelector(_) | Expr::Subquery(_) => ValueType::Matrix,
+        }
+    }
+
+    // yields_float reports whether the expression is a constant scalar that
+    // can be computed without reading any table, e.g. `1 + 2`.
+    pub fn yields_float(&self) -> bool {
+        match self {
+            Expr::Number(_) => true,
+            Expr::Binary(e) => e.lhs.yields_float() && e.rhs.yields_float(),
+            Expr::Unary(e) => e.expr.yields_float(),
+            Expr::Paren(e) => e.yields_float(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum UnaryOp {
+    Add,
+    Sub,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct UnaryExpr {
+    pub op: UnaryOp,
+    pub expr: Expr,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+    Eql,
+    Neq,
+    Gtr,
+    Lss,
+    Gte,
+    Lte,
+    And,
+    Or,
+    Unless,
+}
+
+impl BinaryOp {
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And | BinaryOp::Unless => 2,
+            BinaryOp::Eql
+            | BinaryOp::Neq
+            | BinaryOp::Gtr
+            | BinaryOp::Lss
+            | BinaryOp::Gte
+            | BinaryOp::Lte => 3,
+            BinaryOp::Add | BinaryOp::Sub => 4,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => 5,
+            BinaryOp::Pow => 6,
+        }
+    }
+
+    pub fn is_right_associative(self) -> bool {
+        self == BinaryOp::Pow
+    }
+
+    pub fn is_comparison(self) -> bool {
+        self.precedence() == 3
+    }
+
+    pub fn is_set_operator(self) -> bool {
+        match self {
+            BinaryOp::And | BinaryOp::Or | BinaryOp::Unless => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Cardinality {
+    OneToOne,
+    ManyToOne,
+    OneToMany,
+    ManyToMany,
+}
+
+// VectorMatching describes how the samples of two vectors are matched in a
+// binary operation. It is only present when both operands are vectors.
+#[derive(Debug, PartialEq, Clone)]
+pub struct VectorMatching {
+    pub card: Cardinality,
+    // on is true for `on(...)` and false for `ignoring(...)` or no modifier.
+    pub on: bool,
+    pub matching_labels: Vec<String>,
+    // include holds the labels of a `group_left(...)`/`group_right(...)` modifier.
+    pub include: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BinaryExpr {
+    pub op: BinaryOp,
+    pub lhs: Expr,
+    pub rhs: Expr,
+    pub matching: Option<VectorMatching>,
+    // return_bool is set by the `bool` modifier of comparison operators.
+    pub return_bool: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AggregateOp {
+    Sum,
+    Avg,
+    Count,
+    Min,
+    Max,
+    Stddev,
+    Stdvar,
+    TopK,
+    BottomK,
+    CountValues,
+    Quantile,
+}
+
+impl AggregateOp {
+    pub fn from_name(name: &str) -> Option<AggregateOp> {
+        match name {
+            "sum" => Some(AggregateOp::Sum),
+            "avg" => Some(AggregateOp::Avg),
+            "count" => Some(AggregateOp::Count),
+            "min" => Some(AggregateOp::Min),
+            "max" => Some(AggregateOp::Max),
+            "stddev" => Some(AggregateOp::Stddev),
+            "stdvar" => Some(AggregateOp::Stdvar),
+            "topk" => Some(AggregateOp::TopK),
+            "bottomk" => Some(AggregateOp::BottomK),
+            "count_values" => Some(AggregateOp::CountValues),
+            "quantile" => Some(AggregateOp::Quantile),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AggregateOp::Sum => "sum",
+            AggregateOp::Avg => "avg",
+            AggregateOp::Count => "count",
+            AggregateOp::Min => "min",
+            AggregateOp::Max => "max",
+            AggregateOp::Stddev => "stddev",
+            AggregateOp::Stdvar => "stdvar",
+            AggregateOp::TopK => "topk",
+            AggregateOp::BottomK => "bottomk",
+            AggregateOp::CountValues => "count_values",
+            AggregateOp::Quantile => "quantile",
+        }
+    }
+
+    // param_type returns the type of the leading parameter, if the
+    // aggregation takes one.
+    pub fn param_type(self) -> Option<ValueType> {
+        match self {
+            AggregateOp::TopK | AggregateOp::BottomK | AggregateOp::Quantile => {
+                Some(ValueType::Scalar)
+            }
+            AggregateOp::CountValues => Some(ValueType::String),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct AggregateExpr {
+    pub op: AggregateOp,
+    pub expr: Expr,
+    pub param: Option<Expr>,
+    pub grouping: Vec<String>,
+    // without is true for `without(...)` and false for `by(...)`.
+    pub without: bool,
+}
+
+// Function describes the signature of a PromQL builtin function.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Function {
+    pub name: String,
+    pub arg_types: &'static [ValueType],
+    // variadic is how often the last argument may be repeated; when it is
+    // non-zero the last argument is also optional. -1 means no limit.
+    pub variadic: i32,
+    pub return_type: ValueType,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Call {
+    pub func: Function,
+    pub args: Vec<Expr>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MatchOp {
+    Equal,
+    NotEqual,
+    Regex,
+    NotRegex,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct LabelMatcher {
+    pub name: String,
+    pub op: MatchOp,
+    pub value: String,
+}
+
+// VectorSelector selects the latest sample of every series matching all of
+// its label matchers. A metric name is represented as a `__name__` matcher.
+#[derive(Debug, PartialEq, Clone)]
+pub struct VectorSelector {
+    pub matchers: Vec<LabelMatcher>,
+    pub offset: Duration,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct MatrixSelector {
+    pub matchers: Vec<LabelMatcher>,
+    pub range: Duration,
+    pub offset: Duration,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SubqueryExpr {
+    pub expr: Expr,
+    pub range: Duration,
+    // step is zero when the subquery uses the default resolution, e.g. `[5m:]`.
+    pub step: Duration,
+    pub offset: Duration,
+}
@@ -0,0 +1,76 @@
+#![allow(missing_docs)]
+//! Transpilation of PromQL queries into Flux.
+//!
+//! A PromQL query is first parsed into an [`Expr`](expr::Expr) and then
+//! lowered by a [`Transpiler`] into an `ast::Package` that uses the
+//! `internal/promql` builtins. The result can be printed with the
+//! formatter and type checked like any other Flux program.
+pub mod expr;
+pub mod parser;
+mod transpile;
+
+pub use parser::parse;
+pub use transpile::Transpiler;
+
+use std::fmt;
+
+use crate::ast;
+
+#[derive(Debug, PartialEq)]
+pub struct Error {
+    pub msg: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl From<String> for Error {
+    fn from(msg: String) -> Error {
+        Error { msg }
+    }
+}
+
+impl From<&str> for Error {
+    fn from(msg: &str) -> Error {
+        Error {
+            msg: String::from(msg),
+        }
+    }
+}
+
+impl Transpiler {
+    /// Parses a PromQL query and transpiles it into a Flux package.
+    pub fn transpile_str(&self, query: &str) -> Result<ast::Package, Error> {
+        self.transpile(&parse(query)?)
+    }
+}
+
+/// Escapes a PromQL label name so that it cannot clash with the columns
+/// reserved by Flux. The metric name becomes `_field` and other names that
+/// start with `_` or `~` are prefixed with `~`.
+pub fn escape_label_name(name: &str) -> String {
+    if name == "__name__" {
+        "_field".to_string()
+    } else if name.starts_with('_') || name.starts_with('~') {
+        format!("~{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Reverses `escape_label_name`.
+pub fn unescape_label_name(name: &str) -> String {
+    if name == "_field" {
+        "__name__".to_string()
+    } else if name.starts_with('~') {
+        name[1..].to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests;
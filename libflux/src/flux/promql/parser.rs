@@ -0,0 +1,1065 @@
+use crate::promql::expr::*;
+use crate::promql::Error;
+
+use chrono::Duration;
+use regex::Regex;
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    Duration(Duration),
+    Str(String),
+    LeftParen,
+    RightParen,
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Comma,
+    Colon,
+    Assign,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+    Eql,
+    Neq,
+    Lss,
+    Gtr,
+    Lte,
+    Gte,
+    RegexMatch,
+    RegexNoMatch,
+    EOF,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Ident(name) => format!("identifier {:?}", name),
+            Token::Number(_) => "number".to_string(),
+            Token::Duration(_) => "duration".to_string(),
+            Token::Str(_) => "string".to_string(),
+            Token::LeftParen => "\"(\"".to_string(),
+            Token::RightParen => "\")\"".to_string(),
+            Token::LeftBrace => "\"{\"".to_string(),
+            Token::RightBrace => "\"}\"".to_string(),
+            Token::LeftBracket => "\"[\"".to_string(),
+            Token::RightBracket => "\"]\"".to_string(),
+            Token::Comma => "\",\"".to_string(),
+            Token::Colon => "\":\"".to_string(),
+            Token::Assign => "\"=\"".to_string(),
+            Token::Add => "\"+\"".to_string(),
+            Token::Sub => "\"-\"".to_string(),
+            Token::Mul => "\"*\"".to_string(),
+            Token::Div => "\"/\"".to_string(),
+            Token::Mod => "\"%\"".to_string(),
+            Token::Pow => "\"^\"".to_string(),
+            Token::Eql => "\"==\"".to_string(),
+            Token::Neq => "\"!=\"".to_string(),
+            Token::Lss => "\"<\"".to_string(),
+            Token::Gtr => "\">\"".to_string(),
+            Token::Lte => "\"<=\"".to_string(),
+            Token::Gte => "\">=\"".to_string(),
+            Token::RegexMatch => "\"=~\"".to_string(),
+            Token::RegexNoMatch => "\"!~\"".to_string(),
+            Token::EOF => "end of input".to_string(),
+        }
+    }
+}
+
+fn error_at(pos: usize, msg: String) -> Error {
+    Error::from(format!("parse error at char {}: {}", pos + 1, msg))
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || c == ':'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == ':'
+}
+
+// Lexer splits a PromQL query into tokens. Every token is paired with the
+// (char based) offset it starts at, for error reporting.
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Lexer {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn peek_at(&self, n: usize) -> Option<char> {
+        self.chars.get(self.pos + n).cloned()
+    }
+
+    fn tokens(mut self) -> Result<Vec<(Token, usize)>, Error> {
+        let mut tokens = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let start = self.pos;
+            let c = match self.peek() {
+                Some(c) => c,
+                None => {
+                    tokens.push((Token::EOF, start));
+                    return Ok(tokens);
+                }
+            };
+            let after_duration = match tokens.last() {
+                Some((Token::Duration(_), _)) => true,
+                _ => false,
+            };
+            let tok = if c == ':' && after_duration {
+                // The colon separating the range and step of a subquery.
+                self.pos += 1;
+                Token::Colon
+            } else if c.is_ascii_digit() || (c == '.' && self.peek_number_after_dot()) {
+                self.number()?
+            } else if is_ident_start(c) {
+                self.ident()
+            } else if c == '"' || c == '\'' || c == '`' {
+                self.string(c)?
+            } else {
+                self.operator(c)?
+            };
+            tokens.push((tok, start));
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.pos += 1;
+            } else if c == '#' {
+                while let Some(c) = self.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn peek_number_after_dot(&self) -> bool {
+        match self.peek_at(1) {
+            Some(c) => c.is_ascii_digit(),
+            None => false,
+        }
+    }
+
+    fn ident(&mut self) -> Token {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !is_ident_char(c) {
+                break;
+            }
+            self.pos += 1;
+        }
+        Token::Ident(self.chars[start..self.pos].iter().collect())
+    }
+
+    fn digits(&mut self) -> String {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_digit() {
+                break;
+            }
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    // duration_unit returns the unit starting at the current position and
+    // its length, if a valid duration unit follows.
+    fn duration_unit(&self) -> Option<(Duration, usize)> {
+        let (unit, len) = match (self.peek(), self.peek_at(1)) {
+            (Some('m'), Some('s')) => (Duration::milliseconds(1), 2),
+            (Some('s'), _) => (Duration::seconds(1), 1),
+            (Some('m'), _) => (Duration::minutes(1), 1),
+            (Some('h'), _) => (Duration::hours(1), 1),
+            (Some('d'), _) => (Duration::days(1), 1),
+            (Some('w'), _) => (Duration::weeks(1), 1),
+            (Some('y'), _) => (Duration::days(365), 1),
+            _ => return None,
+        };
+        match self.peek_at(len) {
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => None,
+            _ => Some((unit, len)),
+        }
+    }
+
+    fn number(&mut self) -> Result<Token, Error> {
+        let start = self.pos;
+        if self.peek() == Some('0')
+            && (self.peek_at(1) == Some('x') || self.peek_at(1) == Some('X'))
+        {
+            self.pos += 2;
+            let digits_start = self.pos;
+            while let Some(c) = self.peek() {
+                if !c.is_ascii_hexdigit() {
+                    break;
+                }
+                self.pos += 1;
+            }
+            let digits: String = self.chars[digits_start..self.pos].iter().collect();
+            return match i64::from_str_radix(&digits, 16) {
+                Ok(v) => Ok(Token::Number(v as f64)),
+                Err(_) => Err(error_at(start, format!("bad number {:?}", digits))),
+            };
+        }
+
+        let int = self.digits();
+        if !int.is_empty() && self.duration_unit().is_some() {
+            return self.duration(start, int);
+        }
+        if self.peek() == Some('.') {
+            self.pos += 1;
+            self.digits();
+        }
+        if let Some('e') | Some('E') = self.peek() {
+            self.pos += 1;
+            if let Some('+') | Some('-') = self.peek() {
+                self.pos += 1;
+            }
+            if self.digits().is_empty() {
+                return Err(error_at(start, "bad number exponent".to_string()));
+            }
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        match text.parse::<f64>() {
+            Ok(v) => Ok(Token::Number(v)),
+            Err(_) => Err(error_at(start, format!("bad number {:?}", text))),
+        }
+    }
+
+    // duration lexes a sequence of magnitude/unit pairs like `1h30m`.
+    fn duration(&mut self, start: usize, first: String) -> Result<Token, Error> {
+        let mut total = Duration::zero();
+        let mut magnitude = first;
+        loop {
+            let (unit, len) = match self.duration_unit() {
+                Some(u) => u,
+                None => return Err(error_at(start, "bad duration syntax".to_string())),
+            };
+            self.pos += len;
+            let n = match magnitude.parse::<i32>() {
+                Ok(n) => n,
+                Err(_) => return Err(error_at(start, "duration out of range".to_string())),
+            };
+            total = total + unit * n;
+            match self.peek() {
+                Some(c) if c.is_ascii_digit() => magnitude = self.digits(),
+                _ => return Ok(Token::Duration(total)),
+            }
+        }
+    }
+
+    fn string(&mut self, quote: char) -> Result<Token, Error> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut value = String::new();
+        loop {
+            let c = match self.peek() {
+                Some(c) => c,
+                None => return Err(error_at(start, "unterminated quoted string".to_string())),
+            };
+            self.pos += 1;
+            if c == quote {
+                return Ok(Token::Str(value));
+            }
+            if c == '\\' && quote != '`' {
+                value.push(self.escape(start)?);
+            } else if c == '\n' && quote != '`' {
+                return Err(error_at(start, "unterminated quoted string".to_string()));
+            } else {
+                value.push(c);
+            }
+        }
+    }
+
+    fn escape(&mut self, start: usize) -> Result<char, Error> {
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return Err(error_at(start, "unterminated quoted string".to_string())),
+        };
+        self.pos += 1;
+        let (radix, len) = match c {
+            'a' => return Ok('\x07'),
+            'b' => return Ok('\x08'),
+            'f' => return Ok('\x0c'),
+            'n' => return Ok('\n'),
+            'r' => return Ok('\r'),
+            't' => return Ok('\t'),
+            'v' => return Ok('\x0b'),
+            '\\' | '"' | '\'' | '`' => return Ok(c),
+            'x' => (16, 2),
+            'u' => (16, 4),
+            'U' => (16, 8),
+            '0'..='7' => {
+                self.pos -= 1;
+                (8, 3)
+            }
+            _ => {
+                return Err(error_at(
+                    start,
+                    format!("unknown escape sequence {:?}", format!("\\{}", c)),
+                ))
+            }
+        };
+        if self.pos + len > self.chars.len() {
+            return Err(error_at(start, "unterminated quoted string".to_string()));
+        }
+        let digits: String = self.chars[self.pos..self.pos + len].iter().collect();
+        self.pos += len;
+        match u32::from_str_radix(&digits, radix)
+            .ok()
+            .and_then(std::char::from_u32)
+        {
+            Some(c) => Ok(c),
+            None => Err(error_at(
+                start,
+                format!("invalid escape sequence {:?}", digits),
+            )),
+        }
+    }
+
+    fn operator(&mut self, c: char) -> Result<Token, Error> {
+        let next = self.peek_at(1);
+        let (tok, len) = match (c, next) {
+            ('=', Some('=')) => (Token::Eql, 2),
+            ('=', Some('~')) => (Token::RegexMatch, 2),
+            ('=', _) => (Token::Assign, 1),
+            ('!', Some('=')) => (Token::Neq, 2),
+            ('!', Some('~')) => (Token::RegexNoMatch, 2),
+            ('<', Some('=')) => (Token::Lte, 2),
+            ('<', _) => (Token::Lss, 1),
+            ('>', Some('=')) => (Token::Gte, 2),
+            ('>', _) => (Token::Gtr, 1),
+            ('(', _) => (Token::LeftParen, 1),
+            (')', _) => (Token::RightParen, 1),
+            ('{', _) => (Token::LeftBrace, 1),
+            ('}', _) => (Token::RightBrace, 1),
+            ('[', _) => (Token::LeftBracket, 1),
+            (']', _) => (Token::RightBracket, 1),
+            (',', _) => (Token::Comma, 1),
+            ('+', _) => (Token::Add, 1),
+            ('-', _) => (Token::Sub, 1),
+            ('*', _) => (Token::Mul, 1),
+            ('/', _) => (Token::Div, 1),
+            ('%', _) => (Token::Mod, 1),
+            ('^', _) => (Token::Pow, 1),
+            _ => return Err(error_at(self.pos, format!("unexpected character {:?}", c))),
+        };
+        self.pos += len;
+        Ok(tok)
+    }
+}
+
+// Keywords that can never start a vector selector.
+const KEYWORDS: &[&str] = &[
+    "and",
+    "or",
+    "unless",
+    "by",
+    "without",
+    "on",
+    "ignoring",
+    "group_left",
+    "group_right",
+    "offset",
+    "bool",
+];
+
+fn lookup_function(name: &str) -> Option<Function> {
+    use ValueType::{Matrix as M, Scalar as S, String as Str, Vector as V};
+    let (arg_types, variadic, return_type): (&'static [ValueType], i32, ValueType) = match name {
+        "abs" | "absent" | "ceil" | "exp" | "floor" | "ln" | "log10" | "log2" | "sort"
+        | "sort_desc" | "sqrt" | "timestamp" => (&[V], 0, V),
+        "avg_over_time" | "changes" | "count_over_time" | "delta" | "deriv" | "idelta"
+        | "increase" | "irate" | "max_over_time" | "min_over_time" | "rate" | "resets"
+        | "stddev_over_time" | "stdvar_over_time" | "sum_over_time" => (&[M], 0, V),
+        "clamp_max" | "clamp_min" => (&[V, S], 0, V),
+        "day_of_month" | "day_of_week" | "days_in_month" | "hour" | "minute" | "month" | "year" => {
+            (&[V], 1, V)
+        }
+        "histogram_quantile" => (&[S, V], 0, V),
+        "holt_winters" => (&[M, S, S], 0, V),
+        "label_join" => (&[V, Str, Str, Str], -1, V),
+        "label_replace" => (&[V, Str, Str, Str, Str], 0, V),
+        "predict_linear" => (&[M, S], 0, V),
+        "quantile_over_time" => (&[S, M], 0, V),
+        "round" => (&[V, S], 1, V),
+        "scalar" => (&[V], 0, S),
+        "time" => (&[], 0, S),
+        "vector" => (&[S], 0, V),
+        _ => return None,
+    };
+    Some(Function {
+        name: name.to_string(),
+        arg_types,
+        variadic,
+        return_type,
+    })
+}
+
+// Parser is a recursive descent parser for PromQL expressions.
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    cur: usize,
+}
+
+/// Parses a PromQL expression, applying the same type checks as the
+/// Prometheus parser.
+pub fn parse(src: &str) -> Result<Expr, Error> {
+    let lexer = Lexer {
+        chars: src.chars().collect(),
+        pos: 0,
+    };
+    let mut p = Parser {
+        tokens: lexer.tokens()?,
+        cur: 0,
+    };
+    let expr = p.parse_expr()?;
+    match p.peek() {
+        Token::EOF => Ok(expr),
+        t => Err(p.error(format!("unexpected {}", t.describe()))),
+    }
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.cur].0
+    }
+
+    fn peek_at(&self, n: usize) -> &Token {
+        let i = std::cmp::min(self.cur + n, self.tokens.len() - 1);
+        &self.tokens[i].0
+    }
+
+    fn next(&mut self) -> Token {
+        let t = self.tokens[self.cur].0.clone();
+        if self.cur < self.tokens.len() - 1 {
+            self.cur += 1;
+        }
+        t
+    }
+
+    fn error(&self, msg: String) -> Error {
+        error_at(self.tokens[self.cur].1, msg)
+    }
+
+    fn expect(&mut self, t: Token, context: &str) -> Result<(), Error> {
+        if *self.peek() == t {
+            self.next();
+            Ok(())
+        } else {
+            Err(self.error(format!(
+                "unexpected {} in {}, expected {}",
+                self.peek().describe(),
+                context,
+                t.describe()
+            )))
+        }
+    }
+
+    fn peek_keyword(&self, kw: &str) -> bool {
+        match self.peek() {
+            Token::Ident(name) => name == kw,
+            _ => false,
+        }
+    }
+
+    fn peek_binary_op(&self) -> Option<BinaryOp> {
+        match self.peek() {
+            Token::Add => Some(BinaryOp::Add),
+            Token::Sub => Some(BinaryOp::Sub),
+            Token::Mul => Some(BinaryOp::Mul),
+            Token::Div => Some(BinaryOp::Div),
+            Token::Mod => Some(BinaryOp::Mod),
+            Token::Pow => Some(BinaryOp::Pow),
+            Token::Eql => Some(BinaryOp::Eql),
+            Token::Neq => Some(BinaryOp::Neq),
+            Token::Lss => Some(BinaryOp::Lss),
+            Token::Gtr => Some(BinaryOp::Gtr),
+            Token::Lte => Some(BinaryOp::Lte),
+            Token::Gte => Some(BinaryOp::Gte),
+            Token::Ident(name) => match name.as_str() {
+                "and" => Some(BinaryOp::And),
+                "or" => Some(BinaryOp::Or),
+                "unless" => Some(BinaryOp::Unless),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, Error> {
+        self.parse_binary(0)
+    }
+
+    // parse_binary parses binary expressions whose operators bind at least
+    // as tightly as min_prec, using precedence climbing.
+    fn parse_binary(&mut self, min_prec: u8) -> Result<Expr, Error> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            let op = match self.peek_binary_op() {
+                Some(op) if op.precedence() >= min_prec => op,
+                _ => return Ok(lhs),
+            };
+            let pos = self.tokens[self.cur].1;
+            self.next();
+
+            let return_bool = if self.peek_keyword("bool") {
+                self.next();
+                true
+            } else {
+                false
+            };
+
+            let mut matching = VectorMatching {
+                card: if op.is_set_operator() {
+                    Cardinality::ManyToMany
+                } else {
+                    Cardinality::OneToOne
+                },
+                on: false,
+                matching_labels: Vec::new(),
+                include: Vec::new(),
+            };
+            let mut has_modifier = false;
+            if self.peek_keyword("on") || self.peek_keyword("ignoring") {
+                has_modifier = true;
+                matching.on = self.peek_keyword("on");
+                self.next();
+                matching.matching_labels = self.parse_label_list("vector matching")?;
+                if self.peek_keyword("group_left") || self.peek_keyword("group_right") {
+                    matching.card = if self.peek_keyword("group_left") {
+                        Cardinality::ManyToOne
+                    } else {
+                        Cardinality::OneToMany
+                    };
+                    self.next();
+                    if *self.peek() == Token::LeftParen {
+                        matching.include = self.parse_label_list("grouping")?;
+                    }
+                }
+            }
+
+            let next_prec = if op.is_right_associative() {
+                op.precedence()
+            } else {
+                op.precedence() + 1
+            };
+            let rhs = self.parse_binary(next_prec)?;
+            lhs = new_binary(pos, op, lhs, rhs, matching, has_modifier, return_bool)?;
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, Error> {
+        let op = match self.peek() {
+            Token::Add => UnaryOp::Add,
+            Token::Sub => UnaryOp::Sub,
+            _ => {
+                let expr = self.parse_primary()?;
+                return self.parse_postfix(expr);
+            }
+        };
+        let pos = self.tokens[self.cur].1;
+        self.next();
+        // Unary operators bind less tightly than `^`, so `-2^2` is `-(2^2)`.
+        let expr = self.parse_binary(BinaryOp::Pow.precedence())?;
+        if let Expr::Number(v) = expr {
+            return Ok(Expr::Number(if op == UnaryOp::Sub { -v } else { v }));
+        }
+        match expr.value_type() {
+            ValueType::Scalar | ValueType::Vector => {
+                Ok(Expr::Unary(Box::new(UnaryExpr { op, expr })))
+            }
+            t => Err(error_at(
+                pos,
+                format!(
+                    "unary expression only allowed on expressions of type scalar or instant vector, got {}",
+                    t.name()
+                ),
+            )),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, Error> {
+        match self.peek().clone() {
+            Token::Number(v) => {
+                self.next();
+                Ok(Expr::Number(v))
+            }
+            Token::Str(s) => {
+                self.next();
+                Ok(Expr::String(s))
+            }
+            Token::LeftParen => {
+                self.next();
+                let expr = self.parse_expr()?;
+                self.expect(Token::RightParen, "paren expression")?;
+                Ok(Expr::Paren(Box::new(expr)))
+            }
+            Token::LeftBrace => self.parse_vector_selector(None),
+            Token::Ident(name) => {
+                let lower = name.to_lowercase();
+                if lower == "inf" || lower == "nan" {
+                    self.next();
+                    return Ok(Expr::Number(if lower == "inf" {
+                        std::f64::INFINITY
+                    } else {
+                        std::f64::NAN
+                    }));
+                }
+                if let Some(op) = AggregateOp::from_name(&name) {
+                    match self.peek_at(1) {
+                        Token::LeftParen => return self.parse_aggregate(op),
+                        Token::Ident(kw) if kw == "by" || kw == "without" => {
+                            return self.parse_aggregate(op)
+                        }
+                        _ => {}
+                    }
+                }
+                if *self.peek_at(1) == Token::LeftParen {
+                    return self.parse_call(&name);
+                }
+                if KEYWORDS.contains(&name.as_str()) {
+                    return Err(self.error(format!("unexpected keyword {:?}", name)));
+                }
+                self.next();
+                self.parse_vector_selector(Some(name))
+            }
+            t => Err(self.error(format!("unexpected {}", t.describe()))),
+        }
+    }
+
+    fn parse_postfix(&mut self, mut expr: Expr) -> Result<Expr, Error> {
+        loop {
+            if *self.peek() == Token::LeftBracket {
+                expr = self.parse_range(expr)?;
+            } else if self.peek_keyword("offset") {
+                self.next();
+                let pos = self.tokens[self.cur].1;
+                let offset = self.parse_duration("offset")?;
+                expr = set_offset(pos, expr, offset)?;
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn parse_duration(&mut self, context: &str) -> Result<Duration, Error> {
+        match self.peek().clone() {
+            Token::Duration(d) => {
+                self.next();
+                Ok(d)
+            }
+            t => Err(self.error(format!(
+                "unexpected {} in {}, expected duration",
+                t.describe(),
+                context
+            ))),
+        }
+    }
+
+    // parse_range parses a `[range]` or `[range:step]` suffix, turning the
+    // expression into a matrix selector or a subquery.
+    fn parse_range(&mut self, expr: Expr) -> Result<Expr, Error> {
+        let pos = self.tokens[self.cur].1;
+        self.next();
+        let range = self.parse_duration("range")?;
+        if *self.peek() == Token::Colon {
+            self.next();
+            let step = match self.peek() {
+                Token::Duration(_) => self.parse_duration("subquery")?,
+                _ => Duration::zero(),
+            };
+            self.expect(Token::RightBracket, "subquery")?;
+            return match expr.value_type() {
+                ValueType::Vector => Ok(Expr::Subquery(Box::new(SubqueryExpr {
+                    expr,
+                    range,
+                    step,
+                    offset: Duration::zero(),
+                }))),
+                t => Err(error_at(
+                    pos,
+                    format!(
+                        "subquery is only allowed on instant vector, got {}",
+                        t.name()
+                    ),
+                )),
+            };
+        }
+        self.expect(Token::RightBracket, "range")?;
+        match expr {
+            Expr::VectorSelector(ref vs) if vs.offset.is_zero() => {
+                Ok(Expr::MatrixSelector(MatrixSelector {
+                    matchers: vs.matchers.clone(),
+                    range,
+                    offset: Duration::zero(),
+                }))
+            }
+            _ => Err(error_at(
+                pos,
+                "ranges only allowed for vector selectors".to_string(),
+            )),
+        }
+    }
+
+    fn parse_label_name(&mut self, context: &str) -> Result<String, Error> {
+        match self.peek().clone() {
+            Token::Ident(name) if !name.contains(':') => {
+                self.next();
+                Ok(name)
+            }
+            t => Err(self.error(format!(
+                "unexpected {} in {}, expected label",
+                t.describe(),
+                context
+            ))),
+        }
+    }
+
+    fn parse_label_list(&mut self, context: &str) -> Result<Vec<String>, Error> {
+        self.expect(Token::LeftParen, context)?;
+        let mut labels = Vec::new();
+        while *self.peek() != Token::RightParen {
+            labels.push(self.parse_label_name(context)?);
+            if *self.peek() == Token::Comma {
+                self.next();
+            } else {
+                break;
+            }
+        }
+        self.expect(Token::RightParen, context)?;
+        Ok(labels)
+    }
+
+    fn parse_grouping(&mut self) -> Result<(Vec<String>, bool), Error> {
+        let without = self.peek_keyword("without");
+        self.next();
+        Ok((self.parse_label_list("grouping")?, without))
+    }
+
+    fn parse_aggregate(&mut self, op: AggregateOp) -> Result<Expr, Error> {
+        let pos = self.tokens[self.cur].1;
+        self.next();
+        let mut grouping = None;
+        if self.peek_keyword("by") || self.peek_keyword("without") {
+            grouping = Some(self.parse_grouping()?);
+        }
+        self.expect(Token::LeftParen, "aggregation")?;
+        let param = match op.param_type() {
+            Some(_) => {
+                let param = self.parse_expr()?;
+                self.expect(Token::Comma, "aggregation")?;
+                Some(param)
+            }
+            None => None,
+        };
+        let expr = self.parse_expr()?;
+        self.expect(Token::RightParen, "aggregation")?;
+        if grouping.is_none() && (self.peek_keyword("by") || self.peek_keyword("without")) {
+            grouping = Some(self.parse_grouping()?);
+        }
+
+        if expr.value_type() != ValueType::Vector {
+            return Err(error_at(
+                pos,
+                format!(
+                    "expected type instant vector in aggregation expression, got {}",
+                    expr.value_type().name()
+                ),
+            ));
+        }
+        if let (Some(want), Some(p)) = (op.param_type(), &param) {
+            if p.value_type() != want {
+                return Err(error_at(
+                    pos,
+                    format!(
+                        "expected type {} in aggregation parameter, got {}",
+                        want.name(),
+                        p.value_type().name()
+                    ),
+                ));
+            }
+        }
+
+        let (grouping, without) = grouping.unwrap_or_default();
+        Ok(Expr::Aggregate(Box::new(AggregateExpr {
+            op,
+            expr,
+            param,
+            grouping,
+            without,
+        })))
+    }
+
+    fn parse_call(&mut self, name: &str) -> Result<Expr, Error> {
+        let pos = self.tokens[self.cur].1;
+        let func = match lookup_function(name) {
+            Some(f) => f,
+            None => return Err(self.error(format!("unknown function with name {:?}", name))),
+        };
+        self.next();
+        self.expect(Token::LeftParen, "function call")?;
+        let mut args = Vec::new();
+        while *self.peek() != Token::RightParen {
+            args.push(self.parse_expr()?);
+            if *self.peek() == Token::Comma {
+                self.next();
+            } else {
+                break;
+            }
+        }
+        self.expect(Token::RightParen, "function call")?;
+
+        let nargs = func.arg_types.len();
+        if func.variadic == 0 {
+            if nargs != args.len() {
+                return Err(error_at(
+                    pos,
+                    format!(
+                        "expected {} argument(s) in call to {:?}, got {}",
+                        nargs,
+                        name,
+                        args.len()
+                    ),
+                ));
+            }
+        } else {
+            let min = nargs - 1;
+            if args.len() < min {
+                return Err(error_at(
+                    pos,
+                    format!(
+                        "expected at least {} argument(s) in call to {:?}, got {}",
+                        min,
+                        name,
+                        args.len()
+                    ),
+                ));
+            } else if func.variadic > 0 && args.len() > min + func.variadic as usize {
+                let max = min + func.variadic as usize;
+                return Err(error_at(
+                    pos,
+                    format!(
+                        "expected at most {} argument(s) in call to {:?}, got {}",
+                        max,
+                        name,
+                        args.len()
+                    ),
+                ));
+            }
+        }
+        for (i, arg) in args.iter().enumerate() {
+            let want = func.arg_types[std::cmp::min(i, nargs - 1)];
+            if arg.value_type() != want {
+                return Err(error_at(
+                    pos,
+                    format!(
+                        "expected type {} in call to function {:?}, got {}",
+                        want.name(),
+                        name,
+                        arg.value_type().name()
+                    ),
+                ));
+            }
+        }
+        Ok(Expr::Call(Call { func, args }))
+    }
+
+    fn parse_vector_selector(&mut self, name: Option<String>) -> Result<Expr, Error> {
+        let pos = self.tokens[self.cur].1;
+        let mut matchers = Vec::new();
+        if let Some(name) = &name {
+            matchers.push(LabelMatcher {
+                name: "__name__".to_string(),
+                op: MatchOp::Equal,
+                value: name.clone(),
+            });
+        }
+        if *self.peek() == Token::LeftBrace {
+            self.next();
+            while *self.peek() != Token::RightBrace {
+                let m = self.parse_label_matcher()?;
+                if let Some(name) = &name {
+                    if m.name == "__name__" {
+                        return Err(self.error(format!(
+                            "metric name must not be set twice: {:?} or {:?}",
+                            name, m.value
+                        )));
+                    }
+                }
+                matchers.push(m);
+                if *self.peek() == Token::Comma {
+                    self.next();
+                } else {
+                    break;
+                }
+            }
+            self.expect(Token::RightBrace, "label matching")?;
+        }
+
+        // A selector must not match every series, e.g. `{foo=""}`.
+        let mut non_empty = false;
+        for m in &matchers {
+            if !matches_empty(m)? {
+                non_empty = true;
+            }
+        }
+        if !non_empty {
+            return Err(error_at(
+                pos,
+                "vector selector must contain at least one non-empty matcher".to_string(),
+            ));
+        }
+        Ok(Expr::VectorSelector(VectorSelector {
+            matchers,
+            offset: Duration::zero(),
+        }))
+    }
+
+    fn parse_label_matcher(&mut self) -> Result<LabelMatcher, Error> {
+        let name = self.parse_label_name("label matching")?;
+        let op = match self.next() {
+            Token::Assign => MatchOp::Equal,
+            Token::Neq => MatchOp::NotEqual,
+            Token::RegexMatch => MatchOp::Regex,
+            Token::RegexNoMatch => MatchOp::NotRegex,
+            t => {
+                self.cur -= 1;
+                return Err(self.error(format!(
+                    "unexpected {} in label matching, expected label matching operator",
+                    t.describe()
+                )));
+            }
+        };
+        match self.peek().clone() {
+            Token::Str(value) => {
+                self.next();
+                Ok(LabelMatcher { name, op, value })
+            }
+            t => Err(self.error(format!(
+                "unexpected {} in label matching, expected string",
+                t.describe()
+            ))),
+        }
+    }
+}
+
+// anchored_regex compiles a label matcher regex. PromQL regexes always
+// match the full label value.
+pub fn anchored_regex(value: &str) -> Result<Regex, Error> {
+    match Regex::new(&format!("^(?:{})$", value)) {
+        Ok(re) => Ok(re),
+        Err(e) => Err(Error::from(format!(
+            "invalid regular expression {:?}: {}",
+            value, e
+        ))),
+    }
+}
+
+fn matches_empty(m: &LabelMatcher) -> Result<bool, Error> {
+    Ok(match m.op {
+        MatchOp::Equal => m.value.is_empty(),
+        MatchOp::NotEqual => !m.value.is_empty(),
+        MatchOp::Regex => anchored_regex(&m.value)?.is_match(""),
+        MatchOp::NotRegex => !anchored_regex(&m.value)?.is_match(""),
+    })
+}
+
+fn set_offset(pos: usize, expr: Expr, offset: Duration) -> Result<Expr, Error> {
+    let current = match &expr {
+        Expr::VectorSelector(vs) => vs.offset,
+        Expr::MatrixSelector(ms) => ms.offset,
+        Expr::Subquery(sq) => sq.offset,
+        _ => {
+            return Err(error_at(
+                pos,
+                "offset modifier must be preceded by an instant or range selector".to_string(),
+            ))
+        }
+    };
+    if !current.is_zero() {
+        return Err(error_at(
+            pos,
+            "offset may not be set multiple times".to_string(),
+        ));
+    }
+    Ok(match expr {
+        Expr::VectorSelector(vs) => Expr::VectorSelector(VectorSelector { offset, ..vs }),
+        Expr::MatrixSelector(ms) => Expr::MatrixSelector(MatrixSelector { offset, ..ms }),
+        Expr::Subquery(sq) => Expr::Subquery(Box::new(SubqueryExpr { offset, ..*sq })),
+        _ => unreachable!(),
+    })
+}
+
+fn new_binary(
+    pos: usize,
+    op: BinaryOp,
+    lhs: Expr,
+    rhs: Expr,
+    matching: VectorMatching,
+    has_modifier: bool,
+    return_bool: bool,
+) -> Result<Expr, Error> {
+    let (lt, rt) = (lhs.value_type(), rhs.value_type());
+    let err = |msg: String| Err(error_at(pos, msg));
+    for t in &[lt, rt] {
+        if *t != ValueType::Scalar && *t != ValueType::Vector {
+            return err(
+                "binary expression must contain only scalar and instant vector types".to_string(),
+            );
+        }
+    }
+    if return_bool && !op.is_comparison() {
+        return err("bool modifier can only be used on comparison operators".to_string());
+    }
+    if op.is_comparison() && !return_bool && lt == ValueType::Scalar && rt == ValueType::Scalar {
+        return err("comparisons between scalars must use BOOL modifier".to_string());
+    }
+    let both_vectors = lt == ValueType::Vector && rt == ValueType::Vector;
+    if op.is_set_operator() {
+        if !both_vectors {
+            return err(format!(
+                "set operator {:?} not allowed in binary scalar expression",
+                format!("{:?}", op).to_lowercase()
+            ));
+        }
+        if matching.card != Cardinality::ManyToMany {
+            return err("no grouping allowed for set operations".to_string());
+        }
+    }
+    if !both_vectors && has_modifier {
+        return err("vector matching only allowed between instant vectors".to_string());
+    }
+    if matching.on {
+        for l in &matching.include {
+            if matching.matching_labels.contains(l) {
+                return err(format!(
+                    "label {:?} must not occur in ON and GROUP clause at once",
+                    l
+                ));
+            }
+        }
+    }
+    Ok(Expr::Binary(Box::new(BinaryExpr {
+        op,
+        lhs,
+        rhs,
+        matching: if both_vectors { Some(matching) } else { None },
+        return_bool,
+    })))
+}
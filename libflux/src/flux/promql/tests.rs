@@ -0,0 +1,406 @@
+use super::expr::Expr;
+use super::*;
+use crate::formatter::Formatter;
+
+use chrono::{DateTime, Duration};
+
+// This gives us a colorful diff.
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+fn transpiler(resolution: Duration) -> Transpiler {
+    Transpiler {
+        bucket: "prometheus".to_string(),
+        start: DateTime::parse_from_rfc3339("2019-11-01T10:00:00Z").unwrap(),
+        end: DateTime::parse_from_rfc3339("2019-11-01T11:00:00Z").unwrap(),
+        resolution,
+    }
+}
+
+fn format(pkg: &ast::Package) -> String {
+    let mut fmt = Formatter::new(1024);
+    fmt.format_file(&pkg.files[0], false);
+    let (output, err) = fmt.output();
+    assert_eq!("", err);
+    output.to_string()
+}
+
+fn transpile_helper(resolution: Duration, query: &str, want: &str) {
+    let pkg = match transpiler(resolution).transpile_str(query) {
+        Ok(pkg) => pkg,
+        Err(e) => panic!("failed to transpile {:?}: {}", query, e),
+    };
+    assert_eq!(want, format(&pkg));
+}
+
+fn parse_error(query: &str) -> String {
+    match parse(query) {
+        Ok(expr) => panic!("expected {:?} to fail, got {:?}", query, expr),
+        Err(e) => e.msg,
+    }
+}
+
+fn transpile_error(query: &str) -> String {
+    match transpiler(Duration::zero()).transpile_str(query) {
+        Ok(_) => panic!("expected {:?} to fail to transpile", query),
+        Err(e) => e.msg,
+    }
+}
+
+#[test]
+fn parse_precedence() {
+    let num = |n| Expr::Number(n);
+    let binary = |op, lhs, rhs| {
+        Expr::Binary(Box::new(expr::BinaryExpr {
+            op,
+            lhs,
+            rhs,
+            matching: None,
+            return_bool: false,
+        }))
+    };
+    // `^` is right associative and binds tighter than `*`.
+    assert_eq!(
+        parse("1 + 2 * 3 ^ 4 ^ 5").unwrap(),
+        binary(
+            expr::BinaryOp::Add,
+            num(1.0),
+            binary(
+                expr::BinaryOp::Mul,
+                num(2.0),
+                binary(
+                    expr::BinaryOp::Pow,
+                    num(3.0),
+                    binary(expr::BinaryOp::Pow, num(4.0), num(5.0)),
+                ),
+            ),
+        )
+    );
+    assert_eq!(parse("-2").unwrap(), num(-2.0));
+}
+
+#[test]
+fn parse_selectors() {
+    let matcher = |name: &str, op, value: &str| expr::LabelMatcher {
+        name: name.to_string(),
+        op,
+        value: value.to_string(),
+    };
+    assert_eq!(
+        parse(r#"foo:bar{a!~"b"}[1h30m] offset 5m"#).unwrap(),
+        Expr::MatrixSelector(expr::MatrixSelector {
+            matchers: vec![
+                matcher("__name__", expr::MatchOp::Equal, "foo:bar"),
+                matcher("a", expr::MatchOp::NotRegex, "b"),
+            ],
+            range: Duration::minutes(90),
+            offset: Duration::minutes(5),
+        })
+    );
+}
+
+#[test]
+fn parse_errors() {
+    for (query, want) in &[
+        (
+            "1 < 2",
+            "parse error at char 3: comparisons between scalars must use BOOL modifier",
+        ),
+        (
+            "rate(foo)",
+            r#"parse error at char 1: expected type range vector in call to function "rate", got instant vector"#,
+        ),
+        (
+            "{}",
+            "parse error at char 1: vector selector must contain at least one non-empty matcher",
+        ),
+        (
+            r#"foo{__name__="bar"}"#,
+            r#"parse error at char 19: metric name must not be set twice: "foo" or "bar""#,
+        ),
+        (
+            "foo offset 1m offset 2m",
+            "parse error at char 22: offset may not be set multiple times",
+        ),
+        (
+            "sum(foo)[5m]",
+            "parse error at char 9: ranges only allowed for vector selectors",
+        ),
+        (
+            "foo and 1",
+            r#"parse error at char 5: set operator "and" not allowed in binary scalar expression"#,
+        ),
+        (
+            "foo + on(a) group_left(a) bar",
+            r#"parse error at char 5: label "a" must not occur in ON and GROUP clause at once"#,
+        ),
+        (
+            "bar(foo)",
+            r#"parse error at char 1: unknown function with name "bar""#,
+        ),
+        (
+            r#"foo{a="b"#,
+            "parse error at char 7: unterminated quoted string",
+        ),
+    ] {
+        assert_eq!(parse_error(query), *want, "query: {}", query);
+    }
+}
+
+#[test]
+fn transpile_errors() {
+    for (query, want) in &[
+        (r#""foo""#, "string results are not supported yet"),
+        (
+            "foo and on(a) bar",
+            "error transpiling expression: vector set operations not supported yet",
+        ),
+        (
+            "foo + bar",
+            "error transpiling expression: vector-to-vector binary expressions without on() clause not supported yet",
+        ),
+        (
+            r#"count_values("x", foo)"#,
+            "error transpiling expression: unsupported aggregation type count_values",
+        ),
+        (
+            "round(foo, 2)",
+            "error transpiling expression: round() with more than one argument is not supported yet",
+        ),
+    ] {
+        assert_eq!(transpile_error(query), *want, "query: {}", query);
+    }
+    assert!(
+        transpile_error(r#"label_replace(foo, "a", "$1", "b", "(")"#)
+            .starts_with(r#"error transpiling expression: invalid regular expression "(""#)
+    );
+}
+
+#[test]
+fn escape_labels() {
+    for (name, escaped) in &[
+        ("__name__", "_field"),
+        ("job", "job"),
+        ("_x", "~_x"),
+        ("~x", "~~x"),
+    ] {
+        assert_eq!(escape_label_name(name), *escaped);
+        assert_eq!(unescape_label_name(escaped), *name);
+    }
+}
+
+#[test]
+fn transpile_instant_selector() {
+    transpile_helper(
+        Duration::seconds(0),
+        r#"foo{a="b",c=~"x.*",_d!="e"}"#,
+        r#"import "math"
+import "internal/promql"
+
+from(bucket: "prometheus")
+	|> range(start: 2019-11-01T09:55:00Z, stop: 2019-11-01T11:00:00Z)
+	|> filter(fn: (r) =>
+		(r._field == "foo" and (r.a == "b" and (r.c =~ /^(?:x.*)$/ and r["~_d"] != "e"))))
+	|> last()
+	|> timeShift(duration: 0ns)
+	|> drop(columns: ["_measurement"])
+	|> duplicate(column: "_stop", as: "_time")"#,
+    );
+}
+
+#[test]
+fn transpile_range_query_selector() {
+    transpile_helper(
+        Duration::seconds(7),
+        r#"foo{a="b",c=~"x.*",_d!="e"}"#,
+        r#"import "math"
+import "internal/promql"
+
+from(bucket: "prometheus")
+	|> range(start: 2019-11-01T09:55:00Z, stop: 2019-11-01T11:00:00Z)
+	|> filter(fn: (r) =>
+		(r._field == "foo" and (r.a == "b" and (r.c =~ /^(?:x.*)$/ and r["~_d"] != "e"))))
+	|> window(every: 7000000000ns, period: 300000000000ns, offset: 5000000000ns)
+	|> filter(fn: (r) =>
+		(r._stop >= 2019-11-01T10:00:00Z and r._start <= 2019-11-01T10:55:00Z))
+	|> last()
+	|> timeShift(duration: 0ns)
+	|> drop(columns: ["_measurement"])
+	|> duplicate(column: "_stop", as: "_time")"#,
+    );
+}
+
+#[test]
+fn transpile_rate_with_offset() {
+    transpile_helper(
+        Duration::seconds(0),
+        r#"rate(foo[5m] offset 1m)"#,
+        r#"import "math"
+import "internal/promql"
+
+from(bucket: "prometheus")
+	|> range(start: 2019-11-01T09:54:00Z, stop: 2019-11-01T10:59:00Z)
+	|> filter(fn: (r) =>
+		(r._field == "foo"))
+	|> timeShift(duration: 60000000000ns)
+	|> drop(columns: ["_measurement"])
+	|> promql.extrapolatedRate(isCounter: true, isRate: true)
+	|> drop(columns: ["_field", "_time"])
+	|> duplicate(column: "_stop", as: "_time")"#,
+    );
+}
+
+#[test]
+fn transpile_count_over_time() {
+    transpile_helper(
+        Duration::seconds(7),
+        r#"count_over_time(foo[5m])"#,
+        r#"import "math"
+import "internal/promql"
+
+from(bucket: "prometheus")
+	|> range(start: 2019-11-01T09:55:00Z, stop: 2019-11-01T11:00:00Z)
+	|> filter(fn: (r) =>
+		(r._field == "foo"))
+	|> window(every: 7000000000ns, period: 300000000000ns, offset: 5000000000ns)
+	|> filter(fn: (r) =>
+		(r._stop >= 2019-11-01T10:00:00Z and r._start <= 2019-11-01T10:55:00Z))
+	|> timeShift(duration: 0ns)
+	|> drop(columns: ["_measurement"])
+	|> count()
+	|> filter(fn: (r) =>
+		(exists r._value))
+	|> toFloat()
+	|> drop(columns: ["_field", "_time"])
+	|> filter(fn: (r) =>
+		(r._value > 0.0))
+	|> duplicate(column: "_stop", as: "_time")"#,
+    );
+}
+
+#[test]
+fn transpile_subquery() {
+    transpile_helper(
+        Duration::seconds(0),
+        r#"max_over_time(rate(foo[1m])[5m:1m])"#,
+        r#"import "math"
+import "internal/promql"
+
+from(bucket: "prometheus")
+	|> range(start: 2019-11-01T09:54:00Z, stop: 2019-11-01T11:00:00Z)
+	|> filter(fn: (r) =>
+		(r._field == "foo"))
+	|> window(every: 60000000000ns, period: 60000000000ns, offset: 0ns)
+	|> filter(fn: (r) =>
+		(r._stop >= 2019-11-01T09:55:00Z and r._start <= 2019-11-01T10:59:00Z))
+	|> timeShift(duration: 0ns)
+	|> drop(columns: ["_measurement"])
+	|> promql.extrapolatedRate(isCounter: true, isRate: true)
+	|> drop(columns: ["_field", "_time"])
+	|> duplicate(column: "_stop", as: "_time")
+	|> timeShift(duration: 0ns)
+	|> max()
+	|> filter(fn: (r) =>
+		(exists r._value))
+	|> toFloat()
+	|> drop(columns: ["_field", "_time"])
+	|> duplicate(column: "_stop", as: "_time")"#,
+    );
+}
+
+#[test]
+fn transpile_aggregate_by() {
+    transpile_helper(
+        Duration::seconds(0),
+        r#"sum by (job) (foo)"#,
+        r#"import "math"
+import "internal/promql"
+
+from(bucket: "prometheus")
+	|> range(start: 2019-11-01T09:55:00Z, stop: 2019-11-01T11:00:00Z)
+	|> filter(fn: (r) =>
+		(r._field == "foo"))
+	|> last()
+	|> timeShift(duration: 0ns)
+	|> drop(columns: ["_measurement"])
+	|> group(columns: ["job", "_start", "_stop"], mode: "by")
+	|> sum()
+	|> drop(columns: ["_field", "_time"])
+	|> duplicate(column: "_stop", as: "_time")"#,
+    );
+}
+
+#[test]
+fn transpile_vector_matching() {
+    transpile_helper(
+        Duration::seconds(0),
+        r#"foo / on(job) group_left(env) bar"#,
+        r#"import "math"
+import "internal/promql"
+
+join(tables: {lhs: from(bucket: "prometheus")
+	|> range(start: 2019-11-01T09:55:00Z, stop: 2019-11-01T11:00:00Z)
+	|> filter(fn: (r) =>
+		(r._field == "foo"))
+	|> last()
+	|> timeShift(duration: 0ns)
+	|> drop(columns: ["_measurement"]), rhs: from(bucket: "prometheus")
+	|> range(start: 2019-11-01T09:55:00Z, stop: 2019-11-01T11:00:00Z)
+	|> filter(fn: (r) =>
+		(r._field == "bar"))
+	|> last()
+	|> timeShift(duration: 0ns)
+	|> drop(columns: ["_measurement"])}, on: ["job", "_start", "_stop"])
+	|> map(fn: (r) =>
+		({r with _value: r._value_lhs / r._value_rhs, _stop: r._stop}))
+	|> keep(columns: ["job", "_start", "_stop", "_value", "env"])
+	|> drop(columns: ["_field", "_time"])
+	|> duplicate(column: "_stop", as: "_time")"#,
+    );
+}
+
+#[test]
+fn transpile_scalar_vector_comparison() {
+    transpile_helper(
+        Duration::seconds(0),
+        r#"2 < foo"#,
+        r#"import "math"
+import "internal/promql"
+
+from(bucket: "prometheus")
+	|> range(start: 2019-11-01T09:55:00Z, stop: 2019-11-01T11:00:00Z)
+	|> filter(fn: (r) =>
+		(r._field == "foo"))
+	|> last()
+	|> timeShift(duration: 0ns)
+	|> drop(columns: ["_measurement"])
+	|> filter(fn: (r) =>
+		(2.0 < r._value))
+	|> duplicate(column: "_stop", as: "_time")"#,
+    );
+}
+
+#[test]
+fn transpile_constant_scalar() {
+    transpile_helper(
+        Duration::seconds(7),
+        r#"1 + 2"#,
+        r#"import "math"
+import "internal/promql"
+
+promql.emptyTable()
+	|> range(start: 2019-11-01T09:55:00Z, stop: 2019-11-01T11:00:00Z)
+	|> window(
+		every: 7000000000ns,
+		period: 300000000000ns,
+		offset: 5000000000ns,
+		createEmpty: true,
+	)
+	|> sum()
+	|> filter(fn: (r) =>
+		(r._stop >= 2019-11-01T10:00:00Z and r._start <= 2019-11-01T10:55:00Z))
+	|> map(fn: (r) =>
+		({r with _value: 1.0 + 2.0, _stop: r._stop}))
+	|> duplicate(column: "_stop", as: "_time")"#,
+    );
+}
@@ -0,0 +1,1320 @@
+use crate::ast::{self, BaseNode, Expression, Operator, PropertyKey, Statement};
+use crate::promql::expr::{
+    AggregateExpr, AggregateOp, BinaryExpr, BinaryOp, Call, Expr, LabelMatcher, MatchOp,
+    MatrixSelector, SubqueryExpr, UnaryExpr, UnaryOp, ValueType, VectorSelector,
+};
+use crate::promql::parser::anchored_regex;
+use crate::promql::{escape_label_name, Error};
+
+use chrono::{DateTime, Duration, FixedOffset};
+
+// Taken from Prometheus: the largest and smallest sample values that can be
+// converted to an int64 without overflow.
+const MAX_INT64: f64 = 9_223_372_036_854_774_784.0;
+const MIN_INT64: f64 = -9_223_372_036_854_775_808.0;
+
+// PromQL looks back up to 5m to find the latest sample of an instant vector.
+fn lookback_delta() -> Duration {
+    Duration::minutes(5)
+}
+
+/// A Transpiler converts PromQL expressions into Flux according to a chosen
+/// evaluation time range.
+///
+/// Each PromQL node translates into one or more Flux calls that as a group
+/// keep the following invariants:
+///
+/// - The `_field` column contains the PromQL metric name, if any.
+/// - The `_measurement` column is ignored.
+/// - The `_time` column contains the sample timestamp as long as a raw sample
+///   has been selected and not processed further. Otherwise it is empty.
+/// - The `_stop` column contains the stop timestamp of windows that are
+///   equivalent to the resolution steps in PromQL. Once `_time` is gone,
+///   `_stop` becomes the output timestamp of a sample.
+/// - The `_value` column is always a float and holds the PromQL sample value.
+/// - Other columns map to PromQL label names, with escaping applied
+///   (`_foo` -> `~_foo`).
+/// - Tables are grouped by all columns except `_time` and `_value`, so that
+///   each table represents one PromQL series.
+#[derive(Debug, Clone)]
+pub struct Transpiler {
+    pub bucket: String,
+    pub start: DateTime<FixedOffset>,
+    pub end: DateTime<FixedOffset>,
+    /// The step of a range query, or zero for an instant query.
+    pub resolution: Duration,
+}
+
+impl Transpiler {
+    /// Converts a PromQL expression into a Flux package consisting of a
+    /// single file.
+    pub fn transpile(&self, expr: &Expr) -> Result<ast::Package, Error> {
+        if expr.value_type() == ValueType::String {
+            return Err("string results are not supported yet".into());
+        }
+        let mut flux = match self.transpile_expr(expr) {
+            Ok(e) => e,
+            Err(e) => return Err(format!("error transpiling expression: {}", e).into()),
+        };
+
+        // Scalar constants need to be converted to vectors in the final result.
+        if expr.yields_float() {
+            flux = pipeline(
+                self.generate_zero_windows()?,
+                vec![call("map", vec![("fn", set_const_value_fn(flux))])],
+            );
+        }
+
+        let file = ast::File {
+            base: BaseNode::default(),
+            name: String::new(),
+            metadata: String::new(),
+            package: None,
+            imports: vec![import("math"), import("internal/promql")],
+            body: vec![Statement::Expr(ast::ExprStmt {
+                base: BaseNode::default(),
+                // The resolution step evaluation timestamp needs to become
+                // the output timestamp.
+                expression: pipeline(
+                    flux,
+                    vec![call(
+                        "duplicate",
+                        vec![("column", string("_stop")), ("as", string("_time"))],
+                    )],
+                ),
+            })],
+        };
+        Ok(ast::Package::from(file))
+    }
+
+    fn transpile_expr(&self, expr: &Expr) -> Result<Expression, Error> {
+        match expr {
+            Expr::Paren(e) => self.transpile_expr(e),
+            Expr::Unary(e) => self.transpile_unary_expr(e),
+            Expr::Number(v) => Ok(number(*v)),
+            Expr::String(v) => Ok(string(v)),
+            Expr::VectorSelector(v) => self.transpile_instant_vector_selector(v),
+            Expr::MatrixSelector(v) => self.transpile_range_vector_selector(v),
+            Expr::Aggregate(e) => self.transpile_aggregate_expr(e),
+            Expr::Binary(e) => self.transpile_binary_expr(e),
+            Expr::Call(c) => self.transpile_call(c),
+            Expr::Subquery(sq) => self.transpile_subquery_expr(sq),
+        }
+    }
+
+    // window_calls returns the calls that split the selected data into one
+    // window per resolution step and remove incomplete windows at the edges
+    // of the graph range. Instant queries are not windowed.
+    fn window_calls(
+        &self,
+        period: Duration,
+        aligned_to: DateTime<FixedOffset>,
+        min_stop: DateTime<FixedOffset>,
+        max_start: DateTime<FixedOffset>,
+    ) -> Vec<ast::CallExpr> {
+        if self.resolution <= Duration::zero() {
+            return Vec::new();
+        }
+        vec![
+            call(
+                "window",
+                vec![
+                    ("every", duration(self.resolution)),
+                    ("period", duration(period)),
+                    ("offset", nanoseconds(self.step_offset(aligned_to))),
+                ],
+            ),
+            call(
+                "filter",
+                vec![("fn", window_cutoff_fn(min_stop, max_start))],
+            ),
+        ]
+    }
+
+    fn step_offset(&self, t: DateTime<FixedOffset>) -> i64 {
+        t.timestamp_nanos() % nanos(self.resolution)
+    }
+
+    fn transpile_instant_vector_selector(&self, v: &VectorSelector) -> Result<Expression, Error> {
+        let start = sub(self.start, v.offset)?;
+        let mut calls = vec![
+            // Query the entire graph range.
+            call(
+                "range",
+                vec![
+                    ("start", datetime(sub(start, lookback_delta())?)),
+                    ("stop", datetime(sub(self.end, v.offset)?)),
+                ],
+            ),
+            // Apply label matching filters.
+            call("filter", vec![("fn", label_matchers_fn(&v.matchers)?)]),
+        ];
+        // At every resolution step, look back up to 5m of data.
+        calls.extend(self.window_calls(
+            lookback_delta(),
+            start,
+            start,
+            sub(sub(self.end, lookback_delta())?, v.offset)?,
+        ));
+        calls.extend(vec![
+            // Select the last data point before the evaluation timestamp.
+            call("last", vec![]),
+            // Apply offsets to make past data look like it's in the present.
+            call("timeShift", vec![("duration", duration(v.offset))]),
+            drop_measurement_call(),
+        ]);
+        Ok(pipeline(self.bucket_call(), calls))
+    }
+
+    fn transpile_range_vector_selector(&self, v: &MatrixSelector) -> Result<Expression, Error> {
+        let mut calls = vec![
+            // Query the entire graph range.
+            call(
+                "range",
+                vec![
+                    ("start", datetime(sub(sub(self.start, v.range)?, v.offset)?)),
+                    ("stop", datetime(sub(self.end, v.offset)?)),
+                ],
+            ),
+            // Apply label matching filters.
+            call("filter", vec![("fn", label_matchers_fn(&v.matchers)?)]),
+        ];
+        // At every resolution step, include the specified range of data.
+        calls.extend(self.window_calls(
+            v.range,
+            self.start,
+            sub(self.start, v.offset)?,
+            sub(sub(self.end, v.range)?, v.offset)?,
+        ));
+        calls.extend(vec![
+            // Apply offsets to make past data look like it's in the present.
+            call("timeShift", vec![("duration", duration(v.offset))]),
+            drop_measurement_call(),
+        ]);
+        Ok(pipeline(self.bucket_call(), calls))
+    }
+
+    fn bucket_call(&self) -> Expression {
+        Expression::Call(Box::new(call(
+            "from",
+            vec![("bucket", string(&self.bucket))],
+        )))
+    }
+
+    fn transpile_unary_expr(&self, e: &UnaryExpr) -> Result<Expression, Error> {
+        let expr = match self.transpile_expr(&e.expr) {
+            Ok(expr) => expr,
+            Err(err) => {
+                return Err(
+                    format!("error transpiling expression in unary expression: {}", err).into(),
+                )
+            }
+        };
+        match e.op {
+            UnaryOp::Add => Ok(expr),
+            UnaryOp::Sub if e.expr.yields_float() => {
+                Ok(Expression::Unary(Box::new(ast::UnaryExpr {
+                    base: BaseNode::default(),
+                    operator: Operator::SubtractionOperator,
+                    argument: expr,
+                })))
+            }
+            // Multiply all table _value columns by -1.
+            UnaryOp::Sub => Ok(pipeline(
+                expr,
+                vec![
+                    call(
+                        "map",
+                        vec![(
+                            "fn",
+                            scalar_arith_binary_op_fn(
+                                Operator::MultiplicationOperator,
+                                number(-1.0),
+                                false,
+                            ),
+                        )],
+                    ),
+                    drop_field_and_time_call(),
+                ],
+            )),
+        }
+    }
+
+    fn transpile_aggregate_expr(&self, a: &AggregateExpr) -> Result<Expression, Error> {
+        let expr = match self.transpile_expr(&a.expr) {
+            Ok(expr) => expr,
+            Err(e) => {
+                return Err(format!("error transpiling aggregate sub-expression: {}", e).into())
+            }
+        };
+
+        // The Flux function to call, whether the _field column has to be
+        // dropped afterwards and whether non-grouping columns need to be
+        // dropped explicitly, because the Flux counterparts of some
+        // aggregations keep them.
+        let (name, drop_field, drop_non_grouping) = match a.op {
+            AggregateOp::Sum => ("sum", true, false),
+            AggregateOp::Avg => ("mean", true, false),
+            AggregateOp::Max => ("max", true, true),
+            AggregateOp::Min => ("min", true, true),
+            AggregateOp::Count => ("count", true, false),
+            AggregateOp::Stddev | AggregateOp::Stdvar => ("stddev", true, false),
+            AggregateOp::TopK => ("top", false, false),
+            AggregateOp::BottomK => ("bottom", false, false),
+            AggregateOp::Quantile => ("quantile", true, false),
+            AggregateOp::CountValues => {
+                return Err(format!("unsupported aggregation type {}", a.op.name()).into())
+            }
+        };
+
+        let mut args = Vec::new();
+        match a.op {
+            AggregateOp::TopK | AggregateOp::BottomK => {
+                // TODO: Allow any constant scalars here.
+                let n = match a.param.as_ref().and_then(number_literal) {
+                    Some(n) => n,
+                    None => return Err("arbitrary scalar subexpressions not supported yet".into()),
+                };
+                if n.is_nan() || !(MIN_INT64..=MAX_INT64).contains(&n) {
+                    return Err(format!("scalar value {} overflows int64", n).into());
+                }
+                args.push(("n", integer(n as i64)));
+            }
+            AggregateOp::Quantile => {
+                // TODO: Allow any constant scalars here.
+                let q = match a.param.as_ref().and_then(number_literal) {
+                    Some(q) => q,
+                    None => return Err("arbitrary scalar subexpressions not supported yet".into()),
+                };
+                args.push(("q", number(q)));
+                args.push(("method", string("exact_mean")));
+            }
+            AggregateOp::Stddev | AggregateOp::Stdvar => {
+                args.push(("mode", string("population")));
+            }
+            _ => {}
+        }
+
+        let grouping: Vec<String> = a.grouping.iter().map(|l| escape_label_name(l)).collect();
+        let mut group_cols = grouping.clone();
+        let mut keep_field = false;
+        let mode = if a.without {
+            // "_time" is not always present, but if it is, we don't want to
+            // group by it.
+            group_cols.push("_time".to_string());
+            group_cols.push("_value".to_string());
+            "except"
+        } else {
+            group_cols.push("_start".to_string());
+            group_cols.push("_stop".to_string());
+            keep_field = grouping.iter().any(|l| l == "_field");
+            "by"
+        };
+
+        let mut calls = vec![
+            // Group values according to by() / without() clauses.
+            call(
+                "group",
+                vec![
+                    ("columns", column_list(&group_cols)),
+                    ("mode", string(mode)),
+                ],
+            ),
+            call(name, args),
+        ];
+        if name == "count" {
+            calls.push(call("toFloat", vec![]));
+        }
+        if drop_non_grouping {
+            calls.push(drop_non_grouping_cols_call(&grouping, a.without));
+        }
+        if drop_field && !keep_field {
+            calls.push(drop_field_and_time_call());
+        }
+        if a.op == AggregateOp::Stdvar {
+            calls.push(call(
+                "map",
+                vec![(
+                    "fn",
+                    scalar_arith_binary_math_fn("math.pow", number(2.0), false),
+                )],
+            ));
+        }
+        Ok(pipeline(expr, calls))
+    }
+
+    fn transpile_binary_expr(&self, b: &BinaryExpr) -> Result<Expression, Error> {
+        let lhs = match self.transpile_expr(&b.lhs) {
+            Ok(e) => e,
+            Err(e) => {
+                return Err(format!(
+                    "unable to transpile left-hand side of binary operation: {}",
+                    e
+                )
+                .into())
+            }
+        };
+        let rhs = match self.transpile_expr(&b.rhs) {
+            Ok(e) => e,
+            Err(e) => {
+                return Err(format!(
+                    "unable to transpile right-hand side of binary operation: {}",
+                    e
+                )
+                .into())
+            }
+        };
+
+        let (lhs_float, rhs_float) = (b.lhs.yields_float(), b.rhs.yields_float());
+        if lhs_float && rhs_float {
+            return match binary_op_kind(b.op) {
+                OpKind::Arith(op) => Ok(binary(op, lhs, rhs)),
+                OpKind::MathFn(f) => Ok(Expression::Call(Box::new(call(
+                    f,
+                    vec![("x", lhs), ("y", rhs)],
+                )))),
+                // The parser already requires the bool modifier here.
+                OpKind::Comparison(op) => Ok(Expression::Call(Box::new(call(
+                    "float",
+                    vec![("v", binary(op, lhs, rhs))],
+                )))),
+                OpKind::Set => Err(format!("invalid scalar-scalar binary op {:?}", b.op).into()),
+            };
+        }
+
+        if lhs_float || rhs_float {
+            let swapped = lhs_float;
+            let (table, operand) = if swapped { (rhs, lhs) } else { (lhs, rhs) };
+            let calls = match binary_op_kind(b.op) {
+                OpKind::Arith(op) => vec![
+                    call(
+                        "map",
+                        vec![("fn", scalar_arith_binary_op_fn(op, operand, swapped))],
+                    ),
+                    drop_field_and_time_call(),
+                ],
+                OpKind::MathFn(f) => vec![
+                    call(
+                        "map",
+                        vec![("fn", scalar_arith_binary_math_fn(f, operand, swapped))],
+                    ),
+                    drop_field_and_time_call(),
+                ],
+                OpKind::Comparison(op) if b.return_bool => vec![
+                    call(
+                        "map",
+                        vec![("fn", scalar_arith_binary_op_fn(op, operand, swapped))],
+                    ),
+                    call("toFloat", vec![]),
+                    drop_field_and_time_call(),
+                ],
+                OpKind::Comparison(op) => vec![call(
+                    "filter",
+                    vec![("fn", scalar_comp_binary_op_fn(op, operand, swapped))],
+                )],
+                OpKind::Set => {
+                    return Err(format!("invalid scalar-vector binary op {:?}", b.op).into())
+                }
+            };
+            return Ok(pipeline(table, calls));
+        }
+
+        // Non-constant scalars, like time(), have no vector matching and
+        // are joined on the window bounds only.
+        let (matching_labels, include) = match &b.matching {
+            None => (Vec::new(), Vec::new()),
+            Some(m) if !m.on || m.matching_labels.is_empty() => {
+                return Err(
+                    "vector-to-vector binary expressions without on() clause not supported yet"
+                        .into(),
+                )
+            }
+            Some(m) => (
+                m.matching_labels
+                    .iter()
+                    .map(|l| escape_label_name(l))
+                    .collect(),
+                m.include
+                    .iter()
+                    .map(|l| escape_label_name(l))
+                    .collect::<Vec<String>>(),
+            ),
+        };
+
+        let mut drop_field = true;
+        let mut calls = match binary_op_kind(b.op) {
+            OpKind::Arith(op) => vec![call("map", vec![("fn", vector_arith_binary_op_fn(op))])],
+            OpKind::MathFn(f) => {
+                vec![call("map", vec![("fn", vector_arith_binary_math_fn(f))])]
+            }
+            OpKind::Comparison(op) if b.return_bool => vec![
+                call("map", vec![("fn", vector_arith_binary_op_fn(op))]),
+                call("toFloat", vec![]),
+            ],
+            OpKind::Comparison(op) => {
+                let mut calls = vec![call("filter", vec![("fn", vector_comp_binary_op_fn(op))])];
+                if b.lhs.value_type() == ValueType::Scalar {
+                    // For <scalar> <comp-op> <vector> filter expressions, we
+                    // always want to return the sample value from the vector.
+                    calls.push(call(
+                        "duplicate",
+                        vec![
+                            ("column", string("_value_rhs")),
+                            ("as", string("_value_lhs")),
+                        ],
+                    ));
+                }
+                drop_field = false;
+                calls
+            }
+            OpKind::Set => return Err("vector set operations not supported yet".into()),
+        };
+
+        let mut on_cols = matching_labels;
+        on_cols.push("_start".to_string());
+        on_cols.push("_stop".to_string());
+        let mut keep_cols = on_cols.clone();
+        keep_cols.push("_value".to_string());
+        keep_cols.extend(include);
+        // TODO: Rename and drop the _lhs/_rhs columns once a new join
+        // implementation exists.
+        calls.push(call("keep", vec![("columns", column_list(&keep_cols))]));
+        if drop_field {
+            calls.push(drop_field_and_time_call());
+        }
+
+        let tables = Expression::Object(Box::new(ast::ObjectExpr {
+            base: BaseNode::default(),
+            with: None,
+            properties: vec![property("lhs", lhs), property("rhs", rhs)],
+        }));
+        Ok(pipeline(
+            Expression::Call(Box::new(call(
+                "join",
+                vec![("tables", tables), ("on", column_list(&on_cols))],
+            ))),
+            calls,
+        ))
+    }
+
+    fn transpile_call(&self, c: &Call) -> Result<Expression, Error> {
+        let mut args = Vec::with_capacity(c.args.len());
+        for arg in &c.args {
+            match self.transpile_expr(arg) {
+                Ok(a) => args.push(a),
+                Err(e) => return Err(format!("error transpiling function argument: {}", e).into()),
+            }
+        }
+        let name = c.func.name.as_str();
+        // The parser already checked the argument count and types, so the
+        // arguments can be taken in order.
+        if let Some(f) = aggregate_over_time_fn(name) {
+            return Ok(self.transpile_aggregate_over_time_fn(f, args));
+        }
+        let mut arg = || args.remove(0);
+
+        if let Some(f) = vector_math_fn(name) {
+            if c.args.len() > 1 {
+                return Err(format!(
+                    "{}() with more than one argument is not supported yet",
+                    name
+                )
+                .into());
+            }
+            return Ok(pipeline(
+                arg(),
+                vec![
+                    call("map", vec![("fn", single_arg_float_fn(f, "x"))]),
+                    drop_field_and_time_call(),
+                ],
+            ));
+        }
+
+        if let Some(f) = date_fn(name) {
+            let v = if c.args.is_empty() {
+                self.time_fn()?
+            } else {
+                arg()
+            };
+            return Ok(pipeline(
+                v,
+                vec![
+                    call("map", vec![("fn", single_arg_float_fn(f, "timestamp"))]),
+                    drop_field_and_time_call(),
+                ],
+            ));
+        }
+
+        let calls = match name {
+            "rate" | "delta" | "increase" => vec![call(
+                "promql.extrapolatedRate",
+                vec![
+                    ("isCounter", boolean(name != "delta")),
+                    ("isRate", boolean(name == "rate")),
+                ],
+            )],
+            "irate" | "idelta" => vec![call(
+                "promql.instantRate",
+                vec![("isRate", boolean(name == "irate"))],
+            )],
+            "deriv" => vec![call("promql.linearRegression", vec![])],
+            "predict_linear" => {
+                if !c.args[1].yields_float() {
+                    return Err("non-const scalar expressions not supported yet".into());
+                }
+                let v = arg();
+                return Ok(pipeline(
+                    v,
+                    vec![
+                        call(
+                            "promql.linearRegression",
+                            vec![("predict", boolean(true)), ("fromNow", arg())],
+                        ),
+                        drop_field_and_time_call(),
+                    ],
+                ));
+            }
+            "holt_winters" => {
+                if !c.args[1].yields_float() || !c.args[2].yields_float() {
+                    return Err("non-const scalar expressions not supported yet".into());
+                }
+                let v = arg();
+                return Ok(pipeline(
+                    v,
+                    vec![
+                        call(
+                            "promql.holtWinters",
+                            vec![("smoothingFactor", arg()), ("trendFactor", arg())],
+                        ),
+                        drop_field_and_time_call(),
+                    ],
+                ));
+            }
+            "timestamp" => vec![call("promql.timestamp", vec![])],
+            "time" => return self.time_fn(),
+            "changes" | "resets" => vec![call(&format!("promql.{}", name), vec![])],
+            "clamp_max" | "clamp_min" => {
+                let f = if name == "clamp_max" {
+                    "math.mMin"
+                } else {
+                    "math.mMax"
+                };
+                let v = arg();
+                return Ok(pipeline(
+                    v,
+                    vec![
+                        call(
+                            "map",
+                            vec![("fn", scalar_arith_binary_math_fn(f, arg(), false))],
+                        ),
+                        drop_field_and_time_call(),
+                    ],
+                ));
+            }
+            "label_join" => {
+                let dst = label_argument(&c.args[1], "label_join() destination label")?;
+                let sep = match &c.args[2] {
+                    Expr::String(s) => s.clone(),
+                    _ => return Err("label_join() separator must be string literal".into()),
+                };
+                let mut src = Vec::with_capacity(c.args.len() - 3);
+                for a in &c.args[3..] {
+                    src.push(label_argument(a, "label_join() source labels")?);
+                }
+                return Ok(pipeline(
+                    arg(),
+                    vec![call("map", vec![("fn", label_join_fn(&src, &dst, &sep))])],
+                ));
+            }
+            "label_replace" => {
+                let dst = label_argument(&c.args[1], "label_replace() destination label")?;
+                let (repl, src, regex) = match (&c.args[2], &c.args[3], &c.args[4]) {
+                    (Expr::String(repl), Expr::String(src), Expr::String(regex)) => {
+                        (repl, src, regex)
+                    }
+                    _ => {
+                        return Err(
+                            "non-literal string arguments not supported yet in label_replace()"
+                                .into(),
+                        )
+                    }
+                };
+                anchored_regex(regex)?;
+                // The source label is not validated, as PromQL's
+                // label_replace() also allows invalid source labels.
+                return Ok(pipeline(
+                    arg(),
+                    vec![call(
+                        "promql.labelReplace",
+                        vec![
+                            ("destination", string(&dst)),
+                            ("replacement", string(repl)),
+                            ("source", string(&escape_label_name(src))),
+                            ("regex", string(regex)),
+                        ],
+                    )],
+                ));
+            }
+            "vector" => {
+                if !c.args[0].yields_float() {
+                    return Ok(arg());
+                }
+                return Ok(pipeline(
+                    self.generate_zero_windows()?,
+                    vec![call("map", vec![("fn", set_const_value_fn(arg()))])],
+                ));
+            }
+            "scalar" => {
+                // TODO: Insert NaN values at time steps where there is no
+                // value in the vector. This requires outer join support.
+                return Ok(pipeline(
+                    arg(),
+                    vec![call(
+                        "keep",
+                        vec![("columns", column_list(&["_stop", "_value"]))],
+                    )],
+                ));
+            }
+            "histogram_quantile" => {
+                if !c.args[0].yields_float() {
+                    return Err("non-const scalar expressions not supported yet".into());
+                }
+                let q = arg();
+                return Ok(pipeline(
+                    arg(),
+                    vec![
+                        call(
+                            "group",
+                            vec![
+                                ("columns", column_list(&["_time", "_value", "le"])),
+                                ("mode", string("except")),
+                            ],
+                        ),
+                        call("promql.promHistogramQuantile", vec![("quantile", q)]),
+                        drop_field_and_time_call(),
+                    ],
+                ));
+            }
+            _ => return Err(format!("PromQL function {:?} is not supported yet", name).into()),
+        };
+
+        let mut calls = calls;
+        calls.push(drop_field_and_time_call());
+        Ok(pipeline(args.remove(0), calls))
+    }
+
+    fn transpile_aggregate_over_time_fn(&self, f: &str, mut args: Vec<Expression>) -> Expression {
+        let mut call_fn = f;
+        let mut fn_args = Vec::new();
+        match f {
+            "quantile" => {
+                fn_args.push(("q", args.remove(0)));
+                fn_args.push(("method", string("exact_mean")));
+            }
+            "stddev" | "stdvar" => {
+                call_fn = "stddev";
+                fn_args.push(("mode", string("population")));
+            }
+            _ => {}
+        }
+
+        let mut calls = vec![
+            call(call_fn, fn_args),
+            filter_null_values_call(),
+            call("toFloat", vec![]),
+            drop_field_and_time_call(),
+        ];
+        match f {
+            // Count is the only function that produces a 0 instead of a null
+            // value for an empty table, while PromQL returns no result.
+            "count" => calls.push(call(
+                "filter",
+                vec![(
+                    "fn",
+                    function(binary(
+                        Operator::GreaterThanOperator,
+                        member("r", "_value"),
+                        number(0.0),
+                    )),
+                )],
+            )),
+            "stdvar" => calls.push(call(
+                "map",
+                vec![(
+                    "fn",
+                    scalar_arith_binary_math_fn("math.pow", number(2.0), false),
+                )],
+            )),
+            _ => {}
+        }
+        pipeline(args.remove(0), calls)
+    }
+
+    fn transpile_subquery_expr(&self, sq: &SubqueryExpr) -> Result<Expression, Error> {
+        // Transpile the subexpression with the boundaries and step of the
+        // subquery.
+        let sqt = Transpiler {
+            bucket: self.bucket.clone(),
+            start: sub(sub(self.start, sq.range)?, sq.offset)?,
+            end: sub(self.end, sq.offset)?,
+            resolution: sq.step,
+        };
+        let subquery = sqt.transpile_expr(&sq.expr)?;
+
+        // Window the subexpression data according to the parent query's step
+        // and range.
+        let mut calls = vec![call(
+            "duplicate",
+            vec![("column", string("_stop")), ("as", string("_time"))],
+        )];
+        calls.extend(self.window_calls(
+            sq.range,
+            self.start,
+            sub(self.start, sq.offset)?,
+            sub(sub(self.end, sq.range)?, sq.offset)?,
+        ));
+        // Apply offsets to make past data look like it's in the present.
+        calls.push(call("timeShift", vec![("duration", duration(sq.offset))]));
+        Ok(pipeline(subquery, calls))
+    }
+
+    // generate_zero_windows produces one empty sample per resolution step,
+    // which scalar results are attached to.
+    fn generate_zero_windows(&self) -> Result<Expression, Error> {
+        let mut calls = vec![call(
+            "range",
+            vec![
+                ("start", datetime(sub(self.start, lookback_delta())?)),
+                ("stop", datetime(self.end)),
+            ],
+        )];
+        let mut window = self.window_calls(
+            lookback_delta(),
+            self.start,
+            self.start,
+            sub(self.end, lookback_delta())?,
+        );
+        let cutoff = window.pop();
+        if let Some(mut w) = window.pop() {
+            if let Some(Expression::Object(obj)) = w.arguments.first_mut() {
+                obj.properties.push(property("createEmpty", boolean(true)));
+            }
+            calls.push(w);
+        }
+        calls.push(call("sum", vec![]));
+        calls.extend(cutoff);
+        Ok(pipeline(
+            Expression::Call(Box::new(call("promql.emptyTable", vec![]))),
+            calls,
+        ))
+    }
+
+    fn time_fn(&self) -> Result<Expression, Error> {
+        Ok(pipeline(
+            self.generate_zero_windows()?,
+            vec![call("promql.timestamp", vec![])],
+        ))
+    }
+}
+
+enum OpKind {
+    Arith(Operator),
+    MathFn(&'static str),
+    Comparison(Operator),
+    Set,
+}
+
+fn binary_op_kind(op: BinaryOp) -> OpKind {
+    match op {
+        BinaryOp::Add => OpKind::Arith(Operator::AdditionOperator),
+        BinaryOp::Sub => OpKind::Arith(Operator::SubtractionOperator),
+        BinaryOp::Mul => OpKind::Arith(Operator::MultiplicationOperator),
+        BinaryOp::Div => OpKind::Arith(Operator::DivisionOperator),
+        BinaryOp::Pow => OpKind::MathFn("math.pow"),
+        BinaryOp::Mod => OpKind::MathFn("math.mod"),
+        BinaryOp::Eql => OpKind::Comparison(Operator::EqualOperator),
+        BinaryOp::Neq => OpKind::Comparison(Operator::NotEqualOperator),
+        BinaryOp::Gtr => OpKind::Comparison(Operator::GreaterThanOperator),
+        BinaryOp::Lss => OpKind::Comparison(Operator::LessThanOperator),
+        BinaryOp::Gte => OpKind::Comparison(Operator::GreaterThanEqualOperator),
+        BinaryOp::Lte => OpKind::Comparison(Operator::LessThanEqualOperator),
+        BinaryOp::And | BinaryOp::Or | BinaryOp::Unless => OpKind::Set,
+    }
+}
+
+fn aggregate_over_time_fn(name: &str) -> Option<&'static str> {
+    match name {
+        "sum_over_time" => Some("sum"),
+        "avg_over_time" => Some("mean"),
+        "max_over_time" => Some("max"),
+        "min_over_time" => Some("min"),
+        "count_over_time" => Some("count"),
+        "stddev_over_time" => Some("stddev"),
+        // TODO: Add stdvar() to the Flux stdlib instead of special-casing it.
+        "stdvar_over_time" => Some("stdvar"),
+        "quantile_over_time" => Some("quantile"),
+        _ => None,
+    }
+}
+
+fn vector_math_fn(name: &str) -> Option<&'static str> {
+    match name {
+        "abs" => Some("math.abs"),
+        "ceil" => Some("math.ceil"),
+        "floor" => Some("math.floor"),
+        "exp" => Some("math.exp"),
+        "sqrt" => Some("math.sqrt"),
+        "ln" => Some("math.log"),
+        "log2" => Some("math.log2"),
+        "log10" => Some("math.log10"),
+        "round" => Some("math.round"),
+        _ => None,
+    }
+}
+
+fn date_fn(name: &str) -> Option<&'static str> {
+    match name {
+        "day_of_month" => Some("promql.promqlDayOfMonth"),
+        "day_of_week" => Some("promql.promqlDayOfWeek"),
+        "days_in_month" => Some("promql.promqlDaysInMonth"),
+        "hour" => Some("promql.promqlHour"),
+        "minute" => Some("promql.promqlMinute"),
+        "month" => Some("promql.promqlMonth"),
+        "year" => Some("promql.promqlYear"),
+        _ => None,
+    }
+}
+
+// label_argument validates and escapes a label name passed as a string
+// argument to label_join() or label_replace().
+fn label_argument(e: &Expr, what: &str) -> Result<String, Error> {
+    let name = match e {
+        Expr::String(s) => s,
+        _ => return Err(format!("{} must be string literal", what).into()),
+    };
+    let mut chars = name.chars();
+    let valid = match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    };
+    if !valid {
+        return Err(format!("invalid label name in {}: {}", what, name).into());
+    }
+    Ok(escape_label_name(name))
+}
+
+fn number_literal(e: &Expr) -> Option<f64> {
+    match e {
+        Expr::Number(v) => Some(*v),
+        Expr::Paren(e) => number_literal(e),
+        _ => None,
+    }
+}
+
+fn nanos(d: Duration) -> i64 {
+    // Durations are range checked when they are parsed.
+    d.num_nanoseconds().unwrap_or(std::i64::MAX)
+}
+
+fn sub(t: DateTime<FixedOffset>, d: Duration) -> Result<DateTime<FixedOffset>, Error> {
+    match t.checked_sub_signed(d) {
+        Some(t) => Ok(t),
+        None => Err(format!("time {} minus {} is out of range", t.to_rfc3339(), d).into()),
+    }
+}
+
+// is_identifier reports whether a column name can be written as a plain
+// Flux identifier, rather than a string literal.
+fn is_identifier(name: &str) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "and", "builtin", "else", "empty", "exists", "if", "import", "in", "not", "option", "or",
+        "package", "return", "test", "then",
+    ];
+    let mut chars = name.chars();
+    let start = match chars.next() {
+        Some(c) => c.is_ascii_alphabetic() || c == '_',
+        None => false,
+    };
+    start && chars.all(|c| c.is_ascii_alphanumeric() || c == '_') && !KEYWORDS.contains(&name)
+}
+
+fn identifier(name: &str) -> ast::Identifier {
+    ast::Identifier {
+        base: BaseNode::default(),
+        name: name.to_string(),
+    }
+}
+
+fn property_key(name: &str) -> PropertyKey {
+    if is_identifier(name) {
+        PropertyKey::Identifier(identifier(name))
+    } else {
+        PropertyKey::StringLit(ast::StringLit {
+            base: BaseNode::default(),
+            value: name.to_string(),
+        })
+    }
+}
+
+fn property(key: &str, value: Expression) -> ast::Property {
+    ast::Property {
+        base: BaseNode::default(),
+        key: property_key(key),
+        value: Some(value),
+    }
+}
+
+fn member(object: &str, property: &str) -> Expression {
+    Expression::Member(Box::new(ast::MemberExpr {
+        base: BaseNode::default(),
+        object: Expression::Identifier(identifier(object)),
+        property: property_key(property),
+    }))
+}
+
+fn string(v: &str) -> Expression {
+    Expression::StringLit(ast::StringLit {
+        base: BaseNode::default(),
+        value: v.to_string(),
+    })
+}
+
+fn boolean(v: bool) -> Expression {
+    Expression::Boolean(ast::BooleanLit {
+        base: BaseNode::default(),
+        value: v,
+    })
+}
+
+fn integer(v: i64) -> Expression {
+    Expression::Integer(ast::IntegerLit {
+        base: BaseNode::default(),
+        value: v,
+    })
+}
+
+// number converts a PromQL number into a Flux float. Flux has no literals
+// for infinity and NaN, so those are produced by math functions.
+fn number(v: f64) -> Expression {
+    if v.is_nan() {
+        return Expression::Call(Box::new(call("math.NaN", vec![])));
+    }
+    if v.is_infinite() {
+        let sign = if v > 0.0 { 1 } else { -1 };
+        return Expression::Call(Box::new(call("math.mInf", vec![("sign", integer(sign))])));
+    }
+    Expression::Float(ast::FloatLit {
+        base: BaseNode::default(),
+        value: v,
+    })
+}
+
+fn nanoseconds(n: i64) -> Expression {
+    Expression::Duration(ast::DurationLit {
+        base: BaseNode::default(),
+        values: vec![ast::Duration {
+            magnitude: n,
+            unit: "ns".to_string(),
+        }],
+    })
+}
+
+fn duration(d: Duration) -> Expression {
+    nanoseconds(nanos(d))
+}
+
+fn datetime(t: DateTime<FixedOffset>) -> Expression {
+    Expression::DateTime(ast::DateTimeLit {
+        base: BaseNode::default(),
+        value: t,
+    })
+}
+
+fn column_list<S: AsRef<str>>(cols: &[S]) -> Expression {
+    Expression::Array(Box::new(ast::ArrayExpr {
+        base: BaseNode::default(),
+        elements: cols.iter().map(|c| string(c.as_ref())).collect(),
+    }))
+}
+
+fn binary(operator: Operator, left: Expression, right: Expression) -> Expression {
+    Expression::Binary(Box::new(ast::BinaryExpr {
+        base: BaseNode::default(),
+        operator,
+        left,
+        right,
+    }))
+}
+
+fn import(path: &str) -> ast::ImportDeclaration {
+    ast::ImportDeclaration {
+        base: BaseNode::default(),
+        alias: None,
+        path: ast::StringLit {
+            base: BaseNode::default(),
+            value: path.to_string(),
+        },
+    }
+}
+
+// call builds a call with named arguments. A dotted name like `math.pow`
+// calls a member of an imported package.
+fn call(name: &str, args: Vec<(&str, Expression)>) -> ast::CallExpr {
+    let callee = match name.find('.') {
+        Some(i) => member(&name[..i], &name[i + 1..]),
+        None => Expression::Identifier(identifier(name)),
+    };
+    let arguments = if args.is_empty() {
+        Vec::new()
+    } else {
+        vec![Expression::Object(Box::new(ast::ObjectExpr {
+            base: BaseNode::default(),
+            with: None,
+            properties: args.into_iter().map(|(k, v)| property(k, v)).collect(),
+        }))]
+    };
+    ast::CallExpr {
+        base: BaseNode::default(),
+        callee,
+        arguments,
+    }
+}
+
+fn pipeline(arg: Expression, calls: Vec<ast::CallExpr>) -> Expression {
+    calls.into_iter().fold(arg, |argument, call| {
+        Expression::PipeExpr(Box::new(ast::PipeExpr {
+            base: BaseNode::default(),
+            argument,
+            call,
+        }))
+    })
+}
+
+// function builds `(r) => <body>`.
+fn function(body: Expression) -> Expression {
+    Expression::Function(Box::new(ast::FunctionExpr {
+        base: BaseNode::default(),
+        params: vec![ast::Property {
+            base: BaseNode::default(),
+            key: PropertyKey::Identifier(identifier("r")),
+            value: None,
+        }],
+        body: ast::FunctionBody::Expr(body),
+    }))
+}
+
+// with_r builds `{r with <properties>}`.
+fn with_r(properties: Vec<ast::Property>) -> Expression {
+    Expression::Object(Box::new(ast::ObjectExpr {
+        base: BaseNode::default(),
+        with: Some(identifier("r")),
+        properties,
+    }))
+}
+
+fn filter_null_values_call() -> ast::CallExpr {
+    call(
+        "filter",
+        vec![(
+            "fn",
+            function(Expression::Unary(Box::new(ast::UnaryExpr {
+                base: BaseNode::default(),
+                operator: Operator::ExistsOperator,
+                argument: member("r", "_value"),
+            }))),
+        )],
+    )
+}
+
+fn drop_call(cols: &[&str]) -> ast::CallExpr {
+    call("drop", vec![("columns", column_list(cols))])
+}
+
+fn drop_measurement_call() -> ast::CallExpr {
+    drop_call(&["_measurement"])
+}
+
+fn drop_field_and_time_call() -> ast::CallExpr {
+    drop_call(&["_field", "_time"])
+}
+
+// drop_non_grouping_cols_call drops labels that are not part of the
+// grouping, like every PromQL aggregation does.
+fn drop_non_grouping_cols_call(grouping: &[String], without: bool) -> ast::CallExpr {
+    if without {
+        let cols: Vec<&String> = grouping
+            .iter()
+            .filter(|c| *c != "_value" && *c != "_stop")
+            .collect();
+        return call("drop", vec![("columns", column_list(&cols))]);
+    }
+    // Keep the value and stop columns even if they are not explicitly part
+    // of the grouping labels.
+    let mut cols = grouping.to_vec();
+    cols.push("_value".to_string());
+    cols.push("_stop".to_string());
+    call("keep", vec![("columns", column_list(&cols))])
+}
+
+fn label_matcher(m: &LabelMatcher) -> Result<Expression, Error> {
+    let (op, right) = match m.op {
+        MatchOp::Equal => (Operator::EqualOperator, string(&m.value)),
+        MatchOp::NotEqual => (Operator::NotEqualOperator, string(&m.value)),
+        MatchOp::Regex | MatchOp::NotRegex => {
+            // PromQL regexes always match the full label value.
+            let re = anchored_regex(&m.value)?;
+            let op = if m.op == MatchOp::Regex {
+                Operator::RegexpMatchOperator
+            } else {
+                Operator::NotRegexpMatchOperator
+            };
+            let regex = Expression::Regexp(ast::RegexpLit {
+                base: BaseNode::default(),
+                value: re.as_str().to_string(),
+            });
+            (op, regex)
+        }
+    };
+    Ok(binary(op, member("r", &escape_label_name(&m.name)), right))
+}
+
+// label_matchers_fn ANDs all label matchers together in a right-heavy tree.
+fn label_matchers_fn(matchers: &[LabelMatcher]) -> Result<Expression, Error> {
+    let mut body: Option<Expression> = None;
+    for m in matchers.iter().rev() {
+        let left = label_matcher(m)?;
+        body = Some(match body {
+            None => left,
+            Some(right) => Expression::Logical(Box::new(ast::LogicalExpr {
+                base: BaseNode::default(),
+                operator: ast::LogicalOperator::AndOperator,
+                left,
+                right,
+            })),
+        });
+    }
+    match body {
+        Some(body) => Ok(function(body)),
+        None => Err("empty label matchers".into()),
+    }
+}
+
+// window_cutoff_fn removes extraneous windows at the edges of the range.
+fn window_cutoff_fn(
+    min_stop: DateTime<FixedOffset>,
+    max_start: DateTime<FixedOffset>,
+) -> Expression {
+    function(Expression::Logical(Box::new(ast::LogicalExpr {
+        base: BaseNode::default(),
+        operator: ast::LogicalOperator::AndOperator,
+        left: binary(
+            Operator::GreaterThanEqualOperator,
+            member("r", "_stop"),
+            datetime(min_stop),
+        ),
+        right: binary(
+            Operator::LessThanEqualOperator,
+            member("r", "_start"),
+            datetime(max_start),
+        ),
+    })))
+}
+
+// set_value_fn builds `(r) => ({r with _value: <value>, _stop: r._stop})`.
+fn set_value_fn(value: Expression) -> Expression {
+    function(with_r(vec![
+        property("_value", value),
+        property("_stop", member("r", "_stop")),
+    ]))
+}
+
+fn set_const_value_fn(v: Expression) -> Expression {
+    set_value_fn(v)
+}
+
+fn operands(operand: Expression, swapped: bool) -> (Expression, Expression) {
+    if swapped {
+        (operand, member("r", "_value"))
+    } else {
+        (member("r", "_value"), operand)
+    }
+}
+
+// scalar_arith_binary_op_fn applies an arithmetic operator to all values
+// in a table and a scalar operand.
+fn scalar_arith_binary_op_fn(op: Operator, operand: Expression, swapped: bool) -> Expression {
+    let (lhs, rhs) = operands(operand, swapped);
+    set_value_fn(binary(op, lhs, rhs))
+}
+
+// scalar_arith_binary_math_fn applies a two-argument math function to all
+// values in a table and a scalar operand.
+fn scalar_arith_binary_math_fn(f: &str, operand: Expression, swapped: bool) -> Expression {
+    let (lhs, rhs) = operands(operand, swapped);
+    set_value_fn(Expression::Call(Box::new(call(
+        f,
+        vec![("x", lhs), ("y", rhs)],
+    ))))
+}
+
+// scalar_comp_binary_op_fn compares all values in a table to a scalar.
+fn scalar_comp_binary_op_fn(op: Operator, operand: Expression, swapped: bool) -> Expression {
+    let (lhs, rhs) = operands(operand, swapped);
+    function(binary(op, lhs, rhs))
+}
+
+fn vector_arith_binary_op_fn(op: Operator) -> Expression {
+    set_value_fn(binary(
+        op,
+        member("r", "_value_lhs"),
+        member("r", "_value_rhs"),
+    ))
+}
+
+fn vector_arith_binary_math_fn(f: &str) -> Expression {
+    set_value_fn(Expression::Call(Box::new(call(
+        f,
+        vec![
+            ("x", member("r", "_value_lhs")),
+            ("y", member("r", "_value_rhs")),
+        ],
+    ))))
+}
+
+fn vector_comp_binary_op_fn(op: Operator) -> Expression {
+    function(binary(
+        op,
+        member("r", "_value_lhs"),
+        member("r", "_value_rhs"),
+    ))
+}
+
+// single_arg_float_fn applies a one-argument function to all values.
+fn single_arg_float_fn(f: &str, arg: &str) -> Expression {
+    set_value_fn(Expression::Call(Box::new(call(
+        f,
+        vec![(arg, member("r", "_value"))],
+    ))))
+}
+
+// label_join_fn builds `(r) => ({r with <dst>: <src1> + <sep> + <src2>...,
+// _value: r._value})`.
+fn label_join_fn(src: &[String], dst: &str, sep: &str) -> Expression {
+    let mut value = match src.first() {
+        Some(first) => member("r", first),
+        None => string(""),
+    };
+    for label in src.iter().skip(1) {
+        value = binary(
+            Operator::AdditionOperator,
+            value,
+            binary(Operator::AdditionOperator, string(sep), member("r", label)),
+        );
+    }
+    function(with_r(vec![
+        property(dst, value),
+        property("_value", member("r", "_value")),
+    ]))
+}
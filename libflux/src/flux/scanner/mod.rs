@@ -20,6 +20,8 @@ pub struct Scanner {
     checkpoint_last_newline: *const CChar,
     token: TOK,
     positions: HashMap<Position, u32>,
+    // comments collects the comments skipped over, when it is set.
+    comments: Option<Vec<Token>>,
 }
 
 #[derive(Debug, PartialEq, Clone, Hash)]
@@ -59,6 +61,7 @@ impl Scanner {
             checkpoint_line: 1,
             checkpoint_last_newline: ptr as *const CChar,
             positions: HashMap::new(),
+            comments: None,
         }
     }
 
@@ -87,8 +90,37 @@ impl Scanner {
         self.last_newline = self.checkpoint_last_newline;
     }
 
+    // keep_comments makes the scanner collect the comments it skips over.
+    pub fn keep_comments(&mut self) {
+        self.comments = Some(Vec::new());
+    }
+
+    // take_comments returns the comments skipped over since the last call.
+    pub fn take_comments(&mut self) -> Vec<Token> {
+        match &mut self.comments {
+            Some(comments) => std::mem::take(comments),
+            None => Vec::new(),
+        }
+    }
+
     pub fn offset(&self, pos: &Position) -> u32 {
-        *self.positions.get(pos).expect("position should be in map")
+        match self.positions.get(pos) {
+            Some(offset) => *offset,
+            // A position that no token starts or ends at is found by
+            // counting lines.
+            None => {
+                let data = self.data.as_bytes();
+                let mut start = 0;
+                for _ in 1..pos.line {
+                    match data[start..].iter().position(|&b| b == b'\n') {
+                        Some(i) => start += i + 1,
+                        None => return data.len() as u32,
+                    }
+                }
+                let offset = start + pos.column.saturating_sub(1) as usize;
+                offset.min(data.len()) as u32
+            }
+        }
     }
 
     fn get_eof_token(&self) -> Token {
@@ -110,7 +142,25 @@ impl Scanner {
         }
     }
 
+    // _scan returns the next token that is not a comment. Comments are
+    // skipped in a loop rather than by recursion, as there may be any number
+    // of them in a row.
     fn _scan(&mut self, mode: i32) -> Token {
+        loop {
+            let t = self.scan_one(mode);
+            if t.tok != TOK_COMMENT {
+                return t;
+            }
+            // TODO(affo): return comments to attach them to nodes within the AST.
+            if let Some(comments) = &mut self.comments {
+                comments.push(t);
+            }
+        }
+    }
+
+    // scan_one returns the next token, which may be a comment. Comments are
+    // scanned in the same mode as the token they precede.
+    fn scan_one(&mut self, mode: i32) -> Token {
         if self.p == self.eof {
             return self.get_eof_token();
         }
@@ -209,15 +259,7 @@ impl Scanner {
         // may later go from position to offset by calling offset()
         self.positions.insert(t.start_pos.clone(), t.start_offset);
         self.positions.insert(t.end_pos.clone(), t.end_offset);
-
-        // Skipping comments.
-        // TODO(affo): return comments to attach them to nodes within the AST.
-        match t {
-            Token {
-                tok: TOK_COMMENT, ..
-            } => self.scan(),
-            _ => t,
-        }
+        t
     }
 }
 
@@ -1286,6 +1286,35 @@ a
     );
 }
 
+#[test]
+fn test_scan_keep_comments() {
+    let text = "a // one\n// two\n/b/";
+    let cdata = CString::new(text).expect("CString::new failed");
+    let mut s = Scanner::new(cdata);
+    s.keep_comments();
+    assert_eq!(s.scan().tok, TOK_IDENT);
+    assert!(s.take_comments().is_empty());
+    // The token after the comments is still scanned as a regex.
+    assert_eq!(
+        s.scan_with_regex(),
+        Token {
+            tok: TOK_REGEX,
+            lit: String::from("/b/"),
+            start_offset: 16,
+            end_offset: 19,
+            start_pos: Position { line: 3, column: 1 },
+            end_pos: Position { line: 3, column: 4 }
+        }
+    );
+    let comments: Vec<(u32, u32)> = s
+        .take_comments()
+        .iter()
+        .map(|t| (t.start_offset, t.end_offset))
+        .collect();
+    assert_eq!(comments, vec![(2, 9), (9, 16)]);
+    assert_eq!(s.scan().tok, TOK_EOF);
+}
+
 #[test]
 fn test_scan_eof() {
     let text = r#""#;
@@ -7,6 +7,7 @@ use crate::ast;
 use crate::parser;
 use crate::semantic::builtins::builtins;
 use crate::semantic::convert::convert_file;
+use crate::semantic::doc;
 use crate::semantic::env::Environment;
 use crate::semantic::fresh::Fresher;
 use crate::semantic::import::Importer;
@@ -87,6 +88,42 @@ pub fn infer_stdlib() -> Result<
     Ok((prelude, importer, f))
 }
 
+// Document every package of the standard library in a directory, sorted
+// by import path. Each package is documented with the types inferred for
+// it, as in infer_stdlib.
+pub fn stdlib_docs(path: &str) -> Result<Vec<doc::PackageDoc>, Error> {
+    let (builtins, mut f) = builtin_types()?;
+
+    // Each package is the directory of its file. A file at the root of the
+    // directory belongs to no package and is left out.
+    let mut sources = HashMap::new();
+    let mut files = HashMap::new();
+    for (name, src) in flux_sources(path)? {
+        if let Some((pkg, _)) = name.rsplit_once('/') {
+            files.insert(pkg.to_string(), parser::parse_string(&name, &src));
+            sources.insert(pkg.to_string(), src);
+        }
+    }
+
+    let (prelude, mut imports) = infer_pre(&mut f, &files, &builtins)?;
+
+    let mut paths: Vec<&String> = files.keys().collect();
+    paths.sort();
+    let mut docs = Vec::new();
+    for pkg in paths {
+        // The prelude is inferred without itself.
+        let prelude = if PRELUDE.contains(&pkg.as_str()) {
+            HashMap::new()
+        } else {
+            prelude.clone()
+        };
+        let (types, importer) = infer_pkg(pkg, &mut f, &files, &builtins, prelude, imports)?;
+        imports = importer;
+        docs.push(doc::package_doc(pkg, &sources[pkg], &types));
+    }
+    Ok(docs)
+}
+
 #[allow(clippy::type_complexity)]
 fn builtin_types() -> Result<(HashMap<String, HashMap<String, PolyType>>, Fresher), Error> {
     let mut tv = Tvar(0);
@@ -147,23 +184,34 @@ fn infer_std<I: Importer>(
 
 // Recursively parse all flux files within a directory.
 fn parse_flux_files(path: &str) -> io::Result<Vec<ast::File>> {
-    let mut files = Vec::new();
-    let entries = WalkDir::new(PathBuf::from(path))
+    Ok(flux_sources(path)?
+        .iter()
+        .map(|(name, src)| parser::parse_string(name, src))
+        .collect())
+}
+
+// Recursively read all flux files within a directory, naming each by its
+// path relative to the directory.
+fn flux_sources(path: &str) -> io::Result<Vec<(String, String)>> {
+    let mut sources = Vec::new();
+    let dir = PathBuf::from(path);
+    let entries = WalkDir::new(&dir)
         .into_iter()
         .filter_map(|r| r.ok())
         .filter(|r| r.path().is_file());
 
     for entry in entries {
-        if let Some(path) = entry.path().to_str() {
-            if path.ends_with(".flux") && !path.ends_with("_test.flux") {
-                files.push(parser::parse_string(
-                    path.rsplitn(2, "/stdlib/").collect::<Vec<&str>>()[0],
-                    &fs::read_to_string(path)?,
-                ));
+        let name = entry
+            .path()
+            .strip_prefix(&dir)
+            .unwrap_or_else(|_| entry.path());
+        if let Some(name) = name.to_str() {
+            if name.ends_with(".flux") && !name.ends_with("_test.flux") {
+                sources.push((name.to_string(), fs::read_to_string(entry.path())?));
             }
         }
     }
-    Ok(files)
+    Ok(sources)
 }
 
 // Associates an import path with each file
@@ -428,6 +476,54 @@ mod tests {
         assert_eq!(vec!["system", "date", "math", "strings", "regexp"], names,);
     }
 
+    #[test]
+    fn document_stdlib() {
+        let docs = stdlib_docs("../../../stdlib").unwrap();
+        let (_, imports, _) = infer_stdlib().unwrap();
+        for (path, ty) in &imports {
+            let pkg = docs
+                .iter()
+                .find(|d| &d.path == path)
+                .unwrap_or_else(|| panic!("package {} has no docs", path));
+            let fields = match &ty.expr {
+                MonoType::Row(row) => row.labels(),
+                _ => panic!("package {} is not a record", path),
+            };
+            for name in fields {
+                let member = pkg.members.iter().find(|m| m.name == name);
+                let member = member.unwrap_or_else(|| panic!("{}.{} has no docs", path, name));
+                assert!(!member.typ.is_empty());
+                assert!(pkg
+                    .markdown()
+                    .contains(&format!("## {}.{}\n", pkg.name, name)));
+            }
+        }
+        let universe = docs.iter().find(|d| d.path == "universe").unwrap();
+        let now = universe.members.iter().find(|m| m.name == "now").unwrap();
+        assert_eq!(
+            now.doc.as_deref(),
+            Some("now is a function option whose default behaviour is to return the current system time")
+        );
+        assert_eq!(now.typ, "forall [] () -> time");
+        let filter = universe
+            .members
+            .iter()
+            .find(|m| m.name == "filter")
+            .unwrap();
+        assert_eq!(
+            filter.doc.as_deref(),
+            Some("filter returns the rows of the tables for which fn returns true.")
+        );
+        assert_eq!(
+            filter.typ,
+            r#"forall [A] where A: Row (
+    <-tables: [A],
+    fn: (r: A) -> bool,
+    ?onEmpty: string
+) -> [A]"#
+        );
+    }
+
     #[test]
     fn cyclic_dependency() {
         let a = r#"
@@ -1,6 +1,7 @@
 use crate::semantic::fresh::{Fresh, Fresher};
 use crate::semantic::import::Importer;
 use crate::semantic::parser::parse;
+use crate::semantic::types::PolyType;
 use maplit::hashmap;
 use std::collections::hash_map::Iter;
 use std::collections::HashMap;
@@ -85,8 +86,8 @@ pub fn builtins() -> Builtins<'static> {
                 "scrape" => "forall [t0] where t0: Row (url: string) -> [t0]",
             },
             "experimental" => maplit::hashmap! {
-                 "addDuration" => "forall [] (d: duration, to: time) -> time",
-                 "subDuration" => "forall [] (d: duration, from: time) -> time",
+                 "addDuration" => "forall [t0] where t0: Timeable (d: duration, to: t0) -> time",
+                 "subDuration" => "forall [t0] where t0: Timeable (d: duration, from: t0) -> time",
                  "group" => "forall [t0] where t0: Row (<-tables: [t0], mode: string, columns: [string]) -> [t0]",
                  "objectKeys" => "forall [t0] where t0: Row (o: t0) -> [string]",
                  "set" => "forall [t0, t1, t2] where t0: Row, t1: Row, t2: Row (<-tables: [t0], o: t1) -> [t2]",
@@ -94,7 +95,9 @@ pub fn builtins() -> Builtins<'static> {
                  // must specify exactly one of org, orgID
                  // if host is specified, token must be too.
                  // https://github.com/influxdata/flux/issues/1660
-                 "to" => "forall [t0] where t0: Row (<-tables: [t0], ?bucket: string, ?bucketID: string, ?org: string, ?orgID: string, ?host: string, ?token: string) -> [t0]",
+                 // The rows returned are not related to those given, so that options
+                 // defaulting to it, like monitor.write, may be set to change columns.
+                 "to" => "forall [t0, t1] where t0: Row, t1: Row (<-tables: [t0], ?bucket: string, ?bucketID: string, ?org: string, ?orgID: string, ?host: string, ?token: string) -> [t1]",
             },
             "generate" => maplit::hashmap! {
                 "from" => "forall [] (start: time, stop: time, count: int, fn: (n: int) -> int) -> [{ _start: time | _stop: time | _time: time | _value:int }]",
@@ -328,14 +331,14 @@ pub fn builtins() -> Builtins<'static> {
                 "isLetter" => "forall [] (v: string) -> bool",
                 "isLower" => "forall [] (v: string) -> bool",
                 "isUpper" => "forall [] (v: string) -> bool",
-                "repeat" => "forall [] (v: string, count: int) -> string",
-                "replace" => "forall [] (v: string, old: string, new: string, n: int) -> string",
-                "replaceAll" => "forall [] (v: string, old: string, new: string) -> string",
-                "split" => "forall [] (v: string, t: string) -> string",
-                "splitAfter" => "forall [] (v: string, t: string) -> string",
-                "splitN" => "forall [] (v: string, t: string, n: int) -> string",
-                "splitAfterN" => "forall [] (v: string, t: string, i: int) -> string",
-                "joinStr" => "forall [] (a: [string], v: string) -> {}",
+                "repeat" => "forall [] (v: string, i: int) -> string",
+                "replace" => "forall [] (v: string, t: string, u: string, i: int) -> string",
+                "replaceAll" => "forall [] (v: string, t: string, u: string) -> string",
+                "split" => "forall [] (v: string, t: string) -> [string]",
+                "splitAfter" => "forall [] (v: string, t: string) -> [string]",
+                "splitN" => "forall [] (v: string, t: string, i: int) -> [string]",
+                "splitAfterN" => "forall [] (v: string, t: string, i: int) -> [string]",
+                "joinStr" => "forall [] (arr: [string], v: string) -> string",
                 "strlen" => "forall [] (v: string) -> int",
                 "substring" => "forall [] (v: string, start: int, end: int) -> string",
             },
@@ -360,7 +363,7 @@ pub fn builtins() -> Builtins<'static> {
                 "columns" => r#"
                     forall [t0, t1] where t0: Row, t1: Row (
                         <-tables: [t0],
-                        column: string
+                        ?column: string
                     ) -> [t1]
                 "#,
                 "contains" => r#"
@@ -442,12 +445,13 @@ pub fn builtins() -> Builtins<'static> {
                     ) -> [{ _value: t0 | t1}]
                 "#,
                 "false" => "forall [] bool",
+                // Exactly one of value or usePrevious should be specified.
                 "fill" => r#"
                     forall [t0, t1, t2] where t0: Row, t2: Row (
                         <-tables: [t0],
                         ?column: string,
-                        value: t1,
-                        usePrevious: bool
+                        ?value: t1,
+                        ?usePrevious: bool
                     ) -> [t2]
                 "#,
                 "filter" => r#"
@@ -490,7 +494,7 @@ pub fn builtins() -> Builtins<'static> {
                         ?upperBoundColumn: string,
                         ?countColumn: string,
                         bins: [float],
-                        normalize: bool
+                        ?normalize: bool
                     ) -> [t1]
                 "#,
                 "histogramQuantile" => r#"
@@ -624,12 +628,11 @@ pub fn builtins() -> Builtins<'static> {
                         ?method: string
                     ) -> [t0]
                 "#,
-                // start and stop should be able to constrained to time or duration with a kind constraint:
-                //   https://github.com/influxdata/flux/issues/2243
-                // Also, we should remove the column arguments so we can reuse t0 in the return type:
+                // start and stop are absolute times or durations relative to now.
+                // The columns it adds are related to t0 by its column change, see column_changes:
                 //   https://github.com/influxdata/flux/issues/2253
                 "range" => r#"
-                    forall [t0, t1, t2, t3] where t0: Row, t3: Row (
+                    forall [t0, t1, t2, t3] where t0: Row, t1: Timeable, t2: Timeable, t3: Row (
                         <-tables: [t0],
                         start: t1,
                         ?stop: t2,
@@ -638,8 +641,8 @@ pub fn builtins() -> Builtins<'static> {
                         ?stopColumn: string
                     ) -> [t3]
                 "#,
-                // This function could be updated to get better type inference:
-                //   https://github.com/influxdata/flux/issues/2254
+                // The output is the group key merged with the accumulator, which is
+                // related to t0 by its column change, see column_changes.
                 "reduce" => r#"
                     forall [t0, t1, t2] where t0: Row, t1: Row, t2: Row (
                         <-tables: [t0],
@@ -718,7 +721,7 @@ pub fn builtins() -> Builtins<'static> {
                     forall [t0, t1] where t0: Row, t1: Row (
                         <-tables: [t0],
                         ?column: string,
-                        mode: string
+                        ?mode: string
                     ) -> [t1]
                 "#,
                 "string" => "forall [t0] (v: t0) -> string",
@@ -795,9 +798,95 @@ pub fn builtins() -> Builtins<'static> {
     }
 }
 
+/// ColumnChange describes how a builtin changes the columns of the tables
+/// piped into it, naming the parameters that label those columns.
+///
+/// The signature of such a builtin returns rows unrelated to the rows it is
+/// given. When the labels are literals, inference relates them using the
+/// change instead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnChange {
+    /// Removes the columns listed by the parameter.
+    Remove(&'static str),
+    /// Keeps only the columns listed by the parameter.
+    Keep(&'static str),
+    /// Renames columns with the record of old to new labels in the parameter.
+    Rename(&'static str),
+    /// Copies the column labeled by the first parameter to the label given
+    /// by the second.
+    Copy(&'static str, &'static str),
+    /// Adds time columns labeled by the parameters, or by the default
+    /// labels paired with them.
+    AddTime(&'static [(&'static str, &'static str)]),
+    /// Keeps the columns listed by the first parameter, removes those listed
+    /// by the second and the one labeled by the third, and adds unknown ones.
+    Pivot(&'static str, &'static str, &'static str),
+    /// Merges the record given by the parameter over the columns. Unlike
+    /// the others, the parameter is a value rather than labels.
+    Merge(&'static str),
+    /// Unions the streams of tables listed by the parameter. Only the
+    /// columns they all have are known of the result.
+    Union(&'static str),
+}
+
+impl ColumnChange {
+    /// Whether the parameter labels columns.
+    pub fn has_param(&self, name: &str) -> bool {
+        match *self {
+            ColumnChange::Remove(p) | ColumnChange::Keep(p) | ColumnChange::Rename(p) => p == name,
+            ColumnChange::Copy(a, b) => a == name || b == name,
+            ColumnChange::AddTime(params) => params.iter().any(|(p, _)| *p == name),
+            ColumnChange::Pivot(a, b, c) => a == name || b == name || c == name,
+            ColumnChange::Merge(_) | ColumnChange::Union(_) => false,
+        }
+    }
+
+    /// Whether the parameter lists the streams of tables that are changed.
+    pub fn has_streams(&self, name: &str) -> bool {
+        match *self {
+            ColumnChange::Union(p) => p == name,
+            _ => false,
+        }
+    }
+}
+
+// The column change of the universe builtin `name`, if it changes columns.
+fn universe_column_change(name: &str) -> Option<ColumnChange> {
+    match name {
+        "drop" => Some(ColumnChange::Remove("columns")),
+        "duplicate" => Some(ColumnChange::Copy("column", "as")),
+        "keep" => Some(ColumnChange::Keep("columns")),
+        "pivot" => Some(ColumnChange::Pivot("rowKey", "columnKey", "valueColumn")),
+        "range" => Some(ColumnChange::AddTime(&[
+            ("startColumn", "_start"),
+            ("stopColumn", "_stop"),
+        ])),
+        "reduce" => Some(ColumnChange::Merge("identity")),
+        "rename" => Some(ColumnChange::Rename("columns")),
+        "union" => Some(ColumnChange::Union("tables")),
+        _ => None,
+    }
+}
+
+// The type of a universe builtin.
+pub(crate) fn universe(name: &str) -> Option<&'static str> {
+    builtins().pkgs.get("universe")?.get(name).copied()
+}
+
+/// Returns how the universe function bound to `name` changes columns, if it
+/// does. The function must have the type of the builtin, so that a value
+/// shadowing the builtin is not mistaken for it.
+pub fn column_change(name: &str, ty: &PolyType) -> Option<ColumnChange> {
+    let change = universe_column_change(name)?;
+    match parse(universe(name)?) {
+        Ok(builtin) if &builtin == ty => Some(change),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::semantic::builtins::builtins;
+    use crate::semantic::builtins::{builtins, universe, universe_column_change};
     use crate::semantic::parser as type_parser;
 
     #[test]
@@ -814,4 +903,29 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn column_change_signatures() {
+        // Every column changing builtin must have a signature that parses.
+        for name in &[
+            "drop",
+            "duplicate",
+            "keep",
+            "pivot",
+            "range",
+            "reduce",
+            "rename",
+        ] {
+            assert!(
+                universe_column_change(name).is_some(),
+                "{} has no change",
+                name
+            );
+            assert!(
+                type_parser::parse(universe(name).unwrap()).is_ok(),
+                "{} has no signature",
+                name
+            );
+        }
+    }
 }
@@ -2,6 +2,7 @@ use crate::ast;
 use crate::semantic::fresh::Fresher;
 use crate::semantic::nodes::*;
 use crate::semantic::types::MonoType;
+use std::collections::HashSet;
 use std::result;
 
 pub type SemanticError = String;
@@ -87,7 +88,7 @@ fn convert_import_declaration(
     })
 }
 
-fn convert_statement(stmt: ast::Statement, fresher: &mut Fresher) -> Result<Statement> {
+pub fn convert_statement(stmt: ast::Statement, fresher: &mut Fresher) -> Result<Statement> {
     match stmt {
         ast::Statement::Option(s) => Ok(Statement::Option(Box::new(convert_option_statement(
             *s, fresher,
@@ -225,6 +226,7 @@ fn convert_function_params(
     props: Vec<ast::Property>,
     fresher: &mut Fresher,
 ) -> Result<Vec<FunctionParameter>> {
+    unique(&props, "parameter")?;
     // The iteration here is complex, cannot use iter().map()..., better to write it explicitly.
     let mut params: Vec<FunctionParameter> = Vec::new();
     let mut piped = false;
@@ -308,7 +310,10 @@ fn convert_call_expression(expr: ast::CallExpr, fresher: &mut Fresher) -> Result
         .arguments
         .into_iter()
         .map(|a| match a {
-            ast::Expression::Object(obj) => convert_object_expression(*obj, fresher),
+            ast::Expression::Object(obj) => {
+                unique(&obj.properties, "argument")?;
+                convert_object_expression(*obj, fresher)
+            }
             _ => Err("arguments not an object expression".to_string()),
         })
         .collect::<Result<Vec<ObjectExpr>>>()?;
@@ -328,10 +333,7 @@ fn convert_call_expression(expr: ast::CallExpr, fresher: &mut Fresher) -> Result
 
 fn convert_member_expression(expr: ast::MemberExpr, fresher: &mut Fresher) -> Result<MemberExpr> {
     let object = convert_expression(expr.object, fresher)?;
-    let property = match expr.property {
-        ast::PropertyKey::Identifier(id) => id.name,
-        ast::PropertyKey::StringLit(lit) => lit.value,
-    };
+    let property = expr.property.name().to_string();
     Ok(MemberExpr {
         loc: expr.base.location,
         typ: MonoType::Var(fresher.fresh()),
@@ -412,6 +414,7 @@ fn convert_conditional_expression(
 }
 
 fn convert_object_expression(expr: ast::ObjectExpr, fresher: &mut Fresher) -> Result<ObjectExpr> {
+    unique(&expr.properties, "record key")?;
     let properties = expr
         .properties
         .into_iter()
@@ -429,6 +432,16 @@ fn convert_object_expression(expr: ast::ObjectExpr, fresher: &mut Fresher) -> Re
     })
 }
 
+// Duplicate keys are reported by ast::check. They are refused here as well
+// so that no later stage has to decide which of them wins.
+fn unique(props: &[ast::Property], what: &str) -> Result<()> {
+    let mut seen = HashSet::new();
+    match props.iter().find(|p| !seen.insert(p.key.name())) {
+        Some(p) => Err(format!("duplicate {} {}", what, p.key.name())),
+        None => Ok(()),
+    }
+}
+
 fn convert_property(prop: ast::Property, fresher: &mut Fresher) -> Result<Property> {
     let key = match prop.key {
         ast::PropertyKey::Identifier(id) => convert_identifier(id, fresher)?,
@@ -1871,6 +1884,19 @@ mod tests {
         assert_eq!("only a single argument may be piped".to_string(), got);
     }
 
+    #[test]
+    fn test_convert_duplicate_keys() {
+        for (src, want) in &[
+            ("x = {a: 1, b: 2, a: 3}", "duplicate record key a"),
+            ("x = {r with a: 1, \"a\": 2}", "duplicate record key a"),
+            ("f = (a, b, a=<-) => a", "duplicate parameter a"),
+            ("x = f(x: 1, x: 2)", "duplicate argument x"),
+        ] {
+            let pkg = crate::parser::parse_string("", src).into();
+            assert_eq!(test_convert(pkg).unwrap_err(), *want, "{}", src);
+        }
+    }
+
     #[test]
     fn test_convert_call_multiple_object_arguments() {
         let b = ast::BaseNode::default();
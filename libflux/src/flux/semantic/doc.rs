@@ -0,0 +1,317 @@
+// Reference documentation of a package, made from its source and types.
+//
+// A binding is documented by the comment block directly above it, if the
+// block starts with the name of the binding:
+//
+//     // now is a function option whose default behaviour is to return
+//     // the current system time
+//     option now = system.time
+//
+// A comment at the end of the line a binding starts on documents it in the
+// same way, which suits a run of builtins:
+//
+//     builtin sleep // sleep is the identity function with the side effect ...
+//
+// A block that does not start with the name, such as a heading over a run
+// of builtins, documents nothing. The package itself is documented by the
+// comment block directly above its package clause.
+
+use crate::ast;
+use crate::parser::Parser;
+use crate::semantic::printer::Printer;
+use crate::semantic::types::PolyType;
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PackageDoc {
+    pub path: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+    pub members: Vec<MemberDoc>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct MemberDoc {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+    // The normalized polytype of the member, as printed by the Printer.
+    #[serde(rename = "type")]
+    pub typ: String,
+}
+
+// Document the package at an import path from the source of its file and
+// the types inferred for it. The members are the bindings of the file that
+// have a type, sorted by name.
+pub fn package_doc(path: &str, src: &str, types: &HashMap<String, PolyType>) -> PackageDoc {
+    let mut p = Parser::with_comments(src);
+    let file = p.parse_file(String::new());
+    // A comment that follows a statement on its first line is kept apart
+    // from the blocks, so that it does not join a block below it.
+    let starts: HashMap<u32, u32> = file
+        .body
+        .iter()
+        .map(|stmt| {
+            let start = &stmt.base().location.start;
+            (start.line, start.column)
+        })
+        .collect();
+    let (trailing, comments): (Vec<ast::Comment>, Vec<ast::Comment>) =
+        p.take_comments().into_iter().partition(|c| {
+            let start = &c.location.start;
+            starts
+                .get(&start.line)
+                .filter(|column| start.column > **column)
+                .is_some()
+        });
+    let trailing = comment_blocks(trailing);
+    let blocks = comment_blocks(comments);
+
+    let mut bound = BTreeSet::new();
+    let mut docs = HashMap::new();
+    for stmt in &file.body {
+        let (name, base) = match stmt {
+            ast::Statement::Builtin(s) => (&s.id.name, &s.base),
+            ast::Statement::Variable(s) => (&s.id.name, &s.base),
+            ast::Statement::Option(s) => match &s.assignment {
+                ast::Assignment::Variable(v) => (&v.id.name, &s.base),
+                ast::Assignment::Member(_) => continue,
+            },
+            _ => continue,
+        };
+        if !types.contains_key(name) {
+            continue;
+        }
+        bound.insert(name.clone());
+        let line = base.location.start.line;
+        let doc = block_above(&blocks, line)
+            .into_iter()
+            .chain(trailing.get(&line))
+            .find(|doc| starts_with_name(doc, name));
+        if let Some(doc) = doc {
+            docs.insert(name.clone(), doc.clone());
+        }
+    }
+
+    let doc = file
+        .package
+        .as_ref()
+        .and_then(|pkg| block_above(&blocks, pkg.base.location.start.line))
+        .cloned();
+    let members = bound
+        .into_iter()
+        .map(|name| {
+            let ty = &types[&name];
+            MemberDoc {
+                doc: docs.remove(&name),
+                typ: Printer::default().polytype(ty),
+                name,
+            }
+        })
+        .collect();
+    PackageDoc {
+        path: path.to_string(),
+        name: file.package.map(|pkg| pkg.name.name).unwrap_or_default(),
+        doc,
+        members,
+    }
+}
+
+// Group comments on consecutive lines into blocks of text, keyed by the
+// line each block ends on.
+fn comment_blocks(comments: Vec<ast::Comment>) -> HashMap<u32, String> {
+    let mut blocks = HashMap::new();
+    let mut block: Vec<String> = Vec::new();
+    let mut last = 0;
+    for c in comments {
+        let line = c.location.start.line;
+        if !block.is_empty() && line != last + 1 {
+            blocks.insert(last, block.join("\n"));
+            block.clear();
+        }
+        let text = c.text.trim_start_matches("//");
+        block.push(
+            text.strip_prefix(' ')
+                .unwrap_or(text)
+                .trim_end()
+                .to_string(),
+        );
+        last = line;
+    }
+    if !block.is_empty() {
+        blocks.insert(last, block.join("\n"));
+    }
+    blocks
+}
+
+fn block_above(blocks: &HashMap<u32, String>, line: u32) -> Option<&String> {
+    if line < 2 {
+        return None;
+    }
+    blocks.get(&(line - 1))
+}
+
+// Whether documentation starts with a name, ignoring case and any quoting
+// of the name in backticks.
+fn starts_with_name(doc: &str, name: &str) -> bool {
+    let word = doc
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '`'))
+        .next()
+        .unwrap_or_default()
+        .trim_matches('`');
+    word.eq_ignore_ascii_case(name)
+}
+
+impl PackageDoc {
+    pub fn markdown(&self) -> String {
+        let mut md = format!("# Package `{}`\n\n", self.name);
+        md.push_str(&format!("```flux\nimport \"{}\"\n```\n", self.path));
+        if let Some(doc) = &self.doc {
+            md.push_str(&format!("\n{}\n", doc));
+        }
+        for m in &self.members {
+            md.push_str(&format!(
+                "\n## {}.{}\n\n```\n{}\n```\n",
+                self.name, m.name, m.typ
+            ));
+            if let Some(doc) = &m.doc {
+                md.push_str(&format!("\n{}\n", doc));
+            }
+        }
+        md
+    }
+
+    pub fn json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::parser::parse;
+
+    const SRC: &str = r#"// Package p does things.
+// Two lines of things.
+package p
+
+import "strings"
+
+// Transformation functions
+builtin upper
+builtin title // title capitalizes each word.
+// not a comment on upper or title
+
+// `lower` lowers a string.
+builtin lower
+
+// Count counts the rows of
+// the tables.
+count = (tables=<-) => tables
+
+// not a comment on x
+
+x = 1
+
+// limit is the number of rows.
+option limit = 10
+
+// option to change the host of another package
+option strings.host = "localhost"
+"#;
+
+    fn types() -> HashMap<String, PolyType> {
+        maplit::hashmap! {
+            String::from("upper") => parse("forall [] (v: string) -> string").unwrap(),
+            String::from("lower") => parse("forall [] (v: string) -> string").unwrap(),
+            String::from("title") => parse("forall [] (v: string) -> string").unwrap(),
+            String::from("count") => parse("forall [t3] (<-tables: t3) -> t3").unwrap(),
+            String::from("x") => parse("forall [] int").unwrap(),
+            String::from("limit") => parse("forall [] int").unwrap(),
+        }
+    }
+
+    #[test]
+    fn documents_members() {
+        let got = package_doc("a/p", SRC, &types());
+        let want = PackageDoc {
+            path: "a/p".to_string(),
+            name: "p".to_string(),
+            doc: Some("Package p does things.\nTwo lines of things.".to_string()),
+            members: vec![
+                MemberDoc {
+                    name: "count".to_string(),
+                    doc: Some("Count counts the rows of\nthe tables.".to_string()),
+                    typ: "forall [A] (<-tables: A) -> A".to_string(),
+                },
+                MemberDoc {
+                    name: "limit".to_string(),
+                    doc: Some("limit is the number of rows.".to_string()),
+                    typ: "forall [] int".to_string(),
+                },
+                MemberDoc {
+                    name: "lower".to_string(),
+                    doc: Some("`lower` lowers a string.".to_string()),
+                    typ: "forall [] (v: string) -> string".to_string(),
+                },
+                MemberDoc {
+                    name: "title".to_string(),
+                    doc: Some("title capitalizes each word.".to_string()),
+                    typ: "forall [] (v: string) -> string".to_string(),
+                },
+                MemberDoc {
+                    name: "upper".to_string(),
+                    doc: None,
+                    typ: "forall [] (v: string) -> string".to_string(),
+                },
+                MemberDoc {
+                    name: "x".to_string(),
+                    doc: None,
+                    typ: "forall [] int".to_string(),
+                },
+            ],
+        };
+        assert_eq!(want, got);
+    }
+
+    #[test]
+    fn renders_markdown_and_json() {
+        let src = "package p\n\n// f is the identity.\nf = (v) => v\n";
+        let types = maplit::hashmap! {
+            String::from("f") => parse("forall [t0] (v: t0) -> t0").unwrap(),
+        };
+        let doc = package_doc("p", src, &types);
+        assert_eq!(
+            doc.markdown(),
+            r#"# Package `p`
+
+```flux
+import "p"
+```
+
+## p.f
+
+```
+forall [A] (v: A) -> A
+```
+
+f is the identity.
+"#
+        );
+        assert_eq!(
+            doc.json().unwrap(),
+            r#"{
+  "path": "p",
+  "name": "p",
+  "members": [
+    {
+      "name": "f",
+      "doc": "f is the identity.",
+      "type": "forall [A] (v: A) -> A"
+    }
+  ]
+}"#
+        );
+    }
+}
@@ -101,13 +101,10 @@ impl Environment {
     // that point the type of the function body is inferred and the last frame
     // is popped from the stack and returned to the calling function.
     //
-    // It is invalid to call pop on a type environment with only one stack
-    // frame. This will result in a panic.
+    // A type environment with only one stack frame has nothing to pop, so
+    // pop returns None for it.
     //
-    pub fn pop(self) -> Environment {
-        match self.parent {
-            Some(env) => *env,
-            None => panic!("cannot pop final stack frame from type environment"),
-        }
+    pub fn pop(self) -> Option<Environment> {
+        self.parent.map(|env| *env)
     }
 }
@@ -512,11 +512,11 @@ impl<'a> semantic::walk::Visitor<'_> for SerializingVisitor<'a> {
 
                 let mut block_len = 0;
                 let mut current = &func.body;
-                let body_start_pos = &current.loc().start;
-                let mut body_end_pos = &current.loc().end;
+                let body_start = current.loc();
+                let mut body_end = current.loc();
                 loop {
                     block_len += 1;
-                    body_end_pos = &current.loc().end;
+                    body_end = current.loc();
                     match current {
                         semantic::nodes::Block::Expr(_, next) => {
                             current = next.as_ref();
@@ -531,8 +531,12 @@ impl<'a> semantic::walk::Visitor<'_> for SerializingVisitor<'a> {
                 }
                 let body_loc = ast::SourceLocation {
                     file: func.loc.file.clone(),
-                    start: body_start_pos.clone(),
-                    end: body_end_pos.clone(),
+                    start: body_start.start.clone(),
+                    end: body_end.end.clone(),
+                    span: ast::Span {
+                        start: body_start.span.start,
+                        end: body_end.span.end,
+                    },
                     source: None,
                 };
                 let body_loc = v.create_loc(&body_loc);
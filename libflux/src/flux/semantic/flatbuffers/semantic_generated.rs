@@ -165,10 +165,13 @@ pub mod fbsemantic {
         Nullable = 6,
         Row = 7,
         Negatable = 8,
+        Timeable = 9,
+        Stringable = 10,
+        Lacks = 11,
     }
 
     const ENUM_MIN_KIND: u8 = 0;
-    const ENUM_MAX_KIND: u8 = 8;
+    const ENUM_MAX_KIND: u8 = 11;
 
     impl<'a> flatbuffers::Follow<'a> for Kind {
         type Inner = Self;
@@ -202,7 +205,7 @@ pub mod fbsemantic {
     }
 
     #[allow(non_camel_case_types)]
-    const ENUM_VALUES_KIND: [Kind; 9] = [
+    const ENUM_VALUES_KIND: [Kind; 12] = [
         Kind::Addable,
         Kind::Subtractable,
         Kind::Divisible,
@@ -212,10 +215,13 @@ pub mod fbsemantic {
         Kind::Nullable,
         Kind::Row,
         Kind::Negatable,
+        Kind::Timeable,
+        Kind::Stringable,
+        Kind::Lacks,
     ];
 
     #[allow(non_camel_case_types)]
-    const ENUM_NAMES_KIND: [&'static str; 9] = [
+    const ENUM_NAMES_KIND: [&'static str; 12] = [
         "Addable",
         "Subtractable",
         "Divisible",
@@ -225,6 +231,9 @@ pub mod fbsemantic {
         "Nullable",
         "Row",
         "Negatable",
+        "Timeable",
+        "Stringable",
+        "Lacks",
     ];
 
     pub fn enum_name_kind(e: Kind) -> &'static str {
@@ -2119,6 +2128,9 @@ pub mod fbsemantic {
             args: &'args ConstraintArgs<'args>,
         ) -> flatbuffers::WIPOffset<Constraint<'bldr>> {
             let mut builder = ConstraintBuilder::new(_fbb);
+            if let Some(x) = args.label {
+                builder.add_label(x);
+            }
             if let Some(x) = args.tvar {
                 builder.add_tvar(x);
             }
@@ -2128,6 +2140,7 @@ pub mod fbsemantic {
 
         pub const VT_TVAR: flatbuffers::VOffsetT = 4;
         pub const VT_KIND: flatbuffers::VOffsetT = 6;
+        pub const VT_LABEL: flatbuffers::VOffsetT = 8;
 
         #[inline]
         pub fn tvar(&self) -> Option<Var<'a>> {
@@ -2140,11 +2153,17 @@ pub mod fbsemantic {
                 .get::<Kind>(Constraint::VT_KIND, Some(Kind::Addable))
                 .unwrap()
         }
+        #[inline]
+        pub fn label(&self) -> Option<&'a str> {
+            self._tab
+                .get::<flatbuffers::ForwardsUOffset<&str>>(Constraint::VT_LABEL, None)
+        }
     }
 
     pub struct ConstraintArgs<'a> {
         pub tvar: Option<flatbuffers::WIPOffset<Var<'a>>>,
         pub kind: Kind,
+        pub label: Option<flatbuffers::WIPOffset<&'a str>>,
     }
     impl<'a> Default for ConstraintArgs<'a> {
         #[inline]
@@ -2152,6 +2171,7 @@ pub mod fbsemantic {
             ConstraintArgs {
                 tvar: None,
                 kind: Kind::Addable,
+                label: None,
             }
         }
     }
@@ -2171,6 +2191,11 @@ pub mod fbsemantic {
                 .push_slot::<Kind>(Constraint::VT_KIND, kind, Kind::Addable);
         }
         #[inline]
+        pub fn add_label(&mut self, label: flatbuffers::WIPOffset<&'b str>) {
+            self.fbb_
+                .push_slot_always::<flatbuffers::WIPOffset<_>>(Constraint::VT_LABEL, label);
+        }
+        #[inline]
         pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> ConstraintBuilder<'a, 'b> {
             let start = _fbb.start_table();
             ConstraintBuilder {
@@ -72,13 +72,8 @@ impl From<fb::PolyType<'_>> for Option<PolyType> {
 
 impl From<fb::Constraint<'_>> for Option<(Tvar, Kind)> {
     fn from(c: fb::Constraint) -> Option<(Tvar, Kind)> {
-        Some((c.tvar()?.into(), c.kind().into()))
-    }
-}
-
-impl From<fb::Kind> for Kind {
-    fn from(kind: fb::Kind) -> Kind {
-        match kind {
+        let kind = match c.kind() {
+            fb::Kind::Lacks => Kind::Lacks(c.label()?.to_owned()),
             fb::Kind::Addable => Kind::Addable,
             fb::Kind::Subtractable => Kind::Subtractable,
             fb::Kind::Divisible => Kind::Divisible,
@@ -88,12 +83,15 @@ impl From<fb::Kind> for Kind {
             fb::Kind::Nullable => Kind::Nullable,
             fb::Kind::Row => Kind::Row,
             fb::Kind::Negatable => Kind::Negatable,
-        }
+            fb::Kind::Timeable => Kind::Timeable,
+            fb::Kind::Stringable => Kind::Stringable,
+        };
+        Some((c.tvar()?.into(), kind))
     }
 }
 
-impl From<Kind> for fb::Kind {
-    fn from(kind: Kind) -> fb::Kind {
+impl From<&Kind> for fb::Kind {
+    fn from(kind: &Kind) -> fb::Kind {
         match kind {
             Kind::Addable => fb::Kind::Addable,
             Kind::Subtractable => fb::Kind::Subtractable,
@@ -104,6 +102,9 @@ impl From<Kind> for fb::Kind {
             Kind::Nullable => fb::Kind::Nullable,
             Kind::Row => fb::Kind::Row,
             Kind::Negatable => fb::Kind::Negatable,
+            Kind::Timeable => fb::Kind::Timeable,
+            Kind::Stringable => fb::Kind::Stringable,
+            Kind::Lacks(_) => fb::Kind::Lacks,
         }
     }
 }
@@ -334,11 +335,16 @@ fn build_constraint<'a>(
     constraint: (Tvar, Kind),
 ) -> flatbuffers::WIPOffset<fb::Constraint<'a>> {
     let tvar = build_var(builder, constraint.0);
+    let label = match &constraint.1 {
+        Kind::Lacks(label) => Some(builder.create_string(label)),
+        _ => None,
+    };
     fb::Constraint::create(
         builder,
         &fb::ConstraintArgs {
             tvar: Some(tvar),
-            kind: constraint.1.into(),
+            kind: (&constraint.1).into(),
+            label,
         },
     )
 }
@@ -371,7 +377,8 @@ pub fn build_type<'a>(
             let v = fb::Basic::create(builder, &a);
             (v.as_union_value(), fb::MonoType::Basic)
         }
-        MonoType::String => {
+        // Labels are encoded as the strings they are.
+        MonoType::String | MonoType::Label(_) => {
             let a = fb::BasicArgs {
                 t: fb::Type::String,
             };
@@ -619,6 +626,9 @@ mod tests {
     fn serde_function_types() {
         test_serde("forall [t0] (<-tables: [t0], ?flag: bool, fn: (r: t0) -> bool) -> [t0]");
         test_serde("forall [t0, t1] where t0: Addable, t1: Divisible (a: t0, b: t1) -> bool");
+        test_serde(
+            "forall [t0, t1] where t1: Lacks(a), t1: Lacks(b) (<-tables: [{a: t0 | t1}]) -> [t1]",
+        );
     }
     #[test]
     fn serde_record_types() {
@@ -1,14 +1,24 @@
+use crate::limits::{self, Limit, Limits};
 use crate::semantic::types::{Array, Function, MonoType, PolyType, Property, Row, Tvar};
 use std::collections::{BTreeMap, HashMap};
 use std::hash::Hash;
 
 // Fresher returns incrementing type variables
-pub struct Fresher(pub u64);
+pub struct Fresher(pub u64, Option<Budget>);
+
+// Budget bounds the type variables a fresher makes, and the unification
+// steps taken with it, from the point its limits were set.
+struct Budget {
+    limits: Limits,
+    start: u64,
+    steps: u64,
+    exceeded: Option<limits::Error>,
+}
 
 // Create a tvar fresher from a u64
 impl From<u64> for Fresher {
     fn from(u: u64) -> Fresher {
-        Fresher(u)
+        Fresher(u, None)
     }
 }
 
@@ -18,11 +28,50 @@ impl Fresher {
         self.0 += 1;
         Tvar(u)
     }
+
+    // limit bounds the type variables and inference steps used from now on.
+    pub fn limit(&mut self, limits: Limits) {
+        self.1 = Some(Budget {
+            limits,
+            start: self.0,
+            steps: 0,
+            exceeded: None,
+        });
+    }
+
+    // step counts one unification step, failing once the fresher has gone
+    // over its budget.
+    pub fn step(&mut self) -> Result<(), limits::Error> {
+        let vars = self.0;
+        match &mut self.1 {
+            Some(budget) => {
+                if let Some(err) = budget.exceeded {
+                    return Err(err);
+                }
+                budget.steps += 1;
+                let checked = Limit::TypeVars
+                    .check(budget.limits.max_type_vars, vars - budget.start)
+                    .and_then(|_| {
+                        Limit::InferenceSteps.check(budget.limits.max_inference_steps, budget.steps)
+                    });
+                if let Err(err) = checked {
+                    budget.exceeded = Some(err);
+                }
+                checked
+            }
+            None => Ok(()),
+        }
+    }
+
+    // exceeded returns the limit that inference has gone over, if any.
+    pub fn exceeded(&self) -> Option<&limits::Error> {
+        self.1.as_ref().and_then(|budget| budget.exceeded.as_ref())
+    }
 }
 
 impl Default for Fresher {
     fn default() -> Self {
-        Self(0)
+        Self(0, None)
     }
 }
 
@@ -0,0 +1,391 @@
+//! Classification of the identifiers of an analyzed file by their meaning,
+//! in the style of LSP semantic tokens.
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::ast::Span;
+use crate::semantic::nodes::*;
+use crate::semantic::types::MonoType;
+use crate::semantic::walk::{self, Node, Visitor};
+
+/// TokenKind is what an identifier names.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TokenKind {
+    Namespace,
+    Function,
+    Variable,
+    Parameter,
+    Property,
+}
+
+/// TokenModifier qualifies the kind of an identifier.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TokenModifier {
+    /// The identifier binds a name, rather than referring to one.
+    Declaration,
+    /// The identifier names an option.
+    Readonly,
+    /// The identifier names a builtin, a member of an imported package, or
+    /// a value of the prelude.
+    DefaultLibrary,
+}
+
+/// SemanticToken is a classified range of the source of a file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SemanticToken {
+    pub span: Span,
+    pub kind: TokenKind,
+    pub modifiers: Vec<TokenModifier>,
+}
+
+/// Classifies the identifiers of an analyzed file, given the source it was
+/// parsed from. The tokens are returned in source order.
+pub fn semantic_tokens(file: &File, src: &str) -> Vec<SemanticToken> {
+    let mut c = Classifier {
+        src,
+        scopes: vec![HashMap::new()],
+        pending: vec![],
+        readonly: false,
+        tokens: vec![],
+    };
+    walk::walk(&mut c, Rc::new(Node::File(file)));
+    c.tokens.sort_by_key(|t| t.span.start);
+    c.tokens
+}
+
+// Binding is what a name in scope refers to.
+#[derive(Debug, Clone)]
+struct Binding {
+    kind: TokenKind,
+    modifiers: Vec<TokenModifier>,
+    // builtin is the index of the token that declares a builtin. Builtin
+    // statements carry no type, so the kind of a builtin is learned from the
+    // first reference to it.
+    builtin: Option<usize>,
+}
+
+impl Binding {
+    fn new(kind: TokenKind, modifiers: Vec<TokenModifier>) -> Binding {
+        Binding {
+            kind,
+            modifiers,
+            builtin: None,
+        }
+    }
+}
+
+struct Classifier<'s> {
+    src: &'s str,
+    scopes: Vec<HashMap<String, Binding>>,
+    // pending holds the bindings of the assignments being visited. A name is
+    // only in scope once its assignment is done.
+    pending: Vec<(String, Binding)>,
+    // readonly is set by an option statement for its assignment.
+    readonly: bool,
+    tokens: Vec<SemanticToken>,
+}
+
+fn kind_of(typ: &MonoType) -> TokenKind {
+    match typ {
+        MonoType::Fun(_) => TokenKind::Function,
+        _ => TokenKind::Variable,
+    }
+}
+
+impl<'s> Classifier<'s> {
+    fn push(&mut self, span: Span, kind: TokenKind, modifiers: Vec<TokenModifier>) -> usize {
+        self.tokens.push(SemanticToken {
+            span,
+            kind,
+            modifiers,
+        });
+        self.tokens.len() - 1
+    }
+
+    // declare adds a declaration token for an identifier and returns its
+    // index.
+    fn declare(
+        &mut self,
+        id: &Identifier,
+        kind: TokenKind,
+        modifiers: &[TokenModifier],
+    ) -> Option<usize> {
+        if !id.loc.is_valid() {
+            return None;
+        }
+        let mut decl = vec![TokenModifier::Declaration];
+        decl.extend_from_slice(modifiers);
+        Some(self.push(id.loc.span, kind, decl))
+    }
+
+    fn bind(&mut self, name: &str, binding: Binding) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), binding);
+        }
+    }
+
+    fn lookup(&mut self, name: &str) -> Option<&mut Binding> {
+        self.scopes.iter_mut().rev().find_map(|s| s.get_mut(name))
+    }
+
+    fn reference(&mut self, e: &IdentifierExpr) {
+        if !e.loc.is_valid() {
+            return;
+        }
+        let kind = kind_of(&e.typ);
+        let (kind, modifiers, builtin) = match self.lookup(&e.name) {
+            Some(b) => {
+                let builtin = b.builtin.take();
+                if builtin.is_some() {
+                    b.kind = kind;
+                }
+                (b.kind, b.modifiers.clone(), builtin)
+            }
+            None => (kind, vec![TokenModifier::DefaultLibrary], None),
+        };
+        if let Some(i) = builtin {
+            self.tokens[i].kind = kind;
+        }
+        self.push(e.loc.span, kind, modifiers);
+    }
+
+    // property_span finds the property of a member expression in the
+    // source. Properties written as string literals, r["a"], are left to
+    // lexical highlighting.
+    fn property_span(&self, m: &MemberExpr) -> Option<Span> {
+        let end = m.loc.span.end;
+        let start = end.checked_sub(m.property.len() as u32)?;
+        let before = self.src.get(m.loc.span.start as usize..start as usize)?;
+        let text = self.src.get(start as usize..end as usize)?;
+        if text == m.property && before.trim_end().ends_with('.') {
+            Some(Span { start, end })
+        } else {
+            None
+        }
+    }
+
+    fn member(&mut self, m: &MemberExpr) {
+        let readonly = std::mem::replace(&mut self.readonly, false);
+        let span = match self.property_span(m) {
+            Some(span) => span,
+            None => return,
+        };
+        let package = match &m.object {
+            Expression::Identifier(e) => match self.lookup(&e.name) {
+                Some(b) => b.kind == TokenKind::Namespace,
+                None => false,
+            },
+            _ => false,
+        };
+        if package {
+            let mut modifiers = vec![TokenModifier::DefaultLibrary];
+            if readonly {
+                modifiers.push(TokenModifier::Readonly);
+            }
+            self.push(span, kind_of(&m.typ), modifiers);
+        } else {
+            self.push(span, TokenKind::Property, vec![]);
+        }
+    }
+
+    fn keys(&mut self, props: &[Property], kind: TokenKind) {
+        for p in props {
+            // The key of a shorthand property, {a}, is also its value.
+            if p.key.loc.is_valid() && p.key.loc != *p.value.loc() {
+                self.push(p.key.loc.span, kind, vec![]);
+            }
+        }
+    }
+}
+
+impl<'a, 's> Visitor<'a> for Classifier<'s> {
+    fn visit(&mut self, node: Rc<Node<'a>>) -> bool {
+        match *node {
+            Node::PackageClause(n) => {
+                self.declare(&n.name, TokenKind::Namespace, &[]);
+            }
+            Node::ImportDeclaration(n) => {
+                let name = match &n.alias {
+                    Some(id) => {
+                        self.declare(id, TokenKind::Namespace, &[]);
+                        id.name.clone()
+                    }
+                    None => n.path.value.rsplit('/').next().unwrap_or("").to_string(),
+                };
+                self.bind(&name, Binding::new(TokenKind::Namespace, vec![]));
+            }
+            Node::OptionStmt(_) => self.readonly = true,
+            Node::BuiltinStmt(n) => {
+                let modifiers = vec![TokenModifier::DefaultLibrary];
+                let builtin = self.declare(&n.id, TokenKind::Variable, &modifiers);
+                let binding = Binding {
+                    kind: TokenKind::Variable,
+                    modifiers,
+                    builtin,
+                };
+                self.bind(&n.id.name, binding);
+            }
+            Node::VariableAssgn(n) => {
+                let mut modifiers = vec![];
+                if std::mem::replace(&mut self.readonly, false) {
+                    modifiers.push(TokenModifier::Readonly);
+                }
+                let kind = kind_of(n.init.type_of());
+                self.declare(&n.id, kind, &modifiers);
+                self.pending
+                    .push((n.id.name.clone(), Binding::new(kind, modifiers)));
+            }
+            Node::FunctionExpr(n) => {
+                self.scopes.push(HashMap::new());
+                for p in &n.params {
+                    self.declare(&p.key, TokenKind::Parameter, &[]);
+                    self.bind(&p.key.name, Binding::new(TokenKind::Parameter, vec![]));
+                }
+            }
+            Node::IdentifierExpr(n) => self.reference(n),
+            Node::MemberExpr(n) => self.member(n),
+            Node::ObjectExpr(n) => self.keys(&n.properties, TokenKind::Property),
+            Node::CallExpr(n) => self.keys(&n.arguments, TokenKind::Parameter),
+            _ => {}
+        }
+        true
+    }
+
+    fn done(&mut self, node: Rc<Node<'a>>) {
+        match *node {
+            Node::VariableAssgn(_) => {
+                if let Some((name, binding)) = self.pending.pop() {
+                    self.bind(&name, binding);
+                }
+            }
+            Node::FunctionExpr(_) => {
+                self.scopes.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{semantic_tokens, TokenKind, TokenModifier};
+    use crate::ast;
+    use crate::parser::parse_string;
+    use crate::semantic::convert::convert_with;
+    use crate::semantic::env::Environment;
+    use crate::semantic::fresh::Fresher;
+    use crate::semantic::nodes::{infer_pkg_types, inject_pkg_types};
+    use crate::semantic::parser::parse;
+    use crate::semantic::types::PolyType;
+
+    use TokenKind::*;
+    use TokenModifier::*;
+
+    fn classify(src: &str) -> Vec<(String, TokenKind, Vec<TokenModifier>)> {
+        let file = parse_string("a.flux", src);
+        let pkg = ast::Package {
+            base: file.base.clone(),
+            path: String::from("a"),
+            package: String::from("main"),
+            files: vec![file],
+        };
+        let polytypes = |types: Vec<(&str, &str)>| -> HashMap<String, PolyType> {
+            types
+                .into_iter()
+                .map(|(name, typ)| (name.to_string(), parse(typ).unwrap()))
+                .collect()
+        };
+        let prelude = polytypes(vec![("now", "forall [] () -> time")]);
+        let imports = polytypes(vec![(
+            "strings",
+            "forall [] {toUpper: (v: string) -> string | x: int}",
+        )]);
+        let builtins = polytypes(vec![("fetch", "forall [] () -> int")]);
+        let mut f = Fresher::from(100);
+        let mut pkg = convert_with(pkg, &mut f).unwrap();
+        let env = Environment::new(prelude.into());
+        let (_, sub) = infer_pkg_types(&mut pkg, env, &mut f, &imports, &builtins).unwrap();
+        let pkg = inject_pkg_types(pkg, &sub);
+        semantic_tokens(&pkg.files[0], src)
+            .into_iter()
+            .map(|t| {
+                let text = src[t.span.start as usize..t.span.end as usize].to_string();
+                (text, t.kind, t.modifiers)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn classify_identifiers() {
+        let src = r#"import "strings"
+option limit = 10
+builtin fetch
+f = (r, n=1) => r.a + n
+x = f(r: {a: limit}, n: 2)
+s = strings.toUpper(v: "a")
+t = fetch()
+y = now()
+o = {x}
+"#;
+        let want = vec![
+            ("limit", Variable, vec![Declaration, Readonly]),
+            ("fetch", Function, vec![Declaration, DefaultLibrary]),
+            ("f", Function, vec![Declaration]),
+            ("r", Parameter, vec![Declaration]),
+            ("n", Parameter, vec![Declaration]),
+            ("r", Parameter, vec![]),
+            ("a", Property, vec![]),
+            ("n", Parameter, vec![]),
+            ("x", Variable, vec![Declaration]),
+            ("f", Function, vec![]),
+            ("r", Parameter, vec![]),
+            ("a", Property, vec![]),
+            ("limit", Variable, vec![Readonly]),
+            ("n", Parameter, vec![]),
+            ("s", Variable, vec![Declaration]),
+            ("strings", Namespace, vec![]),
+            ("toUpper", Function, vec![DefaultLibrary]),
+            ("v", Parameter, vec![]),
+            ("t", Variable, vec![Declaration]),
+            ("fetch", Function, vec![DefaultLibrary]),
+            ("y", Variable, vec![Declaration]),
+            ("now", Function, vec![DefaultLibrary]),
+            ("o", Variable, vec![Declaration]),
+            ("x", Variable, vec![]),
+        ];
+        let want: Vec<(String, TokenKind, Vec<TokenModifier>)> = want
+            .into_iter()
+            .map(|(text, kind, modifiers)| (text.to_string(), kind, modifiers))
+            .collect();
+        assert_eq!(want, classify(src));
+    }
+
+    #[test]
+    fn classify_packages_and_members() {
+        let src =
+            "package p\nimport s \"strings\"\noption s.x = 1\nm = {r: 1}\nv = m[\"r\"] + m.r\n";
+        let got: Vec<(String, TokenKind)> = classify(src)
+            .into_iter()
+            .map(|(text, kind, _)| (text, kind))
+            .collect();
+        let want = vec![
+            ("p", Namespace),
+            ("s", Namespace),
+            ("s", Namespace),
+            ("x", Variable),
+            ("m", Variable),
+            ("r", Property),
+            ("v", Variable),
+            ("m", Variable),
+            ("m", Variable),
+            ("r", Property),
+        ];
+        let want: Vec<(String, TokenKind)> = want
+            .into_iter()
+            .map(|(text, kind)| (text.to_string(), kind))
+            .collect();
+        assert_eq!(want, got);
+    }
+}
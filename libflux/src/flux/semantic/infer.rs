@@ -1,12 +1,13 @@
+use crate::ast;
 use crate::semantic::env::Environment;
 use crate::semantic::fresh::Fresher;
-use crate::semantic::sub::{Substitutable, Substitution};
-use crate::semantic::types::{minus, Error, Kind, MonoType, PolyType, Tvar};
+use crate::semantic::sub::{Substitutable, Substitution, TvarStore};
+use crate::semantic::types::{minus, ChangeVars, Error, Kind, MonoType, PolyType, RowChange, Tvar};
 use std::collections::HashMap;
 use std::ops;
 
 // Type constraints are produced during type inference and come
-// in two flavors.
+// in three flavors.
 //
 // A kind constraint asserts that a particular type is of a
 // particular kind or family of types.
@@ -14,14 +15,26 @@ use std::ops;
 // An equality contraint asserts that two types are equivalent
 // and will be unified at some point.
 //
+// A change constraint asserts that the result is the type of the
+// tables after a change to their schema, made by the function named.
+//
 #[derive(Debug, PartialEq)]
 pub enum Constraint {
     Kind(MonoType, Kind),
     Equal(MonoType, MonoType),
+    Change {
+        tables: MonoType,
+        change: RowChange,
+        result: MonoType,
+        by: String,
+        vars: ChangeVars,
+    },
 }
 
+// Each constraint is kept with the location of the expression it comes
+// from, so that an error solving it can point to that expression.
 #[derive(Debug, PartialEq)]
-pub struct Constraints(Vec<Constraint>);
+pub struct Constraints(Vec<(Constraint, Option<ast::SourceLocation>)>);
 
 impl Constraints {
     pub fn empty() -> Constraints {
@@ -29,7 +42,16 @@ impl Constraints {
     }
 
     pub fn add(&mut self, cons: Constraint) {
-        self.0.push(cons);
+        self.0.push((cons, None));
+    }
+
+    // Locate the constraints that do not yet have a location. Constraints
+    // are located by the innermost expression they come from.
+    pub fn locate(mut self, loc: &ast::SourceLocation) -> Constraints {
+        for (_, l) in self.0.iter_mut().filter(|(_, l)| l.is_none()) {
+            *l = Some(loc.clone());
+        }
+        self
     }
 }
 
@@ -45,13 +67,13 @@ impl ops::Add for Constraints {
 
 impl From<Vec<Constraint>> for Constraints {
     fn from(constraints: Vec<Constraint>) -> Constraints {
-        Constraints(constraints)
+        Constraints(constraints.into_iter().map(|c| (c, None)).collect())
     }
 }
 
 impl From<Constraints> for Vec<Constraint> {
     fn from(constraints: Constraints) -> Vec<Constraint> {
-        constraints.0
+        constraints.0.into_iter().map(|(c, _)| c).collect()
     }
 }
 
@@ -61,28 +83,69 @@ impl From<Constraint> for Constraints {
     }
 }
 
-// Solve a set of type constraints
+// Solve a set of type constraints.
+//
+// The bindings made while solving each constraint are recorded in a single
+// store, which is converted to a substitution once all of the constraints
+// have been solved. An error is located at the expression whose
+// constraint could not be solved.
 pub fn solve(
     cons: &Constraints,
     with: &mut HashMap<Tvar, Vec<Kind>>,
     fresher: &mut Fresher,
 ) -> Result<Substitution, Error> {
-    cons.0
-        .iter()
-        .try_fold(Substitution::empty(), |sub, constraint| match constraint {
+    let mut sub = TvarStore::new();
+    for (i, (constraint, loc)) in cons.0.iter().enumerate() {
+        match constraint {
             Constraint::Kind(monotype, kind) => {
-                // Apply the current substitution to the type, then constrain
-                let s = monotype.clone().apply(&sub).constrain(*kind, with)?;
-                Ok(sub.merge(s))
+                monotype.clone().constrain_in(kind.clone(), &mut sub, with)
             }
             Constraint::Equal(first, second) => {
-                // Apply the current substitution to the constraint, then unify
-                let l = first.clone().apply(&sub);
-                let r = second.clone().apply(&sub);
-                let s = l.unify(r, with, fresher)?;
-                Ok(sub.merge(s))
+                first
+                    .clone()
+                    .unify_in(second.clone(), &mut sub, with, fresher)
             }
-        })
+            Constraint::Change {
+                tables,
+                change,
+                result,
+                vars,
+                ..
+            } => change.clone().solve_in(
+                tables.clone(),
+                result.clone(),
+                &mut sub,
+                with,
+                vars,
+                fresher,
+            ),
+        }
+        .map_err(|err| {
+            let err = match loc {
+                Some(loc) => err.at(loc),
+                None => err,
+            };
+            removed_by(err, &cons.0[..i])
+        })?;
+    }
+    Ok(sub.into())
+}
+
+// Point out the call that removed the label a record must not have, which
+// is the last one before the constraint that failed to remove it.
+fn removed_by(err: Error, solved: &[(Constraint, Option<ast::SourceLocation>)]) -> Error {
+    let label = match err.removed_label() {
+        Some(label) => label.to_string(),
+        None => return err,
+    };
+    for (constraint, loc) in solved.iter().rev() {
+        if let Constraint::Change { change, by, .. } = constraint {
+            if change.removes(&label) {
+                return err.removed_by(by, loc.as_ref());
+            }
+        }
+    }
+    err
 }
 
 // Create a parametric type from a monotype by universally quantifying
@@ -144,17 +207,17 @@ mod tests {
 
     #[test]
     fn add_constraints() {
-        let c0 = Constraints(vec![
+        let c0 = Constraints::from(vec![
             Constraint::Equal(MonoType::Var(Tvar(0)), MonoType::Var(Tvar(1))),
             Constraint::Kind(MonoType::Var(Tvar(1)), Kind::Addable),
         ]);
-        let c1 = Constraints(vec![
+        let c1 = Constraints::from(vec![
             Constraint::Equal(MonoType::Var(Tvar(2)), MonoType::Var(Tvar(3))),
             Constraint::Kind(MonoType::Var(Tvar(3)), Kind::Divisible),
         ]);
         assert_eq!(
             c0 + c1,
-            Constraints(vec![
+            Constraints::from(vec![
                 Constraint::Equal(MonoType::Var(Tvar(0)), MonoType::Var(Tvar(1))),
                 Constraint::Kind(MonoType::Var(Tvar(1)), Kind::Addable),
                 Constraint::Equal(MonoType::Var(Tvar(2)), MonoType::Var(Tvar(3))),
@@ -7,10 +7,13 @@ mod infer;
 
 pub mod bootstrap;
 pub mod check;
+pub mod doc;
 pub mod env;
 pub mod fresh;
+pub mod highlight;
 pub mod nodes;
 pub mod parser;
+pub mod printer;
 pub mod sub;
 pub mod types;
 pub mod walk;
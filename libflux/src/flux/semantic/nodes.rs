@@ -10,6 +10,7 @@ extern crate chrono;
 extern crate derivative;
 
 use crate::ast;
+use crate::semantic::builtins::{self, ColumnChange};
 use crate::semantic::infer;
 use crate::semantic::types;
 use crate::semantic::{
@@ -18,7 +19,7 @@ use crate::semantic::{
     import::Importer,
     infer::{Constraint, Constraints},
     sub::{Substitutable, Substitution},
-    types::{Array, Function, Kind, MonoType, PolyType, Tvar},
+    types::{Array, ChangeVars, Function, Kind, MonoType, PolyType, RowChange, Tvar},
 };
 
 use chrono::prelude::DateTime;
@@ -106,6 +107,18 @@ pub enum Statement {
 }
 
 impl Statement {
+    fn infer<S: Importer>(&mut self, env: Environment, f: &mut Fresher, builtins: &S) -> Result {
+        match self {
+            Statement::Builtin(stmt) => Ok((stmt.infer(env, builtins)?, Constraints::empty())),
+            Statement::Variable(stmt) => stmt.infer(env, f),
+            Statement::Option(stmt) => stmt.infer(env, f),
+            Statement::Expr(stmt) => stmt.infer(env, f),
+            Statement::Test(stmt) => stmt.infer(env, f),
+            Statement::Return(_) => Err(Error::invalid_statement(String::from(
+                "cannot have return statement in file block",
+            ))),
+        }
+    }
     fn apply(self, sub: &Substitution) -> Self {
         match self {
             Statement::Expr(stmt) => Statement::Expr(stmt.apply(&sub)),
@@ -208,7 +221,7 @@ impl Expression {
         }
     }
     fn infer(&mut self, env: Environment, f: &mut Fresher) -> Result {
-        match self {
+        let (env, cons) = match self {
             Expression::Identifier(e) => e.infer(env, f),
             Expression::Array(e) => e.infer(env, f),
             Expression::Function(e) => e.infer(env, f),
@@ -229,7 +242,8 @@ impl Expression {
             Expression::Boolean(lit) => lit.infer(env),
             Expression::DateTime(lit) => lit.infer(env),
             Expression::Regexp(lit) => lit.infer(env),
-        }
+        }?;
+        Ok((env, cons.locate(self.loc())))
     }
     fn apply(self, sub: &Substitution) -> Self {
         match self {
@@ -291,6 +305,22 @@ pub fn inject_pkg_types(pkg: Package, sub: &Substitution) -> Package {
     pkg.apply(&sub)
 }
 
+// Infer the types of a single top-level statement, given the environment
+// produced by the statements before it.
+pub fn infer_stmt_types<S: Importer>(
+    stmt: &mut Statement,
+    env: Environment,
+    f: &mut Fresher,
+    builtins: &S,
+) -> std::result::Result<(Environment, Substitution), Error> {
+    let (env, cons) = stmt.infer(env, f, builtins)?;
+    Ok((env, infer::solve(&cons, &mut HashMap::new(), f)?))
+}
+
+pub fn inject_stmt_types(stmt: Statement, sub: &Substitution) -> Statement {
+    stmt.apply(sub)
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Package {
     pub loc: ast::SourceLocation,
@@ -340,7 +370,7 @@ pub struct File {
 impl File {
     fn infer<T, S>(
         &mut self,
-        mut env: Environment,
+        env: Environment,
         f: &mut Fresher,
         importer: &T,
         builtins: &S,
@@ -349,6 +379,27 @@ impl File {
         T: Importer,
         S: Importer,
     {
+        let (env, imports) = self.infer_imports(env, importer)?;
+        let (mut env, constraints) = self.body.iter_mut().try_fold(
+            (env, Constraints::empty()),
+            |(env, rest), node| -> Result {
+                let (env, cons) = node.infer(env, f, builtins)?;
+                Ok((env, cons + rest))
+            },
+        )?;
+
+        for name in imports {
+            env.remove(&name);
+        }
+        Ok((env, constraints))
+    }
+    // Add the packages imported by this file to the type environment,
+    // returning the names they are bound to.
+    pub fn infer_imports<T: Importer>(
+        &self,
+        mut env: Environment,
+        importer: &T,
+    ) -> std::result::Result<(Environment, Vec<String>), Error> {
         let mut imports = Vec::with_capacity(self.imports.len());
 
         for dec in &self.imports {
@@ -359,50 +410,14 @@ impl File {
                 Some(id) => &id.name[..],
             };
 
-            imports.push(name);
+            imports.push(name.to_owned());
 
             match importer.import(path) {
                 Some(poly) => env.add(name.to_owned(), poly),
                 None => return Err(Error::unknown_import_path(path)),
             };
         }
-
-        let (mut env, constraints) =
-            self.body
-                .iter_mut()
-                .try_fold(
-                    (env, Constraints::empty()),
-                    |(env, rest), node| match node {
-                        Statement::Builtin(stmt) => {
-                            let env = stmt.infer(env, builtins)?;
-                            Ok((env, rest))
-                        }
-                        Statement::Variable(stmt) => {
-                            let (env, cons) = stmt.infer(env, f)?;
-                            Ok((env, cons + rest))
-                        }
-                        Statement::Option(stmt) => {
-                            let (env, cons) = stmt.infer(env, f)?;
-                            Ok((env, cons + rest))
-                        }
-                        Statement::Expr(stmt) => {
-                            let (env, cons) = stmt.infer(env, f)?;
-                            Ok((env, cons + rest))
-                        }
-                        Statement::Test(stmt) => {
-                            let (env, cons) = stmt.infer(env, f)?;
-                            Ok((env, cons + rest))
-                        }
-                        Statement::Return(_) => Err(Error::invalid_statement(String::from(
-                            "cannot have return statement in file block",
-                        ))),
-                    },
-                )?;
-
-        for name in imports {
-            env.remove(name);
-        }
-        Ok((env, constraints))
+        Ok((env, imports))
     }
     fn apply(mut self, sub: &Substitution) -> Self {
         self.body = self.body.into_iter().map(|stmt| stmt.apply(&sub)).collect();
@@ -486,8 +501,55 @@ pub struct TestStmt {
 }
 
 impl TestStmt {
+    // A test is run by calling its function with no arguments. The call
+    // returns a test case whose fn is called with the input piped in, and
+    // whose result is compared with the wanted tables:
+    //
+    //     test t = () => ({input: ..., want: ..., fn: (tables=<-) => ...})
+    //
     fn infer(&mut self, env: Environment, f: &mut Fresher) -> Result {
-        self.assignment.infer(env, f)
+        let (env, cons) = self.assignment.infer(env, f)?;
+
+        let poly = match env.lookup(&self.assignment.id.name) {
+            Some(poly) => poly.clone(),
+            None => return Ok((env, cons)),
+        };
+        let (t, mut case_cons) = infer::instantiate(poly, f);
+        let input = MonoType::Var(f.fresh());
+        let want = MonoType::Var(f.fresh());
+        let run = MonoType::Fun(Box::new(Function {
+            req: HashMap::new(),
+            opt: HashMap::new(),
+            pipe: Some(types::Property {
+                k: String::from("<-"),
+                v: input.clone(),
+            }),
+            retn: want.clone(),
+        }));
+        let case = [("fn", run), ("want", want), ("input", input)].iter().fold(
+            MonoType::Var(f.fresh()),
+            |tail, (k, v)| {
+                MonoType::Row(Box::new(types::Row::Extension {
+                    head: types::Property {
+                        k: k.to_string(),
+                        v: v.clone(),
+                    },
+                    tail,
+                }))
+            },
+        );
+        let retn = MonoType::Var(f.fresh());
+        let call = MonoType::Fun(Box::new(Function {
+            req: HashMap::new(),
+            opt: HashMap::new(),
+            pipe: None,
+            retn: retn.clone(),
+        }));
+        case_cons.add(Constraint::Equal(t, call));
+        case_cons.add(Constraint::Equal(retn, case));
+        infer::solve(&case_cons.locate(&self.loc), &mut HashMap::new(), f)?;
+
+        Ok((env, cons))
     }
     fn apply(mut self, sub: &Substitution) -> Self {
         self.assignment = self.assignment.apply(&sub);
@@ -595,6 +657,7 @@ impl VariableAssgn {
 
         // Update the type environment
         env.add(String::from(&self.id.name), p);
+
         Ok((env, constraints))
     }
     fn apply(mut self, sub: &Substitution) -> Self {
@@ -637,9 +700,9 @@ impl StringExpr {
             if let StringExprPart::Interpolated(ref mut ip) = p {
                 let (e, cons) = ip.expression.infer(env, f)?;
                 constraints.append(&mut Vec::from(cons));
-                constraints.push(Constraint::Equal(
+                constraints.push(Constraint::Kind(
                     ip.expression.type_of().clone(),
-                    MonoType::String,
+                    Kind::Stringable,
                 ));
                 env = e
             }
@@ -718,6 +781,25 @@ impl ArrayExpr {
         cons.push(Constraint::Equal(at, self.typ.clone()));
         Ok((env, cons.into()))
     }
+    // Infer the types of the elements without relating them to each other,
+    // as the streams given to union may have different columns.
+    fn infer_elements(
+        &mut self,
+        mut env: Environment,
+        f: &mut Fresher,
+    ) -> std::result::Result<(Environment, Constraints, Vec<MonoType>), Error> {
+        let mut cons = Constraints::empty();
+        let mut elements = Vec::new();
+        for el in &mut self.elements {
+            let (e, c) = el.infer(env, f)?;
+            cons = cons + c;
+            elements.push(el.type_of().clone());
+            env = e;
+        }
+        let at = MonoType::Arr(Box::new(Array(MonoType::Var(f.fresh()))));
+        cons.add(Constraint::Equal(at, self.typ.clone()));
+        Ok((env, cons, elements))
+    }
     fn apply(mut self, sub: &Substitution) -> Self {
         self.typ = self.typ.apply(&sub);
         self.elements = self
@@ -799,7 +881,14 @@ impl FunctionExpr {
         // And use it to infer the body.
         let (nenv, bcons) = self.body.infer(nenv, f)?;
         // Now pop the nested environment, we don't need it anymore.
-        let env = nenv.pop();
+        let env = match nenv.pop() {
+            Some(env) => env,
+            None => {
+                return Err(Error::from(String::from(
+                    "cannot pop final stack frame from type environment",
+                )))
+            }
+        };
         let retn = self.body.type_of().clone();
         let func = MonoType::Fun(Box::new(Function {
             req,
@@ -944,65 +1033,67 @@ impl BinaryExpr {
         let (env, rcons) = self.right.infer(env, f)?;
 
         let cons = match self.operator {
-            // The following operators require both sides to be equal.
+            // The following operators require both sides to be equal. The
+            // right side is unified with the left, so that a mismatch is
+            // reported as the type of the left expected on the right.
             ast::Operator::AdditionOperator => Constraints::from(vec![
-                Constraint::Equal(self.left.type_of().clone(), self.right.type_of().clone()),
+                Constraint::Equal(self.right.type_of().clone(), self.left.type_of().clone()),
                 Constraint::Equal(self.left.type_of().clone(), self.typ.clone()),
                 Constraint::Kind(self.typ.clone(), Kind::Addable),
             ]),
             ast::Operator::SubtractionOperator => Constraints::from(vec![
-                Constraint::Equal(self.left.type_of().clone(), self.right.type_of().clone()),
+                Constraint::Equal(self.right.type_of().clone(), self.left.type_of().clone()),
                 Constraint::Equal(self.left.type_of().clone(), self.typ.clone()),
                 Constraint::Kind(self.typ.clone(), Kind::Subtractable),
             ]),
             ast::Operator::MultiplicationOperator => Constraints::from(vec![
-                Constraint::Equal(self.left.type_of().clone(), self.right.type_of().clone()),
+                Constraint::Equal(self.right.type_of().clone(), self.left.type_of().clone()),
                 Constraint::Equal(self.left.type_of().clone(), self.typ.clone()),
                 Constraint::Kind(self.typ.clone(), Kind::Divisible),
             ]),
             ast::Operator::DivisionOperator => Constraints::from(vec![
-                Constraint::Equal(self.left.type_of().clone(), self.right.type_of().clone()),
+                Constraint::Equal(self.right.type_of().clone(), self.left.type_of().clone()),
                 Constraint::Equal(self.left.type_of().clone(), self.typ.clone()),
                 Constraint::Kind(self.typ.clone(), Kind::Divisible),
             ]),
             ast::Operator::PowerOperator => Constraints::from(vec![
-                Constraint::Equal(self.left.type_of().clone(), self.right.type_of().clone()),
+                Constraint::Equal(self.right.type_of().clone(), self.left.type_of().clone()),
                 Constraint::Equal(self.left.type_of().clone(), self.typ.clone()),
                 Constraint::Kind(self.typ.clone(), Kind::Divisible),
             ]),
             ast::Operator::ModuloOperator => Constraints::from(vec![
-                Constraint::Equal(self.left.type_of().clone(), self.right.type_of().clone()),
+                Constraint::Equal(self.right.type_of().clone(), self.left.type_of().clone()),
                 Constraint::Equal(self.left.type_of().clone(), self.typ.clone()),
                 Constraint::Kind(self.typ.clone(), Kind::Divisible),
             ]),
             ast::Operator::GreaterThanOperator => Constraints::from(vec![
-                Constraint::Equal(self.left.type_of().clone(), self.right.type_of().clone()),
+                Constraint::Equal(self.right.type_of().clone(), self.left.type_of().clone()),
                 Constraint::Equal(self.typ.clone(), MonoType::Bool),
                 Constraint::Kind(self.left.type_of().clone(), Kind::Comparable),
             ]),
             ast::Operator::LessThanOperator => Constraints::from(vec![
-                Constraint::Equal(self.left.type_of().clone(), self.right.type_of().clone()),
+                Constraint::Equal(self.right.type_of().clone(), self.left.type_of().clone()),
                 Constraint::Equal(self.typ.clone(), MonoType::Bool),
                 Constraint::Kind(self.left.type_of().clone(), Kind::Comparable),
             ]),
             ast::Operator::EqualOperator => Constraints::from(vec![
-                Constraint::Equal(self.left.type_of().clone(), self.right.type_of().clone()),
+                Constraint::Equal(self.right.type_of().clone(), self.left.type_of().clone()),
                 Constraint::Equal(self.typ.clone(), MonoType::Bool),
                 Constraint::Kind(self.left.type_of().clone(), Kind::Equatable),
             ]),
             ast::Operator::NotEqualOperator => Constraints::from(vec![
-                Constraint::Equal(self.left.type_of().clone(), self.right.type_of().clone()),
+                Constraint::Equal(self.right.type_of().clone(), self.left.type_of().clone()),
                 Constraint::Equal(self.typ.clone(), MonoType::Bool),
                 Constraint::Kind(self.left.type_of().clone(), Kind::Equatable),
             ]),
             ast::Operator::GreaterThanEqualOperator => Constraints::from(vec![
-                Constraint::Equal(self.left.type_of().clone(), self.right.type_of().clone()),
+                Constraint::Equal(self.right.type_of().clone(), self.left.type_of().clone()),
                 Constraint::Equal(self.typ.clone(), MonoType::Bool),
                 Constraint::Kind(self.left.type_of().clone(), Kind::Equatable),
                 Constraint::Kind(self.left.type_of().clone(), Kind::Comparable),
             ]),
             ast::Operator::LessThanEqualOperator => Constraints::from(vec![
-                Constraint::Equal(self.left.type_of().clone(), self.right.type_of().clone()),
+                Constraint::Equal(self.right.type_of().clone(), self.left.type_of().clone()),
                 Constraint::Equal(self.typ.clone(), MonoType::Bool),
                 Constraint::Kind(self.left.type_of().clone(), Kind::Equatable),
                 Constraint::Kind(self.left.type_of().clone(), Kind::Comparable),
@@ -1050,6 +1141,9 @@ impl CallExpr {
         // update the environment and the constraints, and use the inferred types to
         // build the fields of the type for this call expression.
         let (mut env, mut cons) = self.callee.infer(env, f)?;
+        let mut change = self.column_change(&env);
+        let mut labels = HashMap::new();
+        let mut streams = Vec::new();
         let mut req = HashMap::new();
         let mut pipe = None;
         for Property {
@@ -1058,42 +1152,91 @@ impl CallExpr {
             ..
         } in &mut self.arguments
         {
+            if change.iter().any(|c| c.has_param(&id.name)) {
+                if let Some((ncons, l)) = infer_labels(expr) {
+                    cons = cons + ncons;
+                    labels.insert(id.name.clone(), l);
+                    req.insert(id.name.clone(), expr.type_of().clone());
+                    continue;
+                }
+                // The columns are not known, so neither is the change.
+                change = None;
+            }
+            if change.iter().any(|c| c.has_streams(&id.name)) {
+                if let Expression::Array(arr) = expr {
+                    let (nenv, ncons, elements) = arr.infer_elements(env, f)?;
+                    cons = cons + ncons;
+                    env = nenv;
+                    streams = elements;
+                    req.insert(id.name.clone(), arr.typ.clone());
+                    continue;
+                }
+                // The streams are not listed, so the change is not known.
+                change = None;
+            }
             let (nenv, ncons) = expr.infer(env, f)?;
             cons = cons + ncons;
             env = nenv;
             // Every argument is required in a function call.
             req.insert(id.name.clone(), expr.type_of().clone());
         }
+        // The streams a change is made to are its tables, the first of them
+        // being changed by the others.
+        let mut tables = match streams.first() {
+            Some(first) => Some(first.clone()),
+            None => req.get("tables").cloned(),
+        };
         if let Some(ref mut p) = &mut self.pipe {
             let (nenv, ncons) = p.infer(env, f)?;
             cons = cons + ncons;
             env = nenv;
+            tables = Some(p.type_of().clone());
             pipe = Some(types::Property {
                 k: "<-".to_string(),
                 v: p.type_of().clone(),
             });
         }
-        // Constrain the callee to be a Function.
-        cons.add(Constraint::Equal(
-            self.callee.type_of().clone(),
-            MonoType::Fun(Box::new(Function {
-                opt: HashMap::new(),
-                req,
-                pipe,
-                // The return type of a function call is the type of the call itself.
-                // Remind that, when two functions are unified, their return types are unified too.
-                // As an example take:
-                //   f = (a) => a + 1
-                //   f(a: 0)
-                // The return type of `f` is `int`.
-                // The return type of `f(a: 0)` is `t0` (a fresh type variable).
-                // Upon unification a substitution "t0 => int" is created, so that the compiler
-                // can infer that, for instance, `f(a: 0) + 1` is legal.
-                retn: self.typ.clone(),
-            })),
-        ));
+        let change = change.and_then(|c| row_change(c, &labels, &streams, &req));
+        // Constrain the callee to be a Function. The call is what is found
+        // and the callee what is expected, so that errors read that way.
+        let call = MonoType::Fun(Box::new(Function {
+            opt: HashMap::new(),
+            req,
+            pipe,
+            // The return type of a function call is the type of the call itself.
+            // Remind that, when two functions are unified, their return types are unified too.
+            // As an example take:
+            //   f = (a) => a + 1
+            //   f(a: 0)
+            // The return type of `f` is `int`.
+            // The return type of `f(a: 0)` is `t0` (a fresh type variable).
+            // Upon unification a substitution "t0 => int" is created, so that the compiler
+            // can infer that, for instance, `f(a: 0) + 1` is legal.
+            retn: self.typ.clone(),
+        }));
+        cons.add(Constraint::Equal(call, self.callee.type_of().clone()));
+        // Relate the columns of the tables returned to those of the tables given.
+        if let (Some(change), Some(tables), Expression::Identifier(id)) =
+            (change, tables, &self.callee)
+        {
+            cons.add(Constraint::Change {
+                tables,
+                change,
+                result: self.typ.clone(),
+                by: id.name.clone(),
+                vars: ChangeVars::default(),
+            });
+        }
         Ok((env, cons))
     }
+    // The column change made by the callee, if it is a builtin that changes
+    // the columns of its tables.
+    fn column_change(&self, env: &Environment) -> Option<ColumnChange> {
+        match &self.callee {
+            Expression::Identifier(id) => builtins::column_change(&id.name, env.lookup(&id.name)?),
+            _ => None,
+        }
+    }
     fn apply(mut self, sub: &Substitution) -> Self {
         self.typ = self.typ.apply(&sub);
         self.callee = self.callee.apply(&sub);
@@ -1112,6 +1255,109 @@ impl CallExpr {
     }
 }
 
+// Labels are the literal column labels given to a parameter of a column change.
+enum Labels {
+    List(Vec<String>),
+    Renames(Vec<(String, String)>),
+}
+
+// Infer the type of an argument that labels columns, typing each literal
+// string in it as the label it is. Returns None, inferring nothing, when
+// the argument is not made of literal strings.
+fn infer_labels(expr: &mut Expression) -> Option<(Constraints, Labels)> {
+    match expr {
+        Expression::StringLit(lit) => {
+            Some((lit.infer_label(), Labels::List(vec![lit.value.clone()])))
+        }
+        Expression::Array(arr) => {
+            let mut cons = Constraints::empty();
+            let mut labels = Vec::new();
+            for el in &arr.elements {
+                if let Expression::StringLit(lit) = el {
+                    cons = cons + lit.infer_label();
+                    labels.push(lit.value.clone());
+                } else {
+                    return None;
+                }
+            }
+            let at = MonoType::Arr(Box::new(Array(MonoType::String)));
+            cons.add(Constraint::Equal(arr.typ.clone(), at));
+            Some((cons, Labels::List(labels)))
+        }
+        Expression::Object(obj) if obj.with.is_none() => {
+            let mut cons = Constraints::empty();
+            let mut renames = Vec::new();
+            let mut props = Vec::new();
+            for prop in &obj.properties {
+                if let Expression::StringLit(lit) = &prop.value {
+                    cons = cons + lit.infer_label();
+                    renames.push((prop.key.name.clone(), lit.value.clone()));
+                    props.push(types::Property {
+                        k: prop.key.name.clone(),
+                        v: MonoType::Label(lit.value.clone()),
+                    });
+                } else {
+                    return None;
+                }
+            }
+            let r = types::Row::from_properties(props, MonoType::Row(Box::new(types::Row::Empty)));
+            cons.add(Constraint::Equal(obj.typ.clone(), r));
+            Some((cons, Labels::Renames(renames)))
+        }
+        _ => None,
+    }
+}
+
+// The change a call makes to the rows of its tables, given the labels of
+// the parameters of its column change, the streams it lists and the types
+// of its arguments. Returns None when a parameter the change needs was not
+// given.
+fn row_change(
+    change: ColumnChange,
+    labels: &HashMap<String, Labels>,
+    streams: &[MonoType],
+    args: &HashMap<String, MonoType>,
+) -> Option<RowChange> {
+    let list = |p: &str| match labels.get(p) {
+        Some(Labels::List(l)) => Some(l.clone()),
+        _ => None,
+    };
+    let label = |p: &str| match list(p) {
+        Some(ref l) if l.len() == 1 => Some(l[0].clone()),
+        _ => None,
+    };
+    match change {
+        ColumnChange::Remove(p) => Some(RowChange::Remove(list(p)?)),
+        ColumnChange::Keep(p) => Some(RowChange::Keep(list(p)?)),
+        ColumnChange::Rename(p) => match labels.get(p) {
+            Some(Labels::Renames(renames)) => Some(RowChange::Rename(renames.clone())),
+            _ => None,
+        },
+        ColumnChange::Copy(from, to) => Some(RowChange::Copy(label(from)?, label(to)?)),
+        ColumnChange::AddTime(params) => {
+            let mut props = Vec::new();
+            for (p, default) in params {
+                let k = match labels.get(*p) {
+                    None => (*default).to_string(),
+                    Some(_) => label(p)?,
+                };
+                props.push(types::Property {
+                    k,
+                    v: MonoType::Time,
+                });
+            }
+            Some(RowChange::Add(props))
+        }
+        ColumnChange::Pivot(row_key, column_key, value_column) => {
+            let mut columns = list(column_key)?;
+            columns.push(label(value_column)?);
+            Some(RowChange::Pivot(list(row_key)?, columns))
+        }
+        ColumnChange::Merge(p) => Some(RowChange::Merge(args.get(p)?.clone())),
+        ColumnChange::Union(_) => Some(RowChange::Union(streams.get(1..)?.to_vec())),
+    }
+}
+
 #[derive(Derivative)]
 #[derivative(Debug, PartialEq, Clone)]
 pub struct ConditionalExpr {
@@ -1498,6 +1744,10 @@ impl StringLit {
     fn infer(&self, env: Environment) -> Result {
         infer_literal(env, &self.typ, MonoType::String)
     }
+    // Infer the type of a string literal that labels a column.
+    fn infer_label(&self) -> Constraints {
+        Constraint::Equal(self.typ.clone(), MonoType::Label(self.value.clone())).into()
+    }
     fn apply(mut self, sub: &Substitution) -> Self {
         self.typ = self.typ.apply(&sub);
         self
@@ -1,7 +1,12 @@
-use std::{collections::HashMap, iter::Peekable, slice::Iter, str::Chars};
+use std::{collections::HashMap, fmt, iter::Peekable, slice::Iter, str::Chars};
 
+use crate::ast::{self, LineIndex, Span};
+use crate::limits::Limits;
+use crate::semantic::printer::Printer;
 use crate::semantic::types::{Array, Function, Kind, MonoType, PolyType, Property, Row, Tvar};
 
+use wasm_bindgen::prelude::*;
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 // TokenType holds all possible TokenType values
 pub enum TokenType {
@@ -45,6 +50,11 @@ struct Lexer<'a> {
     source: Peekable<Chars<'a>>,
     tokens: Vec<Token>,
     current_string: String,
+    // spans holds the byte range of each token, and offset and start the
+    // byte offsets of the next character and of the current token.
+    spans: Vec<Span>,
+    offset: u32,
+    start: u32,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -59,18 +69,27 @@ pub struct Token {
 // This function is not meant to be used directly. The user should pass
 // source into parse(), which in turn calls this function.
 pub fn lex(source: &str) -> Vec<Token> {
+    lex_spans(source).0
+}
+
+// lex_spans lexes the source and also returns the span of each token.
+fn lex_spans(source: &str) -> (Vec<Token>, Vec<Span>) {
     let mut lexer = Lexer {
         source: source.chars().peekable(),
         tokens: Vec::new(),
         current_string: String::new(),
+        spans: Vec::new(),
+        offset: 0,
+        start: 0,
     };
     lexer.lex_tokens();
-    lexer.tokens
+    (lexer.tokens, lexer.spans)
 }
 
 impl Lexer<'_> {
     // lex_tokens calls lex_token while there are still characters to lex
     fn lex_tokens(&mut self) {
+        self.start = self.offset;
         while let Some(token_type) = lex_token(self) {
             if token_type == TokenType::WHITESPACE {
                 self.ignore();
@@ -81,6 +100,7 @@ impl Lexer<'_> {
             if token_type == TokenType::EOF {
                 break;
             }
+            self.start = self.offset;
         }
     }
 
@@ -89,6 +109,7 @@ impl Lexer<'_> {
         match self.source.next() {
             None => None,
             Some(letter) => {
+                self.offset += letter.len_utf8() as u32;
                 if is_id_char(letter) {
                     self.current_string.push(letter);
                 }
@@ -100,6 +121,10 @@ impl Lexer<'_> {
     // emit instatiates a Token and pushes that token along with its TokenType and text, if applicable,
     // into the Lexer's tokens vector
     fn emit(&mut self, token: TokenType) {
+        self.spans.push(Span {
+            start: self.start,
+            end: self.offset,
+        });
         if !self.current_string.is_empty() {
             let text = self.current_string.clone();
             self.tokens.push(Token {
@@ -126,6 +151,7 @@ impl Lexer<'_> {
             Some(c) if c.eq(&'"') => {
                 // Advance past quote, so it's not included in identifier.
                 self.source.next();
+                self.offset += 1;
                 true
             }
             _ => false,
@@ -143,6 +169,7 @@ impl Lexer<'_> {
             if let Some(c) = self.source.peek() {
                 if c.eq(&'"') {
                     self.source.next();
+                    self.offset += 1;
                     return Ok(TokenType::IDENTIFIER);
                 }
             }
@@ -221,8 +248,47 @@ fn lex_token(lexer: &mut Lexer) -> Option<TokenType> {
     }
 }
 
+// The number of a type variable. Type variables are either numbered, as in
+// t0, t1, ..., or lettered the way types are printed, as in A, ..., Z, A1,
+// ..., where A is t0 and A1 is t26.
+fn type_var_number(text: &str) -> Option<u64> {
+    let mut chars = text.chars();
+    match chars.next()? {
+        't' => text.trim_start_matches('t').parse::<u64>().ok(),
+        c @ 'A'..='Z' => {
+            let letter = c as u64 - 'A' as u64;
+            let rest = chars.as_str();
+            let n = if rest.is_empty() {
+                0
+            } else {
+                rest.parse::<u64>().ok()?
+            };
+            Some(letter + 26 * n)
+        }
+        _ => None,
+    }
+}
+
 struct Parser<'a> {
     tokens: Peekable<Iter<'a, Token>>,
+    // consumed counts the tokens read so far.
+    consumed: usize,
+    // depth counts the monotypes being parsed that enclose the current one.
+    depth: u64,
+}
+
+/// Error is a type expression parse error located at the last token the
+/// parser read before giving up.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Error {
+    pub location: ast::SourceLocation,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "error at {}: {}", self.location, self.message)
+    }
 }
 
 // parse passes the source text through the Lexer, It then initializes parsing
@@ -234,16 +300,64 @@ pub fn parse(source: &str) -> Result<PolyType, &'static str> {
     let tokens = lex(source);
     let mut parser = Parser {
         tokens: tokens.iter().peekable(),
+        consumed: 0,
+        depth: 0,
     };
     parser.parse_polytype()
 }
 
+/// parse_located is like parse, but requires all of the source to be
+/// consumed and locates its errors.
+pub fn parse_located(source: &str) -> Result<PolyType, Error> {
+    let (tokens, spans) = lex_spans(source);
+    let mut parser = Parser {
+        tokens: tokens.iter().peekable(),
+        consumed: 0,
+        depth: 0,
+    };
+    let result = match parser.parse_polytype() {
+        Ok(_) if parser.peek().token_type != TokenType::EOF => {
+            // Point at the first token left over.
+            parser.consumed += 1;
+            Err("unexpected input after type expression")
+        }
+        result => result,
+    };
+    result.map_err(|msg| {
+        let end = source.len() as u32;
+        let span = match parser.consumed.checked_sub(1) {
+            Some(i) => spans.get(i).cloned(),
+            None => spans.first().cloned(),
+        }
+        .unwrap_or(Span { start: end, end });
+        Error {
+            location: LineIndex::new(source).location(None, span),
+            message: String::from(msg),
+        }
+    })
+}
+
+// Parses a type expression and returns its canonical text, or the located
+// error at which it is invalid.
+#[wasm_bindgen]
+pub fn parse_type(source: &str) -> Result<JsValue, JsValue> {
+    match parse_located(source) {
+        Ok(ty) => Ok(JsValue::from(Printer::default().polytype(&ty))),
+        Err(err) => {
+            Err(JsValue::from_serde(&err).unwrap_or_else(|_| JsValue::from(err.to_string())))
+        }
+    }
+}
+
 impl Parser<'_> {
     // next grabs the next token using the Iter()'s next method and unpacks
     // the value if there are still tokens to parse
     fn next(&mut self) -> Token {
         match self.tokens.next() {
-            Some(token) => (*token).clone(),
+            Some(token) => {
+                self.consumed += 1;
+                (*token).clone()
+            }
             None => Token {
                 token_type: TokenType::EOF,
                 text: None,
@@ -323,16 +437,13 @@ impl Parser<'_> {
     // parse_var parses a single type_var
     fn parse_type_var(&mut self, token: &Token) -> Result<Tvar, &'static str> {
         match &token.text {
-            Some(text) => {
-                let num = text.trim_start_matches('t').parse::<u64>();
-                match num {
-                    Err(_e) => Err("Not a valid type variable"),
-                    Ok(num) => {
-                        self.next();
-                        Ok(Tvar(num))
-                    }
+            Some(text) => match type_var_number(text) {
+                None => Err("Not a valid type variable"),
+                Some(num) => {
+                    self.next();
+                    Ok(Tvar(num))
                 }
-            }
+            },
             None => Err("Type variable must have text"),
         }
     }
@@ -399,13 +510,39 @@ impl Parser<'_> {
             "Nullable" => Ok(Kind::Nullable),
             "Equatable" => Ok(Kind::Equatable),
             "Row" => Ok(Kind::Row),
+            "Timeable" => Ok(Kind::Timeable),
+            "Stringable" => Ok(Kind::Stringable),
+            "Lacks" => {
+                if self.next().token_type != TokenType::LEFTPAREN {
+                    return Err("Lacks must have a label in parens");
+                }
+                let label = self.next();
+                if label.token_type != TokenType::IDENTIFIER
+                    || self.next().token_type != TokenType::RIGHTPAREN
+                {
+                    return Err("Lacks must have a label in parens");
+                }
+                Ok(Kind::Lacks(label.text.unwrap_or_default()))
+            }
             _ => Err("Constraints must have a valid Kind"),
         }
     }
 
-    // parse_monotype parses a monotype
+    // parse_monotype parses a monotype, unless it nests more deeply than
+    // the expressions of the parser for Flux source are allowed to.
     fn parse_monotype(&mut self) -> Result<MonoType, &'static str> {
+        if self.depth >= Limits::DEFAULT_MAX_DEPTH {
+            return Err("Monotype is nested too deeply");
+        }
+        self.depth += 1;
         let next_token = self.peek();
+        let monotype = self.parse_monotype_of(&next_token);
+        self.depth -= 1;
+        monotype
+    }
+
+    // parse_monotype_of parses the monotype that starts with the given token
+    fn parse_monotype_of(&mut self, next_token: &Token) -> Result<MonoType, &'static str> {
         match next_token.token_type {
             TokenType::INT
             | TokenType::UINT
@@ -415,14 +552,14 @@ impl Parser<'_> {
             | TokenType::DURATION
             | TokenType::TIME
             | TokenType::REGEXP
-            | TokenType::BYTES => self.parse_primitives(&next_token),
-            TokenType::IDENTIFIER => match self.parse_type_var(&next_token) {
+            | TokenType::BYTES => self.parse_primitives(next_token),
+            TokenType::IDENTIFIER => match self.parse_type_var(next_token) {
                 Ok(tv) => Ok(MonoType::Var(tv)),
                 Err(e) => Err(e),
             },
-            TokenType::LEFTSQUAREBRAC => self.parse_array(&next_token),
-            TokenType::LEFTPAREN => self.parse_function(&next_token),
-            TokenType::LEFTCURLYBRAC => self.parse_row(&next_token),
+            TokenType::LEFTSQUAREBRAC => self.parse_array(next_token),
+            TokenType::LEFTPAREN => self.parse_function(next_token),
+            TokenType::LEFTCURLYBRAC => self.parse_row(next_token),
             _ => Err("Monotype was not in valid format"),
         }
     }
@@ -698,6 +835,37 @@ mod tests {
         );
     }
     #[test]
+    fn parse_timeable() {
+        let expr = "forall [t0, t1] where t0: Timeable, t1: Timeable (start: t0, ?stop: t1) -> int";
+        assert_eq!(
+            Ok(PolyType {
+                vars: vec![Tvar(0), Tvar(1)],
+                cons: maplit::hashmap! {
+                    Tvar(0) => vec![Kind::Timeable],
+                    Tvar(1) => vec![Kind::Timeable],
+                },
+                expr: MonoType::Fun(Box::new(Function {
+                    req: maplit::hashmap! {"start".to_string() => MonoType::Var(Tvar(0))},
+                    opt: maplit::hashmap! {"stop".to_string() => MonoType::Var(Tvar(1))},
+                    pipe: None,
+                    retn: MonoType::Int,
+                })),
+            }),
+            parse(expr)
+        );
+    }
+    #[test]
+    fn parse_lettered_vars() {
+        let t = parse("forall [A, B1] where A: Lacks(host) (x: A) -> B1").unwrap();
+        assert_eq!(vec![Tvar(0), Tvar(27)], t.vars);
+        assert_eq!(
+            maplit::hashmap! {Tvar(0) => vec![Kind::Lacks("host".to_string())]},
+            t.cons
+        );
+        assert!(parse("forall [a] a").is_err());
+        assert!(parse("forall [AB] AB").is_err());
+    }
+    #[test]
     fn parse_primitives_test() {
         let parse_text = "forall [t0] (x: t0, y: float) -> t0";
 
@@ -2553,4 +2721,42 @@ mod tests {
             toks,
         );
     }
+
+    #[test]
+    fn parse_located_errors() {
+        assert_eq!(
+            Ok(PolyType {
+                vars: Vec::new(),
+                cons: HashMap::new(),
+                expr: MonoType::Int,
+            }),
+            parse_located("forall [] int"),
+        );
+        assert_eq!(
+            "error at @1:15-1:18: unexpected input after type expression",
+            parse_located("forall [] int int").unwrap_err().to_string(),
+        );
+        let err = parse_located("forall [] (a: int,\n  b: int) int").unwrap_err();
+        assert_eq!(
+            (2, 11, 2, 14),
+            (
+                err.location.start.line,
+                err.location.start.column,
+                err.location.end.line,
+                err.location.end.column
+            ),
+        );
+    }
+
+    #[test]
+    fn parse_deeply_nested() {
+        let nested = |n| format!("forall [] {}int{}", "[".repeat(n), "]".repeat(n));
+        assert!(parse_located(&nested(100)).is_ok());
+        assert_eq!(
+            "Monotype is nested too deeply",
+            parse_located(&nested(50_000)).unwrap_err().message,
+        );
+        let functions = format!("forall [] {}int", "() -> ".repeat(50_000));
+        assert!(parse(&functions).is_err());
+    }
 }
@@ -0,0 +1,262 @@
+// Printing of types for people to read.
+//
+// Type variables are internal numbers that mean nothing to a reader, so
+// the printer names them A, B, ... in the order they are first printed.
+// Types printed by the same printer share their names, so a polytype is
+// best printed with a printer of its own.
+
+use crate::semantic::types::{Function, MonoType, PolyType, Row, Tvar};
+use std::collections::{BTreeMap, HashMap};
+
+// The width past which a function type prints its arguments one per line.
+const WIDTH: usize = 80;
+
+// The words of type expressions, which must be quoted to be labels.
+const KEYWORDS: &[&str] = &[
+    "forall", "where", "int", "uint", "float", "string", "bool", "duration", "time", "regexp",
+    "bytes",
+];
+
+#[derive(Debug, Default)]
+pub struct Printer {
+    // The position of each type variable in the order they were named.
+    names: HashMap<Tvar, usize>,
+}
+
+impl Printer {
+    // The name of a type variable, naming it if it has not been printed.
+    pub fn name(&mut self, tv: Tvar) -> String {
+        var_name(self.index(tv))
+    }
+
+    fn index(&mut self, tv: Tvar) -> usize {
+        let n = self.names.len();
+        *self.names.entry(tv).or_insert(n)
+    }
+
+    // Print a polytype in a normal form: type variables are named in the
+    // order they appear, record fields are sorted and kinds implied by the
+    // type are left out. The output parses back to an equal polytype.
+    //
+    //     forall [A, B] where A: Addable (<-tables: [{_value: A | B}]) -> A
+    //
+    pub fn polytype(&mut self, t: &PolyType) -> String {
+        let line = self.polytype_wrapped(t, false);
+        match t.expr {
+            MonoType::Fun(_) if line.len() > WIDTH => self.polytype_wrapped(t, true),
+            _ => line,
+        }
+    }
+
+    fn polytype_wrapped(&mut self, t: &PolyType, wrap: bool) -> String {
+        let expr = match &t.expr {
+            MonoType::Fun(fun) if wrap => {
+                let args = self.arguments(fun);
+                let retn = self.monotype(&fun.retn);
+                format!("(\n    {}\n) -> {}", args.join(",\n    "), retn)
+            }
+            expr => self.monotype(expr),
+        };
+        let mut vars: Vec<(usize, Tvar)> = t.vars.iter().map(|tv| (self.index(*tv), *tv)).collect();
+        vars.sort_unstable();
+        let mut cons: Vec<(usize, String)> = t
+            .explicit_cons()
+            .into_iter()
+            .map(|(tv, mut kinds)| {
+                kinds.sort();
+                kinds.dedup();
+                let kinds: Vec<String> = kinds.iter().map(|k| k.to_string()).collect();
+                let i = self.index(tv);
+                (i, format!("{}: {}", var_name(i), kinds.join(" + ")))
+            })
+            .collect();
+        cons.sort_unstable();
+        let vars: Vec<String> = vars.into_iter().map(|(i, _)| var_name(i)).collect();
+        let cons: Vec<String> = cons.into_iter().map(|(_, c)| c).collect();
+        if cons.is_empty() {
+            format!("forall [{}] {}", vars.join(", "), expr)
+        } else {
+            format!(
+                "forall [{}] where {} {}",
+                vars.join(", "),
+                cons.join(", "),
+                expr
+            )
+        }
+    }
+
+    pub fn monotype(&mut self, t: &MonoType) -> String {
+        match t {
+            MonoType::Var(tv) => self.name(*tv),
+            MonoType::Arr(arr) => format!("[{}]", self.monotype(&arr.0)),
+            MonoType::Row(row) => self.row(row),
+            MonoType::Fun(fun) => {
+                let args = self.arguments(fun);
+                format!("({}) -> {}", args.join(", "), self.monotype(&fun.retn))
+            }
+            _ => t.to_string(),
+        }
+    }
+
+    // Print a row as a record type with its fields sorted by label, and
+    // with its tail if it is open.
+    //
+    //     {a: int | b: string}
+    //     {a: int | A}
+    //
+    fn row(&mut self, row: &Row) -> String {
+        let mut fields = Vec::new();
+        let mut tail = None;
+        let mut r = row;
+        while let Row::Extension { head, tail: t } = r {
+            fields.push(head);
+            match t {
+                MonoType::Row(t) => r = t,
+                t => {
+                    tail = Some(t);
+                    break;
+                }
+            }
+        }
+        // The sort is stable, so fields with the same label stay in order.
+        fields.sort_by(|a, b| a.k.cmp(&b.k));
+        let mut fields: Vec<String> = fields
+            .into_iter()
+            .map(|p| format!("{}: {}", label(&p.k), self.monotype(&p.v)))
+            .collect();
+        if let Some(t) = tail {
+            fields.push(self.monotype(t));
+        }
+        format!("{{{}}}", fields.join(" | "))
+    }
+
+    // Print the arguments of a function with its pipe argument first, then
+    // its required and optional arguments sorted by name.
+    fn arguments(&mut self, fun: &Function) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(pipe) = &fun.pipe {
+            let k = if pipe.k == "<-" {
+                pipe.k.clone()
+            } else {
+                format!("<-{}", label(&pipe.k))
+            };
+            args.push(format!("{}: {}", k, self.monotype(&pipe.v)));
+        }
+        for (k, v) in fun.req.iter().collect::<BTreeMap<_, _>>() {
+            args.push(format!("{}: {}", label(k), self.monotype(v)));
+        }
+        for (k, v) in fun.opt.iter().collect::<BTreeMap<_, _>>() {
+            args.push(format!("?{}: {}", label(k), self.monotype(v)));
+        }
+        args
+    }
+}
+
+// The n-th name of a type variable: A to Z, then A1 to Z1, and so on.
+fn var_name(n: usize) -> String {
+    let letter = (b'A' + (n % 26) as u8) as char;
+    match n / 26 {
+        0 => letter.to_string(),
+        i => format!("{}{}", letter, i),
+    }
+}
+
+// A label, quoted if it would otherwise read as a word of the type.
+fn label(k: &str) -> String {
+    if KEYWORDS.contains(&k) {
+        format!("\"{}\"", k)
+    } else {
+        k.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::builtins::builtins;
+    use crate::semantic::parser::parse;
+
+    fn print(expr: &str) -> String {
+        Printer::default().monotype(&parse(expr).unwrap().expr)
+    }
+
+    #[test]
+    fn names_vars_in_order() {
+        assert_eq!(
+            "(x: A, y: B) -> A",
+            print("forall [t5, t2] (x: t5, y: t2) -> t5")
+        );
+        assert_eq!("[A]", print("forall [t42] [t42]"));
+        let mut p = Printer::default();
+        assert_eq!("A", p.monotype(&MonoType::Var(Tvar(7))));
+        assert_eq!("B", p.monotype(&MonoType::Var(Tvar(3))));
+        assert_eq!("A", p.monotype(&MonoType::Var(Tvar(7))));
+        assert_eq!("Z1", var_name(51));
+    }
+
+    #[test]
+    fn records_and_functions() {
+        assert_eq!(
+            "{a: int | b: string}",
+            print("forall [] {a: int | b: string}")
+        );
+        assert_eq!("{a: int | A}", print("forall [t0] {a: int | t0}"));
+        assert_eq!("{}", print("forall [] {}"));
+        assert_eq!(
+            "(<-tables: [A], fn: (r: A) -> bool, ?n: int) -> [A]",
+            print("forall [t0] (<-tables: [t0], ?n: int, fn: (r: t0) -> bool) -> [t0]")
+        );
+        assert_eq!("(<-: A) -> A", print("forall [t0] (<-: t0) -> t0"));
+    }
+
+    fn pretty(expr: &str) -> String {
+        Printer::default().polytype(&parse(expr).unwrap())
+    }
+
+    #[test]
+    fn normalizes_polytypes() {
+        assert_eq!(
+            "forall [A, B] where A: Addable + Comparable (a: A, r: {b: int | z: A | B}) -> A",
+            pretty("forall [t45, t12] where t45: Row, t12: Comparable + Addable (r: {z: t12 | b: int | t45}, a: t12) -> t12")
+        );
+        assert_eq!("forall [] int", pretty("forall [] int"));
+        assert_eq!(
+            "forall [A] where A: Lacks(host) + Row [A]",
+            pretty("forall [t0] where t0: Row + Lacks(host) [t0]")
+        );
+        assert_eq!(
+            r#"forall [] {"time": time}"#,
+            pretty(r#"forall [] {"time": time}"#)
+        );
+    }
+
+    #[test]
+    fn wraps_long_signatures() {
+        assert_eq!(
+            "forall [A, B] (
+    <-tables: [{_start: time | _stop: time | _time: time | _value: float | A}],
+    ?isCounter: bool,
+    ?isRate: bool
+) -> [{_value: float | B}]",
+            pretty("forall [t0, t1] (<-tables: [{_start: time | _stop: time | _time: time | _value: float | t0}], ?isCounter: bool, ?isRate: bool) -> [{_value: float | t1}]")
+        );
+    }
+
+    #[test]
+    fn parses_back() {
+        for (path, pkg) in builtins().iter() {
+            for (name, expr) in pkg {
+                let want = parse(expr).unwrap();
+                let printed = Printer::default().polytype(&want);
+                assert_eq!(
+                    Ok(want),
+                    parse(&printed),
+                    "{}.{} printed as {}",
+                    path,
+                    name,
+                    printed
+                );
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::semantic::types::{MonoType, Tvar};
+use crate::semantic::types::{Array, Function, MonoType, Property, Row, Tvar};
 use std::collections::HashMap;
 
 // A substitution defines a function that takes a monotype as input
@@ -57,3 +57,134 @@ pub trait Substitutable {
     fn apply(self, sub: &Substitution) -> Self;
     fn free_vars(&self) -> Vec<Tvar>;
 }
+
+// A TvarStore records the type variable bindings discovered while solving
+// a set of constraints. It is a union-find structure over type variables:
+// a type variable is bound either to another type variable, joining the
+// two into one set, or to a monotype. The last type variable along a chain
+// of links represents its set, and any kind constraints on the set are kept
+// under it.
+//
+// Bound types are stored as they were unified and followed lazily, so a new
+// binding never rewrites the bindings made before it.
+//
+#[derive(Debug, Default)]
+pub struct TvarStore {
+    bindings: HashMap<Tvar, MonoType>,
+}
+
+impl TvarStore {
+    pub fn new() -> TvarStore {
+        TvarStore::default()
+    }
+
+    // Bind an unbound representative type variable to a monotype.
+    pub fn bind(&mut self, tv: Tvar, t: MonoType) {
+        self.bindings.insert(tv, t);
+    }
+
+    // Find the representative of the set containing tv, pointing every
+    // type variable along the way directly at it.
+    pub fn find(&mut self, tv: Tvar) -> Tvar {
+        let root = self.root(tv);
+        let mut tv = tv;
+        while tv != root {
+            match self.bindings.insert(tv, MonoType::Var(root)) {
+                Some(MonoType::Var(next)) => tv = next,
+                _ => break,
+            }
+        }
+        root
+    }
+
+    // Find the representative of the set containing tv without
+    // compressing the path to it.
+    pub fn root(&self, mut tv: Tvar) -> Tvar {
+        while let Some(MonoType::Var(next)) = self.bindings.get(&tv) {
+            tv = *next;
+        }
+        tv
+    }
+
+    // The monotype bound to the set containing tv, if any.
+    pub fn lookup(&self, tv: Tvar) -> Option<&MonoType> {
+        self.bindings.get(&self.root(tv))
+    }
+
+    // Replace a type variable at the top of a monotype with its
+    // representative, or with the monotype its set is bound to.
+    pub fn resolve(&mut self, t: MonoType) -> MonoType {
+        match t {
+            MonoType::Var(tv) => {
+                let root = self.find(tv);
+                match self.bindings.get(&root) {
+                    Some(t) => t.clone(),
+                    None => MonoType::Var(root),
+                }
+            }
+            t => t,
+        }
+    }
+
+    // Apply every binding in the store to a monotype.
+    pub fn apply(&self, t: &MonoType) -> MonoType {
+        match t {
+            MonoType::Var(tv) => {
+                let root = self.root(*tv);
+                match self.bindings.get(&root) {
+                    Some(t) => self.apply(t),
+                    None => MonoType::Var(root),
+                }
+            }
+            MonoType::Arr(arr) => MonoType::Arr(Box::new(Array(self.apply(&arr.0)))),
+            MonoType::Row(row) => MonoType::Row(Box::new(self.apply_row(row))),
+            MonoType::Fun(fun) => MonoType::Fun(Box::new(self.apply_fun(fun))),
+            t => t.clone(),
+        }
+    }
+
+    pub fn apply_row(&self, row: &Row) -> Row {
+        match row {
+            Row::Empty => Row::Empty,
+            Row::Extension { head, tail } => Row::Extension {
+                head: self.apply_property(head),
+                tail: self.apply(tail),
+            },
+        }
+    }
+
+    pub fn apply_fun(&self, fun: &Function) -> Function {
+        Function {
+            req: self.apply_args(&fun.req),
+            opt: self.apply_args(&fun.opt),
+            pipe: fun.pipe.as_ref().map(|p| self.apply_property(p)),
+            retn: self.apply(&fun.retn),
+        }
+    }
+
+    fn apply_property(&self, p: &Property) -> Property {
+        Property {
+            k: p.k.clone(),
+            v: self.apply(&p.v),
+        }
+    }
+
+    fn apply_args(&self, args: &HashMap<String, MonoType>) -> HashMap<String, MonoType> {
+        args.iter()
+            .map(|(k, v)| (k.clone(), self.apply(v)))
+            .collect()
+    }
+}
+
+// Derive the idempotent substitution equivalent to a store.
+impl From<TvarStore> for Substitution {
+    fn from(store: TvarStore) -> Substitution {
+        Substitution(
+            store
+                .bindings
+                .keys()
+                .map(|tv| (*tv, store.apply(&MonoType::Var(*tv))))
+                .collect(),
+        )
+    }
+}
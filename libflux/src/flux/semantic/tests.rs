@@ -24,12 +24,14 @@
 use std::collections::HashMap;
 
 use crate::semantic::bootstrap::build_polytype;
+use crate::semantic::builtins;
 use crate::semantic::convert::convert_with;
 use crate::semantic::env::Environment;
 use crate::semantic::fresh::Fresher;
 use crate::semantic::import::Importer;
 use crate::semantic::nodes;
 use crate::semantic::parser::parse;
+use crate::semantic::printer::Printer;
 use crate::semantic::sub::Substitutable;
 use crate::semantic::types::{MaxTvar, MonoType, PolyType};
 
@@ -223,6 +225,7 @@ macro_rules! test_infer {
 /// 1. An optional type environment (representing a prelude)
 /// 2. Optional package imports (for any import statements)
 /// 3. A flux program that will not type check
+/// 4. Optionally, the error it fails with
 ///
 /// # Example
 ///
@@ -236,7 +239,7 @@ macro_rules! test_infer {
 /// ```
 ///
 macro_rules! test_infer_err {
-    ( $(imp: $imp:expr,)? $(env: $env:expr,)? src: $src:expr $(,)? ) => {{
+    ( $(imp: $imp:expr,)? $(env: $env:expr,)? src: $src:expr $(, err: $err:expr)? $(,)? ) => {{
         #[allow(unused_mut, unused_assignments)]
         let mut imp = HashMap::new();
         $(
@@ -247,8 +250,8 @@ macro_rules! test_infer_err {
         $(
             env = $env;
         )?
-        if let Ok(env) = infer_types($src, env, imp, None) {
-            panic!(
+        match infer_types($src, env, imp, None) {
+            Ok(env) => panic!(
                 "\n\n{}\n\n{}\n",
                 "expected type error but instead inferred the following types:"
                     .red()
@@ -257,7 +260,13 @@ macro_rules! test_infer_err {
                     .iter()
                     .fold(String::new(), |acc, (name, poly)| acc
                         + &format!("\t{}: {}\n", name, poly))
-            )
+            ),
+            #[allow(unused_variables)]
+            Err(e) => {
+                $(
+                    assert_eq!($err, e.to_string());
+                )?
+            }
         }
     }};
 }
@@ -420,62 +429,26 @@ fn string_interpolation() {
     test_infer! {
         env: map![
             "name" => "forall [] string",
+            "b" => "forall [] bool",
+            "i" => "forall [] int",
+            "u" => "forall [] uint",
+            "x" => "forall [] float",
+            "d" => "forall [] duration",
+            "t" => "forall [] time",
         ],
         src: r#"
             message = "Hello, ${name}!"
+            values = "${b} ${i} ${u} ${x} ${d} ${t}"
+            f = (v) => "v = ${v}"
+            g = f(v: 1.0)
         "#,
         exp: map![
             "message" => "forall [] string",
+            "values" => "forall [] string",
+            "f" => "forall [t0] where t0: Stringable (v: t0) -> string",
+            "g" => "forall [] string",
         ],
     }
-    test_infer_err! {
-        env: map![
-            "name" => "forall [] bool",
-        ],
-        src: r#"
-            "Hello, ${name}!"
-        "#,
-    }
-    test_infer_err! {
-        env: map![
-            "name" => "forall [] int",
-        ],
-        src: r#"
-            "Hello, ${name}!"
-        "#,
-    }
-    test_infer_err! {
-        env: map![
-            "name" => "forall [] uint",
-        ],
-        src: r#"
-            "Hello, ${name}!"
-        "#,
-    }
-    test_infer_err! {
-        env: map![
-            "name" => "forall [] float",
-        ],
-        src: r#"
-            "Hello, ${name}!"
-        "#,
-    }
-    test_infer_err! {
-        env: map![
-            "name" => "forall [] duration",
-        ],
-        src: r#"
-            "Hello, ${name}!"
-        "#,
-    }
-    test_infer_err! {
-        env: map![
-            "name" => "forall [] time",
-        ],
-        src: r#"
-            "Hello, ${name}!"
-        "#,
-    }
     test_infer_err! {
         env: map![
             "name" => "forall [] regexp",
@@ -516,6 +489,12 @@ fn string_interpolation() {
             "Hello, ${name}!"
         "#,
     }
+    test_infer_err! {
+        src: r#"
+            f = (v) => "v = ${v}"
+            f(v: /a/)
+        "#,
+    }
 }
 #[test]
 fn array_lit() {
@@ -2679,16 +2658,18 @@ fn constrain_tvars() {
         src: r#"
             f = (x) => "x = ${x}"
             a = f(x: "10")
+            b = f(x: 10)
         "#,
         exp: map![
-            "f" => "forall [] (x: string) -> string",
+            "f" => "forall [t0] where t0: Stringable (x: t0) -> string",
             "a" => "forall [] string",
+            "b" => "forall [] string",
         ],
     }
     test_infer_err! {
         src: r#"
             f = (x) => "x = ${x}"
-            f(x: 10)
+            f(x: (y) => y)
         "#,
     }
 }
@@ -3122,3 +3103,385 @@ fn function_default_arguments_and_pipes() {
         ],
     }
 }
+
+// The type of a universe builtin, as it is given to the prelude.
+fn universe(name: &str) -> &'static str {
+    builtins::universe(name).unwrap()
+}
+
+fn column_change_env() -> HashMap<&'static str, &'static str> {
+    map![
+        "from" => "forall [t0] where t0: Row (bucket: string) -> [t0]",
+        "drop" => universe("drop"),
+        "duplicate" => universe("duplicate"),
+        "keep" => universe("keep"),
+        "map" => universe("map"),
+        "pivot" => universe("pivot"),
+        "range" => universe("range"),
+        "reduce" => universe("reduce"),
+        "rename" => universe("rename"),
+        "union" => universe("union"),
+    ]
+}
+
+#[test]
+fn column_changes() {
+    test_infer! {
+        env: column_change_env(),
+        src: r#"
+            data = [{_time: 2020-01-01T00:00:00Z, host: "a", _value: 1.0}]
+            a = data |> drop(columns: ["host", "region"])
+            b = data |> keep(columns: ["_value"])
+            c = data |> rename(columns: {host: "server"})
+            d = data |> duplicate(column: "_value", as: "v")
+            e = drop(tables: data, columns: [])
+        "#,
+        exp: map![
+            "data" => "forall [] [{_time: time | host: string | _value: float}]",
+            "a" => "forall [] [{_time: time | _value: float}]",
+            "b" => "forall [] [{_value: float}]",
+            "c" => "forall [] [{server: string | _time: time | _value: float}]",
+            "d" => "forall [] [{v: float | _time: time | host: string | _value: float}]",
+            "e" => "forall [] [{_time: time | host: string | _value: float}]",
+        ],
+    }
+}
+// A function that changes columns generalizes over the columns it is not
+// told about, relating those of the tables it returns to those it is given.
+#[test]
+fn column_changes_generalize() {
+    let src = r#"
+        drop_a = (t=<-) => t |> drop(columns: ["a"])
+        keep_a = (t=<-) => t |> keep(columns: ["a"])
+        rename_a = (t=<-) => t |> rename(columns: {a: "b"})
+        x = [{a: 1, c: "x"}] |> drop_a()
+        y = [{a: 1, c: "x"}] |> keep_a()
+        z = [{a: 1, c: "x"}] |> rename_a()
+    "#;
+    let env = infer_types(src, column_change_env(), HashMap::new(), None).unwrap();
+    let printed = |name: &str| Printer::default().polytype(&env.values[name]);
+    assert_eq!(
+        printed("drop_a"),
+        "forall [A, B] where B: Lacks(a) (<-t: [{a: A | B}]) -> [B]"
+    );
+    assert_eq!(
+        printed("keep_a"),
+        "forall [A, B] (<-t: [{a: A | B}]) -> [{a: A}]"
+    );
+    assert_eq!(
+        printed("rename_a"),
+        "forall [A, B] where B: Lacks(a) (<-t: [{a: A | B}]) -> [{b: A | B}]"
+    );
+    assert_eq!(printed("x"), "forall [] [{c: string}]");
+    assert_eq!(printed("y"), "forall [] [{a: int}]");
+    assert_eq!(printed("z"), "forall [] [{b: int | c: string}]");
+}
+#[test]
+fn column_changes_open_rows() {
+    test_infer! {
+        env: column_change_env(),
+        src: r#"
+            a = from(bucket: "b")
+                |> range(start: -1h)
+                |> drop(columns: ["host"])
+                |> map(fn: (r) => ({start: r._start, v: r._value}))
+            b = from(bucket: "b")
+                |> range(start: -1h, stopColumn: "end")
+                |> pivot(rowKey: ["_stop", "end"], columnKey: ["_field"], valueColumn: "_value")
+                |> map(fn: (r) => ({end: r.end, cpu: r.cpu}))
+        "#,
+        exp: map![
+            "a" => "forall [t0] [{start: time | v: t0}]",
+            "b" => "forall [t0] [{end: time | cpu: t0}]",
+        ],
+    }
+}
+#[test]
+fn column_changes_reject_removed_columns() {
+    test_infer_err! {
+        env: column_change_env(),
+        src: r#"
+            from(bucket: "b")
+                |> drop(columns: ["host"])
+                |> map(fn: (r) => ({v: r.host}))
+        "#,
+    }
+    test_infer_err! {
+        env: column_change_env(),
+        src: r#"
+            [{a: 1, b: 2}]
+                |> keep(columns: ["a"])
+                |> map(fn: (r) => ({v: r.b}))
+        "#,
+    }
+    test_infer_err! {
+        env: column_change_env(),
+        src: r#"
+            from(bucket: "b")
+                |> rename(columns: {host: "server"})
+                |> map(fn: (r) => ({v: r.host}))
+        "#,
+    }
+    test_infer_err! {
+        env: column_change_env(),
+        src: r#"
+            from(bucket: "b")
+                |> pivot(rowKey: ["_time"], columnKey: ["_field"], valueColumn: "_value")
+                |> map(fn: (r) => ({v: r._value}))
+        "#,
+    }
+    test_infer_err! {
+        env: column_change_env(),
+        src: r#"
+            from(bucket: "b")
+                |> drop(columns: ["host"])
+                |> map(fn: (r) => ({r with v: 1}))
+                |> map(fn: (r) => ({v: r.host}))
+        "#,
+    }
+}
+// The streams given to union may have different columns, of which only
+// those they all have are known.
+#[test]
+fn union_streams() {
+    test_infer! {
+        env: column_change_env(),
+        src: r#"
+            data = [{_time: 2020-01-01T00:00:00Z, host: "a", _value: 1.0}]
+            a = union(tables: [data |> drop(columns: ["host"]), data])
+            b = union(tables: [data, [{host: "b", n: 1}]])
+            c = union(tables: [data, data])
+        "#,
+        exp: map![
+            "data" => "forall [] [{_time: time | host: string | _value: float}]",
+            "a" => "forall [t0] [{_time: time | _value: float | t0}]",
+            "b" => "forall [t0] [{host: string | t0}]",
+            "c" => "forall [t0] [{_time: time | host: string | _value: float | t0}]",
+        ],
+    }
+    test_infer_err! {
+        env: column_change_env(),
+        src: r#"
+            union(tables: [[{a: 1}], [{a: "x"}]])
+        "#,
+    }
+}
+#[test]
+fn reduce_merges_accumulator() {
+    test_infer! {
+        env: column_change_env(),
+        src: r#"
+            a = [{host: "a", _value: 1}]
+                |> reduce(identity: {sum: 0, _value: 0.0}, fn: (r, accumulator) => ({
+                    sum: r._value + accumulator.sum,
+                    _value: accumulator._value,
+                }))
+            b = from(bucket: "b")
+                |> reduce(identity: {count: 0}, fn: (r, accumulator) => ({count: accumulator.count + 1}))
+                |> map(fn: (r) => ({c: r.count, h: r.host}))
+            c = (tables=<-) => tables
+                |> reduce(identity: {count: 0}, fn: (r, accumulator) => ({count: accumulator.count + 1}))
+                |> map(fn: (r) => ({h: r.host}))
+        "#,
+        exp: map![
+            "a" => "forall [] [{sum: int | _value: float | host: string}]",
+            "b" => "forall [t0] [{c: int | h: t0}]",
+            // The columns of the group key are drawn from the input, which may
+            // have those of the accumulator, as with remove.
+            "c" => "forall [t0, t1, t2] where t0: Lacks(count) (<-tables: [{count: t2 | host: t1 | t0}]) -> [{h: t1}]",
+        ],
+    }
+    test_infer_err! {
+        env: column_change_env(),
+        src: r#"
+            from(bucket: "b")
+                |> reduce(identity: {count: 0}, fn: (r, accumulator) => ({count: accumulator.count + 1}))
+                |> map(fn: (r) => ({c: r.count + 1.0}))
+        "#,
+    }
+    // The columns after the reduction are those of its input and accumulator.
+    test_infer_err! {
+        env: column_change_env(),
+        src: r#"
+            [{host: "a", _value: 1}]
+                |> reduce(identity: {count: 0}, fn: (r, accumulator) => ({count: accumulator.count + 1}))
+                |> map(fn: (r) => ({m: r.missing}))
+        "#,
+    }
+    test_infer_err! {
+        env: column_change_env(),
+        src: r#"
+            [{_value: "a"}]
+                |> reduce(identity: {sum: 0.0}, fn: (r, accumulator) => ({sum: r._value + accumulator.sum}))
+        "#,
+    }
+}
+#[test]
+fn range_times() {
+    test_infer! {
+        env: column_change_env(),
+        src: r#"
+            a = from(bucket: "b") |> range(start: -1h)
+            b = from(bucket: "b") |> range(start: 2020-01-01T00:00:00Z, stop: -5m)
+            last = (tables=<-, start) => tables |> range(start: start)
+            c = from(bucket: "b") |> last(start: -1h)
+        "#,
+        exp: map![
+            "a" => "forall [t0] where t0: Row [{_start: time | _stop: time | t0}]",
+            "b" => "forall [t0] where t0: Row [{_start: time | _stop: time | t0}]",
+            "last" => "forall [t0, t1] where t0: Timeable, t1: Row (<-tables: [t1], start: t0) -> [{_start: time | _stop: time | t1}]",
+            "c" => "forall [t0] where t0: Row [{_start: time | _stop: time | t0}]",
+        ],
+    }
+    test_infer_err! {
+        env: column_change_env(),
+        src: r#"
+            from(bucket: "b") |> range(start: "yesterday")
+        "#,
+    }
+    test_infer_err! {
+        env: column_change_env(),
+        src: r#"
+            from(bucket: "b") |> range(start: -1h, stop: 10)
+        "#,
+    }
+    test_infer_err! {
+        env: column_change_env(),
+        src: r#"
+            last = (tables=<-, start) => tables |> range(start: start)
+            from(bucket: "b") |> last(start: "yesterday")
+        "#,
+    }
+}
+#[test]
+fn column_changes_need_literal_labels() {
+    test_infer! {
+        env: column_change_env(),
+        src: r#"
+            columns = ["host"]
+            a = from(bucket: "b")
+                |> drop(columns: columns)
+                |> map(fn: (r) => ({v: r.host}))
+            drop = (tables=<-, columns) => tables
+            b = from(bucket: "b")
+                |> drop(columns: ["host"])
+                |> map(fn: (r) => ({v: r.host}))
+        "#,
+        exp: map![
+            "columns" => "forall [] [string]",
+            "a" => "forall [t0] [{v: t0}]",
+            "drop" => "forall [t0, t1] (<-tables: t0, columns: t1) -> t0",
+            "b" => "forall [t0] [{v: t0}]",
+        ],
+    }
+}
+#[test]
+fn error_trail() {
+    test_infer_err! {
+        src: r#"x = "foo" + 10"#,
+        err: "error at program@1:5-1:15: expected string but found int",
+    }
+    test_infer_err! {
+        src: r#"x = 1 < "a""#,
+        err: "error at program@1:5-1:12: expected int but found string",
+    }
+    test_infer_err! {
+        src: r#"
+            f = (r) => r.a + 1
+            x = f(r: {a: "a"})
+        "#,
+        err: "error at program@3:17-3:31: expected int but found string in field a of argument r",
+    }
+    test_infer_err! {
+        src: r#"
+            f = (fn) => fn(r: 1) + 1
+            x = f(fn: (r) => "a")
+        "#,
+        err: "error at program@3:17-3:34: expected int but found string in return value of argument fn",
+    }
+    test_infer_err! {
+        src: r#"
+            f = (a) => a[0] + 1
+            x = f(a: ["a"])
+        "#,
+        err: "error at program@3:17-3:28: expected int but found string in array element of argument a",
+    }
+}
+#[test]
+fn error_hints() {
+    test_infer_err! {
+        env: map![
+            "f" => "forall [t0] (<-tables: [t0]) -> [t0]",
+        ],
+        src: "x = f()",
+        err: "error at program@1:5-1:8: missing pipe argument tables (did you forget to pipe into the call with |>?)",
+    }
+    test_infer_err! {
+        src: r#"
+            f = (count) => count + 1
+            x = f(cuont: 1)
+        "#,
+        err: "error at program@3:17-3:28: found unexpected argument cuont (did you mean count?)",
+    }
+    test_infer_err! {
+        src: r#"
+            r = {value: 1}
+            x = r.valeu
+        "#,
+        err: "error at program@3:17-3:24: missing field valeu (similar to field value)",
+    }
+    test_infer_err! {
+        src: r#"
+            f = (r) => r.value
+            x = f(r: {valeu: 1})
+        "#,
+        err: "error at program@3:17-3:33: missing field value in argument r (similar to field valeu)",
+    }
+    test_infer_err! {
+        src: r#"
+            f = (r) => r.value
+            x = f(r: {a: 1})
+        "#,
+        err: "error at program@3:17-3:29: missing field value in argument r",
+    }
+}
+#[test]
+fn test_statements() {
+    test_infer! {
+        env: map![
+            "load" => "forall [t0] (csv: string) -> [t0]",
+        ],
+        src: r#"
+            t = (table=<-) => table
+            test ok = () => ({input: load(csv: "a"), want: load(csv: "b"), fn: t})
+        "#,
+        exp: map![
+            "t" => "forall [t0] (<-table: t0) -> t0",
+            "ok" => "forall [t0, t1, t2] () -> {input: [t0] | want: [t1] | fn: (<-table: t2) -> t2}",
+        ],
+    }
+    test_infer_err! {
+        src: r#"
+            test t = () => ({input: 1, fn: (table=<-) => table})
+        "#,
+        err: "error at program@2:13-2:65: missing field want",
+    }
+    test_infer_err! {
+        src: r#"
+            test t = () => ({input: 1, want: 1, fn: (tables) => tables})
+        "#,
+        err: "error at program@2:13-2:73: missing pipe argument in field fn (did you forget to pipe into the call with |>?)",
+    }
+    test_infer_err! {
+        src: r#"
+            test t = () => ({input: 1, want: "a", fn: (table=<-) => table})
+        "#,
+        err: "error at program@2:13-2:76: expected string but found int in return value of field fn",
+    }
+    test_infer_err! {
+        src: r#"
+            test t = (x) => ({input: x, want: x, fn: (table=<-) => table})
+        "#,
+        err: "error at program@2:13-2:75: found unexpected argument x",
+    }
+}
@@ -1,7 +1,11 @@
+use crate::ast;
+use crate::limits;
 use crate::semantic::fresh::{Fresh, Fresher};
-use crate::semantic::sub::{Substitutable, Substitution};
+use crate::semantic::printer::Printer;
+use crate::semantic::sub::{Substitutable, Substitution, TvarStore};
 
 use std::{
+    cell::RefCell,
     cmp,
     collections::{BTreeMap, BTreeSet, HashMap},
     fmt,
@@ -49,6 +53,10 @@ impl PartialEq for PolyType {
         let mut a = self.clone().fresh(&mut f, &mut HashMap::new());
         let mut b = poly.clone().fresh(&mut g, &mut HashMap::new());
 
+        // Types are equal whether or not they spell out implied kinds.
+        a.cons = a.explicit_cons();
+        b.cons = b.explicit_cons();
+
         a.vars.sort();
         b.vars.sort();
 
@@ -100,6 +108,28 @@ impl PolyType {
             .collect::<Vec<_>>()
             .join(", ")
     }
+    // The constraints of the type without the kinds implied by the type
+    // itself. A type variable that is the tail of a row can only be a row,
+    // so a Row kind on it is implied.
+    pub fn explicit_cons(&self) -> HashMap<Tvar, Vec<Kind>> {
+        let mut tails = Vec::new();
+        self.expr.row_tails(&mut tails);
+        self.cons
+            .iter()
+            .filter_map(|(tv, kinds)| {
+                let kinds: Vec<Kind> = kinds
+                    .iter()
+                    .filter(|k| **k != Kind::Row || !tails.contains(tv))
+                    .cloned()
+                    .collect();
+                if kinds.is_empty() {
+                    None
+                } else {
+                    Some((*tv, kinds))
+                }
+            })
+            .collect()
+    }
     fn display_kinds(kinds: &[Kind]) -> String {
         kinds
             .iter()
@@ -126,45 +156,215 @@ pub fn minus<T: PartialEq>(vars: &[T], mut from: Vec<T>) -> Vec<T> {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Error {
     msg: String,
+    // What the types that failed to unify are part of, innermost first,
+    // for example a field of a record given as an argument.
+    trail: Vec<String>,
+    label: Option<Label>,
+    hint: Option<String>,
+    // The location of the expression whose constraint failed, boxed to
+    // keep the error small.
+    loc: Option<Box<ast::SourceLocation>>,
+}
+
+// The label an error is about.
+#[derive(Debug, Clone, PartialEq)]
+enum Label {
+    // Expected but not found, which may be misspelled.
+    Missing(String),
+    // Found, but a schema change removed it.
+    Removed(String),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str(&self.msg)
+        if let Some(loc) = &self.loc {
+            write!(f, "error at {}: ", loc)?;
+        }
+        f.write_str(&self.msg)?;
+        if !self.trail.is_empty() {
+            write!(f, " in {}", self.trail.join(" of "))?;
+        }
+        if let Some(hint) = &self.hint {
+            write!(f, " ({})", hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<limits::Error> for Error {
+    fn from(err: limits::Error) -> Error {
+        Error::new(err.to_string())
     }
 }
 
 impl Error {
+    fn new(msg: String) -> Error {
+        Error {
+            msg,
+            trail: Vec::new(),
+            label: None,
+            hint: None,
+            loc: None,
+        }
+    }
     // An error can occur when the unification of two types
     // contradicts what we have already inferred about the types
     // in our program.
-    fn cannot_unify<T, S>(t: &T, with: &S) -> Error
-    where
-        T: fmt::Display,
-        S: fmt::Display,
-    {
-        Error {
-            msg: format!("cannot unify {} with {}", t, with),
-        }
+    fn cannot_unify(found: &MonoType, expected: &MonoType) -> Error {
+        let mut p = Printer::default();
+        Error::new(format!(
+            "expected {} but found {}",
+            p.monotype(expected),
+            p.monotype(found)
+        ))
     }
     // An error can occur if we constrain a type with a kind to
     // which it does not belong.
-    fn cannot_constrain<T: fmt::Display>(t: &T, with: Kind) -> Error {
-        Error {
-            msg: format!("{} is not of kind {}", t, with,),
-        }
+    fn cannot_constrain(t: &MonoType, with: Kind) -> Error {
+        Error::new(format!(
+            "{} is not of kind {}",
+            Printer::default().monotype(t),
+            with
+        ))
+    }
+    // An error can occur if a record has a property that a
+    // schema change has removed from it.
+    fn unexpected_label(t: &MonoType, label: &str) -> Error {
+        let mut err = Error::new(format!(
+            "record {} must not have label {}",
+            Printer::default().monotype(t),
+            label
+        ));
+        err.label = Some(Label::Removed(label.to_string()));
+        err
     }
     // An error can occur if we attempt to unify a type variable
     // with a monotype that contains that same type variable.
-    fn occurs_check<T: fmt::Display>(tv: Tvar, t: T) -> Error {
-        Error {
-            msg: format!("type variable {} occurs in {}", tv, t),
+    fn occurs_check(tv: Tvar, t: &MonoType) -> Error {
+        let mut p = Printer::default();
+        let var = p.name(tv);
+        Error::new(format!("type variable {} occurs in {}", var, p.monotype(t)))
+    }
+    // An error can occur if one record has a field the other does not.
+    fn missing_field(label: &str, found: bool) -> Error {
+        let mut err = if found {
+            Error::new(format!("found unexpected field {}", label))
+        } else {
+            Error::new(format!("missing field {}", label))
+        };
+        err.label = Some(Label::Missing(label.to_string()));
+        err
+    }
+    // An error can occur if a function is called with arguments that are
+    // not its parameters.
+    fn missing_argument(label: &str, pipe: bool) -> Error {
+        if !pipe {
+            return Error::new(format!("missing required argument {}", label));
+        }
+        let mut err = if label == "<-" {
+            Error::new("missing pipe argument".to_string())
+        } else {
+            Error::new(format!("missing pipe argument {}", label))
+        };
+        err.hint = Some("did you forget to pipe into the call with |>?".to_string());
+        err
+    }
+    fn unexpected_argument(label: &str, params: &[&str]) -> Error {
+        let mut err = Error::new(format!("found unexpected argument {}", label));
+        err.hint = closest(label, params.iter().copied()).map(|p| format!("did you mean {}?", p));
+        err
+    }
+
+    // Record that the error occurred within a part of the unified types.
+    fn within(mut self, context: String) -> Error {
+        self.trail.push(context);
+        self
+    }
+
+    // Point out the label closest to a missing one, which may be its
+    // misspelling or the other way around. Outer records know more labels,
+    // so their suggestion replaces that of inner ones.
+    fn suggest<'a>(mut self, labels: impl Iterator<Item = &'a str>) -> Error {
+        if let Some(Label::Missing(missing)) = &self.label {
+            if let Some(label) = closest(missing, labels.filter(|l| *l != missing)) {
+                self.hint = Some(format!("similar to field {}", label));
+            }
+        }
+        self
+    }
+
+    // The label a record must not have, if that is the error.
+    pub fn removed_label(&self) -> Option<&str> {
+        match &self.label {
+            Some(Label::Removed(label)) => Some(label),
+            _ => None,
+        }
+    }
+
+    // Point out the call that removed the label a record must not have.
+    pub fn removed_by(mut self, call: &str, loc: Option<&ast::SourceLocation>) -> Error {
+        if let Some(Label::Removed(label)) = &self.label {
+            self.hint = Some(match loc {
+                Some(loc) => format!("column {} was removed by {} at {}", label, call, loc),
+                None => format!("column {} was removed by {}", label, call),
+            });
+        }
+        self
+    }
+
+    // Attach the location of the expression the error comes from, unless
+    // it already has one.
+    pub fn at(mut self, loc: &ast::SourceLocation) -> Error {
+        if self.loc.is_none() {
+            self.loc = Some(Box::new(loc.clone()));
         }
+        self
     }
 }
 
+// The candidate closest to a possibly misspelled label, if any is close.
+fn closest<'a>(label: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let max = match label.chars().count() {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    };
+    candidates
+        .map(|c| (edit_distance(label, c), c))
+        .filter(|(d, _)| *d <= max)
+        .min()
+        .map(|(_, c)| c)
+}
+
+// The edit distance between two strings, counting the insertion, removal
+// or substitution of a character, or the swap of two adjacent ones, as
+// one edit each.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // d[i][j] is the distance between the first i characters of a and the
+    // first j characters of b.
+    let mut d: Vec<Vec<usize>> = (0..=a.len()).map(|i| vec![i; b.len() + 1]).collect();
+    for (j, x) in d[0].iter_mut().enumerate() {
+        *x = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut n = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                n = n.min(d[i - 2][j - 2] + 1);
+            }
+            d[i][j] = n;
+        }
+    }
+    d[a.len()][b.len()]
+}
+
 // Kind represents a class or family of types
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Kind {
     Addable,
     Subtractable,
@@ -175,6 +375,15 @@ pub enum Kind {
     Nullable,
     Row,
     Negatable,
+    // Timeable is the family of times and durations, for parameters
+    // that accept either an absolute or a relative time.
+    Timeable,
+    // Stringable is the family of basic types, which can be
+    // interpolated into a string.
+    Stringable,
+    // Lacks is the family of rows without the given label.
+    // It records the columns a schema change has removed.
+    Lacks(String),
 }
 
 impl fmt::Display for Kind {
@@ -189,6 +398,9 @@ impl fmt::Display for Kind {
             Kind::Nullable => f.write_str("Nullable"),
             Kind::Row => f.write_str("Row"),
             Kind::Negatable => f.write_str("Negatable"),
+            Kind::Timeable => f.write_str("Timeable"),
+            Kind::Stringable => f.write_str("Stringable"),
+            Kind::Lacks(label) => write!(f, "Lacks({})", label),
         }
     }
 }
@@ -222,6 +434,9 @@ pub enum MonoType {
     Time,
     Regexp,
     Bytes,
+    // Label is a string literal used to name a record property.
+    // It is a string, but unlike other strings its value is known.
+    Label(String),
     Var(Tvar),
     Arr(Box<Array>),
     Row(Box<Row>),
@@ -240,6 +455,7 @@ impl fmt::Display for MonoType {
             MonoType::Time => f.write_str("time"),
             MonoType::Regexp => f.write_str("regexp"),
             MonoType::Bytes => f.write_str("bytes"),
+            MonoType::Label(label) => write!(f, "{:?}", label),
             MonoType::Var(var) => var.fmt(f),
             MonoType::Arr(arr) => arr.fmt(f),
             MonoType::Row(obj) => obj.fmt(f),
@@ -259,7 +475,8 @@ impl Substitutable for MonoType {
             | MonoType::Duration
             | MonoType::Time
             | MonoType::Regexp
-            | MonoType::Bytes => self,
+            | MonoType::Bytes
+            | MonoType::Label(_) => self,
             MonoType::Var(tvr) => sub.apply(tvr),
             MonoType::Arr(arr) => MonoType::Arr(Box::new(arr.apply(sub))),
             MonoType::Row(obj) => MonoType::Row(Box::new(obj.apply(sub))),
@@ -276,7 +493,8 @@ impl Substitutable for MonoType {
             | MonoType::Duration
             | MonoType::Time
             | MonoType::Regexp
-            | MonoType::Bytes => Vec::new(),
+            | MonoType::Bytes
+            | MonoType::Label(_) => Vec::new(),
             MonoType::Var(tvr) => vec![*tvr],
             MonoType::Arr(arr) => arr.free_vars(),
             MonoType::Row(obj) => obj.free_vars(),
@@ -296,7 +514,8 @@ impl MaxTvar for MonoType {
             | MonoType::Duration
             | MonoType::Time
             | MonoType::Regexp
-            | MonoType::Bytes => Tvar(0),
+            | MonoType::Bytes
+            | MonoType::Label(_) => Tvar(0),
             MonoType::Var(tvr) => tvr.max_tvar(),
             MonoType::Arr(arr) => arr.max_tvar(),
             MonoType::Row(obj) => obj.max_tvar(),
@@ -312,13 +531,55 @@ impl From<Row> for MonoType {
 }
 
 impl MonoType {
+    // Collect the type variables that are the tails of rows in the type.
+    fn row_tails(&self, tails: &mut Vec<Tvar>) {
+        match self {
+            MonoType::Arr(arr) => arr.0.row_tails(tails),
+            MonoType::Row(row) => {
+                if let Row::Extension { head, tail } = row.as_ref() {
+                    head.v.row_tails(tails);
+                    match tail {
+                        MonoType::Var(tv) => tails.push(*tv),
+                        t => t.row_tails(tails),
+                    }
+                }
+            }
+            MonoType::Fun(fun) => {
+                for t in fun.req.values().chain(fun.opt.values()) {
+                    t.row_tails(tails);
+                }
+                if let Some(pipe) = &fun.pipe {
+                    pipe.v.row_tails(tails);
+                }
+                fun.retn.row_tails(tails);
+            }
+            _ => (),
+        }
+    }
+
+    // Unify two monotypes, returning the substitution that makes them equal.
     pub fn unify(
         self,
         with: Self,
         cons: &mut TvarKinds,
         f: &mut Fresher,
     ) -> Result<Substitution, Error> {
-        match (self, with) {
+        let mut sub = TvarStore::new();
+        self.unify_in(with, &mut sub, cons, f)?;
+        Ok(sub.into())
+    }
+
+    // Unify two monotypes, recording the bindings it produces in a store
+    // of bindings made so far.
+    pub fn unify_in(
+        self,
+        with: Self,
+        sub: &mut TvarStore,
+        cons: &mut TvarKinds,
+        f: &mut Fresher,
+    ) -> Result<(), Error> {
+        f.step()?;
+        match (sub.resolve(self), sub.resolve(with)) {
             (MonoType::Bool, MonoType::Bool)
             | (MonoType::Int, MonoType::Int)
             | (MonoType::Uint, MonoType::Uint)
@@ -327,21 +588,45 @@ impl MonoType {
             | (MonoType::Duration, MonoType::Duration)
             | (MonoType::Time, MonoType::Time)
             | (MonoType::Regexp, MonoType::Regexp)
-            | (MonoType::Bytes, MonoType::Bytes) => Ok(Substitution::empty()),
-            (MonoType::Var(tv), t) => tv.unify(t, cons),
-            (t, MonoType::Var(tv)) => tv.unify(t, cons),
-            (MonoType::Arr(t), MonoType::Arr(s)) => t.unify(*s, cons, f),
-            (MonoType::Row(t), MonoType::Row(s)) => t.unify(*s, cons, f),
-            (MonoType::Fun(t), MonoType::Fun(s)) => t.unify(*s, cons, f),
-            (t, with) => Err(Error::cannot_unify(&t, &with)),
+            | (MonoType::Bytes, MonoType::Bytes)
+            // A label is a string, so it unifies with any string.
+            | (MonoType::Label(_), MonoType::String)
+            | (MonoType::String, MonoType::Label(_)) => Ok(()),
+            (MonoType::Label(a), MonoType::Label(b)) if a == b => Ok(()),
+            (MonoType::Var(tv), t) => tv.unify(t, sub, cons),
+            (t, MonoType::Var(tv)) => tv.unify(t, sub, cons),
+            (MonoType::Arr(t), MonoType::Arr(s)) => t.unify(*s, sub, cons, f),
+            (MonoType::Row(t), MonoType::Row(s)) => {
+                let mut labels = t.labels();
+                labels.append(&mut s.labels());
+                t.unify(*s, sub, cons, f)
+                    .map_err(|e| e.suggest(labels.iter().map(String::as_str)))
+            }
+            (MonoType::Fun(t), MonoType::Fun(s)) => t.unify(*s, sub, cons, f),
+            (t, with) => Err(Error::cannot_unify(
+                &sub.apply(&t),
+                &sub.apply(&with),
+            )),
         }
     }
 
     pub fn constrain(self, with: Kind, cons: &mut TvarKinds) -> Result<Substitution, Error> {
-        match self {
+        let mut sub = TvarStore::new();
+        self.constrain_in(with, &mut sub, cons)?;
+        Ok(sub.into())
+    }
+
+    pub fn constrain_in(
+        self,
+        with: Kind,
+        sub: &mut TvarStore,
+        cons: &mut TvarKinds,
+    ) -> Result<(), Error> {
+        let t = sub.resolve(self);
+        match t {
             MonoType::Bool => match with {
-                Kind::Equatable | Kind::Nullable => Ok(Substitution::empty()),
-                _ => Err(Error::cannot_constrain(&self, with)),
+                Kind::Equatable | Kind::Nullable | Kind::Stringable => Ok(()),
+                _ => Err(Error::cannot_constrain(&t, with)),
             },
             MonoType::Int => match with {
                 Kind::Addable
@@ -351,8 +636,9 @@ impl MonoType {
                 | Kind::Comparable
                 | Kind::Equatable
                 | Kind::Nullable
-                | Kind::Negatable => Ok(Substitution::empty()),
-                _ => Err(Error::cannot_constrain(&self, with)),
+                | Kind::Negatable
+                | Kind::Stringable => Ok(()),
+                _ => Err(Error::cannot_constrain(&t, with)),
             },
             MonoType::Uint => match with {
                 Kind::Addable
@@ -362,8 +648,9 @@ impl MonoType {
                 | Kind::Comparable
                 | Kind::Equatable
                 | Kind::Nullable
-                | Kind::Negatable => Ok(Substitution::empty()),
-                _ => Err(Error::cannot_constrain(&self, with)),
+                | Kind::Negatable
+                | Kind::Stringable => Ok(()),
+                _ => Err(Error::cannot_constrain(&t, with)),
             },
             MonoType::Float => match with {
                 Kind::Addable
@@ -373,41 +660,53 @@ impl MonoType {
                 | Kind::Comparable
                 | Kind::Equatable
                 | Kind::Nullable
-                | Kind::Negatable => Ok(Substitution::empty()),
-                _ => Err(Error::cannot_constrain(&self, with)),
+                | Kind::Negatable
+                | Kind::Stringable => Ok(()),
+                _ => Err(Error::cannot_constrain(&t, with)),
             },
-            MonoType::String => match with {
-                Kind::Addable | Kind::Comparable | Kind::Equatable | Kind::Nullable => {
-                    Ok(Substitution::empty())
-                }
-                _ => Err(Error::cannot_constrain(&self, with)),
+            MonoType::String | MonoType::Label(_) => match with {
+                Kind::Addable
+                | Kind::Comparable
+                | Kind::Equatable
+                | Kind::Nullable
+                | Kind::Stringable => Ok(()),
+                _ => Err(Error::cannot_constrain(&t, with)),
             },
             MonoType::Duration => match with {
-                Kind::Comparable | Kind::Equatable | Kind::Nullable | Kind::Negatable => {
-                    Ok(Substitution::empty())
-                }
-                _ => Err(Error::cannot_constrain(&self, with)),
+                Kind::Comparable
+                | Kind::Equatable
+                | Kind::Nullable
+                | Kind::Negatable
+                | Kind::Timeable
+                | Kind::Stringable => Ok(()),
+                _ => Err(Error::cannot_constrain(&t, with)),
             },
             MonoType::Time => match with {
-                Kind::Comparable | Kind::Equatable | Kind::Nullable => Ok(Substitution::empty()),
-                _ => Err(Error::cannot_constrain(&self, with)),
+                Kind::Comparable
+                | Kind::Equatable
+                | Kind::Nullable
+                | Kind::Timeable
+                | Kind::Stringable => Ok(()),
+                _ => Err(Error::cannot_constrain(&t, with)),
             },
-            MonoType::Regexp => Err(Error::cannot_constrain(&self, with)),
+            MonoType::Regexp => Err(Error::cannot_constrain(&t, with)),
             MonoType::Bytes => match with {
-                Kind::Equatable => Ok(Substitution::empty()),
-                _ => Err(Error::cannot_constrain(&self, with)),
+                Kind::Equatable => Ok(()),
+                _ => Err(Error::cannot_constrain(&t, with)),
             },
             MonoType::Var(tvr) => {
                 tvr.constrain(with, cons);
-                Ok(Substitution::empty())
+                Ok(())
             }
-            MonoType::Arr(arr) => arr.constrain(with, cons),
-            MonoType::Row(obj) => obj.constrain(with, cons),
-            MonoType::Fun(fun) => fun.constrain(with, cons),
+            MonoType::Arr(arr) => arr.constrain(with, sub, cons),
+            MonoType::Row(obj) => obj.constrain(with, sub, cons),
+            MonoType::Fun(fun) => fun.constrain(with, sub, cons),
         }
     }
 
-    fn contains(&self, tv: Tvar) -> bool {
+    // Whether a type variable occurs in a monotype once the bindings in
+    // the store are applied to it.
+    fn contains(&self, tv: Tvar, sub: &TvarStore) -> bool {
         match self {
             MonoType::Bool
             | MonoType::Int
@@ -417,11 +716,15 @@ impl MonoType {
             | MonoType::Duration
             | MonoType::Time
             | MonoType::Regexp
-            | MonoType::Bytes => false,
-            MonoType::Var(tvr) => tv == *tvr,
-            MonoType::Arr(arr) => arr.contains(tv),
-            MonoType::Row(row) => row.contains(tv),
-            MonoType::Fun(fun) => fun.contains(tv),
+            | MonoType::Bytes
+            | MonoType::Label(_) => false,
+            MonoType::Var(tvr) => match sub.lookup(*tvr) {
+                Some(t) => t.contains(tv, sub),
+                None => tv == sub.root(*tvr),
+            },
+            MonoType::Arr(arr) => arr.contains(tv, sub),
+            MonoType::Row(row) => row.contains(tv, sub),
+            MonoType::Fun(fun) => fun.contains(tv, sub),
         }
     }
 }
@@ -444,35 +747,36 @@ impl MaxTvar for Tvar {
 }
 
 impl Tvar {
-    fn unify(self, with: MonoType, cons: &mut TvarKinds) -> Result<Substitution, Error> {
+    // Unify an unbound representative type variable with a monotype.
+    fn unify(self, with: MonoType, sub: &mut TvarStore, cons: &mut TvarKinds) -> Result<(), Error> {
         match with {
             MonoType::Var(tv) => {
                 if self == tv {
-                    // The empty substitution will always
-                    // unify a type variable with itself.
-                    Ok(Substitution::empty())
+                    // A type variable always unifies with itself.
+                    Ok(())
                 } else {
                     // Unify two distinct type variables.
                     // This will update the kind constraints
                     // associated with these type variables.
-                    self.unify_with_tvar(tv, cons)
+                    self.unify_with_tvar(tv, sub, cons);
+                    Ok(())
                 }
             }
             _ => {
-                if with.contains(self) {
+                if with.contains(self, sub) {
                     // Invalid recursive type
-                    Err(Error::occurs_check(self, with))
+                    Err(Error::occurs_check(self, &sub.apply(&with)))
                 } else {
                     // Unify a type variable with a monotype.
                     // The monotype must satisify any
                     // constraints placed on the type variable.
-                    self.unify_with_type(with, cons)
+                    self.unify_with_type(with, sub, cons)
                 }
             }
         }
     }
 
-    fn unify_with_tvar(self, tv: Tvar, cons: &mut TvarKinds) -> Result<Substitution, Error> {
+    fn unify_with_tvar(self, tv: Tvar, sub: &mut TvarStore, cons: &mut TvarKinds) {
         // Kind constraints for both type variables
         let kinds = union(
             cons.remove(&self).unwrap_or_default(),
@@ -481,25 +785,28 @@ impl Tvar {
         if !kinds.is_empty() {
             cons.insert(tv, kinds);
         }
-        Ok(Substitution::from(
-            maplit::hashmap! {self => MonoType::Var(tv)},
-        ))
+        sub.bind(self, MonoType::Var(tv));
     }
 
-    fn unify_with_type(self, t: MonoType, cons: &mut TvarKinds) -> Result<Substitution, Error> {
-        let sub = Substitution::from(maplit::hashmap! {self => t.clone()});
+    fn unify_with_type(
+        self,
+        t: MonoType,
+        sub: &mut TvarStore,
+        cons: &mut TvarKinds,
+    ) -> Result<(), Error> {
         match cons.remove(&self) {
-            None => Ok(sub),
-            Some(kinds) => Ok(sub.merge(kinds.into_iter().try_fold(
-                Substitution::empty(),
-                |sub, kind| {
+            None => sub.bind(self, t),
+            Some(kinds) => {
+                sub.bind(self, t.clone());
+                for kind in kinds {
                     // The monotype that is being unified with the
                     // tvar must be constrained with the same kinds
                     // as that of the tvar.
-                    Ok(sub.merge(t.clone().constrain(kind, cons)?))
-                },
-            )?)),
+                    t.clone().constrain_in(kind, sub, cons)?;
+                }
+            }
         }
+        Ok(())
     }
 
     fn constrain(self, with: Kind, cons: &mut TvarKinds) {
@@ -545,21 +852,27 @@ impl Array {
     fn unify(
         self,
         with: Self,
+        sub: &mut TvarStore,
         cons: &mut TvarKinds,
         f: &mut Fresher,
-    ) -> Result<Substitution, Error> {
-        self.0.unify(with.0, cons, f)
+    ) -> Result<(), Error> {
+        self.0
+            .unify_in(with.0, sub, cons, f)
+            .map_err(|e| e.within("array element".to_string()))
     }
 
-    fn constrain(self, with: Kind, cons: &mut TvarKinds) -> Result<Substitution, Error> {
+    fn constrain(self, with: Kind, sub: &mut TvarStore, cons: &mut TvarKinds) -> Result<(), Error> {
         match with {
-            Kind::Equatable => self.0.constrain(with, cons),
-            _ => Err(Error::cannot_constrain(&self, with)),
+            Kind::Equatable => self.0.constrain_in(with, sub, cons),
+            _ => Err(Error::cannot_constrain(
+                &MonoType::Arr(Box::new(Array(sub.apply(&self.0)))),
+                with,
+            )),
         }
     }
 
-    fn contains(&self, tv: Tvar) -> bool {
-        self.0.contains(tv)
+    fn contains(&self, tv: Tvar, sub: &TvarStore) -> bool {
+        self.0.contains(tv, sub)
     }
 }
 
@@ -687,11 +1000,12 @@ impl Row {
     fn unify(
         self,
         with: Self,
+        sub: &mut TvarStore,
         cons: &mut TvarKinds,
         fresher: &mut Fresher,
-    ) -> Result<Substitution, Error> {
-        match (self, with) {
-            (Row::Empty, Row::Empty) => Ok(Substitution::empty()),
+    ) -> Result<(), Error> {
+        match (self.resolve_tail(sub), with.resolve_tail(sub)) {
+            (Row::Empty, Row::Empty) => Ok(()),
             (
                 Row::Extension {
                     head: Property { k: a, v: t },
@@ -712,34 +1026,33 @@ impl Row {
                             head: Property { k: b, v: u },
                             tail: MonoType::Var(r),
                         };
-                        Err(Error::cannot_unify(&l, &r))
+                        Err(Error::cannot_unify(
+                            &MonoType::from(sub.apply_row(&l)),
+                            &MonoType::from(sub.apply_row(&r)),
+                        ))
                     } else {
-                        t.unify(u, cons, fresher)
+                        t.unify_in(u, sub, cons, fresher)
+                            .map_err(|e| e.within(format!("field {}", a)))
                     }
                 } else if a == b {
-                    let lv = MonoType::Var(l);
-                    let rv = MonoType::Var(r);
-                    let sub = t.unify(u, cons, fresher)?;
-                    apply_then_unify(lv, rv, sub, cons, fresher)
+                    t.unify_in(u, sub, cons, fresher)
+                        .map_err(|e| e.within(format!("field {}", a)))?;
+                    MonoType::Var(l).unify_in(MonoType::Var(r), sub, cons, fresher)
                 } else {
                     let var = fresher.fresh();
-                    let sub = l.unify(
+                    l.unify(
                         MonoType::from(Row::Extension {
                             head: Property { k: b, v: u },
                             tail: MonoType::Var(var),
                         }),
-                        cons,
-                    )?;
-                    apply_then_unify(
-                        MonoType::Var(r),
-                        MonoType::from(Row::Extension {
-                            head: Property { k: a, v: t },
-                            tail: MonoType::Var(var),
-                        }),
                         sub,
                         cons,
-                        fresher,
-                    )
+                    )?;
+                    MonoType::from(Row::Extension {
+                        head: Property { k: a, v: t },
+                        tail: MonoType::Var(var),
+                    })
+                    .unify_in(MonoType::Var(r), sub, cons, fresher)
                 }
             }
             (
@@ -753,59 +1066,85 @@ impl Row {
                 },
             ) => {
                 if a == b {
-                    let sub = t.unify(u, cons, fresher)?;
-                    apply_then_unify(l, r, sub, cons, fresher)
+                    t.unify_in(u, sub, cons, fresher)
+                        .map_err(|e| e.within(format!("field {}", a)))?;
+                    l.unify_in(r, sub, cons, fresher)
                 } else {
                     let var = fresher.fresh();
-                    let sub = l.unify(
+                    l.unify_in(
                         MonoType::from(Row::Extension {
                             head: Property { k: b, v: u },
                             tail: MonoType::Var(var),
                         }),
-                        cons,
-                        fresher,
-                    )?;
-                    apply_then_unify(
-                        r,
-                        MonoType::from(Row::Extension {
-                            head: Property { k: a, v: t },
-                            tail: MonoType::Var(var),
-                        }),
                         sub,
                         cons,
                         fresher,
-                    )
+                    )?;
+                    MonoType::from(Row::Extension {
+                        head: Property { k: a, v: t },
+                        tail: MonoType::Var(var),
+                    })
+                    .unify_in(r, sub, cons, fresher)
                 }
             }
-            (Row::Empty, Row::Extension { head, tail }) => Err(Error::cannot_unify(
-                &Row::Empty,
-                &Row::Extension { head, tail },
-            )),
-            (Row::Extension { head, tail }, Row::Empty) => Err(Error::cannot_unify(
-                &Row::Empty,
-                &Row::Extension { head, tail },
-            )),
+            (Row::Empty, Row::Extension { head, .. }) => Err(Error::missing_field(&head.k, false)),
+            (Row::Extension { head, .. }, Row::Empty) => Err(Error::missing_field(&head.k, true)),
+        }
+    }
+
+    // Resolve the tail of a row so that its shape can be matched on.
+    fn resolve_tail(self, sub: &mut TvarStore) -> Row {
+        match self {
+            Row::Extension { head, tail } => Row::Extension {
+                head,
+                tail: sub.resolve(tail),
+            },
+            Row::Empty => Row::Empty,
+        }
+    }
+
+    // The labels of the properties known to be in the row.
+    pub fn labels(&self) -> Vec<String> {
+        let mut labels = Vec::new();
+        let mut r = self;
+        while let Row::Extension { head, tail } = r {
+            labels.push(head.k.clone());
+            match tail {
+                MonoType::Row(t) => r = t,
+                _ => break,
+            }
         }
+        labels
     }
 
-    fn constrain(self, with: Kind, cons: &mut TvarKinds) -> Result<Substitution, Error> {
+    fn constrain(self, with: Kind, sub: &mut TvarStore, cons: &mut TvarKinds) -> Result<(), Error> {
         match with {
-            Kind::Row => Ok(Substitution::empty()),
+            Kind::Row => Ok(()),
             Kind::Equatable => match self {
-                Row::Empty => Ok(Substitution::empty()),
+                Row::Empty => Ok(()),
                 Row::Extension { head, tail } => {
-                    let sub = head.v.constrain(with, cons)?;
-                    Ok(sub.merge(tail.constrain(with, cons)?))
+                    head.v.constrain_in(with.clone(), sub, cons)?;
+                    tail.constrain_in(with, sub, cons)
                 }
             },
-            _ => Err(Error::cannot_constrain(&self, with)),
+            Kind::Lacks(ref label) => match self {
+                Row::Empty => Ok(()),
+                Row::Extension { ref head, .. } if &head.k == label => Err(
+                    Error::unexpected_label(&MonoType::from(sub.apply_row(&self)), label),
+                ),
+                Row::Extension { tail, .. } => tail.constrain_in(with, sub, cons),
+            },
+            _ => Err(Error::cannot_constrain(
+                &MonoType::from(sub.apply_row(&self)),
+                with,
+            )),
         }
     }
 
-    fn contains(&self, tv: Tvar) -> bool {
+    fn contains(&self, tv: Tvar, sub: &TvarStore) -> bool {
         match self {
             Row::Empty => false,
-            Row::Extension { head, tail } => head.v.contains(tv) && tail.contains(tv),
+            Row::Extension { head, tail } => head.v.contains(tv, sub) || tail.contains(tv, sub),
         }
     }
 
@@ -824,23 +1163,312 @@ impl Row {
     }
 }
 
-// Unification requires that the current substitution be applied
-// to both sides of a constraint before unifying.
-//
-// This helper function applies a substitution to a constraint
-// before unifying the two types. Note the substitution produced
-// from unification is merged with input substitution before it
-// is returned.
-//
-fn apply_then_unify(
-    l: MonoType,
-    r: MonoType,
-    sub: Substitution,
+// RowChange is a change a function makes to the schema of the tables it
+// is given, that is, to the properties of their rows.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowChange {
+    Remove(Vec<String>),
+    Keep(Vec<String>),
+    // Pairs of old and new labels.
+    Rename(Vec<(String, String)>),
+    // Copies the property with the first label to the second label.
+    Copy(String, String),
+    Add(Vec<Property>),
+    // Keeps the first labels, the row key, and removes the second ones.
+    Pivot(Vec<String>, Vec<String>),
+    // Merges a record over the rows, the way `with` extends a record.
+    Merge(MonoType),
+    // Unions the tables with other streams of tables. Only the properties
+    // the rows of all of them have are known.
+    Union(Vec<MonoType>),
+}
+
+// ChangeVars are the type variables a change makes up when it is solved,
+// kept by what they stand for. A change is solved again with the
+// constraints of the program it is in, and it must then make up the same
+// variables, as they may have been generalized in the type of a variable.
+#[derive(Debug, Default)]
+pub struct ChangeVars(RefCell<HashMap<String, Tvar>>);
+
+impl ChangeVars {
+    fn var(&self, key: &str, f: &mut Fresher) -> MonoType {
+        let tv = *self
+            .0
+            .borrow_mut()
+            .entry(key.to_string())
+            .or_insert_with(|| f.fresh());
+        MonoType::Var(tv)
+    }
+}
+
+// The variables made up by a change do not make it a different change.
+impl PartialEq for ChangeVars {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl RowChange {
+    // Whether the change removes the labeled property from the rows.
+    pub fn removes(&self, label: &str) -> bool {
+        match self {
+            RowChange::Remove(labels) => labels.iter().any(|l| l == label),
+            RowChange::Rename(renames) => {
+                renames.iter().any(|(from, _)| from == label)
+                    && !renames.iter().any(|(_, to)| to == label)
+            }
+            RowChange::Pivot(_, columns) => columns.iter().any(|c| c == label),
+            _ => false,
+        }
+    }
+
+    // Solve the change by unifying `result` with the tables `tables` become.
+    //
+    // The change is made to the properties known of the rows when it is
+    // solved, so it must be solved after the constraints on `tables`.
+    pub fn solve_in(
+        self,
+        tables: MonoType,
+        result: MonoType,
+        sub: &mut TvarStore,
+        cons: &mut TvarKinds,
+        vars: &ChangeVars,
+        f: &mut Fresher,
+    ) -> Result<(), Error> {
+        let elt = vars.var("elt", f);
+        tables.unify_in(MonoType::Arr(Box::new(Array(elt.clone()))), sub, cons, f)?;
+        let row = sub.apply(&elt);
+        let changed = match self {
+            RowChange::Remove(labels) => Row::remove(row, &labels, sub, cons, vars, f)?,
+            RowChange::Keep(labels) => Row::keep(row, &labels, sub, cons, vars, f)?,
+            RowChange::Rename(renames) => Row::rename(row, &renames, sub, cons, vars, f)?,
+            RowChange::Copy(from, to) => {
+                let (props, _) =
+                    expose(row.clone(), std::slice::from_ref(&from), sub, cons, vars, f)?;
+                match props.into_iter().find(|p| p.k == from) {
+                    Some(p) => Row::from_properties(vec![Property { k: to, v: p.v }], row),
+                    None => return Err(Error::missing_field(&from, false)),
+                }
+            }
+            RowChange::Add(props) => Row::from_properties(props, row),
+            RowChange::Pivot(row_key, columns) => {
+                let (props, _) = expose(row, &row_key, sub, cons, vars, f)?;
+                let kept = props
+                    .into_iter()
+                    .filter(|p| row_key.contains(&p.k))
+                    .collect();
+                // The columns it adds are named by values, so they are unknown.
+                let added = lacking(vars.var("added", f), &columns, cons);
+                Row::from_properties(kept, added)
+            }
+            RowChange::Merge(with) => {
+                let with = sub.apply(&with);
+                Row::merge(row, with, sub, cons, vars, f)?
+            }
+            RowChange::Union(others) => {
+                let (mut props, _) = split(row);
+                for (i, other) in others.into_iter().enumerate() {
+                    let elt = vars.var(&format!("elt{}", i + 1), f);
+                    other.unify_in(MonoType::Arr(Box::new(Array(elt.clone()))), sub, cons, f)?;
+                    let (known, _) = split(sub.apply(&elt));
+                    props.retain(|p| known.iter().any(|q| q.k == p.k));
+                    for p in &props {
+                        if let Some(q) = known.iter().find(|q| q.k == p.k) {
+                            p.v.clone().unify_in(q.v.clone(), sub, cons, f)?;
+                        }
+                    }
+                }
+                // The properties some of them have are unknown.
+                Row::from_properties(props, vars.var("others", f))
+            }
+        };
+        result.unify_in(MonoType::Arr(Box::new(Array(changed))), sub, cons, f)
+    }
+}
+
+impl Row {
+    // Extend a tail with properties, the first property being the head.
+    pub fn from_properties(props: Vec<Property>, tail: MonoType) -> MonoType {
+        props.into_iter().rev().fold(tail, |tail, head| {
+            MonoType::from(Row::Extension { head, tail })
+        })
+    }
+
+    // Remove labeled properties from a row. The properties may be in the
+    // unknown part of an open row, so its row variable is bound to a row of
+    // them, whose own variable is left to lack them.
+    //
+    //     {a: t | b: u | 'r} -> {b: u | 'r} where 'r lacks a
+    //     'r -> 's where 'r = {a: 'u | 's} and 's lacks a
+    //
+    pub fn remove(
+        row: MonoType,
+        labels: &[String],
+        sub: &mut TvarStore,
+        cons: &mut TvarKinds,
+        vars: &ChangeVars,
+        f: &mut Fresher,
+    ) -> Result<MonoType, Error> {
+        let (props, tail) = expose(row, labels, sub, cons, vars, f)?;
+        let props = props
+            .into_iter()
+            .filter(|p| !labels.contains(&p.k))
+            .collect();
+        Ok(Row::from_properties(props, lacking(tail, labels, cons)))
+    }
+
+    // Keep only the labeled properties of a row. The rest of the row is
+    // dropped, so the result is closed.
+    //
+    //     {a: t | b: u | 'r} -> {a: t}
+    //     'r -> {a: 'u} where 'r = {a: 'u | 's}
+    //
+    pub fn keep(
+        row: MonoType,
+        labels: &[String],
+        sub: &mut TvarStore,
+        cons: &mut TvarKinds,
+        vars: &ChangeVars,
+        f: &mut Fresher,
+    ) -> Result<MonoType, Error> {
+        let (props, _) = expose(row, labels, sub, cons, vars, f)?;
+        let kept = props
+            .into_iter()
+            .filter(|p| labels.contains(&p.k))
+            .collect();
+        Ok(Row::from_properties(kept, MonoType::from(Row::Empty)))
+    }
+
+    // Rename labeled properties of a row, given pairs of old and new labels.
+    //
+    //     {a: t | 'r} -> {b: t | 'r} where 'r lacks a
+    //     'r -> {b: 'u | 's} where 'r = {a: 'u | 's} and 's lacks a
+    //
+    pub fn rename(
+        row: MonoType,
+        renames: &[(String, String)],
+        sub: &mut TvarStore,
+        cons: &mut TvarKinds,
+        vars: &ChangeVars,
+        f: &mut Fresher,
+    ) -> Result<MonoType, Error> {
+        let from = renames.iter().map(|(a, _)| a.clone()).collect::<Vec<_>>();
+        let (props, tail) = expose(row, &from, sub, cons, vars, f)?;
+        let mut renamed: Vec<Property> = Vec::new();
+        for (from, to) in renames {
+            if let Some(p) = props.iter().find(|p| &p.k == from) {
+                renamed.push(Property {
+                    k: to.clone(),
+                    v: p.v.clone(),
+                });
+            }
+        }
+        let kept = props
+            .into_iter()
+            .filter(|p| !from.contains(&p.k))
+            .collect::<Vec<_>>();
+        renamed.extend(kept);
+        Ok(Row::from_properties(renamed, lacking(tail, &from, cons)))
+    }
+
+    // Merge a record over a row, the way `with` extends a record: its
+    // properties replace those of the row with the same labels, which are
+    // removed from the row as remove does, so the rest of the row is that of
+    // the row given. When the record is open, the properties it may replace
+    // are unknown, so the result is the record itself, its tail included.
+    //
+    //     {a: t | b: u | 'r} with {a: v} -> {a: v | b: u | 'r} where 'r lacks a
+    //     'r with {a: v} -> {a: v | 's} where 'r = {a: 'u | 's} and 's lacks a
+    //     {a: t | b: u | 'r} with {a: v | 'q} -> {a: v | 'q}
+    //
+    pub fn merge(
+        row: MonoType,
+        with: MonoType,
+        sub: &mut TvarStore,
+        cons: &mut TvarKinds,
+        vars: &ChangeVars,
+        f: &mut Fresher,
+    ) -> Result<MonoType, Error> {
+        let (props, tail) = split(with);
+        if tail != MonoType::from(Row::Empty) {
+            return Ok(Row::from_properties(props, tail));
+        }
+        let labels = props.iter().map(|p| p.k.clone()).collect::<Vec<_>>();
+        let rest = Row::remove(row, &labels, sub, cons, vars, f)?;
+        Ok(Row::from_properties(props, rest))
+    }
+}
+
+// The properties known of a row, from its head, and its tail, which is
+// either an empty row or a row variable.
+fn split(row: MonoType) -> (Vec<Property>, MonoType) {
+    let mut props = Vec::new();
+    let mut t = row;
+    while let MonoType::Row(r) = t {
+        match *r {
+            Row::Empty => return (props, MonoType::from(Row::Empty)),
+            Row::Extension { head, tail } => {
+                props.push(head);
+                t = tail;
+            }
+        }
+    }
+    (props, t)
+}
+
+// Make the labeled properties of a row known, returning its properties and
+// its tail. The variable of an open row is bound to a row of the labels it
+// may have, with fresh types, extending a fresh variable. This ties what is
+// made of the row to the row itself, rather than to a fresh row unrelated
+// to it. A label the variable lacks is not one of its properties.
+fn expose(
+    row: MonoType,
+    labels: &[String],
+    sub: &mut TvarStore,
     cons: &mut TvarKinds,
+    vars: &ChangeVars,
     f: &mut Fresher,
-) -> Result<Substitution, Error> {
-    let s = l.apply(&sub).unify(r.apply(&sub), cons, f)?;
-    Ok(sub.merge(s))
+) -> Result<(Vec<Property>, MonoType), Error> {
+    let (mut props, tail) = split(row);
+    let tv = match tail {
+        MonoType::Var(tv) => tv,
+        tail => return Ok((props, tail)),
+    };
+    let mut missing: Vec<Property> = Vec::new();
+    for label in labels {
+        let lacked = cons
+            .get(&tv)
+            .is_some_and(|kinds| kinds.contains(&Kind::Lacks(label.clone())));
+        let known = props.iter().chain(missing.iter()).any(|p| &p.k == label);
+        if !lacked && !known {
+            missing.push(Property {
+                k: label.clone(),
+                v: vars.var(&format!(".{}", label), f),
+            });
+        }
+    }
+    if missing.is_empty() {
+        return Ok((props, tail));
+    }
+    let rest = vars.var("rest", f);
+    tail.unify_in(
+        Row::from_properties(missing.clone(), rest.clone()),
+        sub,
+        cons,
+        f,
+    )?;
+    props.extend(missing);
+    Ok((props, rest))
+}
+
+// Constrain the variable of an open row to lack the labels.
+fn lacking(tail: MonoType, labels: &[String], cons: &mut TvarKinds) -> MonoType {
+    if let MonoType::Var(tv) = tail {
+        for label in labels {
+            tv.constrain(Kind::Lacks(label.clone()), cons);
+        }
+    }
+    tail
 }
 
 // A key value pair representing a property type in a record
@@ -1053,107 +1681,163 @@ impl Function {
     fn unify(
         self,
         with: Self,
+        sub: &mut TvarStore,
         cons: &mut TvarKinds,
         fresh: &mut Fresher,
-    ) -> Result<Substitution, Error> {
+    ) -> Result<(), Error> {
+        // Check the arguments before taking the functions apart so that
+        // the error describes them as they were given.
+        self.check_args(&with, sub)?;
         // Some aliasing for coherence with the doc.
         let mut f = self;
         let mut g = with;
-        // Pre-compute error while f and g are not consumed.
-        let err = Error::cannot_unify(&f, &g);
         // Fix pipe arguments:
         // Make them required arguments with the correct name.
         match (f.pipe, g.pipe) {
-            // Both functions have pipe arguments.
+            // Both functions have pipe arguments. At least one is unnamed or
+            // they are both named with the same name. This means they should
+            // match. Enforce this condition by inserting the pipe argument
+            // into the required ones with the same key.
             (Some(fp), Some(gp)) => {
-                if fp.k != "<-" && gp.k != "<-" && fp.k != gp.k {
-                    // Both are named and the name differs, fail unification.
-                    return Err(err);
-                } else {
-                    // At least one is unnamed or they are both named with the same name.
-                    // This means they should match. Enforce this condition by inserting
-                    // the pipe argument into the required ones with the same key.
-                    f.req.insert(fp.k.clone(), fp.v);
-                    g.req.insert(fp.k.clone(), gp.v);
-                }
+                f.req.insert(fp.k.clone(), fp.v);
+                g.req.insert(fp.k, gp.v);
             }
-            // F has a pipe argument and g does not.
+            // F has a named pipe argument and g does not, simply put it into
+            // the required ones.
             (Some(fp), None) => {
-                if fp.k == "<-" {
-                    // The pipe argument is unnamed and g does not have one.
-                    // Fail unification.
-                    return Err(err);
-                } else {
-                    // This is a named argument, simply put it into the required ones.
-                    f.req.insert(fp.k, fp.v);
-                }
+                f.req.insert(fp.k, fp.v);
             }
-            // G has a pipe argument and f does not.
+            // G has a named pipe argument and f does not.
             (None, Some(gp)) => {
-                if gp.k == "<-" {
-                    // The pipe argument is unnamed and f does not have one.
-                    // Fail unification.
-                    return Err(err);
-                } else {
-                    // This is a named argument, simply put it into the required ones.
-                    g.req.insert(gp.k, gp.v);
-                }
+                g.req.insert(gp.k, gp.v);
             }
             // Nothing to do.
             (None, None) => (),
         }
-        // Now that f has not been consumed yet, check that every required argument in g is in f too.
-        for (arg_name, _) in g.req.iter() {
-            if !f.req.contains_key(arg_name) && !f.opt.contains_key(arg_name) {
-                return Err(err);
-            }
-        }
-        let mut sub = Substitution::empty();
         // Unify f's required arguments.
         for (arg_name, f_arg_type) in f.req.into_iter() {
             if let Some(g_arg_type) = g.req.remove(&arg_name) {
                 // The required argument is in g's required arguments.
-                sub = apply_then_unify(f_arg_type, g_arg_type, sub, cons, fresh)?;
+                f_arg_type
+                    .unify_in(g_arg_type, sub, cons, fresh)
+                    .map_err(|e| e.within(argument(&arg_name)))?;
             } else if let Some(g_arg_type) = g.opt.remove(&arg_name) {
                 // The required argument is in g's optional arguments.
-                sub = apply_then_unify(f_arg_type, g_arg_type, sub, cons, fresh)?;
-            } else {
-                return Err(err);
+                f_arg_type
+                    .unify_in(g_arg_type, sub, cons, fresh)
+                    .map_err(|e| e.within(argument(&arg_name)))?;
             }
         }
         // Unify f's optional arguments.
         for (arg_name, f_arg_type) in f.opt.into_iter() {
             if let Some(g_arg_type) = g.req.remove(&arg_name) {
                 // The optional argument is in g's required arguments.
-                sub = apply_then_unify(f_arg_type, g_arg_type, sub, cons, fresh)?;
+                f_arg_type
+                    .unify_in(g_arg_type, sub, cons, fresh)
+                    .map_err(|e| e.within(argument(&arg_name)))?;
             } else if let Some(g_arg_type) = g.opt.remove(&arg_name) {
                 // The optional argument is in g's optional arguments.
-                sub = apply_then_unify(f_arg_type, g_arg_type, sub, cons, fresh)?;
+                f_arg_type
+                    .unify_in(g_arg_type, sub, cons, fresh)
+                    .map_err(|e| e.within(argument(&arg_name)))?;
             }
         }
         // Unify return types.
-        sub = apply_then_unify(f.retn, g.retn, sub, cons, fresh)?;
-        Ok(sub)
+        f.retn
+            .unify_in(g.retn, sub, cons, fresh)
+            .map_err(|e| e.within("return value".to_string()))
+    }
+
+    // Check that the arguments of two functions are compatible: their pipe
+    // arguments match and every required argument of each function is an
+    // argument of the other.
+    fn check_args(&self, g: &Function, sub: &TvarStore) -> Result<(), Error> {
+        let f = self;
+        let mismatch = || {
+            Error::cannot_unify(
+                &MonoType::Fun(Box::new(sub.apply_fun(f))),
+                &MonoType::Fun(Box::new(sub.apply_fun(g))),
+            )
+        };
+        let (f_pipe, g_pipe) = match (&f.pipe, &g.pipe) {
+            (Some(fp), Some(gp)) => {
+                if fp.k != "<-" && gp.k != "<-" && fp.k != gp.k {
+                    // Both are named and the name differs.
+                    return Err(mismatch());
+                }
+                (Some(fp.k.as_str()), Some(fp.k.as_str()))
+            }
+            (Some(fp), None) => {
+                if fp.k == "<-" {
+                    // The pipe argument is unnamed and g does not have one.
+                    return Err(Error::new("found unexpected pipe argument".to_string()));
+                }
+                (Some(fp.k.as_str()), None)
+            }
+            (None, Some(gp)) => {
+                if gp.k == "<-" {
+                    // The pipe argument is unnamed and f does not have one.
+                    return Err(Error::missing_argument(&gp.k, true));
+                }
+                (None, Some(gp.k.as_str()))
+            }
+            (None, None) => (None, None),
+        };
+        let f_has = |k: &str| f.req.contains_key(k) || f.opt.contains_key(k) || f_pipe == Some(k);
+        let g_has = |k: &str| g.req.contains_key(k) || g.opt.contains_key(k) || g_pipe == Some(k);
+        // Check the arguments in order of name so that the error is the same
+        // each time. An unexpected argument is reported first since it may
+        // be a misspelling of the missing one.
+        let mut given: Vec<&str> = f.req.keys().map(String::as_str).chain(f_pipe).collect();
+        given.sort_unstable();
+        if let Some(k) = given.into_iter().find(|k| !g_has(k)) {
+            let params: Vec<&str> = g
+                .req
+                .keys()
+                .chain(g.opt.keys())
+                .map(String::as_str)
+                .chain(g_pipe)
+                .collect();
+            return Err(Error::unexpected_argument(k, &params));
+        }
+        let mut required: Vec<&str> = g.req.keys().map(String::as_str).chain(g_pipe).collect();
+        required.sort_unstable();
+        if let Some(k) = required.into_iter().find(|k| !f_has(k)) {
+            return Err(Error::missing_argument(k, Some(k) == g_pipe));
+        }
+        Ok(())
     }
 
-    fn constrain(self, with: Kind, _: &mut TvarKinds) -> Result<Substitution, Error> {
-        Err(Error::cannot_constrain(&self, with))
+    fn constrain(self, with: Kind, sub: &mut TvarStore, _: &mut TvarKinds) -> Result<(), Error> {
+        Err(Error::cannot_constrain(
+            &MonoType::Fun(Box::new(sub.apply_fun(&self))),
+            with,
+        ))
     }
 
-    fn contains(&self, tv: Tvar) -> bool {
+    fn contains(&self, tv: Tvar, sub: &TvarStore) -> bool {
         if let Some(pipe) = &self.pipe {
-            self.req.values().any(|t| t.contains(tv))
-                || self.opt.values().any(|t| t.contains(tv))
-                || pipe.v.contains(tv)
-                || self.retn.contains(tv)
+            self.req.values().any(|t| t.contains(tv, sub))
+                || self.opt.values().any(|t| t.contains(tv, sub))
+                || pipe.v.contains(tv, sub)
+                || self.retn.contains(tv, sub)
         } else {
-            self.req.values().any(|t| t.contains(tv))
-                || self.opt.values().any(|t| t.contains(tv))
-                || self.retn.contains(tv)
+            self.req.values().any(|t| t.contains(tv, sub))
+                || self.opt.values().any(|t| t.contains(tv, sub))
+                || self.retn.contains(tv, sub)
         }
     }
 }
 
+// How an argument is named in the trail of an error.
+fn argument(name: &str) -> String {
+    if name == "<-" {
+        "pipe argument".to_string()
+    } else {
+        format!("argument {}", name)
+    }
+}
+
 pub trait MaxTvar {
     fn max_tvar(&self) -> Tvar;
 }
@@ -1783,7 +2467,7 @@ mod tests {
     }
     #[test]
     fn constrain_rows() {
-        let sub = Row::Empty.constrain(Kind::Row, &mut HashMap::new());
+        let sub = MonoType::from(Row::Empty).constrain(Kind::Row, &mut HashMap::new());
         assert_eq!(Ok(Substitution::empty()), sub);
 
         let unallowable_cons = vec![
@@ -1795,11 +2479,53 @@ mod tests {
             Kind::Nullable,
         ];
         for c in unallowable_cons {
-            let sub = Row::Empty.constrain(c, &mut HashMap::new());
-            assert_eq!(Err(Error::cannot_constrain(&Row::Empty, c)), sub);
+            let sub = MonoType::from(Row::Empty).constrain(c.clone(), &mut HashMap::new());
+            assert_eq!(
+                Err(Error::cannot_constrain(&MonoType::from(Row::Empty), c)),
+                sub
+            );
+        }
+    }
+    #[test]
+    fn constrain_timeable() {
+        for t in [MonoType::Time, MonoType::Duration].iter().cloned() {
+            let sub = t.constrain(Kind::Timeable, &mut HashMap::new());
+            assert_eq!(Ok(Substitution::empty()), sub);
+        }
+        for t in [MonoType::Int, MonoType::String, MonoType::Regexp].iter() {
+            let sub = t.clone().constrain(Kind::Timeable, &mut HashMap::new());
+            assert_eq!(Err(Error::cannot_constrain(t, Kind::Timeable)), sub);
         }
     }
     #[test]
+    fn constrain_stringable() {
+        let basic = [
+            MonoType::Bool,
+            MonoType::Int,
+            MonoType::Uint,
+            MonoType::Float,
+            MonoType::String,
+            MonoType::Duration,
+            MonoType::Time,
+        ];
+        for t in basic.iter().cloned() {
+            let sub = t.constrain(Kind::Stringable, &mut HashMap::new());
+            assert_eq!(Ok(Substitution::empty()), sub);
+        }
+        let err = MonoType::Regexp
+            .constrain(Kind::Stringable, &mut HashMap::new())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "regexp is not of kind Stringable");
+        let err = MonoType::Bytes
+            .constrain(Kind::Stringable, &mut HashMap::new())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "bytes is not of kind Stringable");
+        let err = MonoType::Arr(Box::new(Array(MonoType::Int)))
+            .constrain(Kind::Stringable, &mut HashMap::new())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "[int] is not of kind Stringable");
+    }
+    #[test]
     fn unify_error() {
         let err = MonoType::Int
             .unify(
@@ -1810,10 +2536,307 @@ mod tests {
             .unwrap_err();
         assert_eq!(
             err.to_string(),
-            String::from("cannot unify int with string"),
+            String::from("expected string but found int"),
         );
     }
     #[test]
+    fn unify_labels() {
+        let label = |l: &str| MonoType::Label(l.to_string());
+        let mut f = Fresher::default();
+        assert!(label("a")
+            .unify(label("a"), &mut HashMap::new(), &mut f)
+            .is_ok());
+        assert!(label("a")
+            .unify(MonoType::String, &mut HashMap::new(), &mut f)
+            .is_ok());
+        assert!(MonoType::String
+            .unify(label("a"), &mut HashMap::new(), &mut f)
+            .is_ok());
+        let err = label("a")
+            .unify(label("b"), &mut HashMap::new(), &mut f)
+            .unwrap_err();
+        assert_eq!(err.to_string(), r#"expected "b" but found "a""#);
+        assert!(label("a")
+            .unify(MonoType::Int, &mut HashMap::new(), &mut f)
+            .is_err());
+    }
+    #[test]
+    fn constrain_lacks() {
+        let row = |k: &str, tail: MonoType| {
+            MonoType::from(Row::Extension {
+                head: Property {
+                    k: k.to_string(),
+                    v: MonoType::Int,
+                },
+                tail,
+            })
+        };
+        let lacks = || Kind::Lacks("b".to_string());
+        let mut cons = HashMap::new();
+        let sub = row("a", MonoType::Var(Tvar(0))).constrain(lacks(), &mut cons);
+        assert_eq!(Ok(Substitution::empty()), sub);
+        assert_eq!(Some(&vec![lacks()]), cons.get(&Tvar(0)));
+
+        let err = row("a", row("b", MonoType::from(Row::Empty)))
+            .constrain(lacks(), &mut HashMap::new())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "record {b: int} must not have label b");
+
+        // A row variable that lacks a label cannot be extended by it.
+        let mut cons = HashMap::new();
+        cons.insert(Tvar(0), vec![lacks()]);
+        let err = MonoType::Var(Tvar(0))
+            .unify(
+                row("b", MonoType::Var(Tvar(1))),
+                &mut cons,
+                &mut Fresher::from(2),
+            )
+            .unwrap_err();
+        assert_eq!(err.to_string(), "record {b: int | A} must not have label b");
+    }
+    #[test]
+    fn change_rows() {
+        let labels = |ls: &[&str]| ls.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        let prop = |k: &str, v: MonoType| Property {
+            k: k.to_string(),
+            v,
+        };
+        let closed = Row::from_properties(
+            vec![
+                prop("a", MonoType::Int),
+                prop("b", MonoType::Float),
+                prop("c", MonoType::String),
+            ],
+            MonoType::from(Row::Empty),
+        );
+        let open = Row::from_properties(vec![prop("a", MonoType::Int)], MonoType::Var(Tvar(0)));
+        let mut f = Fresher::from(10);
+        let mut sub = TvarStore::new();
+        let mut cons = HashMap::new();
+
+        let t = Row::remove(
+            closed.clone(),
+            &labels(&["b", "x"]),
+            &mut sub,
+            &mut cons,
+            &ChangeVars::default(),
+            &mut f,
+        );
+        assert_eq!("{a:int | c:string | {}}", t.unwrap().to_string());
+        let t = Row::keep(
+            closed.clone(),
+            &labels(&["c", "a"]),
+            &mut sub,
+            &mut cons,
+            &ChangeVars::default(),
+            &mut f,
+        );
+        assert_eq!("{a:int | c:string | {}}", t.unwrap().to_string());
+        let renames = vec![
+            ("a".to_string(), "x".to_string()),
+            ("y".to_string(), "z".to_string()),
+        ];
+        let t = Row::rename(
+            closed,
+            &renames,
+            &mut sub,
+            &mut cons,
+            &ChangeVars::default(),
+            &mut f,
+        );
+        assert_eq!("{x:int | b:float | c:string | {}}", t.unwrap().to_string());
+        assert!(cons.is_empty());
+
+        // The variable of an open row is bound to a row of the labels it
+        // may have, so the result is made of the row.
+        let t = Row::remove(
+            open.clone(),
+            &labels(&["a"]),
+            &mut sub,
+            &mut cons,
+            &ChangeVars::default(),
+            &mut f,
+        );
+        assert_eq!("t0", t.unwrap().to_string());
+        let t = Row::remove(
+            open.clone(),
+            &labels(&["b"]),
+            &mut sub,
+            &mut cons,
+            &ChangeVars::default(),
+            &mut f,
+        );
+        assert_eq!("{a:int | t11}", t.unwrap().to_string());
+        assert_eq!(
+            "{b:t10 | t11}",
+            sub.apply(&MonoType::Var(Tvar(0))).to_string()
+        );
+        let lacks = |ls: &[&str]| ls.iter().map(|l| Kind::Lacks(l.to_string())).collect();
+        assert_eq!(Some(&lacks(&["a", "b"])), cons.get(&Tvar(11)));
+
+        // A row is kept as a closed row.
+        let mut sub = TvarStore::new();
+        let mut cons = HashMap::new();
+        let t = Row::keep(
+            open.clone(),
+            &labels(&["b"]),
+            &mut sub,
+            &mut cons,
+            &ChangeVars::default(),
+            &mut f,
+        );
+        assert_eq!("{b:t12 | {}}", t.unwrap().to_string());
+        assert_eq!(
+            "{b:t12 | t13}",
+            sub.apply(&MonoType::Var(Tvar(0))).to_string()
+        );
+
+        let mut sub = TvarStore::new();
+        let mut cons = HashMap::new();
+        let t = Row::rename(
+            open.clone(),
+            &renames,
+            &mut sub,
+            &mut cons,
+            &ChangeVars::default(),
+            &mut f,
+        );
+        assert_eq!("{x:int | z:t14 | t15}", t.unwrap().to_string());
+        assert_eq!(Some(&lacks(&["a", "y"])), cons.get(&Tvar(15)));
+
+        // A label the variable already lacks is not one of its properties.
+        let mut sub = TvarStore::new();
+        let mut cons = HashMap::new();
+        cons.insert(Tvar(0), lacks(&["b"]));
+        let t = Row::remove(
+            open,
+            &labels(&["b"]),
+            &mut sub,
+            &mut cons,
+            &ChangeVars::default(),
+            &mut f,
+        );
+        assert_eq!("{a:int | t0}", t.unwrap().to_string());
+        assert_eq!("t0", sub.apply(&MonoType::Var(Tvar(0))).to_string());
+    }
+    #[test]
+    fn merge_rows() {
+        let prop = |k: &str, v: MonoType| Property {
+            k: k.to_string(),
+            v,
+        };
+        let row = Row::from_properties(
+            vec![prop("a", MonoType::Int), prop("b", MonoType::Float)],
+            MonoType::Var(Tvar(0)),
+        );
+        let with = |tail: MonoType| {
+            Row::from_properties(
+                vec![prop("a", MonoType::String), prop("c", MonoType::Bool)],
+                tail,
+            )
+        };
+        let mut sub = TvarStore::new();
+        let mut f = Fresher::from(10);
+        let mut cons = HashMap::new();
+
+        // The row variable is bound to a row of the labels it does not have,
+        // whose own variable is the rest of the row.
+        let t = Row::merge(
+            row.clone(),
+            with(MonoType::from(Row::Empty)),
+            &mut sub,
+            &mut cons,
+            &ChangeVars::default(),
+            &mut f,
+        )
+        .unwrap();
+        assert_eq!("{a:string | c:bool | b:float | t11}", t.to_string());
+        assert_eq!(
+            "{c:t10 | t11}",
+            sub.apply(&MonoType::Var(Tvar(0))).to_string()
+        );
+        let lacks = |ls: &[&str]| ls.iter().map(|l| Kind::Lacks(l.to_string())).collect();
+        assert_eq!(Some(&lacks(&["a", "c"])), cons.get(&Tvar(11)));
+
+        // An open record may replace any property of the row, so it is kept
+        // whole.
+        let t = Row::merge(
+            row,
+            with(MonoType::Var(Tvar(1))),
+            &mut sub,
+            &mut cons,
+            &ChangeVars::default(),
+            &mut f,
+        )
+        .unwrap();
+        assert_eq!("{a:string | c:bool | t1}", t.to_string());
+    }
+    #[test]
+    fn solve_change_again() {
+        // A change solved again makes up the same variables, so its result
+        // is the same.
+        let tables = MonoType::Arr(Box::new(Array(Row::from_properties(
+            vec![Property {
+                k: "a".to_string(),
+                v: MonoType::Int,
+            }],
+            MonoType::Var(Tvar(0)),
+        ))));
+        let change = RowChange::Remove(vec!["b".to_string()]);
+        let vars = ChangeVars::default();
+        let mut f = Fresher::from(10);
+        let mut solve = || {
+            let mut sub = TvarStore::new();
+            let mut cons = HashMap::new();
+            change
+                .clone()
+                .solve_in(
+                    tables.clone(),
+                    MonoType::Var(Tvar(1)),
+                    &mut sub,
+                    &mut cons,
+                    &vars,
+                    &mut f,
+                )
+                .unwrap();
+            (sub.apply(&MonoType::Var(Tvar(1))), cons)
+        };
+        let (first, first_cons) = solve();
+        let (second, second_cons) = solve();
+        assert_eq!("[{a:int | t12}]", first.to_string());
+        assert_eq!(first, second);
+        assert_eq!(first_cons, second_cons);
+    }
+    #[test]
+    fn unify_recursive_row() {
+        let row = MonoType::from(Row::Extension {
+            head: Property {
+                k: String::from("a"),
+                v: MonoType::Int,
+            },
+            tail: MonoType::Var(Tvar(0)),
+        });
+        let err = MonoType::Var(Tvar(0))
+            .unify(row, &mut HashMap::new(), &mut Fresher::default())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            String::from("type variable A occurs in {a: int | A}"),
+        );
+    }
+    #[test]
+    fn closest_label() {
+        assert_eq!(1, edit_distance("value", "valeu"));
+        assert_eq!(2, edit_distance("_value", "value_"));
+        assert_eq!(3, edit_distance("", "abc"));
+        let labels = ["_time", "_value", "host"];
+        assert_eq!(Some("_value"), closest("_valeu", labels.iter().copied()));
+        assert_eq!(Some("host"), closest("hots", labels.iter().copied()));
+        assert_eq!(None, closest("region", labels.iter().copied()));
+        // Short labels must match exactly to be suggested.
+        assert_eq!(None, closest("hs", ["h"].iter().copied()));
+    }
+    #[test]
     fn unify_tvars() {
         let sub = MonoType::Var(Tvar(0))
             .unify(
@@ -1864,13 +2887,17 @@ mod tests {
         {
             // this extends the first map with the second by generating a new one.
             let mut cons = f_cons.into_iter().chain(g_cons).collect();
-            let res = f
-                .clone()
-                .unify(*g.clone(), &mut cons, &mut Fresher::default());
+            let res = MonoType::Fun(f.clone()).unify(
+                MonoType::Fun(g.clone()),
+                &mut cons,
+                &mut Fresher::default(),
+            );
             assert!(res.is_err());
-            let res = g
-                .clone()
-                .unify(*f.clone(), &mut cons, &mut Fresher::default());
+            let res = MonoType::Fun(g.clone()).unify(
+                MonoType::Fun(f.clone()),
+                &mut cons,
+                &mut Fresher::default(),
+            );
             assert!(res.is_err());
         } else {
             panic!("the monotypes under examination are not functions");
@@ -1893,13 +2920,17 @@ mod tests {
         ) = (f, g)
         {
             let mut cons = f_cons.into_iter().chain(g_cons).collect();
-            let res = f
-                .clone()
-                .unify(*g.clone(), &mut cons, &mut Fresher::default());
+            let res = MonoType::Fun(f.clone()).unify(
+                MonoType::Fun(g.clone()),
+                &mut cons,
+                &mut Fresher::default(),
+            );
             assert!(res.is_err());
-            let res = g
-                .clone()
-                .unify(*f.clone(), &mut cons, &mut Fresher::default());
+            let res = MonoType::Fun(g.clone()).unify(
+                MonoType::Fun(f.clone()),
+                &mut cons,
+                &mut Fresher::default(),
+            );
             assert!(res.is_err());
         } else {
             panic!("the monotypes under examination are not functions");
@@ -1927,8 +2958,12 @@ mod tests {
             expr: MonoType::Fun(f),
         } = fn_type
         {
-            let sub = f
-                .unify(call_type, &mut cons, &mut Fresher::default())
+            let sub = MonoType::Fun(f)
+                .unify(
+                    MonoType::Fun(Box::new(call_type)),
+                    &mut cons,
+                    &mut Fresher::default(),
+                )
                 .unwrap();
             assert_eq!(
                 sub,
@@ -1961,7 +2996,9 @@ mod tests {
         {
             // this extends the first map with the second by generating a new one.
             let mut cons = f_cons.into_iter().chain(g_cons).collect();
-            let sub = f.unify(*g, &mut cons, &mut Fresher::default()).unwrap();
+            let sub = MonoType::Fun(f)
+                .unify(MonoType::Fun(g), &mut cons, &mut Fresher::default())
+                .unwrap();
             assert_eq!(
                 sub,
                 Substitution::from(maplit::hashmap! {
@@ -1997,7 +3034,9 @@ mod tests {
         {
             // this extends the first map with the second by generating a new one.
             let mut cons = f_cons.into_iter().chain(g_cons).collect();
-            let sub = f.unify(*g, &mut cons, &mut Fresher::default()).unwrap();
+            let sub = MonoType::Fun(f)
+                .unify(MonoType::Fun(g), &mut cons, &mut Fresher::default())
+                .unwrap();
             assert_eq!(
                 sub,
                 Substitution::from(maplit::hashmap! {
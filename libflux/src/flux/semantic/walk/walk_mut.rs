@@ -237,7 +237,7 @@ impl<'a> NodeMut<'a> {
             Expression::Regexp(ref mut e) => NodeMut::RegexpLit(e),
         }
     }
-    fn from_stmt(stmt: &'a mut Statement) -> NodeMut {
+    pub fn from_stmt(stmt: &'a mut Statement) -> NodeMut {
         match *stmt {
             Statement::Expr(ref mut s) => NodeMut::ExprStmt(s),
             Statement::Variable(ref mut s) => NodeMut::VariableAssgn(s),
@@ -0,0 +1,558 @@
+//! fluxc is a command-line front end to libflux. It formats, checks and
+//! inspects Flux source files without going through Go or the wasm demo.
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use flux::ast::{self, PositionEncoding};
+use flux::formatter::Formatter;
+use flux::parser::Parser;
+use flux::semantic::bootstrap::stdlib_docs;
+use flux::semantic::nodes::{Assignment, Statement};
+use libstd::testing::{check_tests, run_tests, Outcome};
+
+const USAGE: &str = "usage: fluxc <command> [flags] [files...]
+
+commands:
+    fmt [--check]                   format files in place, or list unformatted files with --check
+    check                           parse and type check files
+    ast [--format json|flatbuffers] print the AST of each file (alias: parse)
+    types                           print the type of each top-level binding
+    check-tests                     type check the test statements of each file
+    test [--stdlib dir]             run the test statements of each file
+    doc [--format markdown|json] [--out dir] <stdlib dir>
+                                    document every package of the standard library,
+                                    one file per package with --out
+
+check and ast take --position-encoding utf-8|utf-16 to choose how the
+columns of source positions are counted. The default is utf-8 bytes.
+
+Files may be paths, directories or glob patterns. With no files, or the file
+`-`, the source is read from stdin.
+
+check-tests checks that each test returns a test case of input, want and fn,
+each test against the rest of its file. test also runs each test that checks
+and compares the tables it gets with the tables it wants. Tests that call a
+builtin the runner does not implement are skipped. The standard library is
+read from --stdlib, or else from the nearest directory above each file that
+holds universe/universe.flux.
+
+exit codes:
+    0    success
+    1    errors were found, or --check found unformatted files
+    2    invalid usage or I/O error
+";
+
+const EXIT_OK: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Fmt {
+        check: bool,
+    },
+    Check {
+        encoding: PositionEncoding,
+    },
+    Ast {
+        format: AstFormat,
+        encoding: PositionEncoding,
+    },
+    Types,
+    CheckTests,
+    Test {
+        stdlib: Option<PathBuf>,
+    },
+    Doc {
+        format: DocFormat,
+        out: Option<PathBuf>,
+    },
+    Help,
+}
+
+#[derive(Debug, PartialEq)]
+enum DocFormat {
+    Markdown,
+    Json,
+}
+
+#[derive(Debug, PartialEq)]
+enum AstFormat {
+    Json,
+    Flatbuffers,
+}
+
+#[derive(Debug, PartialEq)]
+enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+impl Input {
+    fn name(&self) -> String {
+        match self {
+            Input::Stdin => String::from("<stdin>"),
+            Input::File(path) => path.display().to_string(),
+        }
+    }
+
+    fn read(&self) -> Result<String, String> {
+        let mut src = String::new();
+        match self {
+            Input::Stdin => io::stdin().read_to_string(&mut src).map(|_| src),
+            Input::File(path) => fs::read_to_string(path),
+        }
+        .map_err(|e| format!("{}: {}", self.name(), e))
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let code = match parse_args(&args) {
+        Ok((Command::Help, _)) => {
+            print!("{}", USAGE);
+            EXIT_OK
+        }
+        Ok((Command::Doc { format, out }, paths)) => doc(&format, out.as_deref(), &paths[0]),
+        Ok((cmd, paths)) => match expand_paths(&paths) {
+            Ok(inputs) => run(&cmd, &inputs),
+            Err(err) => {
+                eprintln!("fluxc: {}", err);
+                EXIT_USAGE
+            }
+        },
+        Err(err) => {
+            eprintln!("fluxc: {}\n\n{}", err, USAGE);
+            EXIT_USAGE
+        }
+    };
+    process::exit(code);
+}
+
+// parse_args splits the command line into a command and the paths it
+// operates on.
+fn parse_args(args: &[String]) -> Result<(Command, Vec<String>), String> {
+    let (name, rest) = match args.split_first() {
+        Some((name, rest)) => (name.as_str(), rest),
+        None => return Err(String::from("missing command")),
+    };
+    let mut check = false;
+    let mut format = AstFormat::Json;
+    let mut encoding = PositionEncoding::Utf8;
+    let mut doc_format = DocFormat::Markdown;
+    let mut out = None;
+    let mut stdlib = None;
+    let mut paths = vec![];
+    let mut iter = rest.iter();
+    while let Some(arg) = iter.next() {
+        match (name, arg.as_str()) {
+            ("fmt", "--check") => check = true,
+            ("ast", "--format") | ("parse", "--format") => {
+                format = match iter.next().map(String::as_str) {
+                    Some("json") => AstFormat::Json,
+                    Some("flatbuffers") => AstFormat::Flatbuffers,
+                    Some(f) => return Err(format!("unknown AST format {:?}", f)),
+                    None => return Err(String::from("--format requires a value")),
+                }
+            }
+            ("doc", "--format") => {
+                doc_format = match iter.next().map(String::as_str) {
+                    Some("markdown") => DocFormat::Markdown,
+                    Some("json") => DocFormat::Json,
+                    Some(f) => return Err(format!("unknown doc format {:?}", f)),
+                    None => return Err(String::from("--format requires a value")),
+                }
+            }
+            ("doc", "--out") => match iter.next() {
+                Some(dir) => out = Some(PathBuf::from(dir)),
+                None => return Err(String::from("--out requires a value")),
+            },
+            ("test", "--stdlib") => match iter.next() {
+                Some(dir) => stdlib = Some(PathBuf::from(dir)),
+                None => return Err(String::from("--stdlib requires a value")),
+            },
+            ("check", "--position-encoding")
+            | ("ast", "--position-encoding")
+            | ("parse", "--position-encoding") => {
+                encoding = match iter.next() {
+                    Some(e) => e.parse()?,
+                    None => return Err(String::from("--position-encoding requires a value")),
+                }
+            }
+            (_, "-") => paths.push(arg.clone()),
+            (_, flag) if flag.starts_with('-') => {
+                return Err(format!("unknown flag {:?} for {}", flag, name))
+            }
+            _ => paths.push(arg.clone()),
+        }
+    }
+    let cmd = match name {
+        "fmt" => Command::Fmt { check },
+        "check" => Command::Check { encoding },
+        "ast" | "parse" => Command::Ast { format, encoding },
+        "types" => Command::Types,
+        "check-tests" => Command::CheckTests,
+        "test" => Command::Test { stdlib },
+        "doc" if paths.len() != 1 => {
+            return Err(String::from("doc takes the standard library directory"))
+        }
+        "doc" => Command::Doc {
+            format: doc_format,
+            out,
+        },
+        "help" | "-h" | "--help" => Command::Help,
+        _ => return Err(format!("unknown command {:?}", name)),
+    };
+    Ok((cmd, paths))
+}
+
+// expand_paths resolves the path arguments into inputs. Directories are
+// searched recursively for .flux files and glob patterns are expanded in
+// sorted order.
+fn expand_paths(paths: &[String]) -> Result<Vec<Input>, String> {
+    if paths.is_empty() {
+        return Ok(vec![Input::Stdin]);
+    }
+    let mut inputs = vec![];
+    for path in paths {
+        if path == "-" {
+            inputs.push(Input::Stdin);
+            continue;
+        }
+        let pattern = if PathBuf::from(path).is_dir() {
+            format!("{}/**/*.flux", path.trim_end_matches('/'))
+        } else if path.contains(&['*', '?', '['][..]) {
+            path.clone()
+        } else {
+            inputs.push(Input::File(PathBuf::from(path)));
+            continue;
+        };
+        let mut matches = glob::glob(&pattern)
+            .map_err(|e| format!("invalid pattern {:?}: {}", path, e))?
+            .collect::<Result<Vec<PathBuf>, _>>()
+            .map_err(|e| e.to_string())?;
+        if matches.is_empty() {
+            return Err(format!("no Flux files match {:?}", path));
+        }
+        matches.sort();
+        inputs.extend(matches.into_iter().map(Input::File));
+    }
+    Ok(inputs)
+}
+
+fn run(cmd: &Command, inputs: &[Input]) -> i32 {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut code = EXIT_OK;
+    for input in inputs {
+        let src = match input.read() {
+            Ok(src) => src,
+            Err(err) => {
+                eprintln!("fluxc: {}", err);
+                return EXIT_USAGE;
+            }
+        };
+        let name = input.name();
+        let result = match cmd {
+            Command::Fmt { check } => fmt(input, &name, &src, *check, &mut out),
+            Command::Check { encoding } => check(&name, &src, *encoding),
+            Command::Ast { format, encoding } => dump_ast(&name, &src, format, *encoding, &mut out),
+            Command::Types => types(&name, &src, inputs.len() > 1, &mut out),
+            Command::CheckTests => test_cases(&name, &src, &mut out),
+            Command::Test { stdlib } => test(input, &name, &src, stdlib.as_deref(), &mut out),
+            Command::Doc { .. } | Command::Help => Ok(true),
+        };
+        match result {
+            Ok(true) => {}
+            Ok(false) => code = EXIT_FAILURE,
+            Err(errs) => {
+                for err in errs {
+                    eprintln!("{}", describe(&name, &err));
+                }
+                code = EXIT_FAILURE;
+            }
+        }
+    }
+    code
+}
+
+// describe prefixes an error with the file it was found in. Errors that
+// already locate themselves within the file are left as they are.
+fn describe(name: &str, err: &str) -> String {
+    match err.strip_prefix("error at ") {
+        Some(located) if located.starts_with(&format!("{}@", name)) => located.to_string(),
+        _ => format!("{}: {}", name, err),
+    }
+}
+
+// parse parses a source file and returns any syntax errors found in it. The
+// parser puts a bad expression in place of an expression it cannot read,
+// which is reported here so that it never reaches semantic analysis.
+fn parse(mut p: Parser, name: &str) -> Result<ast::File, Vec<String>> {
+    let file = p.parse_file(name.to_string());
+    let mut errs = ast::check::check(ast::walk::Node::File(&file));
+    if errs.is_empty() {
+        ast::walk::walk(
+            &ast::walk::create_visitor(&mut |n| {
+                if let ast::walk::Node::BadExpr(e) = *n {
+                    errs.push(ast::check::Error {
+                        location: e.base.location.clone(),
+                        message: e.text.clone(),
+                    });
+                }
+            }),
+            ast::walk::Node::File(&file),
+        );
+    }
+    if errs.is_empty() {
+        Ok(file)
+    } else {
+        Err(errs.iter().map(|e| e.to_string()).collect())
+    }
+}
+
+// format returns the canonical formatting of a source file. The formatter
+// reproduces string literals from their source text, so it is kept.
+fn format(name: &str, src: &str) -> Result<String, Vec<String>> {
+    let file = parse(Parser::with_source(src), name)?;
+    let mut f = Formatter::new(src.len());
+    f.format_file(&file, true);
+    match f.output() {
+        (_, err) if !err.is_empty() => Err(vec![err.to_string()]),
+        (out, _) => {
+            let mut out = String::from(out);
+            if !out.ends_with('\n') {
+                out.push('\n');
+            }
+            Ok(out)
+        }
+    }
+}
+
+// fmt rewrites a file to its formatted form. Stdin is formatted to stdout.
+// With check set nothing is written; the names of unformatted files are
+// listed instead.
+fn fmt(
+    input: &Input,
+    name: &str,
+    src: &str,
+    check: bool,
+    out: &mut dyn Write,
+) -> Result<bool, Vec<String>> {
+    let formatted = format(name, src)?;
+    if formatted == src {
+        if !check && *input == Input::Stdin {
+            write(out, formatted.as_bytes())?;
+        }
+        return Ok(true);
+    }
+    if check {
+        write(out, format!("{}\n", name).as_bytes())?;
+        return Ok(false);
+    }
+    match input {
+        Input::Stdin => write(out, formatted.as_bytes())?,
+        Input::File(path) => {
+            fs::write(path, formatted).map_err(|e| vec![e.to_string()])?;
+        }
+    }
+    Ok(true)
+}
+
+// analyze parses and type checks a source file against the standard library.
+fn analyze(
+    name: &str,
+    src: &str,
+    encoding: PositionEncoding,
+) -> Result<flux::semantic::nodes::Package, Vec<String>> {
+    let file = parse(Parser::with_encoding(src, encoding), name)?;
+    libstd::analyze(file.into()).map_err(|e| vec![e.to_string()])
+}
+
+fn check(name: &str, src: &str, encoding: PositionEncoding) -> Result<bool, Vec<String>> {
+    analyze(name, src, encoding).map(|_| true)
+}
+
+fn dump_ast(
+    name: &str,
+    src: &str,
+    format: &AstFormat,
+    encoding: PositionEncoding,
+    out: &mut dyn Write,
+) -> Result<bool, Vec<String>> {
+    let pkg: ast::Package = Parser::with_encoding(src, encoding)
+        .parse_file(name.to_string())
+        .into();
+    match format {
+        AstFormat::Json => {
+            let json = serde_json::to_string_pretty(&pkg).map_err(|e| vec![e.to_string()])?;
+            write(out, format!("{}\n", json).as_bytes())?;
+        }
+        AstFormat::Flatbuffers => {
+            let (buf, offset) = ast::flatbuffers::serialize(&pkg).map_err(|e| vec![e])?;
+            write(out, &buf[offset..])?;
+        }
+    }
+    Ok(true)
+}
+
+// types prints the inferred type of every top-level variable and option
+// binding, prefixed by the file name when several files are given.
+fn types(name: &str, src: &str, with_name: bool, out: &mut dyn Write) -> Result<bool, Vec<String>> {
+    let pkg = analyze(name, src, PositionEncoding::Utf8)?;
+    let mut s = String::new();
+    if with_name {
+        s.push_str(&format!("{}:\n", name));
+    }
+    for file in &pkg.files {
+        for stmt in &file.body {
+            let assgn = match stmt {
+                Statement::Variable(v) => v.as_ref(),
+                Statement::Option(o) => match &o.assignment {
+                    Assignment::Variable(v) => v,
+                    Assignment::Member(_) => continue,
+                },
+                _ => continue,
+            };
+            s.push_str(&format!("{}: {}\n", assgn.id.name, assgn.poly_type_of()));
+        }
+    }
+    write(out, s.as_bytes())?;
+    Ok(true)
+}
+
+// test_cases checks the test statements of a file, listing each with the
+// result of its check.
+fn test_cases(name: &str, src: &str, out: &mut dyn Write) -> Result<bool, Vec<String>> {
+    parse(Parser::new(src), name)?;
+    let results = check_tests(name, src).map_err(|e| vec![e.to_string()])?;
+    let mut s = String::new();
+    for r in &results {
+        match &r.error {
+            None => s.push_str(&format!("ok    {}: {}\n", name, r.case.name)),
+            Some(err) => s.push_str(&format!("FAIL  {}: {}\n    {}\n", name, r.case.name, err)),
+        }
+    }
+    write(out, s.as_bytes())?;
+    Ok(results.iter().all(|r| r.passed()))
+}
+
+// test runs the test statements of a file, listing each with its outcome.
+// Only tests that fail or error fail the file.
+fn test(
+    input: &Input,
+    name: &str,
+    src: &str,
+    stdlib: Option<&Path>,
+    out: &mut dyn Write,
+) -> Result<bool, Vec<String>> {
+    parse(Parser::new(src), name)?;
+    let stdlib = match stdlib {
+        Some(dir) => dir.to_path_buf(),
+        None => find_stdlib(input)?,
+    };
+    let runs = run_tests(name, src, &stdlib).map_err(|e| vec![e.to_string()])?;
+    let mut s = String::new();
+    let mut ok = true;
+    for r in &runs {
+        let (status, detail) = match &r.outcome {
+            Outcome::Passed => ("ok  ", None),
+            Outcome::Skipped(reason) => ("skip", Some(reason)),
+            Outcome::Failed(diff) => ("FAIL", Some(diff)),
+            Outcome::Error(err) => ("FAIL", Some(err)),
+        };
+        s.push_str(&format!("{}  {}: {}\n", status, name, r.case.name));
+        if let Some(detail) = detail {
+            for line in detail.lines() {
+                s.push_str(&format!("    {}\n", line));
+            }
+        }
+        ok &= match r.outcome {
+            Outcome::Failed(_) | Outcome::Error(_) => false,
+            Outcome::Passed | Outcome::Skipped(_) => true,
+        };
+    }
+    write(out, s.as_bytes())?;
+    Ok(ok)
+}
+
+// find_stdlib returns the nearest directory above a file, or above the
+// working directory for stdin, that holds the universe package.
+fn find_stdlib(input: &Input) -> Result<PathBuf, Vec<String>> {
+    let start = match input {
+        Input::Stdin => env::current_dir().map_err(|e| vec![e.to_string()])?,
+        Input::File(path) => fs::canonicalize(path).map_err(|e| vec![e.to_string()])?,
+    };
+    start
+        .ancestors()
+        .find(|dir| dir.join("universe/universe.flux").is_file())
+        .map(Path::to_path_buf)
+        .ok_or_else(|| {
+            vec![String::from(
+                "cannot find the standard library; give its directory with --stdlib",
+            )]
+        })
+}
+
+// doc documents every package of the standard library in a directory. The
+// docs are written to stdout, or to a file per package named by its import
+// path under the out directory.
+fn doc(format: &DocFormat, out: Option<&Path>, dir: &str) -> i32 {
+    let docs = match stdlib_docs(dir) {
+        Ok(docs) => docs,
+        Err(err) => {
+            eprintln!("fluxc: {}", err.msg);
+            return EXIT_FAILURE;
+        }
+    };
+    let result = match out {
+        Some(out) => docs.iter().try_for_each(|pkg| {
+            let (text, ext) = match format {
+                DocFormat::Markdown => (pkg.markdown(), "md"),
+                DocFormat::Json => (pkg.json().map_err(|e| e.to_string())? + "\n", "json"),
+            };
+            let path = out.join(format!("{}.{}", pkg.path, ext));
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            fs::write(&path, text).map_err(|e| format!("{}: {}", path.display(), e))
+        }),
+        None => {
+            let text = match format {
+                DocFormat::Markdown => Ok(docs
+                    .iter()
+                    .map(|pkg| pkg.markdown())
+                    .collect::<Vec<_>>()
+                    .join("\n")),
+                DocFormat::Json => serde_json::to_string_pretty(&docs)
+                    .map(|json| json + "\n")
+                    .map_err(|e| e.to_string()),
+            };
+            text.and_then(|text| {
+                write(&mut io::stdout(), text.as_bytes()).map_err(|e| e.join("\n"))
+            })
+        }
+    };
+    match result {
+        Ok(()) => EXIT_OK,
+        Err(err) => {
+            eprintln!("fluxc: {}", err);
+            EXIT_USAGE
+        }
+    }
+}
+
+// write writes output for the user. Output that nobody reads any more, as
+// when piped to head, ends the run quietly.
+fn write(out: &mut dyn Write, buf: &[u8]) -> Result<(), Vec<String>> {
+    match out.write_all(buf) {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => process::exit(EXIT_OK),
+        result => result.map_err(|e| vec![e.to_string()]),
+    }
+}
+
+#[cfg(test)]
+mod tests;
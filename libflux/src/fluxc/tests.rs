@@ -0,0 +1,319 @@
+use super::*;
+
+fn args(s: &str) -> Vec<String> {
+    s.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn parse_commands() {
+    assert_eq!(
+        parse_args(&args("fmt --check a.flux b.flux")).unwrap(),
+        (
+            Command::Fmt { check: true },
+            vec![String::from("a.flux"), String::from("b.flux")]
+        )
+    );
+    assert_eq!(
+        parse_args(&args("ast --format flatbuffers -")).unwrap(),
+        (
+            Command::Ast {
+                format: AstFormat::Flatbuffers,
+                encoding: PositionEncoding::Utf8,
+            },
+            vec![String::from("-")]
+        )
+    );
+    assert_eq!(
+        parse_args(&args("types")).unwrap(),
+        (Command::Types, vec![])
+    );
+    assert_eq!(
+        parse_args(&args("check --position-encoding utf-16")).unwrap(),
+        (
+            Command::Check {
+                encoding: PositionEncoding::Utf16
+            },
+            vec![]
+        )
+    );
+    assert_eq!(
+        parse_args(&args("doc --format json --out docs stdlib")).unwrap(),
+        (
+            Command::Doc {
+                format: DocFormat::Json,
+                out: Some(PathBuf::from("docs")),
+            },
+            vec![String::from("stdlib")]
+        )
+    );
+    assert_eq!(
+        parse_args(&args("check-tests stdlib")).unwrap(),
+        (Command::CheckTests, vec![String::from("stdlib")])
+    );
+    assert_eq!(
+        parse_args(&args("test --stdlib lib a_test.flux")).unwrap(),
+        (
+            Command::Test {
+                stdlib: Some(PathBuf::from("lib"))
+            },
+            vec![String::from("a_test.flux")]
+        )
+    );
+    assert_eq!(parse_args(&args("--help")).unwrap().0, Command::Help);
+}
+
+#[test]
+fn parse_command_errors() {
+    for &(cmd, want) in &[
+        ("", "missing command"),
+        ("lint a.flux", "unknown command \"lint\""),
+        ("check --check", "unknown flag \"--check\" for check"),
+        ("ast --format", "--format requires a value"),
+        ("ast --format yaml", "unknown AST format \"yaml\""),
+        (
+            "check --position-encoding latin1",
+            "unknown position encoding \"latin1\"",
+        ),
+        ("doc", "doc takes the standard library directory"),
+        ("doc --format html stdlib", "unknown doc format \"html\""),
+        ("doc --out", "--out requires a value"),
+        (
+            "fmt --position-encoding utf-8",
+            "unknown flag \"--position-encoding\" for fmt",
+        ),
+    ] {
+        assert_eq!(parse_args(&args(cmd)).unwrap_err(), want, "{}", cmd);
+    }
+}
+
+#[test]
+fn expand_stdin_and_globs() {
+    assert_eq!(expand_paths(&[]).unwrap(), vec![Input::Stdin]);
+
+    let dir = env::temp_dir().join(format!("fluxc-test-{}", process::id()));
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    for name in &["b.flux", "a.flux", "notes.txt", "sub/c.flux"] {
+        fs::write(dir.join(name), "x = 1\n").unwrap();
+    }
+    let d = dir.display().to_string();
+
+    let got = expand_paths(&[format!("{}/*.flux", d), String::from("-")]).unwrap();
+    assert_eq!(
+        got,
+        vec![
+            Input::File(dir.join("a.flux")),
+            Input::File(dir.join("b.flux")),
+            Input::Stdin,
+        ]
+    );
+    let got = expand_paths(std::slice::from_ref(&d)).unwrap();
+    assert_eq!(
+        got,
+        vec![
+            Input::File(dir.join("a.flux")),
+            Input::File(dir.join("b.flux")),
+            Input::File(dir.join("sub/c.flux")),
+        ]
+    );
+    assert!(expand_paths(&[format!("{}/*.influxql", d)]).is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn fmt_stdin() {
+    let mut out = vec![];
+    let ok = fmt(&Input::Stdin, "<stdin>", "x=1+2", false, &mut out).unwrap();
+    assert!(ok);
+    assert_eq!(String::from_utf8(out).unwrap(), "x = 1 + 2\n");
+
+    let mut out = vec![];
+    let ok = fmt(&Input::Stdin, "<stdin>", "x=1+2", true, &mut out).unwrap();
+    assert!(!ok);
+    assert_eq!(String::from_utf8(out).unwrap(), "<stdin>\n");
+
+    let mut out = vec![];
+    let ok = fmt(&Input::Stdin, "<stdin>", "x = 1 + 2\n", true, &mut out).unwrap();
+    assert!(ok);
+    assert!(out.is_empty());
+}
+
+#[test]
+fn check_errors() {
+    let utf8 = PositionEncoding::Utf8;
+    assert!(check("a.flux", "x = 1 + 2", utf8).unwrap());
+
+    let errs = check("a.flux", "x = {a: 1, b}", utf8).unwrap_err();
+    assert_eq!(
+        errs,
+        vec!["error at a.flux@1:5-1:14: cannot mix implicit and explicit properties"]
+    );
+
+    let errs = check("a.flux", r#"x = 1 + "a""#, utf8).unwrap_err();
+    assert_eq!(errs.len(), 1);
+
+    let errs = check("a.flux", "x = 1 +", utf8).unwrap_err();
+    assert_eq!(
+        errs,
+        vec!["error at a.flux@1:8-1:8: invalid token for primary expression: EOF"]
+    );
+
+    let src = "x = {a: \"é\", b}";
+    let errs = check("a.flux", src, utf8).unwrap_err();
+    assert_eq!(
+        errs,
+        vec!["error at a.flux@1:5-1:17: cannot mix implicit and explicit properties"]
+    );
+    let errs = check("a.flux", src, PositionEncoding::Utf16).unwrap_err();
+    assert_eq!(
+        errs,
+        vec!["error at a.flux@1:5-1:16: cannot mix implicit and explicit properties"]
+    );
+}
+
+#[test]
+fn describe_errors() {
+    assert_eq!(
+        describe("a.flux", "error at a.flux@1:5-1:14: cannot mix"),
+        "a.flux@1:5-1:14: cannot mix"
+    );
+    assert_eq!(
+        describe("a.flux", "undeclared variable y"),
+        "a.flux: undeclared variable y"
+    );
+}
+
+#[test]
+fn dump_ast_json() {
+    let mut out = vec![];
+    dump_ast(
+        "a.flux",
+        "x = 1",
+        &AstFormat::Json,
+        PositionEncoding::Utf8,
+        &mut out,
+    )
+    .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(json["type"], "Package");
+    assert_eq!(json["files"][0]["body"][0]["id"]["name"], "x");
+}
+
+#[test]
+fn binding_types() {
+    let src = r#"
+        x = 1
+        f = (a, b) => a + b + 1.0
+        option now = () => 2020-01-01T00:00:00Z
+        f(a: 1.0, b: 2.0)
+    "#;
+    let mut out = vec![];
+    types("a.flux", src, false, &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "x: forall [] int\n\
+         f: forall [] (a:float, b:float) -> float\n\
+         now: forall [] () -> time\n"
+    );
+}
+
+#[test]
+fn doc_files() {
+    let dir = env::temp_dir().join(format!("fluxc-doc-{}", process::id()));
+    assert_eq!(
+        doc(&DocFormat::Markdown, Some(&dir), "../../../stdlib"),
+        EXIT_OK
+    );
+    let md = fs::read_to_string(dir.join("universe.md")).unwrap();
+    assert!(md.starts_with("# Package `universe`\n"));
+    assert!(md.contains("\n## universe.now\n"));
+    assert!(dir.join("influxdata/influxdb/v1.md").is_file());
+
+    assert_eq!(
+        doc(&DocFormat::Json, Some(&dir), "../../../stdlib"),
+        EXIT_OK
+    );
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("strings.json")).unwrap()).unwrap();
+    assert_eq!(json["path"], "strings");
+
+    assert_eq!(doc(&DocFormat::Json, None, "no/such/dir"), EXIT_FAILURE);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn check_test_cases() {
+    let src = r#"
+import "testing"
+
+f = (table=<-) => table
+test ok = () => ({input: testing.loadStorage(csv: "a"), want: testing.loadMem(csv: "b"), fn: f})
+test bad = () => ({input: testing.loadStorage(csv: "a"), fn: f})
+"#;
+    let mut out = vec![];
+    assert!(!test_cases("a_test.flux", src, &mut out).unwrap());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "ok    a_test.flux: ok
+FAIL  a_test.flux: bad
+    error at a_test.flux@6:1-6:65: missing field want
+"
+    );
+
+    let mut out = vec![];
+    assert!(test_cases("a.flux", "x = 1", &mut out).unwrap());
+    assert!(out.is_empty());
+}
+
+#[test]
+fn run_test_cases() {
+    let src = r#"
+import "testing"
+
+inData = "
+#datatype,string,long,dateTime:RFC3339,double
+#group,false,false,false,false
+#default,_result,,,
+,result,table,_time,_value
+,,0,2018-05-22T19:53:26Z,1.5
+,,0,2018-05-22T19:53:36Z,2.5
+"
+outData = "
+#datatype,string,long,double
+#group,false,false,false
+#default,_result,,
+,result,table,_value
+,,0,4.5
+"
+f = (table=<-) => table |> sum()
+test ok = () => ({input: testing.loadStorage(csv: inData), want: testing.loadMem(csv: inData), fn: (table=<-) => table})
+test wrong = () => ({input: testing.loadStorage(csv: inData), want: testing.loadMem(csv: outData), fn: f})
+"#;
+    let stdlib = Path::new("../../../stdlib");
+    let mut out = vec![];
+    assert!(!test(&Input::Stdin, "a_test.flux", src, Some(stdlib), &mut out).unwrap());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "ok    a_test.flux: ok
+FAIL  a_test.flux: wrong
+    table {}
+    -   {_value=4.5}
+    +   {_value=4}
+"
+    );
+
+    let input = Input::File(stdlib.join("universe/sum_test.flux"));
+    let mut out = vec![];
+    assert!(test(
+        &input,
+        "sum_test.flux",
+        &input.read().unwrap(),
+        None,
+        &mut out
+    )
+    .unwrap());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "ok    sum_test.flux: _sum\n"
+    );
+}
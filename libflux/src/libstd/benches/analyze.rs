@@ -0,0 +1,59 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+const QUERY: &str = r#"
+from(bucket: "telegraf")
+    |> range(start: -1h)
+    |> filter(fn: (r) => r._measurement == "cpu" and r._field == "usage_user")
+    |> aggregateWindow(every: 1m, fn: mean)
+"#;
+
+fn stdlib(c: &mut Criterion) {
+    c.bench_function("prelude", |b| b.iter(|| black_box(libstd::prelude())));
+    c.bench_function("imports", |b| b.iter(|| black_box(libstd::imports())));
+    c.bench_function("fresher", |b| b.iter(|| black_box(libstd::fresher())));
+    c.bench_function("shared_prelude", |b| {
+        b.iter(|| black_box(libstd::shared_prelude()))
+    });
+    c.bench_function("shared_imports", |b| {
+        b.iter(|| black_box(libstd::shared_imports()))
+    });
+}
+
+fn analyze(c: &mut Criterion) {
+    let file = flux::parser::parse_string("query.flux", QUERY);
+    c.bench_function("analyze", |b| {
+        b.iter(|| black_box(libstd::analyze(file.clone().into()).unwrap()))
+    });
+}
+
+// large_program generates a script of about `lines` top-level statements
+// that build records, functions and table pipelines on top of each other.
+fn large_program(lines: usize) -> String {
+    let mut src = String::from("r0 = {a: 0, b: \"x\", c: 1.0}\n");
+    for i in 0..lines / 4 {
+        src.push_str(&format!(
+            "f{i} = (r) => ({{r with a: r.a + {i}, c: r.c * 2.0}})\n\
+             r{n} = f{i}(r: r{i})\n\
+             g{i} = (tables=<-, v) => tables |> map(fn: (r) => ({{r with _value: r._value + v}}))\n\
+             t{i} = from(bucket: \"b{i}\") |> range(start: -1h) |> g{i}(v: r{n}.a)\n",
+            i = i,
+            n = i + 1,
+        ));
+    }
+    src
+}
+
+fn analyze_large(c: &mut Criterion) {
+    let mut group = c.benchmark_group("analyze_large");
+    group.sample_size(10);
+    for lines in &[100, 400, 1000] {
+        let file = flux::parser::parse_string("large.flux", &large_program(*lines));
+        group.bench_function(format!("{}_lines", lines), |b| {
+            b.iter(|| black_box(libstd::analyze(file.clone().into()).unwrap()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, stdlib, analyze, analyze_large);
+criterion_main!(benches);
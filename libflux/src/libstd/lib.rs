@@ -7,22 +7,60 @@ use flux::semantic::env::Environment;
 use flux::semantic::flatbuffers::semantic_generated::fbsemantic as fb;
 use flux::semantic::fresh::Fresher;
 use flux::semantic::nodes::{infer_pkg_types, inject_pkg_types};
+use flux::Limits;
+use lazy_static::lazy_static;
 
-pub fn prelude() -> Option<Environment> {
+pub mod session;
+pub mod testing;
+pub use session::AnalysisSession;
+
+lazy_static! {
+    // The standard library is decoded from its flatbuffers on first use and
+    // shared by every call, and thread, after that.
+    static ref PRELUDE: Option<Environment> = decode_prelude();
+    static ref IMPORTS: Option<Environment> = decode_imports();
+    static ref FRESHER: u64 = decode_fresher().0;
+}
+
+fn decode_prelude() -> Option<Environment> {
     let buf = include_bytes!(concat!(env!("OUT_DIR"), "/prelude.data"));
     flatbuffers::get_root::<fb::TypeEnvironment>(buf).into()
 }
 
-pub fn imports() -> Option<Environment> {
+fn decode_imports() -> Option<Environment> {
     let buf = include_bytes!(concat!(env!("OUT_DIR"), "/stdlib.data"));
     flatbuffers::get_root::<fb::TypeEnvironment>(buf).into()
 }
 
-pub fn fresher() -> Fresher {
+fn decode_fresher() -> Fresher {
     let buf = include_bytes!(concat!(env!("OUT_DIR"), "/fresher.data"));
     flatbuffers::get_root::<fb::Fresher>(buf).into()
 }
 
+/// Returns the types of the prelude, the builtins visible to every package.
+/// The returned environment is shared; use `prelude()` for an owned copy.
+pub fn shared_prelude() -> Option<&'static Environment> {
+    PRELUDE.as_ref()
+}
+
+/// Returns the types of every standard library package, keyed by import path.
+/// The returned environment is shared; use `imports()` for an owned copy.
+pub fn shared_imports() -> Option<&'static Environment> {
+    IMPORTS.as_ref()
+}
+
+pub fn prelude() -> Option<Environment> {
+    PRELUDE.clone()
+}
+
+pub fn imports() -> Option<Environment> {
+    IMPORTS.clone()
+}
+
+pub fn fresher() -> Fresher {
+    Fresher::from(*FRESHER)
+}
+
 /// # Safety
 ///
 /// Ths function is unsafe because it dereferences a raw pointer.
@@ -32,39 +70,95 @@ pub unsafe extern "C" fn flux_analyze(
     out_sem_pkg: *mut *const flux_semantic_pkg_t,
 ) -> *mut flux_error_t {
     let ast_pkg = *Box::from_raw(ast_pkg as *mut ast::Package);
-    match analyze(ast_pkg) {
-        Ok(sem_pkg) => {
-            let sem_pkg = Box::into_raw(Box::new(sem_pkg)) as *const flux_semantic_pkg_t;
-            *out_sem_pkg = sem_pkg;
-            std::ptr::null_mut()
-        }
-        Err(err) => {
-            let errh = flux::ErrorHandle { err: Box::new(err) };
-            Box::into_raw(Box::new(errh)) as *mut flux_error_t
-        }
-    }
+    flux::catch_errors(|| {
+        let sem_pkg = analyze(ast_pkg)?;
+        *out_sem_pkg = Box::into_raw(Box::new(sem_pkg)) as *const flux_semantic_pkg_t;
+        Ok(())
+    })
+}
+
+/// # Safety
+///
+/// This function is unsafe because it dereferences raw pointers passed
+/// in as parameters. For example, if that pointer is NULL, undefined behavior
+/// could occur.
+#[no_mangle]
+pub unsafe extern "C" fn flux_analyze_with_limits(
+    ast_pkg: *mut flux_ast_pkg_t,
+    limits: *const flux::flux_limits_t,
+    out_sem_pkg: *mut *const flux_semantic_pkg_t,
+) -> *mut flux_error_t {
+    let ast_pkg = *Box::from_raw(ast_pkg as *mut ast::Package);
+    let limits = flux::read_limits(limits);
+    flux::catch_errors(|| {
+        let sem_pkg = analyze_with_limits(ast_pkg, limits)?;
+        *out_sem_pkg = Box::into_raw(Box::new(sem_pkg)) as *const flux_semantic_pkg_t;
+        Ok(())
+    })
 }
 
 /// analyze consumes the given AST package and returns a semantic package
 /// that has been type-inferred.  This function is aware of the standard library
-/// and prelude.
+/// and prelude. It enforces the default limits.
 pub fn analyze(ast_pkg: ast::Package) -> Result<flux::semantic::nodes::Package, flux::Error> {
-    let pkgpath = ast_pkg.path.clone();
+    analyze_with_limits(ast_pkg, Limits::default())
+}
+
+/// analyze_with_limits is like analyze, but stops once the package breaks
+/// one of the given limits, returning an error that names it.
+pub fn analyze_with_limits(
+    ast_pkg: ast::Package,
+    limits: Limits,
+) -> Result<flux::semantic::nodes::Package, flux::Error> {
+    // Analysis recurses over the nesting of the AST, so the depth is
+    // checked before anything else walks it.
+    ast::check::check_depth(ast::walk::Node::Package(&ast_pkg), limits.max_depth)?;
     let mut f = fresher();
-    let mut sem_pkg = flux::semantic::convert::convert_with(ast_pkg, &mut f)?;
+    f.limit(limits);
+    analyze_with(ast_pkg, &mut f).map_err(|err| match f.exceeded() {
+        Some(limit) => flux::Error::from(*limit),
+        None => err,
+    })
+}
+
+/// analyze_source_with_limits parses and analyzes a single file of source
+/// that may come from an untrusted user. If parsing or inference goes past
+/// any of the given limits, the returned error names the limit.
+pub fn analyze_source_with_limits(
+    name: &str,
+    src: &str,
+    limits: Limits,
+) -> Result<flux::semantic::nodes::Package, flux::Error> {
+    let file = flux::parser::parse_string_with_limits(name, src, limits)?;
+    analyze_with_limits(file.into(), limits)
+}
+
+fn analyze_with(
+    ast_pkg: ast::Package,
+    f: &mut Fresher,
+) -> Result<flux::semantic::nodes::Package, flux::Error> {
+    // Syntax errors are reported before analysis, which expects a valid AST.
+    if let Some(err) = ast::check::check(ast::walk::Node::Package(&ast_pkg))
+        .into_iter()
+        .next()
+    {
+        return Err(flux::Error::from(err.to_string()));
+    }
+    let pkgpath = ast_pkg.path.clone();
+    let mut sem_pkg = flux::semantic::convert::convert_with(ast_pkg, f)?;
 
     check::check(&sem_pkg)?;
 
-    let prelude = match prelude() {
-        Some(prelude) => Environment::new(prelude),
+    let prelude = match shared_prelude() {
+        Some(prelude) => Environment::new(prelude.clone()),
         None => return Err(flux::Error::from("missing prelude")),
     };
-    let imports = match imports() {
+    let imports = match shared_imports() {
         Some(imports) => imports,
         None => return Err(flux::Error::from("missing stdlib imports")),
     };
-    let builtin_importer = builtins().importer_for(&pkgpath, &mut f);
-    let (_, sub) = infer_pkg_types(&mut sem_pkg, prelude, &mut f, &imports, &builtin_importer)?;
+    let builtin_importer = builtins().importer_for(&pkgpath, f);
+    let (_, sub) = infer_pkg_types(&mut sem_pkg, prelude, f, imports, &builtin_importer)?;
     sem_pkg = inject_pkg_types(sem_pkg, &sub);
     Ok(sem_pkg)
 }
@@ -109,4 +203,316 @@ mod tests {
 
         assert_eq!(want, got.lookup("x").expect("'x' not found").clone());
     }
+
+    #[test]
+    fn analyze_dropped_columns() {
+        let analyze =
+            |src: &str| super::analyze(flux::parser::parse_string("main.flux", src).into());
+        let err = analyze(
+            r#"
+            from(bucket: "b")
+                |> range(start: -1h)
+                |> drop(columns: ["host"])
+                |> map(fn: (r) => ({v: r.host}))
+            "#,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("must not have label host")
+                && err
+                    .to_string()
+                    .contains("column host was removed by drop at"),
+            "{}",
+            err
+        );
+        analyze(
+            r#"
+            from(bucket: "b")
+                |> range(start: -1h)
+                |> drop(columns: ["host"])
+                |> map(fn: (r) => ({v: r._value, start: r._start}))
+            "#,
+        )
+        .unwrap();
+    }
+
+    // fill takes one of value or usePrevious and stddev defaults its mode,
+    // as the Go implementations do.
+    #[test]
+    fn analyze_optional_fill_and_stddev_parameters() {
+        let analyze =
+            |src: &str| super::analyze(flux::parser::parse_string("main.flux", src).into());
+        for call in &[
+            "fill(value: 0.0)",
+            "fill(column: \"_value\", value: 0.0)",
+            "fill(usePrevious: true)",
+            "stddev()",
+            "stddev(mode: \"population\")",
+        ] {
+            let src = format!("from(bucket: \"b\") |> range(start: -1h) |> {}", call);
+            if let Err(e) = analyze(&src) {
+                panic!("{}: {}", call, e);
+            }
+        }
+        let err = analyze(r#"from(bucket: "b") |> fill(usePrevious: 1)"#).unwrap_err();
+        assert!(
+            err.to_string().contains("expected bool but found int"),
+            "{}",
+            err
+        );
+        let err = analyze(r#"from(bucket: "b") |> stddev(mode: 1)"#).unwrap_err();
+        assert!(
+            err.to_string().contains("expected string but found int"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn analyze_relative_durations() {
+        let analyze =
+            |src: &str| super::analyze(flux::parser::parse_string("main.flux", src).into());
+        for call in &[
+            "experimental.addDuration(d: 1h, to: now())",
+            "experimental.addDuration(d: 1h, to: -5m)",
+            "experimental.subDuration(d: 1h, from: 2020-01-01T00:00:00Z)",
+            "experimental.subDuration(d: 1h, from: -5m)",
+        ] {
+            let src = format!("import \"experimental\"\nx = {}", call);
+            if let Err(e) = analyze(&src) {
+                panic!("{}: {}", call, e);
+            }
+        }
+        let err =
+            analyze("import \"experimental\"\nexperimental.addDuration(d: 1h, to: 1)").unwrap_err();
+        assert!(
+            err.to_string().contains("int is not of kind Timeable"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn analyze_from_many_threads() {
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                std::thread::spawn(move || {
+                    let src = format!("x = {}\ny = x + 1", i);
+                    let ast = flux::parser::parse_string("main.flux", &src);
+                    super::analyze(ast.into()).unwrap()
+                })
+            })
+            .collect();
+        for h in handles {
+            let pkg = h.join().unwrap();
+            assert_eq!(pkg.files[0].body.len(), 2);
+        }
+        assert_eq!(super::shared_prelude().unwrap(), &super::prelude().unwrap());
+    }
+
+    #[test]
+    fn transpiled_promql_type_checks() {
+        use chrono::{DateTime, Duration};
+        use flux::formatter::Formatter;
+        use flux::promql::Transpiler;
+
+        let queries = vec![
+            r#"foo{a="b", c=~"x.*", _d!="e"}"#,
+            "foo offset 1m",
+            "rate(foo[5m])",
+            "irate(foo[5m])",
+            "deriv(foo[5m])",
+            "predict_linear(foo[5m], 60)",
+            "holt_winters(foo[5m], 0.5, 0.5)",
+            "changes(foo[5m])",
+            "resets(foo[5m])",
+            "timestamp(foo)",
+            "sum by (job) (foo)",
+            "avg without (instance) (foo)",
+            "max by (job) (foo)",
+            "min without (job) (foo)",
+            "count(foo)",
+            "stddev(foo)",
+            "stdvar(foo)",
+            "topk(3, foo)",
+            "bottomk(3, foo)",
+            "quantile(0.9, foo)",
+            "sum_over_time(foo[5m])",
+            "count_over_time(foo[5m])",
+            "stdvar_over_time(foo[5m])",
+            "quantile_over_time(0.9, foo[5m])",
+            "abs(foo)",
+            "ln(foo)",
+            "hour()",
+            "day_of_week(foo)",
+            "clamp_max(foo, 10)",
+            r#"label_join(foo, "dst", ",", "a", "_b")"#,
+            r#"label_replace(foo, "dst", "$1", "src", "(.*)")"#,
+            "histogram_quantile(0.9, rate(foo_bucket[5m]))",
+            "vector(1)",
+            "scalar(foo)",
+            "time()",
+            "1 + 2 * 3",
+            "2 ^ 3 % 2",
+            "1 > bool 2",
+            "-foo",
+            "foo * 2",
+            "2 - foo",
+            "foo ^ 2",
+            "foo > 2",
+            "foo > bool 2",
+            "foo + on(job) bar",
+            "foo / on(job) group_left(env) bar",
+            "foo > on(job) bar",
+            "foo + time()",
+            "max_over_time(rate(foo[1m])[5m:1m])",
+            "Inf",
+            "NaN",
+        ];
+        for resolution in &[Duration::zero(), Duration::seconds(10)] {
+            let t = Transpiler {
+                bucket: "prometheus".to_string(),
+                start: DateTime::parse_from_rfc3339("2019-11-01T10:00:00Z").unwrap(),
+                end: DateTime::parse_from_rfc3339("2019-11-01T11:00:00Z").unwrap(),
+                resolution: *resolution,
+            };
+            for q in &queries {
+                let pkg = t.transpile_str(q).unwrap();
+                let mut fmt = Formatter::new(1024);
+                fmt.format_file(&pkg.files[0], false);
+                let (src, err) = fmt.output();
+                assert_eq!("", err, "formatting {}", q);
+                let ast = flux::parser::parse_string("promql.flux", src);
+                if let Err(e) = super::analyze(ast.into()) {
+                    panic!("{}: {}\n{}", q, e, src);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn transpiled_influxql_type_checks() {
+        let dir =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../flux/influxql/testdata");
+        let mut count = 0;
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "flux") {
+                continue;
+            }
+            let src = std::fs::read_to_string(&path).unwrap();
+            let ast = flux::parser::parse_string("influxql.flux", &src);
+            if let Err(e) = super::analyze(ast.into()) {
+                panic!("{}: {}\n{}", path.display(), e, src);
+            }
+            count += 1;
+        }
+        assert!(
+            count > 0,
+            "no transpiled queries found in {}",
+            dir.display()
+        );
+    }
+
+    #[test]
+    fn analyze_with_limits() {
+        use flux::limits::{Limit, Limits};
+
+        let src = r#"
+            f = (r) => ({r with a: r.b + 1, c: r.d * 2.0, e: r.f == "g"})
+            x = from(bucket: "b") |> map(fn: f) |> filter(fn: (r) => r.a > 0)
+        "#;
+        let limits = Limits {
+            max_source_len: Some(1024),
+            max_depth: Some(32),
+            max_tokens: Some(128),
+            max_type_vars: Some(1000),
+            max_inference_steps: Some(1000),
+        };
+        if let Err(e) = super::analyze_source_with_limits("main.flux", src, limits) {
+            panic!("{}", e);
+        }
+
+        let exceeded = |limits: Limits| {
+            super::analyze_source_with_limits("main.flux", src, limits)
+                .unwrap_err()
+                .limit()
+                .map(|err| err.limit)
+        };
+        assert_eq!(
+            Some(Limit::Tokens),
+            exceeded(Limits {
+                max_tokens: Some(16),
+                ..limits
+            })
+        );
+        assert_eq!(
+            Some(Limit::TypeVars),
+            exceeded(Limits {
+                max_type_vars: Some(10),
+                ..limits
+            })
+        );
+        assert_eq!(
+            Some(Limit::InferenceSteps),
+            exceeded(Limits {
+                max_inference_steps: Some(10),
+                ..limits
+            })
+        );
+    }
+
+    #[test]
+    fn analyze_deep_nesting_by_default() {
+        use flux::limits::Limits;
+        use flux::parser::{parse_string, parse_string_with_limits};
+
+        // The default limits are meant for a stack of 8 MiB, which the main
+        // thread of a program usually has but a test thread does not.
+        std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(|| {
+                let limit = format!(
+                    "nesting depth limit of {} exceeded",
+                    Limits::DEFAULT_MAX_DEPTH
+                );
+                let analyze = |src: &str| super::analyze(parse_string("", src).into());
+
+                let deep = [
+                    format!("x = {}1{}", "(".repeat(50_000), ")".repeat(50_000)),
+                    format!("x = {}1{}", "[".repeat(50_000), "]".repeat(50_000)),
+                    format!("x = 1{}", " + 1".repeat(50_000)),
+                    format!("x = {}1", "() => ".repeat(50_000)),
+                ];
+                for src in deep.iter() {
+                    let err = analyze(src).unwrap_err().to_string();
+                    assert!(err.ends_with(&limit), "{}", err);
+                }
+
+                // Just within the limit, analysis and serialization fit in
+                // the stack.
+                let within = [
+                    format!("x = {}1{}", "[".repeat(127), "]".repeat(127)),
+                    format!("x = 1{}", " + 1".repeat(127)),
+                    format!("x = {}1", "() => ".repeat(63)),
+                ];
+                for src in within.iter() {
+                    let pkg = analyze(src).unwrap();
+                    flux::semantic::flatbuffers::serialize(&pkg).unwrap();
+                }
+
+                // An AST that was parsed without a limit is checked before
+                // it is analyzed.
+                let src = format!("x = 1{}", " + 1".repeat(1000));
+                let limits = Limits {
+                    max_depth: None,
+                    ..Limits::default()
+                };
+                let file = parse_string_with_limits("", &src, limits).unwrap();
+                assert_eq!(super::analyze(file.into()).unwrap_err().to_string(), limit);
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
 }
@@ -0,0 +1,436 @@
+//! Incremental analysis for long-lived editor sessions.
+//!
+//! An [`AnalysisSession`] shares the decoded standard library and keeps
+//! the parsed, converted and inferred form of every top-level statement of
+//! its open documents. An edit re-parses, and re-converts, only the
+//! statements it touches, and re-infers only the statements that refer to a
+//! binding whose type changed.
+//! The result is the same as running [`analyze`](crate::analyze) on the whole
+//! document, up to the naming of type variables.
+use std::collections::HashMap;
+
+use flux::ast::{self, walk};
+use flux::parser::parse_string;
+use flux::semantic::builtins::builtins;
+use flux::semantic::check;
+use flux::semantic::convert::{convert_file, convert_statement};
+use flux::semantic::env::Environment;
+use flux::semantic::fresh::Fresher;
+use flux::semantic::nodes::{self, infer_stmt_types, inject_stmt_types, Statement};
+use flux::semantic::types::PolyType;
+use flux::semantic::walk::{walk_mut, NodeMut};
+use flux::Error;
+
+/// A TextEdit replaces the bytes `start..end` of a document with `text`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TextEdit {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// Stats counts the top-level statements that were parsed, converted and
+/// inferred again by the last update of a document.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Stats {
+    pub parsed: usize,
+    pub converted: usize,
+    pub inferred: usize,
+}
+
+/// AnalysisSession analyzes a set of documents, each as a package of its
+/// own, reusing work across edits.
+pub struct AnalysisSession {
+    prelude: &'static Environment,
+    imports: &'static Environment,
+    fresher: Fresher,
+    docs: HashMap<String, Document>,
+}
+
+struct Document {
+    src: String,
+    file: ast::File,
+    entries: Vec<Entry>,
+    stats: Stats,
+}
+
+// Entry caches the work done for one top-level statement of a document.
+struct Entry {
+    // names holds every identifier the statement mentions. It is a superset
+    // of the bindings the statement depends on.
+    names: Vec<String>,
+    converted: Option<Statement>,
+    inferred: Option<Inferred>,
+}
+
+struct Inferred {
+    // deps holds the type of each of the entry's names at the time the
+    // statement was inferred.
+    deps: Vec<(String, Option<PolyType>)>,
+    stmt: Statement,
+    bindings: Vec<(String, PolyType)>,
+}
+
+impl AnalysisSession {
+    pub fn new() -> Result<AnalysisSession, Error> {
+        let prelude = match crate::shared_prelude() {
+            Some(prelude) => prelude,
+            None => return Err(Error::from("missing prelude")),
+        };
+        let imports = match crate::shared_imports() {
+            Some(imports) => imports,
+            None => return Err(Error::from("missing stdlib imports")),
+        };
+        Ok(AnalysisSession {
+            prelude,
+            imports,
+            fresher: crate::fresher(),
+            docs: HashMap::new(),
+        })
+    }
+
+    /// Opens a document, or replaces its contents, and analyzes it.
+    pub fn open(&mut self, name: &str, src: &str) -> Result<nodes::Package, Error> {
+        let file = parse_string(name, src);
+        let doc = Document {
+            src: src.to_string(),
+            entries: file.body.iter().map(Entry::new).collect(),
+            stats: Stats {
+                parsed: file.body.len(),
+                ..Stats::default()
+            },
+            file,
+        };
+        self.docs.insert(name.to_string(), doc);
+        self.analyze(name)
+    }
+
+    /// Applies an edit to an open document and analyzes it again.
+    pub fn edit(&mut self, name: &str, edit: &TextEdit) -> Result<nodes::Package, Error> {
+        match self.docs.get_mut(name) {
+            Some(doc) => doc.edit(edit)?,
+            None => return Err(Error::from(format!("document {} is not open", name))),
+        }
+        self.analyze(name)
+    }
+
+    pub fn close(&mut self, name: &str) {
+        self.docs.remove(name);
+    }
+
+    /// Returns the current contents of an open document.
+    pub fn source(&self, name: &str) -> Option<&str> {
+        self.docs.get(name).map(|doc| doc.src.as_str())
+    }
+
+    /// Returns the AST of an open document.
+    pub fn ast(&self, name: &str) -> Option<&ast::File> {
+        self.docs.get(name).map(|doc| &doc.file)
+    }
+
+    /// Reports the work done by the last update of an open document.
+    pub fn stats(&self, name: &str) -> Option<&Stats> {
+        self.docs.get(name).map(|doc| &doc.stats)
+    }
+
+    fn analyze(&mut self, name: &str) -> Result<nodes::Package, Error> {
+        let doc = match self.docs.get_mut(name) {
+            Some(doc) => doc,
+            None => return Err(Error::from(format!("document {} is not open", name))),
+        };
+        let f = &mut self.fresher;
+
+        // Syntax errors, conversion and checks run over the whole package
+        // before inference, as they do in analyze, so that errors are reported
+        // in the same order.
+        if let Some(err) = ast::check::check(ast::walk::Node::File(&doc.file))
+            .into_iter()
+            .next()
+        {
+            return Err(Error::from(err.to_string()));
+        }
+        let header = ast::File {
+            base: doc.file.base.clone(),
+            name: doc.file.name.clone(),
+            metadata: doc.file.metadata.clone(),
+            package: doc.file.package.clone(),
+            imports: doc.file.imports.clone(),
+            body: vec![],
+        };
+        let mut file = convert_file(header, f)?;
+        for (stmt, entry) in doc.file.body.iter().zip(doc.entries.iter_mut()) {
+            if entry.converted.is_none() {
+                entry.converted = Some(convert_statement(stmt.clone(), f)?);
+                doc.stats.converted += 1;
+            }
+        }
+        file.body = doc
+            .entries
+            .iter()
+            .map(|e| e.converted.clone().unwrap())
+            .collect();
+        let mut pkg = nodes::Package {
+            loc: ast::SourceLocation::default(),
+            package: String::from(doc.file.get_package()),
+            files: vec![file],
+        };
+        check::check(&pkg)?;
+
+        let builtin_importer = builtins().importer_for("", f);
+        let (mut env, _) =
+            pkg.files[0].infer_imports(Environment::new(self.prelude.clone()), self.imports)?;
+        let mut body = Vec::with_capacity(doc.entries.len());
+        for entry in doc.entries.iter_mut() {
+            let deps: Vec<(String, Option<PolyType>)> = entry
+                .names
+                .iter()
+                .map(|n| (n.clone(), env.lookup(n).cloned()))
+                .collect();
+            match &entry.inferred {
+                Some(inferred) if inferred.deps == deps => {
+                    for (name, pty) in &inferred.bindings {
+                        env.add(name.clone(), pty.clone());
+                    }
+                }
+                _ => {
+                    let mut stmt = entry.converted.clone().unwrap();
+                    let (new_env, sub) = infer_stmt_types(&mut stmt, env, f, &builtin_importer)?;
+                    env = new_env;
+                    let bindings = bound_names(&stmt)
+                        .into_iter()
+                        .filter_map(|n| env.values.get(&n).map(|pty| (n, pty.clone())))
+                        .collect();
+                    entry.inferred = Some(Inferred {
+                        deps,
+                        stmt: inject_stmt_types(stmt, &sub),
+                        bindings,
+                    });
+                    doc.stats.inferred += 1;
+                }
+            }
+            body.push(entry.inferred.as_ref().unwrap().stmt.clone());
+        }
+        pkg.files[0].body = body;
+        Ok(pkg)
+    }
+}
+
+impl Entry {
+    fn new(stmt: &ast::Statement) -> Entry {
+        let mut names = vec![];
+        walk::walk(
+            &walk::create_visitor(&mut |n| {
+                if let walk::Node::Identifier(id) = *n {
+                    names.push(id.name.clone());
+                }
+            }),
+            walk::Node::from_stmt(stmt),
+        );
+        names.sort();
+        names.dedup();
+        Entry {
+            names,
+            converted: None,
+            inferred: None,
+        }
+    }
+
+    // relocate moves the locations of the cached statements by delta bytes.
+    fn relocate(&mut self, delta: i64, lines: &ast::LineIndex) {
+        let mut shift = |n: &mut NodeMut| {
+            let loc = n.loc();
+            if loc.is_valid() {
+                let moved = shifted(loc, delta, lines);
+                n.set_loc(moved);
+            }
+        };
+        if let Some(stmt) = &mut self.converted {
+            walk_mut(&mut shift, &mut NodeMut::from_stmt(stmt));
+        }
+        if let Some(inferred) = &mut self.inferred {
+            walk_mut(&mut shift, &mut NodeMut::from_stmt(&mut inferred.stmt));
+        }
+    }
+}
+
+// relocate_stmt moves the locations of a parsed statement by delta bytes.
+fn relocate_stmt(stmt: &mut ast::Statement, delta: i64, lines: &ast::LineIndex) {
+    let mut shift = |n: &mut ast::walk_mut::NodeMut| {
+        let base = n.base_mut();
+        base.location = shifted(&base.location, delta, lines);
+    };
+    ast::walk_mut::walk_mut(&mut shift, &mut ast::walk_mut::NodeMut::from_stmt(stmt));
+}
+
+fn shifted(loc: &ast::SourceLocation, delta: i64, lines: &ast::LineIndex) -> ast::SourceLocation {
+    let span = ast::Span {
+        start: (loc.span.start as i64 + delta) as u32,
+        end: (loc.span.end as i64 + delta) as u32,
+    };
+    let mut moved = lines.location(loc.file.clone(), span);
+    moved.source = loc.source.clone();
+    moved
+}
+
+// bound_names returns the names a top-level statement adds to the type
+// environment.
+fn bound_names(stmt: &Statement) -> Vec<String> {
+    match stmt {
+        Statement::Variable(v) => vec![v.id.name.clone()],
+        Statement::Option(o) => match &o.assignment {
+            nodes::Assignment::Variable(v) => vec![v.id.name.clone()],
+            nodes::Assignment::Member(_) => vec![],
+        },
+        Statement::Test(t) => vec![t.assignment.id.name.clone()],
+        Statement::Builtin(b) => vec![b.id.name.clone()],
+        Statement::Expr(_) | Statement::Return(_) => vec![],
+    }
+}
+
+impl Document {
+    fn edit(&mut self, edit: &TextEdit) -> Result<(), Error> {
+        if edit.start > edit.end
+            || edit.end > self.src.len()
+            || !self.src.is_char_boundary(edit.start)
+            || !self.src.is_char_boundary(edit.end)
+        {
+            return Err(Error::from(format!(
+                "invalid edit range {}..{}",
+                edit.start, edit.end
+            )));
+        }
+        let mut src = String::with_capacity(self.src.len() + edit.text.len());
+        src.push_str(&self.src[..edit.start]);
+        src.push_str(&edit.text);
+        src.push_str(&self.src[edit.end..]);
+        self.src = src;
+        self.stats = Stats::default();
+        if !self.reparse_statements(edit) {
+            self.reparse_all();
+        }
+        Ok(())
+    }
+
+    fn reparse_all(&mut self) {
+        self.file = parse_string(&self.file.name, &self.src);
+        self.entries = self.file.body.iter().map(Entry::new).collect();
+        self.stats.parsed = self.file.body.len();
+    }
+
+    // reparse_statements re-parses only the statements an edit touches,
+    // along with a guard on each side: the statement before the edit and the
+    // first statement that starts after it. Each guard must come out as it
+    // was, otherwise the edit moved a statement boundary and the whole file
+    // must be parsed again. Returns false in that case.
+    //
+    // The statements after the edit are not parsed again. They keep their
+    // cached entries, with their locations moved along with the text.
+    fn reparse_statements(&mut self, edit: &TextEdit) -> bool {
+        let body = &self.file.body;
+        let n = body.len();
+        let header_end = match (self.file.imports.last(), &self.file.package) {
+            (Some(imp), _) => Some(imp.base.location.span.end as usize),
+            (None, Some(pkg)) => Some(pkg.base.location.span.end as usize),
+            (None, None) => None,
+        };
+        if n == 0 || header_end.is_some_and(|end| edit.start <= end) {
+            return false;
+        }
+        let spans: Vec<ast::Span> = body.iter().map(|s| s.base().location.span).collect();
+        let delta = edit.text.len() as i64 - (edit.end - edit.start) as i64;
+
+        // Statements before first end before the edit and statements from
+        // after on start after it. The ones in between touch the edit.
+        let first = spans
+            .iter()
+            .take_while(|s| (s.end as usize) < edit.start)
+            .count();
+        let after = first
+            + spans[first..]
+                .iter()
+                .take_while(|s| (s.start as usize) <= edit.end)
+                .count();
+        let lo = first.saturating_sub(1);
+        let start = if lo < first {
+            spans[lo].start as usize
+        } else {
+            header_end.unwrap_or(0)
+        };
+        let end = match spans.get(after) {
+            Some(span) => (span.end as i64 + delta) as usize,
+            None => self.src.len(),
+        };
+
+        // Blank out the source before the first guard, keeping its line
+        // breaks, so that the statements get the same locations as in a full
+        // parse.
+        let mut padded: String = self.src[..start]
+            .bytes()
+            .map(|b| if b == b'\n' { '\n' } else { ' ' })
+            .collect();
+        padded.push_str(&self.src[start..end]);
+        let parsed = parse_string(&self.file.name, &padded);
+        if parsed.package.is_some() || !parsed.imports.is_empty() {
+            return false;
+        }
+        let mut stmts = parsed.body;
+        if lo < first && stmts.first() != Some(&body[lo]) {
+            return false;
+        }
+        let lines = ast::LineIndex::new(&self.src);
+        let mut rest = body[after..].to_vec();
+        for stmt in &mut rest {
+            relocate_stmt(stmt, delta, &lines);
+        }
+        if after < n {
+            if stmts.last() != rest.first() {
+                return false;
+            }
+            stmts.pop();
+        }
+        self.stats.parsed = stmts.len() + if after < n { 1 } else { 0 };
+
+        // The guard before the edit came out unchanged, so its entry is kept
+        // along with the entries before it.
+        let lead = first - lo;
+        let mut moved = self.entries.split_off(after);
+        for entry in &mut moved {
+            entry.relocate(delta, &lines);
+        }
+        self.entries.truncate(first);
+        self.entries.extend(stmts[lead..].iter().map(Entry::new));
+        self.entries.extend(moved);
+        self.file.body.truncate(first);
+        self.file.body.extend(stmts.into_iter().skip(lead));
+        self.file.body.extend(rest);
+
+        // The file spans from its first token to the end of its last statement.
+        let first_loc = match (&self.file.package, self.file.imports.first()) {
+            (Some(pkg), _) => &pkg.base.location,
+            (None, Some(imp)) => &imp.base.location,
+            (None, None) => match self.file.body.first() {
+                Some(stmt) => &stmt.base().location,
+                None => return false,
+            },
+        };
+        let last_loc = match self.file.body.last() {
+            Some(stmt) => &stmt.base().location,
+            None => return false,
+        };
+        let loc = ast::SourceLocation {
+            file: self.file.base.location.file.clone(),
+            start: first_loc.start.clone(),
+            end: last_loc.end.clone(),
+            span: ast::Span {
+                start: first_loc.span.start,
+                end: last_loc.span.end,
+            },
+            source: None,
+        };
+        self.file.base.location = loc;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests;
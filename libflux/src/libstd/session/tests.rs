@@ -0,0 +1,233 @@
+use super::*;
+
+use std::rc::Rc;
+
+use flux::semantic::walk;
+
+// describe lists every node of a semantic package with its location and
+// type. Type variables are renamed in order of appearance so that packages
+// inferred with different freshers can be compared.
+fn describe(pkg: &nodes::Package) -> Vec<String> {
+    let mut nodes = vec![];
+    walk::walk(
+        &mut |n: Rc<walk::Node>| {
+            let typ = match n.type_of() {
+                Some(t) => t.to_string(),
+                None => String::new(),
+            };
+            nodes.push(format!("{} {} {}", n, n.loc(), typ));
+        },
+        Rc::new(walk::Node::Package(pkg)),
+    );
+    let mut names: HashMap<String, String> = HashMap::new();
+    nodes
+        .into_iter()
+        .map(|line| {
+            let mut out = String::new();
+            let mut chars = line.chars().peekable();
+            let mut prev = ' ';
+            while let Some(c) = chars.next() {
+                let starts_var = c == 't'
+                    && !prev.is_alphanumeric()
+                    && chars.peek().is_some_and(char::is_ascii_digit);
+                if starts_var {
+                    let mut var = String::from("t");
+                    while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                        var.push(*d);
+                        chars.next();
+                    }
+                    let next = format!("t{}", names.len());
+                    out.push_str(names.entry(var).or_insert(next));
+                    prev = '0';
+                } else {
+                    out.push(c);
+                    prev = c;
+                }
+            }
+            out
+        })
+        .collect()
+}
+
+// assert_same_as_analyze checks that the session's view of a document is
+// what a full parse and analysis of its source produce.
+fn assert_same_as_analyze(
+    session: &AnalysisSession,
+    name: &str,
+    got: &Result<nodes::Package, Error>,
+) {
+    let src = session.source(name).unwrap();
+    let file = parse_string(name, src);
+    assert_eq!(
+        session.ast(name).unwrap(),
+        &file,
+        "AST differs for:\n{}",
+        src
+    );
+    match (crate::analyze(file.into()), got) {
+        (Ok(want), Ok(got)) => assert_eq!(describe(&want), describe(got), "source:\n{}", src),
+        (Err(want), Err(got)) => assert_eq!(want.to_string(), got.to_string()),
+        (want, got) => panic!("want {:?}, got {:?} for:\n{}", want, got, src),
+    }
+}
+
+fn replace(session: &AnalysisSession, name: &str, old: &str, new: &str) -> TextEdit {
+    let start = session.source(name).unwrap().find(old).unwrap();
+    TextEdit {
+        start,
+        end: start + old.len(),
+        text: new.to_string(),
+    }
+}
+
+const SRC: &str = r#"import "strings"
+
+a = 1
+b = "x"
+f = (r) => r.v + a
+g = (s) => strings.toUpper(v: s) + b
+c = f(r: {v: a})
+d = g(s: "y")
+"#;
+
+#[test]
+fn open_matches_analyze() {
+    let mut session = AnalysisSession::new().unwrap();
+    let got = session.open("a.flux", SRC);
+    assert_same_as_analyze(&session, "a.flux", &got);
+    assert_eq!(
+        session.stats("a.flux").unwrap(),
+        &Stats {
+            parsed: 6,
+            converted: 6,
+            inferred: 6,
+        }
+    );
+}
+
+#[test]
+fn edit_reinfers_dependents() {
+    let mut session = AnalysisSession::new().unwrap();
+    session.open("a.flux", SRC).unwrap();
+
+    // Changing the type of a is seen by f and c but not by b, g or d. Only a
+    // and the guard after it are parsed again.
+    let edit = replace(&session, "a.flux", "a = 1", "a = 1.5");
+    let got = session.edit("a.flux", &edit);
+    assert_same_as_analyze(&session, "a.flux", &got);
+    assert_eq!(
+        session.stats("a.flux").unwrap(),
+        &Stats {
+            parsed: 2,
+            converted: 1,
+            inferred: 3,
+        }
+    );
+
+    // Editing within g keeps its type, so d is not inferred again.
+    let edit = replace(&session, "a.flux", "+ b", "+ \"z\"");
+    let got = session.edit("a.flux", &edit);
+    assert_same_as_analyze(&session, "a.flux", &got);
+    let stats = session.stats("a.flux").unwrap();
+    assert_eq!(stats.parsed, 3);
+    assert_eq!(stats.converted, 1);
+    assert_eq!(stats.inferred, 1);
+}
+
+#[test]
+fn edit_reuses_later_statements() {
+    let mut session = AnalysisSession::new().unwrap();
+    session.open("a.flux", SRC).unwrap();
+
+    // A new statement before f moves every later statement down. Only b, the
+    // new statement, f and g are parsed again, and g, c and d keep their
+    // entries.
+    let edit = replace(&session, "a.flux", "f = (r)", "y = 2\nf = (r)");
+    let got = session.edit("a.flux", &edit);
+    assert_same_as_analyze(&session, "a.flux", &got);
+    assert_eq!(
+        session.stats("a.flux").unwrap(),
+        &Stats {
+            parsed: 4,
+            converted: 2,
+            inferred: 2,
+        }
+    );
+
+    // Removing it again moves them back up.
+    let edit = replace(&session, "a.flux", "y = 2\n", "");
+    let got = session.edit("a.flux", &edit);
+    assert_same_as_analyze(&session, "a.flux", &got);
+    assert_eq!(
+        session.stats("a.flux").unwrap(),
+        &Stats {
+            parsed: 3,
+            converted: 1,
+            inferred: 1,
+        }
+    );
+}
+
+#[test]
+fn edit_sequence_matches_analyze() {
+    let mut session = AnalysisSession::new().unwrap();
+    session.open("a.flux", SRC).unwrap();
+    let edits = vec![
+        // type a character at a time
+        ("a = 1", "a = 12"),
+        ("a = 12", "a = 123"),
+        // add and remove lines
+        ("b = \"x\"\n", "b = \"x\"\ne = b + \"w\"\n"),
+        ("e = b + \"w\"\n", ""),
+        // join two statements onto one line
+        ("b = \"x\"\nf", "b = \"x\" f"),
+        // continue a statement onto the next one
+        ("c = f(r: {v: a})\n", "c = f(r: {v: a})\n+ "),
+        ("c = f(r: {v: a})\n+ ", "c = f(r: {v: a})\n"),
+        // edit the imports
+        ("import \"strings\"", "import \"strings\"\nimport \"math\""),
+        // insert before the first statement and append after the last
+        ("\na = ", "\nz = 0\na = "),
+        ("d = g(s: \"y\")\n", "d = g(s: \"y\")\nh = d + b\n"),
+    ];
+    for (old, new) in edits {
+        let edit = replace(&session, "a.flux", old, new);
+        let got = session.edit("a.flux", &edit);
+        assert_same_as_analyze(&session, "a.flux", &got);
+    }
+}
+
+#[test]
+fn edit_errors() {
+    let mut session = AnalysisSession::new().unwrap();
+    session.open("a.flux", SRC).unwrap();
+
+    let edit = replace(&session, "a.flux", "a = 1", "a = true");
+    let got = session.edit("a.flux", &edit);
+    assert!(got.is_err());
+    assert_same_as_analyze(&session, "a.flux", &got);
+
+    let edit = replace(&session, "a.flux", "a = true", "a = 1");
+    let got = session.edit("a.flux", &edit);
+    assert!(got.is_ok());
+    assert_same_as_analyze(&session, "a.flux", &got);
+
+    let edit = replace(&session, "a.flux", "b = \"x\"", "a = \"x\"");
+    let got = session.edit("a.flux", &edit);
+    assert!(got.is_err());
+    assert_same_as_analyze(&session, "a.flux", &got);
+
+    let bad = TextEdit {
+        start: 10,
+        end: 1000,
+        text: String::new(),
+    };
+    assert_eq!(
+        session.edit("a.flux", &bad).unwrap_err().to_string(),
+        "invalid edit range 10..1000"
+    );
+    assert_eq!(
+        session.edit("b.flux", &bad).unwrap_err().to_string(),
+        "document b.flux is not open"
+    );
+}
@@ -0,0 +1,1683 @@
+//! The native implementations of the builtins of the standard library
+//! that the evaluator supports. They follow the Go implementations, as
+//! far as the tests of the standard library show their behaviour.
+use std::cmp::Ordering;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use regex::Regex;
+
+use super::csv;
+use super::eval::{call, compare, error, Args, Builtin, Function, Result};
+use super::value::{
+    add_duration, format_time_nanos, timestamp, Duration, Record, Scope, Stream, Table, Tables,
+    Value,
+};
+
+/// Returns the value of a builtin of a package. A builtin function that is
+/// not implemented is a function that fails when it is called, so that
+/// only the programs that call it fail.
+pub fn builtin(pkg: &str, name: &str, scope: &Scope) -> Value {
+    let call: Option<Builtin> = match (pkg, name) {
+        ("universe", "true") => return Value::Bool(true),
+        ("universe", "false") => return Value::Bool(false),
+        ("universe", "inf") => return Value::Duration(Duration::INF),
+        ("universe", "columns") => Some(columns),
+        ("universe", "contains") => Some(contains),
+        ("universe", "count") => Some(count),
+        ("universe", "cumulativeSum") => Some(cumulative_sum),
+        ("universe", "difference") => Some(difference),
+        ("universe", "distinct") => Some(distinct),
+        ("universe", "drop") => Some(drop),
+        ("universe", "duplicate") => Some(duplicate),
+        ("universe", "fill") => Some(fill),
+        ("universe", "filter") => Some(filter),
+        ("universe", "first") => Some(first),
+        ("universe", "group") => Some(group),
+        ("universe", "keep") => Some(keep),
+        ("universe", "keys") => Some(keys),
+        ("universe", "last") => Some(last),
+        ("universe", "length") => Some(length),
+        ("universe", "limit") => Some(limit),
+        ("universe", "map") => Some(map),
+        ("universe", "max") => Some(max),
+        ("universe", "mean") => Some(mean),
+        ("universe", "min") => Some(min),
+        ("universe", "pivot") => Some(pivot),
+        ("universe", "range") => Some(range),
+        ("universe", "rename") => Some(rename),
+        ("universe", "set") => Some(set),
+        ("universe", "sort") => Some(sort),
+        ("universe", "sum") => Some(sum),
+        ("universe", "tail") => Some(tail),
+        ("universe", "union") => Some(union),
+        ("universe", "unique") => Some(unique),
+        ("universe", "window") => Some(window),
+        ("universe", "yield") => Some(yield_),
+        ("universe", "bool") => Some(to_bool),
+        ("universe", "float") => Some(to_float),
+        ("universe", "int") => Some(to_int),
+        ("universe", "string") => Some(to_string),
+        ("universe", "time") => Some(to_time),
+        ("universe", "uint") => Some(to_uint),
+        ("csv", "from") => Some(csv_from),
+        ("experimental", "addDuration") => Some(add_duration_to),
+        ("experimental", "subDuration") => Some(sub_duration_from),
+        ("system", "time") => Some(system_time),
+        ("date", _) => date(name),
+        ("math", _) => match math_constant(name) {
+            Some(v) => return v,
+            None => math(name),
+        },
+        ("strings", _) => strings(name),
+        ("regexp", _) => regexp(name),
+        _ => None,
+    };
+    let name = match pkg {
+        "universe" => name.to_string(),
+        _ => format!("{}.{}", pkg, name),
+    };
+    Value::Function(Rc::new(Function::Builtin {
+        name,
+        call,
+        scope: scope.clone(),
+    }))
+}
+
+/// Returns a stream of tables that keeps the bounds of the stream it is
+/// computed from.
+fn output(input: &Stream, tables: Vec<Table>) -> Result<Value> {
+    Ok(Value::Stream(Rc::new(Stream {
+        tables,
+        bounds: input.bounds,
+    })))
+}
+
+/// Merges the tables of the same group key, in the order the keys are
+/// first seen.
+fn regroup(tables: Vec<Table>) -> Vec<Table> {
+    let mut out = Tables::default();
+    for t in tables {
+        let columns = t.columns.clone();
+        let table = out.table(t.key.clone(), &columns);
+        for row in &t.rows {
+            table.push_record(t.record(row));
+        }
+    }
+    out.0
+}
+
+fn column(t: &Table, label: &str) -> Result<usize> {
+    match t.col(label) {
+        Some(j) => Ok(j),
+        None => error(format!("column {} does not exist", label)),
+    }
+}
+
+fn row_fn(f: &Value, t: &Table, row: &[Value]) -> Result<Value> {
+    let mut args = Record::new();
+    args.insert(String::from("r"), Value::Record(Rc::new(t.record(row))));
+    call(f, args, None)
+}
+
+fn column_fn(f: &Value, label: &str) -> Result<Value> {
+    let mut args = Record::new();
+    args.insert(String::from("column"), Value::String(label.to_string()));
+    call(f, args, None)
+}
+
+/// Returns a table with the same rows, keeping the columns for which keep
+/// returns true. The group key loses the columns that are not kept.
+fn keep_columns(t: &Table, mut keep: impl FnMut(&str) -> Result<bool>) -> Result<Table> {
+    let mut kept = vec![];
+    for (j, c) in t.columns.iter().enumerate() {
+        if keep(c)? {
+            kept.push(j);
+        }
+    }
+    let columns: Vec<String> = kept.iter().map(|&j| t.columns[j].clone()).collect();
+    let key = t
+        .key
+        .iter()
+        .filter(|(k, _)| columns.contains(k))
+        .cloned()
+        .collect();
+    // A table without columns has no rows either.
+    let mut out = Table::new(key, columns);
+    if !kept.is_empty() {
+        out.rows = t
+            .rows
+            .iter()
+            .map(|row| kept.iter().map(|&j| row[j].clone()).collect())
+            .collect();
+    }
+    Ok(out)
+}
+
+// range keeps the rows whose time is within the bounds, adding the bounds
+// to the group key. A table already in bounds that overlap them is given
+// the intersection of both.
+fn range(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let start = args.time("start")?;
+    let stop = match args.get("stop") {
+        Some(Value::Time(t)) => t,
+        Some(Value::Duration(d)) => add_duration(args.now()?, d),
+        Some(v) => {
+            return error(format!(
+                "range: stop must be a time, found {}",
+                v.type_name()
+            ))
+        }
+        None => args.now()?,
+    };
+    let time_col = args.opt_string("timeColumn", "_time")?;
+    let start_col = args.opt_string("startColumn", "_start")?;
+    let stop_col = args.opt_string("stopColumn", "_stop")?;
+    let mut tables = vec![];
+    for t in &input.tables {
+        let time = column(t, &time_col)?;
+        let (key_start, key_stop) = match (t.key_value(&start_col), t.key_value(&stop_col)) {
+            (Some(Value::Time(s)), Some(Value::Time(e))) if *s < stop && start < *e => {
+                (start.max(*s), stop.min(*e))
+            }
+            _ => (start, stop),
+        };
+        let mut key: Vec<(String, Value)> = t
+            .key
+            .iter()
+            .filter(|(k, _)| *k != start_col && *k != stop_col)
+            .cloned()
+            .collect();
+        key.push((start_col.clone(), Value::Time(key_start)));
+        key.push((stop_col.clone(), Value::Time(key_stop)));
+        // The bounds columns a table does not have come first.
+        let mut columns: Vec<String> = [&start_col, &stop_col]
+            .iter()
+            .filter(|c| t.col(c).is_none())
+            .map(|c| c.to_string())
+            .collect();
+        columns.extend(t.columns.iter().cloned());
+        let mut out = Table::new(key, columns);
+        for row in &t.rows {
+            match row[time] {
+                Value::Time(ts) if start <= ts && ts < stop => {
+                    let mut r = t.record(row);
+                    r.insert(start_col.clone(), Value::Time(key_start));
+                    r.insert(stop_col.clone(), Value::Time(key_stop));
+                    out.push_record(r);
+                }
+                _ => {}
+            }
+        }
+        tables.push(out);
+    }
+    let bounds = match input.bounds {
+        Some((s, e)) if s < stop && start < e => (start.max(s), stop.min(e)),
+        _ => (start, stop),
+    };
+    Ok(Value::Stream(Rc::new(Stream {
+        tables,
+        bounds: Some(bounds),
+    })))
+}
+
+fn filter(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let f = args.function("fn")?;
+    let drop_empty = args.opt_string("onEmpty", "drop")? == "drop";
+    let mut tables = vec![];
+    for t in &input.tables {
+        let mut out = Table::new(t.key.clone(), t.columns.clone());
+        for row in &t.rows {
+            if let Value::Bool(true) = row_fn(&f, t, row)? {
+                out.rows.push(row.clone());
+            }
+        }
+        if !out.rows.is_empty() || !drop_empty {
+            tables.push(out);
+        }
+    }
+    output(&input, tables)
+}
+
+// map groups each row by the columns of the group key of its table that
+// the function returns, with the values it returns for them. The columns
+// of a table are the properties returned for the first row of the table.
+fn map(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let f = args.function("fn")?;
+    let merge_key = args.opt_bool("mergeKey", false)?;
+    let mut out = Tables::default();
+    for t in &input.tables {
+        let mut on: Option<Vec<String>> = None;
+        let mut columns = vec![];
+        for row in &t.rows {
+            let r = match row_fn(&f, t, row)? {
+                Value::Record(r) => r,
+                v => {
+                    return error(format!(
+                        "map: fn must return a record, found {}",
+                        v.type_name()
+                    ))
+                }
+            };
+            let on = on.get_or_insert_with(|| {
+                columns = r.keys().cloned().collect();
+                if merge_key {
+                    for (k, _) in &t.key {
+                        if !columns.contains(k) {
+                            columns.push(k.clone());
+                        }
+                    }
+                }
+                t.key
+                    .iter()
+                    .map(|(k, _)| k.clone())
+                    .filter(|k| merge_key || r.contains_key(k))
+                    .collect()
+            });
+            let key = on
+                .iter()
+                .map(|k| {
+                    let v = r.get(k).or_else(|| t.key_value(k)).cloned();
+                    (k.clone(), v.unwrap_or(Value::Null))
+                })
+                .collect();
+            let table = out.table(key, &columns);
+            let row = table
+                .columns
+                .iter()
+                .map(|c| match r.get(c) {
+                    Some(v) => v.clone(),
+                    None if merge_key => t.key_value(c).cloned().unwrap_or(Value::Null),
+                    None => Value::Null,
+                })
+                .collect();
+            table.rows.push(row);
+        }
+    }
+    output(&input, out.0)
+}
+
+fn drop(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let columns = args.opt_strings("columns", &[])?;
+    let f = args.opt_function("fn")?;
+    let mut tables = vec![];
+    for t in &input.tables {
+        tables.push(keep_columns(t, |c| match &f {
+            Some(f) => Ok(column_fn(f, c)? != Value::Bool(true)),
+            None => Ok(!columns.iter().any(|d| d == c)),
+        })?);
+    }
+    output(&input, regroup(tables))
+}
+
+fn keep(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let columns = args.opt_strings("columns", &[])?;
+    let f = args.opt_function("fn")?;
+    let mut tables = vec![];
+    for t in &input.tables {
+        tables.push(keep_columns(t, |c| match &f {
+            Some(f) => Ok(column_fn(f, c)? == Value::Bool(true)),
+            None => Ok(columns.iter().any(|k| k == c)),
+        })?);
+    }
+    output(&input, regroup(tables))
+}
+
+fn rename(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let renames = match args.get("columns") {
+        Some(Value::Record(r)) => Some(r),
+        Some(v) => {
+            return error(format!(
+                "rename: columns must be a record, found {}",
+                v.type_name()
+            ))
+        }
+        None => None,
+    };
+    let f = args.opt_function("fn")?;
+    let new_name = |c: &str| -> Result<String> {
+        if let Some(renames) = &renames {
+            return Ok(match renames.get(c) {
+                Some(Value::String(to)) => to.clone(),
+                _ => c.to_string(),
+            });
+        }
+        match &f {
+            Some(f) => match column_fn(f, c)? {
+                Value::String(to) => Ok(to),
+                v => error(format!(
+                    "rename: fn must return a string, found {}",
+                    v.type_name()
+                )),
+            },
+            None => Ok(c.to_string()),
+        }
+    };
+    let mut tables = vec![];
+    for t in &input.tables {
+        let mut key = vec![];
+        for (k, v) in &t.key {
+            key.push((new_name(k)?, v.clone()));
+        }
+        let columns = t
+            .columns
+            .iter()
+            .map(|c| new_name(c))
+            .collect::<Result<Vec<String>>>()?;
+        let mut out = Table::new(key, columns);
+        out.rows = t.rows.clone();
+        tables.push(out);
+    }
+    output(&input, regroup(tables))
+}
+
+fn duplicate(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let from = args.string("column")?;
+    let to = args.string("as")?;
+    let mut tables = vec![];
+    for t in &input.tables {
+        let j = column(t, &from)?;
+        let mut out = t.clone();
+        let k = match out.col(&to) {
+            Some(k) => k,
+            None => {
+                out.columns.push(to.clone());
+                for row in &mut out.rows {
+                    row.push(Value::Null);
+                }
+                out.columns.len() - 1
+            }
+        };
+        for row in &mut out.rows {
+            row[k] = row[j].clone();
+        }
+        // A key column that is overwritten stays in the key only if it is
+        // overwritten by another key column.
+        if out.in_key(&to) {
+            let from_key = t.key_value(&from).cloned();
+            out.key.retain(|(c, _)| *c != to);
+            if let Some(v) = from_key {
+                out.key.push((to.clone(), v));
+                out.key.sort_by(|a, b| a.0.cmp(&b.0));
+            }
+        }
+        tables.push(out);
+    }
+    output(&input, regroup(tables))
+}
+
+fn group(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let columns = args.opt_strings("columns", &[])?;
+    let except = match args.opt_string("mode", "by")?.as_str() {
+        "by" => false,
+        "except" => true,
+        mode => return error(format!("group: unknown mode {}", mode)),
+    };
+    let mut out = Tables::default();
+    for t in &input.tables {
+        let on: Vec<usize> = if except {
+            (0..t.columns.len())
+                .filter(|&j| !columns.contains(&t.columns[j]))
+                .collect()
+        } else {
+            columns.iter().filter_map(|c| t.col(c)).collect()
+        };
+        for row in &t.rows {
+            let key = on
+                .iter()
+                .map(|&j| (t.columns[j].clone(), row[j].clone()))
+                .collect();
+            out.table(key, &t.columns).push_record(t.record(row));
+        }
+    }
+    output(&input, out.0)
+}
+
+fn yield_(args: &mut Args) -> Result<Value> {
+    Ok(Value::Stream(args.tables()?))
+}
+
+// Rows are sorted by the columns in order. Nulls sort first, whichever the
+// order.
+fn sort(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let columns = args.opt_strings("columns", &["_value"])?;
+    let desc = args.opt_bool("desc", false)?;
+    let mut tables = vec![];
+    for t in &input.tables {
+        let cols: Vec<usize> = columns.iter().filter_map(|c| t.col(c)).collect();
+        let mut out = t.clone();
+        out.rows.sort_by(|a, b| {
+            for &j in &cols {
+                let o = match (&a[j], &b[j]) {
+                    (Value::Null, Value::Null) => Ordering::Equal,
+                    (Value::Null, _) => return Ordering::Less,
+                    (_, Value::Null) => return Ordering::Greater,
+                    (x, y) => compare(x, y).unwrap_or(Ordering::Equal),
+                };
+                if o != Ordering::Equal {
+                    return if desc { o.reverse() } else { o };
+                }
+            }
+            Ordering::Equal
+        });
+        tables.push(out);
+    }
+    output(&input, tables)
+}
+
+fn limit(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let n = args.int("n")?.max(0) as usize;
+    let offset = args.opt_int("offset", 0)?.max(0) as usize;
+    let mut tables = vec![];
+    for t in &input.tables {
+        let mut out = t.clone();
+        out.rows = t.rows.iter().skip(offset).take(n).cloned().collect();
+        tables.push(out);
+    }
+    output(&input, tables)
+}
+
+fn tail(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let n = args.int("n")?.max(0) as usize;
+    let offset = args.opt_int("offset", 0)?.max(0) as usize;
+    let mut tables = vec![];
+    for t in &input.tables {
+        let stop = t.rows.len().saturating_sub(offset);
+        let mut out = t.clone();
+        out.rows = t.rows[stop.saturating_sub(n)..stop].to_vec();
+        tables.push(out);
+    }
+    output(&input, tables)
+}
+
+fn fill(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let label = args.opt_string("column", "_value")?;
+    let use_previous = args.opt_bool("usePrevious", false)?;
+    let value = args.get("value");
+    if value.is_none() && !use_previous {
+        return error(String::from(
+            "fill: either value or usePrevious must be given",
+        ));
+    }
+    let mut tables = vec![];
+    for t in &input.tables {
+        let j = column(t, &label)?;
+        let mut out = t.clone();
+        let mut previous = Value::Null;
+        for row in &mut out.rows {
+            if row[j].is_null() {
+                row[j] = match &value {
+                    Some(v) if !use_previous => v.clone(),
+                    _ => previous.clone(),
+                };
+            } else {
+                previous = row[j].clone();
+            }
+        }
+        if let (Some((_, v)), Some(first)) = (
+            out.key.iter_mut().find(|(k, _)| *k == label),
+            out.rows.first(),
+        ) {
+            *v = first[j].clone();
+        }
+        tables.push(out);
+    }
+    output(&input, regroup(tables))
+}
+
+fn set(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let label = args.string("key")?;
+    let value = Value::String(args.string("value")?);
+    let mut tables = vec![];
+    for t in &input.tables {
+        let mut out = t.clone();
+        match out.col(&label) {
+            Some(j) => {
+                for row in &mut out.rows {
+                    row[j] = value.clone();
+                }
+            }
+            None => {
+                out.columns.push(label.clone());
+                for row in &mut out.rows {
+                    row.push(value.clone());
+                }
+            }
+        }
+        for (k, v) in &mut out.key {
+            if *k == label {
+                *v = value.clone();
+            }
+        }
+        tables.push(out);
+    }
+    output(&input, regroup(tables))
+}
+
+/// Returns the tables of a selector, each with the row that select
+/// chooses from the values of the column, or no row.
+fn selector(args: &mut Args, select: fn(&[Value]) -> Option<usize>) -> Result<Value> {
+    let input = args.tables()?;
+    let label = args.opt_string("column", "_value")?;
+    let mut tables = vec![];
+    for t in &input.tables {
+        let j = column(t, &label)?;
+        let values: Vec<Value> = t.rows.iter().map(|row| row[j].clone()).collect();
+        let mut out = Table::new(t.key.clone(), t.columns.clone());
+        if let Some(i) = select(&values) {
+            out.rows.push(t.rows[i].clone());
+        }
+        tables.push(out);
+    }
+    output(&input, tables)
+}
+
+/// Returns the index of the first value that is ordered before every
+/// earlier value, skipping nulls.
+fn select_by(values: &[Value], want: Ordering) -> Option<usize> {
+    let mut selected: Option<usize> = None;
+    for (i, v) in values.iter().enumerate() {
+        if v.is_null() {
+            continue;
+        }
+        match selected {
+            Some(s) if compare(v, &values[s]) != Some(want) => {}
+            _ => selected = Some(i),
+        }
+    }
+    selected
+}
+
+fn first(args: &mut Args) -> Result<Value> {
+    selector(args, |vs| vs.iter().position(|v| !v.is_null()))
+}
+
+fn last(args: &mut Args) -> Result<Value> {
+    selector(args, |vs| vs.iter().rposition(|v| !v.is_null()))
+}
+
+fn max(args: &mut Args) -> Result<Value> {
+    selector(args, |vs| select_by(vs, Ordering::Greater))
+}
+
+fn min(args: &mut Args) -> Result<Value> {
+    selector(args, |vs| select_by(vs, Ordering::Less))
+}
+
+/// Returns the tables of an aggregate, each with the columns of its group
+/// key and the aggregated column, in a single row.
+fn aggregate(args: &mut Args, agg: fn(&[Value]) -> Result<Value>) -> Result<Value> {
+    let input = args.tables()?;
+    let label = args.opt_string("column", "_value")?;
+    let mut tables = vec![];
+    for t in &input.tables {
+        let j = column(t, &label)?;
+        if t.in_key(&label) {
+            return error(String::from(
+                "cannot aggregate columns that are part of the group key",
+            ));
+        }
+        let values: Vec<Value> = t.rows.iter().map(|row| row[j].clone()).collect();
+        let mut columns: Vec<String> = t.key.iter().map(|(k, _)| k.clone()).collect();
+        columns.push(label.clone());
+        let mut out = Table::new(t.key.clone(), columns);
+        let mut row: Vec<Value> = t.key.iter().map(|(_, v)| v.clone()).collect();
+        row.push(agg(&values)?);
+        out.rows.push(row);
+        tables.push(out);
+    }
+    output(&input, tables)
+}
+
+fn count(args: &mut Args) -> Result<Value> {
+    aggregate(args, |vs| Ok(Value::Int(vs.len() as i64)))
+}
+
+fn add(a: &Value, b: &Value) -> Result<Value> {
+    Ok(match (a, b) {
+        (Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_add(*b)),
+        (Value::UInt(a), Value::UInt(b)) => Value::UInt(a.wrapping_add(*b)),
+        (Value::Float(a), Value::Float(b)) => Value::Float(a + b),
+        (a, b) => return error(format!("cannot add {} to {}", b.type_name(), a.type_name())),
+    })
+}
+
+// The sum of no values, or of nulls only, is null.
+fn sum_of(vs: &[Value]) -> Result<Value> {
+    if let Some(fs) = floats(vs) {
+        return Ok(Value::Float(float_sum(&fs)));
+    }
+    let mut sum = Value::Null;
+    for v in vs.iter().filter(|v| !v.is_null()) {
+        sum = match sum {
+            Value::Null => v.clone(),
+            s => add(&s, v)?,
+        };
+    }
+    Ok(sum)
+}
+
+/// Returns the values if they are all floats and there is at least one.
+fn floats(vs: &[Value]) -> Option<Vec<f64>> {
+    if vs.is_empty() {
+        return None;
+    }
+    vs.iter()
+        .map(|v| match v {
+            Value::Float(f) => Some(*f),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Sums floats in the order Go does: Go sums a column without nulls with
+/// Arrow's vectorized sum, which adds the values into 16 lanes, adds the
+/// lanes pairwise and then adds the values left over. The order matters
+/// to the last digit of the sums the tests want.
+fn float_sum(vs: &[f64]) -> f64 {
+    const LANES: usize = 16;
+    let n = vs.len() / LANES * LANES;
+    let mut lanes = [0.0; LANES];
+    for (i, v) in vs[..n].iter().enumerate() {
+        lanes[i % LANES] += v;
+    }
+    let mut width = LANES;
+    while width > 1 {
+        width /= 2;
+        for i in 0..width {
+            lanes[i] += lanes[i + width];
+        }
+    }
+    vs[n..].iter().fold(lanes[0], |sum, v| sum + v)
+}
+
+fn sum(args: &mut Args) -> Result<Value> {
+    aggregate(args, sum_of)
+}
+
+// mean sums integers as integers, as Go does, before dividing.
+fn mean(args: &mut Args) -> Result<Value> {
+    aggregate(args, |vs| {
+        let n = vs.iter().filter(|v| !v.is_null()).count();
+        if n == 0 {
+            return Ok(Value::Null);
+        }
+        let sum = match sum_of(vs)? {
+            Value::Int(i) => i as f64,
+            Value::UInt(u) => u as f64,
+            Value::Float(f) => f,
+            v => return error(format!("cannot take the mean of a {}", v.type_name())),
+        };
+        Ok(Value::Float(sum / n as f64))
+    })
+}
+
+// difference replaces the values of each column with their difference
+// from the value before, dropping the first row unless it is kept. A
+// negative difference is null if only non-negative ones are wanted.
+fn difference(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let non_negative = args.opt_bool("nonNegative", false)?;
+    let columns = args.opt_strings("columns", &["_value"])?;
+    let keep_first = args.opt_bool("keepFirst", false)?;
+    let mut tables = vec![];
+    for t in &input.tables {
+        let cols: Vec<usize> = columns.iter().filter_map(|c| t.col(c)).collect();
+        let mut previous = vec![Value::Null; cols.len()];
+        let mut out = Table::new(t.key.clone(), t.columns.clone());
+        for (i, row) in t.rows.iter().enumerate() {
+            let mut row = row.clone();
+            for (p, &j) in previous.iter_mut().zip(&cols) {
+                let v = std::mem::replace(&mut row[j], Value::Null);
+                row[j] = match (&*p, &v) {
+                    (_, Value::Null) | (Value::Null, _) => Value::Null,
+                    (Value::Int(a), Value::Int(b)) => Value::Int(b.wrapping_sub(*a)),
+                    (Value::UInt(a), Value::UInt(b)) if a > b => Value::Int(-((a - b) as i64)),
+                    (Value::UInt(a), Value::UInt(b)) => Value::Int((b - a) as i64),
+                    (Value::Float(a), Value::Float(b)) => Value::Float(b - a),
+                    (_, v) => {
+                        return error(format!("difference: unsupported type {}", v.type_name()))
+                    }
+                };
+                let negative = match row[j] {
+                    Value::Int(d) => d < 0,
+                    Value::Float(d) => d < 0.0,
+                    _ => false,
+                };
+                if non_negative && negative {
+                    row[j] = Value::Null;
+                }
+                if !v.is_null() {
+                    *p = v;
+                }
+            }
+            if i > 0 || keep_first {
+                out.rows.push(row);
+            }
+        }
+        tables.push(out);
+    }
+    output(&input, tables)
+}
+
+fn cumulative_sum(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let columns = args.opt_strings("columns", &["_value"])?;
+    let mut tables = vec![];
+    for t in &input.tables {
+        let cols: Vec<usize> = columns.iter().filter_map(|c| t.col(c)).collect();
+        // A null adds nothing to the sum, which starts at the zero of the
+        // type of the column.
+        let mut sums: Vec<Value> = cols
+            .iter()
+            .map(
+                |&j| match t.rows.iter().map(|row| &row[j]).find(|v| !v.is_null()) {
+                    Some(Value::Int(_)) => Value::Int(0),
+                    Some(Value::UInt(_)) => Value::UInt(0),
+                    Some(Value::Float(_)) => Value::Float(0.0),
+                    _ => Value::Null,
+                },
+            )
+            .collect();
+        let mut out = t.clone();
+        for row in &mut out.rows {
+            for (s, &j) in sums.iter_mut().zip(&cols) {
+                if !row[j].is_null() {
+                    *s = add(s, &row[j])?;
+                }
+                row[j] = s.clone();
+            }
+        }
+        tables.push(out);
+    }
+    output(&input, tables)
+}
+
+/// Returns a table of the group key of a table and a column of values,
+/// a row for each.
+fn key_table(t: &Table, label: &str, values: Vec<Value>) -> Table {
+    let mut columns: Vec<String> = t.key.iter().map(|(k, _)| k.clone()).collect();
+    columns.push(label.to_string());
+    let mut out = Table::new(t.key.clone(), columns);
+    for v in values {
+        let mut row: Vec<Value> = t.key.iter().map(|(_, v)| v.clone()).collect();
+        row.push(v);
+        out.rows.push(row);
+    }
+    out
+}
+
+fn distinct(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let label = args.opt_string("column", "_value")?;
+    let mut tables = vec![];
+    for t in &input.tables {
+        let values = match (t.key_value(&label), t.col(&label)) {
+            (Some(v), _) => vec![v.clone()],
+            (None, Some(j)) => {
+                let mut values: Vec<Value> = vec![];
+                for row in &t.rows {
+                    if !values.contains(&row[j]) {
+                        values.push(row[j].clone());
+                    }
+                }
+                values
+            }
+            (None, None) => vec![Value::String(String::new())],
+        };
+        tables.push(key_table(t, "_value", values));
+    }
+    output(&input, tables)
+}
+
+fn unique(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let label = args.opt_string("column", "_value")?;
+    let mut tables = vec![];
+    for t in &input.tables {
+        let j = column(t, &label)?;
+        let mut seen: Vec<&Value> = vec![];
+        let mut out = Table::new(t.key.clone(), t.columns.clone());
+        for row in &t.rows {
+            if !seen.contains(&&row[j]) {
+                seen.push(&row[j]);
+                out.rows.push(row.clone());
+            }
+        }
+        tables.push(out);
+    }
+    output(&input, tables)
+}
+
+fn keys(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let label = args.opt_string("column", "_value")?;
+    let mut tables = vec![];
+    for t in &input.tables {
+        let labels = t
+            .columns
+            .iter()
+            .filter(|c| t.in_key(c))
+            .map(|c| Value::String(c.clone()))
+            .collect();
+        tables.push(key_table(t, &label, labels));
+    }
+    output(&input, tables)
+}
+
+fn columns(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let label = args.opt_string("column", "_value")?;
+    let mut tables = vec![];
+    for t in &input.tables {
+        let labels = t.columns.iter().map(|c| Value::String(c.clone())).collect();
+        tables.push(key_table(t, &label, labels));
+    }
+    output(&input, tables)
+}
+
+// pivot makes a row of each row key, with a column for each column key
+// holding its value. The columns of the group key that are neither pivoted
+// nor the value are kept, with the row key columns.
+fn pivot(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let row_key = args.strings("rowKey")?;
+    let column_key = args.strings("columnKey")?;
+    let value_column = args.string("valueColumn")?;
+    // The tables with the row of each row key.
+    let mut out: Vec<(Table, Vec<String>)> = vec![];
+    for t in &input.tables {
+        let value = column(t, &value_column)?;
+        let rows = row_key
+            .iter()
+            .map(|c| column(t, c))
+            .collect::<Result<Vec<usize>>>()?;
+        let cols = column_key
+            .iter()
+            .map(|c| column(t, c))
+            .collect::<Result<Vec<usize>>>()?;
+        let kept: Vec<usize> = (0..t.columns.len())
+            .filter(|&j| {
+                let c = &t.columns[j];
+                !column_key.contains(c)
+                    && *c != value_column
+                    && (t.in_key(c) || row_key.contains(c))
+            })
+            .collect();
+        let mut key: Vec<(String, Value)> = t
+            .key
+            .iter()
+            .filter(|(k, _)| !column_key.contains(k) && *k != value_column)
+            .cloned()
+            .collect();
+        key.sort_by(|a, b| a.0.cmp(&b.0));
+        let i = match out.iter().position(|(o, _)| o.key == key) {
+            Some(i) => i,
+            None => {
+                let columns = kept.iter().map(|&j| t.columns[j].clone()).collect();
+                out.push((Table::new(key, columns), vec![]));
+                out.len() - 1
+            }
+        };
+        let (table, row_keys) = &mut out[i];
+        for row in &t.rows {
+            let rk: String = rows.iter().map(|&j| pivot_label(&row[j])).collect();
+            let ck: Vec<String> = cols.iter().map(|&j| pivot_label(&row[j])).collect();
+            let ck = ck.join("_");
+            let c = match table.col(&ck) {
+                Some(c) => c,
+                None => {
+                    table.columns.push(ck);
+                    for r in &mut table.rows {
+                        r.push(Value::Null);
+                    }
+                    table.columns.len() - 1
+                }
+            };
+            let r = match row_keys.iter().position(|k| *k == rk) {
+                Some(r) => r,
+                None => {
+                    let mut new: Vec<Value> = kept.iter().map(|&j| row[j].clone()).collect();
+                    new.resize(table.columns.len(), Value::Null);
+                    table.rows.push(new);
+                    row_keys.push(rk);
+                    table.rows.len() - 1
+                }
+            };
+            table.rows[r][c] = row[value].clone();
+        }
+    }
+    output(&input, out.into_iter().map(|(t, _)| t).collect())
+}
+
+fn pivot_label(v: &Value) -> String {
+    v.to_string()
+}
+
+fn union(args: &mut Args) -> Result<Value> {
+    let streams = match args.required("tables")? {
+        Value::Array(vs) => vs,
+        v => {
+            return error(format!(
+                "union: tables must be an array, found {}",
+                v.type_name()
+            ))
+        }
+    };
+    let mut tables = vec![];
+    for s in streams.iter() {
+        match s {
+            Value::Stream(s) => tables.extend(s.tables.iter().cloned()),
+            v => {
+                return error(format!(
+                    "union: tables must be streams, found {}",
+                    v.type_name()
+                ))
+            }
+        }
+    }
+    output(&Stream::default(), regroup(tables))
+}
+
+/// Returns the windows that overlap the bounds, clipped to the bounds of
+/// the stream.
+fn windows(
+    (start, stop): (i64, i64),
+    every: i64,
+    period: i64,
+    offset: i64,
+    clip: (i64, i64),
+) -> Vec<(i64, i64)> {
+    let t = start - offset;
+    let mut w_stop = t - t % every + every + offset;
+    let mut w_start = w_stop - period;
+    let mut out = vec![];
+    while w_start < stop {
+        out.push((w_start.max(clip.0), w_stop.min(clip.1)));
+        w_start += every;
+        w_stop += every;
+    }
+    out
+}
+
+// window groups the rows of each table by the windows of time they fall
+// in, each window adding its bounds to the group key.
+fn window(args: &mut Args) -> Result<Value> {
+    let input = args.tables()?;
+    let every = args.opt_duration("every")?;
+    let period = args.opt_duration("period")?;
+    let offset = args.opt_duration("offset")?.unwrap_or(Duration {
+        months: 0,
+        nanoseconds: 0,
+    });
+    let time_col = args.opt_string("timeColumn", "_time")?;
+    let start_col = args.opt_string("startColumn", "_start")?;
+    let stop_col = args.opt_string("stopColumn", "_stop")?;
+    let create_empty = args.opt_bool("createEmpty", false)?;
+    let (every, period) = match (every, period) {
+        (Some(e), Some(p)) => (e, p),
+        (Some(e), None) => (e, e),
+        (None, Some(p)) => (p, p),
+        (None, None) => return error(String::from("window: every or period must be given")),
+    };
+    if every.months != 0 || period.months != 0 || offset.months != 0 {
+        return Err(super::eval::Error::Unsupported(String::from(
+            "window by months",
+        )));
+    }
+    let inf = every == Duration::INF;
+    let (every, period) = (every.nanoseconds, period.nanoseconds);
+    if every == 0 {
+        return error(String::from("window: every cannot be zero"));
+    }
+    let offset = offset.nanoseconds.rem_euclid(every);
+    let bounds = input.bounds.unwrap_or((i64::MIN, i64::MAX));
+    let window_key = |t: &Table, (start, stop): (i64, i64)| -> Vec<(String, Value)> {
+        let mut key: Vec<(String, Value)> = t
+            .key
+            .iter()
+            .filter(|(k, _)| *k != start_col && *k != stop_col)
+            .cloned()
+            .collect();
+        key.push((start_col.clone(), Value::Time(start)));
+        key.push((stop_col.clone(), Value::Time(stop)));
+        key
+    };
+    let mut out = Tables::default();
+    for t in &input.tables {
+        let time = column(t, &time_col)?;
+        let mut columns = t.columns.clone();
+        for c in &[&start_col, &stop_col] {
+            if !columns.contains(c) {
+                columns.push(c.to_string());
+            }
+        }
+        if create_empty {
+            let ws = if inf {
+                vec![(i64::MIN, i64::MAX)]
+            } else {
+                windows(bounds, every, period, offset, bounds)
+            };
+            for w in ws {
+                out.table(window_key(t, w), &columns);
+            }
+        }
+        for row in &t.rows {
+            let ts = match row[time] {
+                Value::Time(ts) => ts,
+                _ => continue,
+            };
+            let ws = if inf {
+                vec![bounds]
+            } else {
+                windows((ts, ts + 1), every, period, offset, bounds)
+            };
+            for w in ws {
+                let mut r = t.record(row);
+                r.insert(start_col.clone(), Value::Time(w.0));
+                r.insert(stop_col.clone(), Value::Time(w.1));
+                out.table(window_key(t, w), &columns).push_record(r);
+            }
+        }
+    }
+    // Go emits each window once time passes its stop, so the tables come
+    // in the order of their windows.
+    let mut tables = out.0;
+    tables.sort_by_key(
+        |t| match (t.key_value(&stop_col), t.key_value(&start_col)) {
+            (Some(Value::Time(stop)), Some(Value::Time(start))) => (*stop, *start),
+            _ => (i64::MAX, i64::MAX),
+        },
+    );
+    output(&input, tables)
+}
+
+fn length(args: &mut Args) -> Result<Value> {
+    match args.required("arr")? {
+        Value::Array(vs) => Ok(Value::Int(vs.len() as i64)),
+        v => error(format!(
+            "length: arr must be an array, found {}",
+            v.type_name()
+        )),
+    }
+}
+
+fn contains(args: &mut Args) -> Result<Value> {
+    let value = args.required("value")?;
+    match args.required("set")? {
+        Value::Array(vs) => Ok(Value::Bool(vs.contains(&value))),
+        v => error(format!(
+            "contains: set must be an array, found {}",
+            v.type_name()
+        )),
+    }
+}
+
+fn conversion(args: &mut Args, convert: fn(Value) -> Option<Value>) -> Result<Value> {
+    let v = args.required("v")?;
+    if v.is_null() {
+        return Ok(Value::Null);
+    }
+    let found = format!("{} {}", v.type_name(), v);
+    match convert(v) {
+        Some(v) => Ok(v),
+        None => error(format!("{}: cannot convert {}", args.name, found)),
+    }
+}
+
+fn to_bool(args: &mut Args) -> Result<Value> {
+    conversion(args, |v| match v {
+        Value::Bool(b) => Some(Value::Bool(b)),
+        Value::String(s) => s.parse().ok().map(Value::Bool),
+        Value::Int(0) | Value::UInt(0) => Some(Value::Bool(false)),
+        Value::Int(1) | Value::UInt(1) => Some(Value::Bool(true)),
+        Value::Float(f) if f == 0.0 || f == 1.0 => Some(Value::Bool(f == 1.0)),
+        _ => None,
+    })
+}
+
+fn to_float(args: &mut Args) -> Result<Value> {
+    conversion(args, |v| match v {
+        Value::Float(f) => Some(Value::Float(f)),
+        Value::Int(i) => Some(Value::Float(i as f64)),
+        Value::UInt(u) => Some(Value::Float(u as f64)),
+        Value::Bool(b) => Some(Value::Float(if b { 1.0 } else { 0.0 })),
+        Value::String(s) => s.parse().ok().map(Value::Float),
+        _ => None,
+    })
+}
+
+fn to_int(args: &mut Args) -> Result<Value> {
+    conversion(args, |v| match v {
+        Value::Int(i) => Some(Value::Int(i)),
+        Value::UInt(u) => Some(Value::Int(u as i64)),
+        Value::Float(f) => Some(Value::Int(float_to_int(f))),
+        Value::Bool(b) => Some(Value::Int(if b { 1 } else { 0 })),
+        Value::Time(t) => Some(Value::Int(t)),
+        Value::Duration(d) if d.months == 0 => Some(Value::Int(d.nanoseconds)),
+        Value::String(s) => s.parse().ok().map(Value::Int),
+        _ => None,
+    })
+}
+
+fn to_uint(args: &mut Args) -> Result<Value> {
+    conversion(args, |v| match v {
+        Value::UInt(u) => Some(Value::UInt(u)),
+        Value::Int(i) => Some(Value::UInt(i as u64)),
+        Value::Float(f) => Some(Value::UInt(float_to_uint(f))),
+        Value::Bool(b) => Some(Value::UInt(if b { 1 } else { 0 })),
+        Value::Time(t) => Some(Value::UInt(t as u64)),
+        Value::String(s) => s.parse().ok().map(Value::UInt),
+        _ => None,
+    })
+}
+
+/// Converts a float to an int as Go does on amd64, where a float out of
+/// range converts to the smallest int.
+fn float_to_int(f: f64) -> i64 {
+    const TWO_63: f64 = 9_223_372_036_854_775_808.0;
+    if !(-TWO_63..TWO_63).contains(&f) {
+        i64::MIN
+    } else {
+        f as i64
+    }
+}
+
+/// Converts a float to a uint as Go does on amd64, where a negative float
+/// converts through an int and a float past the largest uint converts to
+/// 2^63.
+fn float_to_uint(f: f64) -> u64 {
+    if f < 0.0 {
+        float_to_int(f) as u64
+    } else if f.is_nan() || f >= 18_446_744_073_709_551_616.0 {
+        1 << 63
+    } else {
+        f as u64
+    }
+}
+
+// string formats times with all nine fractional digits.
+fn to_string(args: &mut Args) -> Result<Value> {
+    conversion(args, |v| match v {
+        Value::Time(t) => Some(Value::String(format_time_nanos(t))),
+        Value::Bool(_)
+        | Value::Int(_)
+        | Value::UInt(_)
+        | Value::Float(_)
+        | Value::String(_)
+        | Value::Duration(_) => Some(Value::String(v.to_string())),
+        _ => None,
+    })
+}
+
+fn to_time(args: &mut Args) -> Result<Value> {
+    conversion(args, |v| match v {
+        Value::Time(t) => Some(Value::Time(t)),
+        Value::Int(i) => Some(Value::Time(i)),
+        Value::UInt(u) => Some(Value::Time(u as i64)),
+        Value::String(s) => chrono::DateTime::parse_from_rfc3339(&s)
+            .ok()
+            .map(|t| Value::Time(timestamp(&t.naive_utc()))),
+        _ => None,
+    })
+}
+
+fn csv_from(args: &mut Args) -> Result<Value> {
+    let src = match (args.get("csv"), args.get("file")) {
+        (Some(Value::String(csv)), None) => csv,
+        (None, Some(Value::String(file))) => match std::fs::read_to_string(&file) {
+            Ok(csv) => csv,
+            Err(err) => return error(format!("csv.from: {}: {}", file, err)),
+        },
+        _ => {
+            return error(String::from(
+                "csv.from: exactly one of csv and file must be given",
+            ))
+        }
+    };
+    match csv::decode(&src) {
+        Ok(tables) => output(&Stream::default(), tables),
+        Err(err) => error(format!("csv.from: {}", err)),
+    }
+}
+
+fn duration_arg(args: &mut Args, name: &str) -> Result<Duration> {
+    match args.required(name)? {
+        Value::Duration(d) => Ok(d),
+        v => error(format!(
+            "{}: {} must be a duration, found {}",
+            args.name,
+            name,
+            v.type_name()
+        )),
+    }
+}
+
+fn add_duration_to(args: &mut Args) -> Result<Value> {
+    let d = duration_arg(args, "d")?;
+    let to = args.time("to")?;
+    Ok(Value::Time(add_duration(to, d)))
+}
+
+fn sub_duration_from(args: &mut Args) -> Result<Value> {
+    let d = duration_arg(args, "d")?;
+    let from = args.time("from")?;
+    let d = Duration {
+        months: -d.months,
+        nanoseconds: -d.nanoseconds,
+    };
+    Ok(Value::Time(add_duration(from, d)))
+}
+
+fn system_time(_: &mut Args) -> Result<Value> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or(0);
+    Ok(Value::Time(now))
+}
+
+fn time_arg(args: &mut Args, name: &str) -> Result<NaiveDateTime> {
+    match args.required(name)? {
+        Value::Time(t) => match NaiveDateTime::from_timestamp_opt(
+            t.div_euclid(1_000_000_000),
+            t.rem_euclid(1_000_000_000) as u32,
+        ) {
+            Some(t) => Ok(t),
+            None => error(format!("{}: time {} out of range", args.name, t)),
+        },
+        v => error(format!(
+            "{}: argument {} must be a time, found {}",
+            args.name,
+            name,
+            v.type_name()
+        )),
+    }
+}
+
+fn date(name: &str) -> Option<Builtin> {
+    // Each part of a time is an int, as the Go package returns it.
+    macro_rules! part {
+        ($f:expr) => {{
+            fn part(args: &mut Args) -> Result<Value> {
+                let t = time_arg(args, "t")?;
+                #[allow(clippy::redundant_closure_call)]
+                Ok(Value::Int(($f)(t) as i64))
+            }
+            Some(part)
+        }};
+    }
+    match name {
+        "second" => part!(|t: NaiveDateTime| t.second()),
+        "minute" => part!(|t: NaiveDateTime| t.minute()),
+        "hour" => part!(|t: NaiveDateTime| t.hour()),
+        "weekDay" => part!(|t: NaiveDateTime| t.weekday().num_days_from_sunday()),
+        "monthDay" => part!(|t: NaiveDateTime| t.day()),
+        "yearDay" => part!(|t: NaiveDateTime| t.ordinal()),
+        "month" => part!(|t: NaiveDateTime| t.month()),
+        "year" => part!(|t: NaiveDateTime| t.year()),
+        "week" => part!(|t: NaiveDateTime| t.iso_week().week()),
+        "quarter" => part!(|t: NaiveDateTime| t.month0() / 3 + 1),
+        "millisecond" => part!(|t: NaiveDateTime| t.nanosecond() / 1_000_000),
+        "microsecond" => part!(|t: NaiveDateTime| t.nanosecond() / 1_000),
+        "nanosecond" => part!(|t: NaiveDateTime| t.nanosecond()),
+        "truncate" => Some(date_truncate),
+        _ => None,
+    }
+}
+
+fn date_truncate(args: &mut Args) -> Result<Value> {
+    let t = match args.required("t")? {
+        Value::Time(t) => t,
+        v => {
+            return error(format!(
+                "date.truncate: t must be a time, found {}",
+                v.type_name()
+            ))
+        }
+    };
+    match args.opt_duration("unit")? {
+        Some(Duration {
+            months: 0,
+            nanoseconds: unit,
+        }) if unit > 0 => Ok(Value::Time(t - t.rem_euclid(unit))),
+        _ => Err(super::eval::Error::Unsupported(String::from(
+            "date.truncate by months",
+        ))),
+    }
+}
+
+fn math_constant(name: &str) -> Option<Value> {
+    use std::f64::consts;
+    Some(match name {
+        "pi" => Value::Float(consts::PI),
+        "e" => Value::Float(consts::E),
+        "phi" => Value::Float(1.618_033_988_749_895),
+        "sqrt2" => Value::Float(consts::SQRT_2),
+        "sqrte" => Value::Float(consts::E.sqrt()),
+        "sqrtpi" => Value::Float(consts::PI.sqrt()),
+        "sqrtphi" => Value::Float(1.618_033_988_749_895_f64.sqrt()),
+        "ln2" => Value::Float(consts::LN_2),
+        "log2e" => Value::Float(consts::LOG2_E),
+        "ln10" => Value::Float(consts::LN_10),
+        "log10e" => Value::Float(consts::LOG10_E),
+        "maxfloat" => Value::Float(f64::MAX),
+        "smallestNonzeroFloat" => Value::Float(f64::from_bits(1)),
+        "maxint" => Value::Int(i64::MAX),
+        "minint" => Value::Int(i64::MIN),
+        "maxuint" => Value::UInt(u64::MAX),
+        _ => return None,
+    })
+}
+
+fn float_arg(args: &mut Args, name: &str) -> Result<f64> {
+    match args.required(name)? {
+        Value::Float(f) => Ok(f),
+        v => error(format!(
+            "{}: {} must be a float, found {}",
+            args.name,
+            name,
+            v.type_name()
+        )),
+    }
+}
+
+fn math(name: &str) -> Option<Builtin> {
+    // Each function takes the float x.
+    macro_rules! unary {
+        ($f:expr) => {{
+            fn unary(args: &mut Args) -> Result<Value> {
+                let x = float_arg(args, "x")?;
+                #[allow(clippy::redundant_closure_call)]
+                Ok(Value::Float(($f)(x)))
+            }
+            Some(unary)
+        }};
+    }
+    // Each function takes the floats x and y.
+    macro_rules! binary {
+        ($f:expr) => {{
+            fn binary(args: &mut Args) -> Result<Value> {
+                let x = float_arg(args, "x")?;
+                let y = float_arg(args, "y")?;
+                #[allow(clippy::redundant_closure_call)]
+                Ok(Value::Float(($f)(x, y)))
+            }
+            Some(binary)
+        }};
+    }
+    match name {
+        "abs" => unary!(f64::abs),
+        "acos" => unary!(f64::acos),
+        "acosh" => unary!(f64::acosh),
+        "asin" => unary!(f64::asin),
+        "asinh" => unary!(f64::asinh),
+        "atan" => unary!(f64::atan),
+        "atanh" => unary!(f64::atanh),
+        "cbrt" => unary!(f64::cbrt),
+        "ceil" => unary!(f64::ceil),
+        "cos" => unary!(f64::cos),
+        "cosh" => unary!(f64::cosh),
+        "exp" => unary!(f64::exp),
+        "exp2" => unary!(f64::exp2),
+        "expm1" => unary!(f64::exp_m1),
+        "floor" => unary!(f64::floor),
+        "log" => unary!(f64::ln),
+        "log10" => unary!(f64::log10),
+        "log1p" => unary!(f64::ln_1p),
+        "log2" => unary!(f64::log2),
+        "round" => unary!(f64::round),
+        "sin" => unary!(f64::sin),
+        "sinh" => unary!(f64::sinh),
+        "sqrt" => unary!(f64::sqrt),
+        "tan" => unary!(f64::tan),
+        "tanh" => unary!(f64::tanh),
+        "trunc" => unary!(f64::trunc),
+        "atan2" => binary!(f64::atan2),
+        "copysign" => binary!(f64::copysign),
+        "dim" => binary!(|x: f64, y: f64| (x - y).max(0.0)),
+        "hypot" => binary!(f64::hypot),
+        "mMax" => binary!(f64::max),
+        "mMin" => binary!(f64::min),
+        "mod" => binary!(|x: f64, y: f64| x % y),
+        "pow" => binary!(f64::powf),
+        _ => None,
+    }
+}
+
+fn strings(name: &str) -> Option<Builtin> {
+    // Each function takes the string v and returns its result for it.
+    macro_rules! unary {
+        ($f:expr) => {{
+            fn unary(args: &mut Args) -> Result<Value> {
+                let v = args.string("v")?;
+                #[allow(clippy::redundant_closure_call)]
+                Ok(($f)(v.as_str()))
+            }
+            Some(unary)
+        }};
+    }
+    // Each function takes the string v and a second string argument.
+    macro_rules! binary {
+        ($arg:expr, $f:expr) => {{
+            fn binary(args: &mut Args) -> Result<Value> {
+                let v = args.string("v")?;
+                let a = args.string($arg)?;
+                #[allow(clippy::redundant_closure_call)]
+                Ok(($f)(v.as_str(), a.as_str()))
+            }
+            Some(binary)
+        }};
+    }
+    match name {
+        "strlen" => unary!(|v: &str| Value::Int(v.chars().count() as i64)),
+        "toUpper" => unary!(|v: &str| Value::String(v.to_uppercase())),
+        "toLower" => unary!(|v: &str| Value::String(v.to_lowercase())),
+        "trimSpace" => unary!(|v: &str| Value::String(v.trim().to_string())),
+        "title" => unary!(|v: &str| Value::String(title(v))),
+        "trim" => binary!("cutset", |v: &str, c: &str| Value::String(
+            v.trim_matches(|x| c.contains(x)).to_string()
+        )),
+        "trimLeft" => binary!("cutset", |v: &str, c: &str| Value::String(
+            v.trim_start_matches(|x| c.contains(x)).to_string()
+        )),
+        "trimRight" => binary!("cutset", |v: &str, c: &str| Value::String(
+            v.trim_end_matches(|x| c.contains(x)).to_string()
+        )),
+        "trimPrefix" => binary!("prefix", |v: &str, p: &str| Value::String(
+            v.strip_prefix(p).unwrap_or(v).to_string()
+        )),
+        "trimSuffix" => binary!("suffix", |v: &str, p: &str| Value::String(
+            v.strip_suffix(p).unwrap_or(v).to_string()
+        )),
+        "hasPrefix" => binary!("prefix", |v: &str, p: &str| Value::Bool(v.starts_with(p))),
+        "hasSuffix" => binary!("suffix", |v: &str, p: &str| Value::Bool(v.ends_with(p))),
+        "containsStr" => binary!("substr", |v: &str, p: &str| Value::Bool(v.contains(p))),
+        "countStr" => binary!("substr", |v: &str, p: &str| Value::Int(if p.is_empty() {
+            v.chars().count() as i64 + 1
+        } else {
+            v.matches(p).count() as i64
+        })),
+        "split" => binary!("t", |v: &str, t: &str| split_n(v, t, -1)),
+        "replaceAll" => Some(strings_replace_all),
+        "replace" => Some(strings_replace),
+        "repeat" => Some(strings_repeat),
+        "splitN" => Some(strings_split_n),
+        "joinStr" => Some(strings_join),
+        "substring" => Some(strings_substring),
+        _ => None,
+    }
+}
+
+/// Capitalizes the first letter of each word, as Go's strings.Title does.
+fn title(v: &str) -> String {
+    let mut out = String::new();
+    let mut start = true;
+    for c in v.chars() {
+        if start && c.is_alphabetic() {
+            out.extend(c.to_uppercase());
+        } else {
+            out.push(c);
+        }
+        start = !(c.is_alphanumeric() || c == '_' || c == '\'');
+    }
+    out
+}
+
+/// Splits a string around a separator into at most n parts, or all of
+/// them if n is negative, as Go's strings.SplitN does.
+fn split_n(v: &str, t: &str, n: i64) -> Value {
+    let parts: Vec<&str> = match (n, t) {
+        (0, _) => vec![],
+        (n, "") if n < 0 => v.split_terminator("").skip(1).collect(),
+        (n, _) if n < 0 => v.split(t).collect(),
+        (n, _) => v.splitn(n as usize, t).collect(),
+    };
+    Value::Array(Rc::new(
+        parts
+            .into_iter()
+            .map(|p| Value::String(p.to_string()))
+            .collect(),
+    ))
+}
+
+fn strings_replace_all(args: &mut Args) -> Result<Value> {
+    let v = args.string("v")?;
+    let t = args.string("t")?;
+    let u = args.string("u")?;
+    Ok(Value::String(v.replace(&t, &u)))
+}
+
+fn strings_replace(args: &mut Args) -> Result<Value> {
+    let v = args.string("v")?;
+    let t = args.string("t")?;
+    let u = args.string("u")?;
+    let i = args.int("i")?;
+    Ok(Value::String(if i < 0 {
+        v.replace(&t, &u)
+    } else {
+        v.replacen(&t, &u, i as usize)
+    }))
+}
+
+fn strings_repeat(args: &mut Args) -> Result<Value> {
+    let v = args.string("v")?;
+    let i = args.int("i")?;
+    if i < 0 {
+        return error(String::from("strings.repeat: negative count"));
+    }
+    Ok(Value::String(v.repeat(i as usize)))
+}
+
+fn strings_split_n(args: &mut Args) -> Result<Value> {
+    let v = args.string("v")?;
+    let t = args.string("t")?;
+    let i = args.int("i")?;
+    Ok(split_n(&v, &t, i))
+}
+
+fn strings_join(args: &mut Args) -> Result<Value> {
+    let arr = args.strings("arr")?;
+    let v = args.string("v")?;
+    Ok(Value::String(arr.join(&v)))
+}
+
+// substring counts the characters of v, not its bytes.
+fn strings_substring(args: &mut Args) -> Result<Value> {
+    let v: Vec<char> = args.string("v")?.chars().collect();
+    let start = args.int("start")?;
+    let end = args.int("end")?;
+    if start < 0 || end < start || end as usize > v.len() {
+        return error(format!(
+            "strings.substring: indices {}:{} out of range for a string of length {}",
+            start,
+            end,
+            v.len()
+        ));
+    }
+    Ok(Value::String(
+        v[start as usize..end as usize].iter().collect(),
+    ))
+}
+
+fn regexp_arg(args: &mut Args) -> Result<Rc<Regex>> {
+    match args.required("r")? {
+        Value::Regexp(re) => Ok(re),
+        v => error(format!(
+            "{}: r must be a regexp, found {}",
+            args.name,
+            v.type_name()
+        )),
+    }
+}
+
+fn regexp(name: &str) -> Option<Builtin> {
+    match name {
+        "compile" => Some(regexp_compile),
+        "matchRegexpString" => Some(regexp_match),
+        "findString" => Some(regexp_find),
+        "findStringIndex" => Some(regexp_find_index),
+        "replaceAllString" => Some(regexp_replace_all),
+        "quoteMeta" => Some(regexp_quote_meta),
+        _ => None,
+    }
+}
+
+fn regexp_compile(args: &mut Args) -> Result<Value> {
+    let v = args.string("v")?;
+    match Regex::new(&v) {
+        Ok(re) => Ok(Value::Regexp(Rc::new(re))),
+        Err(err) => error(format!("regexp.compile: {}", err)),
+    }
+}
+
+fn regexp_match(args: &mut Args) -> Result<Value> {
+    let re = regexp_arg(args)?;
+    Ok(Value::Bool(re.is_match(&args.string("v")?)))
+}
+
+fn regexp_find(args: &mut Args) -> Result<Value> {
+    let re = regexp_arg(args)?;
+    let v = args.string("v")?;
+    Ok(Value::String(
+        re.find(&v)
+            .map(|m| m.as_str().to_string())
+            .unwrap_or_default(),
+    ))
+}
+
+fn regexp_find_index(args: &mut Args) -> Result<Value> {
+    let re = regexp_arg(args)?;
+    let v = args.string("v")?;
+    let index = match re.find(&v) {
+        Some(m) => vec![Value::Int(m.start() as i64), Value::Int(m.end() as i64)],
+        None => vec![],
+    };
+    Ok(Value::Array(Rc::new(index)))
+}
+
+// The replacement refers to groups as $1, as Go's does.
+fn regexp_replace_all(args: &mut Args) -> Result<Value> {
+    let re = regexp_arg(args)?;
+    let v = args.string("v")?;
+    let t = args.string("t")?;
+    Ok(Value::String(re.replace_all(&v, t.as_str()).into_owned()))
+}
+
+fn regexp_quote_meta(args: &mut Args) -> Result<Value> {
+    Ok(Value::String(regex::escape(&args.string("v")?)))
+}
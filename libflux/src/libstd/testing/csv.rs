@@ -0,0 +1,184 @@
+//! Decoding of annotated CSV, the format the test cases of the standard
+//! library write their input and wanted tables in:
+//!
+//! ```text
+//! #datatype,string,long,dateTime:RFC3339,double,string
+//! #group,false,false,false,false,true
+//! #default,_result,,,,
+//! ,result,table,_time,_value,host
+//! ,,0,2018-05-22T19:53:26Z,1.5,a
+//! ```
+//!
+//! Each block of annotations, header and rows holds the tables whose rows
+//! follow, told apart by the table column. A table is grouped by the
+//! columns its group annotation marks true. An empty value is the default
+//! of its column, or null if the column has none. Blank lines are ignored.
+//!
+//! A document may hold several results, told apart by the result column.
+//! Only the tables of the first are decoded, as Go's csv.from does.
+use chrono::DateTime;
+
+use super::value::{timestamp, Table, Value};
+
+/// Decodes the tables of an annotated CSV document, in the order they
+/// appear.
+pub fn decode(csv: &str) -> Result<Vec<Table>, String> {
+    let mut tables = vec![];
+    let mut result: Option<String> = None;
+    let records = split(csv)?;
+    let mut records = records.iter().filter(|r| !is_blank(r)).peekable();
+    loop {
+        if records.peek().is_none() {
+            return Ok(tables);
+        }
+        let mut types = None;
+        let mut groups = None;
+        let mut defaults = None;
+        while let Some(r) = records.peek() {
+            match r[0].as_str() {
+                "#datatype" => types = Some(r[1..].to_vec()),
+                "#group" => {
+                    groups = Some(r[1..].iter().map(|g| g == "true").collect::<Vec<bool>>())
+                }
+                "#default" => defaults = Some(r[1..].to_vec()),
+                a if a.starts_with('#') => return Err(format!("unknown annotation {}", a)),
+                _ => break,
+            }
+            records.next();
+        }
+        let header = match records.next() {
+            Some(h) => h[1..].to_vec(),
+            None => return Err(String::from("missing header")),
+        };
+        let types = match types {
+            Some(types) => types,
+            None => return Err(String::from("missing #datatype annotation")),
+        };
+        if header.iter().any(|h| h == "error") && header.iter().any(|h| h == "reference") {
+            return Err(String::from("the CSV holds an error instead of tables"));
+        }
+        let n = header.len();
+        let groups = groups.unwrap_or_else(|| vec![false; n]);
+        let defaults = defaults.unwrap_or_else(|| vec![String::new(); n]);
+        if types.len() != n || groups.len() != n || defaults.len() != n {
+            return Err(String::from(
+                "the annotations and the header have a different number of columns",
+            ));
+        }
+        let table_col = header.iter().position(|h| h == "table");
+        let result_col = header.iter().position(|h| h == "result");
+        // The columns of the tables, by their index in the records.
+        let cols: Vec<usize> = (0..n)
+            .filter(|&j| header[j] != "result" && header[j] != "table")
+            .collect();
+        let labels: Vec<String> = cols.iter().map(|&j| header[j].clone()).collect();
+
+        let mut id = None;
+        while let Some(r) = records.peek() {
+            if r[0].starts_with('#') {
+                break;
+            }
+            let r = &r[1..];
+            if r.len() != n {
+                return Err(format!(
+                    "expected {} columns, found {}: {}",
+                    n,
+                    r.len(),
+                    r.join(",")
+                ));
+            }
+            let field = |j: usize| if r[j].is_empty() { &defaults[j] } else { &r[j] };
+            if let Some(name) = result_col.map(field) {
+                match &result {
+                    Some(first) if first != name => return Ok(tables),
+                    Some(_) => {}
+                    None => result = Some(name.clone()),
+                }
+            }
+            let value = |j: usize| -> Result<Value, String> {
+                parse(field(j), &types[j]).map_err(|e| format!("column {}: {}", header[j], e))
+            };
+            let row = cols
+                .iter()
+                .map(|&j| value(j))
+                .collect::<Result<Vec<Value>, String>>()?;
+            let row_id = table_col.map(field);
+            if id != Some(row_id) {
+                let key = cols
+                    .iter()
+                    .zip(&row)
+                    .filter(|(&j, _)| groups[j])
+                    .map(|(&j, v)| (header[j].clone(), v.clone()))
+                    .collect();
+                tables.push(Table::new(key, labels.clone()));
+                id = Some(row_id);
+            }
+            tables.last_mut().unwrap().rows.push(row);
+            records.next();
+        }
+    }
+}
+
+fn is_blank(r: &[String]) -> bool {
+    r.iter().all(|f| f.trim().is_empty())
+}
+
+/// Parses a value of a CSV data type. The empty value is null.
+fn parse(v: &str, typ: &str) -> Result<Value, String> {
+    if v.is_empty() {
+        return Ok(Value::Null);
+    }
+    let invalid = |e: &dyn ToString| format!("invalid {} {:?}: {}", typ, v, e.to_string());
+    Ok(match typ {
+        "string" => Value::String(v.to_string()),
+        "long" => Value::Int(v.parse().map_err(|e| invalid(&e))?),
+        "unsignedLong" => Value::UInt(v.parse().map_err(|e| invalid(&e))?),
+        "double" => Value::Float(v.parse().map_err(|e| invalid(&e))?),
+        "boolean" => Value::Bool(v.parse().map_err(|e| invalid(&e))?),
+        "dateTime" | "dateTime:RFC3339" | "dateTime:RFC3339Nano" => Value::Time(timestamp(
+            &DateTime::parse_from_rfc3339(v)
+                .map_err(|e| invalid(&e))?
+                .naive_utc(),
+        )),
+        _ => return Err(format!("unsupported data type {}", typ)),
+    })
+}
+
+/// Splits a CSV document into records of fields. Fields may be quoted, in
+/// which case they may hold commas, newlines and doubled quotes. A blank
+/// line is a record of one empty field.
+fn split(csv: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = csv.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(String::from("unterminated quoted field"));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
@@ -0,0 +1,83 @@
+//! Comparison of the tables a test got with the tables it wants, as the
+//! diff function of the testing package compares them. Tables are matched
+//! by group key, a table missing on one side being empty, and the rows of
+//! matched tables are compared in order. Rows are equal if they have the
+//! same columns, apart from those of the group key, and the same values.
+use std::collections::BTreeMap;
+
+use super::value::{Table, Value};
+
+/// Returns the difference between the tables wanted and got, or None if
+/// they do not differ. Each table that differs is listed by its group key
+/// with the rows that differ, the wanted row marked - and the row got
+/// marked +.
+pub fn diff(want: &[Table], got: &[Table]) -> Option<String> {
+    let mut keys: Vec<&Vec<(String, Value)>> = vec![];
+    for t in want.iter().chain(got) {
+        if !keys.contains(&&t.key) {
+            keys.push(&t.key);
+        }
+    }
+    let mut out = String::new();
+    for key in keys {
+        let want = rows(want, key);
+        let got = rows(got, key);
+        let mut lines = vec![];
+        for i in 0..want.len().max(got.len()) {
+            match (want.get(i), got.get(i)) {
+                (Some(w), Some(g)) if w == g => {}
+                (w, g) => {
+                    if let Some(w) = w {
+                        lines.push(format!("-   {}", format_row(w)));
+                    }
+                    if let Some(g) = g {
+                        lines.push(format!("+   {}", format_row(g)));
+                    }
+                }
+            }
+        }
+        if !lines.is_empty() {
+            out.push_str(&format!(
+                "table {}\n",
+                format_row(&key.iter().cloned().collect())
+            ));
+            for line in lines {
+                out.push_str(&line);
+                out.push('\n');
+            }
+        }
+    }
+    if out.is_empty() {
+        None
+    } else {
+        Some(out)
+    }
+}
+
+/// Returns the rows of the tables of a group key, without the columns of
+/// the key.
+fn rows(tables: &[Table], key: &[(String, Value)]) -> Vec<BTreeMap<String, Value>> {
+    tables
+        .iter()
+        .filter(|t| t.key == key)
+        .flat_map(|t| {
+            t.rows.iter().map(move |row| {
+                t.record(row)
+                    .into_iter()
+                    .filter(|(label, _)| !t.in_key(label))
+                    .collect()
+            })
+        })
+        .collect()
+}
+
+fn format_row(r: &BTreeMap<String, Value>) -> String {
+    let fields: Vec<String> = r
+        .iter()
+        .map(|(k, v)| match v {
+            Value::String(s) => format!("{}={:?}", k, s),
+            v => format!("{}={}", k, v),
+        })
+        .collect();
+    format!("{{{}}}", fields.join(", "))
+}
@@ -0,0 +1,641 @@
+//! An evaluator of the semantic graph. It walks the graph of a file,
+//! importing the Flux packages of the standard library from their source
+//! and implementing the builtins they declare natively, as far as the
+//! tests of the standard library need them.
+//!
+//! Programs are assumed to type check, so type errors are reported as
+//! plainly as possible rather than explained.
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use flux::ast;
+use flux::parser::parse_string;
+use flux::semantic::convert::convert_file;
+use flux::semantic::fresh::Fresher;
+use flux::semantic::nodes::{
+    Assignment, Block, Expression, File, FunctionExpr, Statement, StringExprPart,
+};
+use regex::Regex;
+
+use super::builtins;
+use super::value::{add_duration, timestamp, Duration, Record, Scope, Stream, Value};
+
+/// The packages whose members are in scope in every file.
+const PRELUDE: [&str; 2] = ["universe", "influxdata/influxdb"];
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Error {
+    /// The program calls a builtin that is not implemented here.
+    Unsupported(String),
+    Runtime(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Unsupported(name) => write!(f, "{} is not implemented by the test runner", name),
+            Error::Runtime(msg) => f.write_str(msg),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+pub fn error<T>(msg: String) -> Result<T> {
+    Err(Error::Runtime(msg))
+}
+
+/// Builtin is the native implementation of a builtin function.
+pub type Builtin = fn(&mut Args) -> Result<Value>;
+
+pub enum Function {
+    Flux {
+        expr: Box<FunctionExpr>,
+        scope: Scope,
+    },
+    /// Builtin is a builtin function of a package, with the scope of the
+    /// package. A builtin without an implementation fails when called.
+    Builtin {
+        name: String,
+        call: Option<Builtin>,
+        scope: Scope,
+    },
+}
+
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Function::Flux { .. } => f.write_str("<function>"),
+            Function::Builtin { name, .. } => write!(f, "<builtin {}>", name),
+        }
+    }
+}
+
+/// Args are the arguments of a call to a builtin. Each argument is taken
+/// by the builtin as it reads it.
+pub struct Args {
+    pub name: String,
+    args: Record,
+    pipe: Option<Value>,
+    scope: Scope,
+}
+
+impl Args {
+    pub fn get(&mut self, name: &str) -> Option<Value> {
+        self.args.remove(name)
+    }
+
+    pub fn required(&mut self, name: &str) -> Result<Value> {
+        match self.get(name) {
+            Some(v) => Ok(v),
+            None => error(format!("{}: missing required argument {}", self.name, name)),
+        }
+    }
+
+    fn invalid<T>(&self, name: &str, want: &str, v: &Value) -> Result<T> {
+        error(format!(
+            "{}: argument {} must be {}, found {}",
+            self.name,
+            name,
+            want,
+            v.type_name()
+        ))
+    }
+
+    /// Returns the piped tables, or the tables argument if nothing is piped.
+    pub fn tables(&mut self) -> Result<Rc<Stream>> {
+        let v = match self.pipe.take() {
+            Some(v) => v,
+            None => self.required("tables")?,
+        };
+        match v {
+            Value::Stream(s) => Ok(s),
+            v => self.invalid("tables", "a stream", &v),
+        }
+    }
+
+    pub fn string(&mut self, name: &str) -> Result<String> {
+        match self.required(name)? {
+            Value::String(s) => Ok(s),
+            v => self.invalid(name, "a string", &v),
+        }
+    }
+
+    pub fn opt_string(&mut self, name: &str, default: &str) -> Result<String> {
+        match self.get(name) {
+            None => Ok(default.to_string()),
+            Some(Value::String(s)) => Ok(s),
+            Some(v) => self.invalid(name, "a string", &v),
+        }
+    }
+
+    pub fn int(&mut self, name: &str) -> Result<i64> {
+        match self.required(name)? {
+            Value::Int(i) => Ok(i),
+            v => self.invalid(name, "an int", &v),
+        }
+    }
+
+    pub fn opt_int(&mut self, name: &str, default: i64) -> Result<i64> {
+        match self.get(name) {
+            None => Ok(default),
+            Some(Value::Int(i)) => Ok(i),
+            Some(v) => self.invalid(name, "an int", &v),
+        }
+    }
+
+    pub fn opt_bool(&mut self, name: &str, default: bool) -> Result<bool> {
+        match self.get(name) {
+            None => Ok(default),
+            Some(Value::Bool(b)) => Ok(b),
+            Some(v) => self.invalid(name, "a bool", &v),
+        }
+    }
+
+    pub fn opt_duration(&mut self, name: &str) -> Result<Option<Duration>> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(Value::Duration(d)) => Ok(Some(d)),
+            Some(v) => self.invalid(name, "a duration", &v),
+        }
+    }
+
+    pub fn strings(&mut self, name: &str) -> Result<Vec<String>> {
+        let v = self.required(name)?;
+        self.to_strings(name, v)
+    }
+
+    pub fn opt_strings(&mut self, name: &str, default: &[&str]) -> Result<Vec<String>> {
+        match self.get(name) {
+            None => Ok(default.iter().map(|s| s.to_string()).collect()),
+            Some(v) => self.to_strings(name, v),
+        }
+    }
+
+    fn to_strings(&self, name: &str, v: Value) -> Result<Vec<String>> {
+        match &v {
+            Value::Array(vs) => vs
+                .iter()
+                .map(|v| match v {
+                    Value::String(s) => Ok(s.clone()),
+                    v => self.invalid(name, "an array of strings", v),
+                })
+                .collect(),
+            v => self.invalid(name, "an array of strings", v),
+        }
+    }
+
+    pub fn function(&mut self, name: &str) -> Result<Value> {
+        match self.required(name)? {
+            v @ Value::Function(_) => Ok(v),
+            v => self.invalid(name, "a function", &v),
+        }
+    }
+
+    pub fn opt_function(&mut self, name: &str) -> Result<Option<Value>> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(v @ Value::Function(_)) => Ok(Some(v)),
+            Some(v) => self.invalid(name, "a function", &v),
+        }
+    }
+
+    /// Returns a time argument. A duration is the time that long after now.
+    pub fn time(&mut self, name: &str) -> Result<i64> {
+        match self.required(name)? {
+            Value::Time(t) => Ok(t),
+            Value::Duration(d) => Ok(add_duration(self.now()?, d)),
+            v => self.invalid(name, "a time or a duration", &v),
+        }
+    }
+
+    /// Returns the time the now option of the package of the builtin says
+    /// it is.
+    pub fn now(&self) -> Result<i64> {
+        let now = match self.scope.lookup("now") {
+            Some(now) => now,
+            None => return error(String::from("now is not defined")),
+        };
+        match call(&now, Record::new(), None)? {
+            Value::Time(t) => Ok(t),
+            v => error(format!("now must return a time, found {}", v.type_name())),
+        }
+    }
+}
+
+/// Calls a function with its arguments and the value piped into it.
+pub fn call(f: &Value, mut args: Record, mut pipe: Option<Value>) -> Result<Value> {
+    let f = match f {
+        Value::Function(f) => f,
+        v => return error(format!("cannot call a value of type {}", v.type_name())),
+    };
+    match f.as_ref() {
+        Function::Flux { expr, scope } => {
+            let frame = Scope::new(Some(scope.clone()));
+            for param in &expr.params {
+                let name = &param.key.name;
+                let arg = if param.is_pipe {
+                    pipe.take().or_else(|| args.remove(name))
+                } else {
+                    args.remove(name)
+                };
+                let v = match (arg, &param.default) {
+                    (Some(v), _) => v,
+                    // Defaults are evaluated where the function is defined.
+                    (None, Some(default)) => eval(default, scope)?,
+                    (None, None) => return error(format!("missing required argument {}", name)),
+                };
+                frame.set(name, v);
+            }
+            if let Some(name) = args.keys().next() {
+                return error(format!("unexpected argument {}", name));
+            }
+            eval_block(&expr.body, &frame)
+        }
+        Function::Builtin {
+            name,
+            call: Some(call),
+            scope,
+        } => call(&mut Args {
+            name: name.clone(),
+            args,
+            pipe,
+            scope: scope.clone(),
+        }),
+        Function::Builtin {
+            name, call: None, ..
+        } => Err(Error::Unsupported(name.clone())),
+    }
+}
+
+/// Interpreter evaluates files against the standard library in a
+/// directory. The packages a file imports are evaluated once and shared by
+/// the files the interpreter evaluates, options included.
+pub struct Interpreter {
+    stdlib: PathBuf,
+    fresher: Fresher,
+    packages: HashMap<String, Scope>,
+    prelude: Option<Scope>,
+}
+
+impl Interpreter {
+    pub fn new(stdlib: &Path) -> Result<Interpreter> {
+        let mut interpreter = Interpreter {
+            stdlib: stdlib.to_path_buf(),
+            fresher: Fresher::default(),
+            packages: HashMap::new(),
+            prelude: None,
+        };
+        // The prelude is the scope of universe, so that the options a file
+        // sets, such as now, are seen by the builtins of universe.
+        let prelude = interpreter.import(PRELUDE[0])?;
+        interpreter.prelude = Some(prelude.clone());
+        for path in &PRELUDE[1..] {
+            for (k, v) in interpreter.import(path)?.bindings() {
+                prelude.set(&k, v);
+            }
+        }
+        Ok(interpreter)
+    }
+
+    /// Returns a scope for a file, in which the prelude is in scope.
+    pub fn scope(&self) -> Scope {
+        Scope::new(self.prelude.clone())
+    }
+
+    /// Returns the scope of a package of the standard library, evaluating
+    /// its files the first time it is imported.
+    pub fn import(&mut self, path: &str) -> Result<Scope> {
+        if let Some(scope) = self.packages.get(path) {
+            return Ok(scope.clone());
+        }
+        let dir = self.stdlib.join(path);
+        let mut names = match fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| {
+                    let name = p.to_string_lossy();
+                    name.ends_with(".flux") && !name.ends_with("_test.flux")
+                })
+                .collect::<Vec<PathBuf>>(),
+            Err(err) => return error(format!("cannot import {:?}: {}", path, err)),
+        };
+        names.sort();
+        // The packages the prelude imports are evaluated before there is a
+        // prelude, and do without it.
+        let scope = Scope::new(self.prelude.clone());
+        for name in names {
+            let src = match fs::read_to_string(&name) {
+                Ok(src) => src,
+                Err(err) => return error(format!("{}: {}", name.display(), err)),
+            };
+            let file = parse_string(&name.to_string_lossy(), &src);
+            let file = match convert_file(file, &mut self.fresher) {
+                Ok(file) => file,
+                Err(err) => return error(format!("{}: {}", name.display(), err)),
+            };
+            self.eval_file(&file, &scope, path)?;
+        }
+        self.packages.insert(path.to_string(), scope.clone());
+        Ok(scope)
+    }
+
+    /// Evaluates the statements of a file of a package in a scope. Only
+    /// the bindings of the file are evaluated; its expression statements
+    /// are not.
+    pub fn eval_file(&mut self, file: &File, scope: &Scope, pkg: &str) -> Result<()> {
+        for import in &file.imports {
+            let path = &import.path.value;
+            let value = Value::Package(self.import(path)?);
+            match &import.alias {
+                Some(alias) => scope.set(&alias.name, value),
+                None => scope.set(path.rsplit('/').next().unwrap_or(path), value),
+            }
+        }
+        for stmt in &file.body {
+            match stmt {
+                Statement::Variable(v) => scope.set(&v.id.name, eval(&v.init, scope)?),
+                Statement::Test(t) => {
+                    let v = &t.assignment;
+                    scope.set(&v.id.name, eval(&v.init, scope)?)
+                }
+                Statement::Option(o) => match &o.assignment {
+                    Assignment::Variable(v) => scope.set_option(&v.id.name, eval(&v.init, scope)?),
+                    Assignment::Member(m) => match eval(&m.member.object, scope)? {
+                        Value::Package(p) => p.set(&m.member.property, eval(&m.init, scope)?),
+                        v => return error(format!("cannot set an option of a {}", v.type_name())),
+                    },
+                },
+                Statement::Builtin(b) => {
+                    scope.set(&b.id.name, builtins::builtin(pkg, &b.id.name, scope))
+                }
+                Statement::Expr(_) | Statement::Return(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn eval_block(block: &Block, scope: &Scope) -> Result<Value> {
+    let mut block = block;
+    loop {
+        match block {
+            Block::Variable(v, next) => {
+                scope.set(&v.id.name, eval(&v.init, scope)?);
+                block = next;
+            }
+            Block::Expr(e, next) => {
+                eval(&e.expression, scope)?;
+                block = next;
+            }
+            Block::Return(r) => return eval(&r.argument, scope),
+        }
+    }
+}
+
+/// Returns whether a condition holds. Null does not.
+fn truthy(v: &Value) -> Result<bool> {
+    match v {
+        Value::Bool(b) => Ok(*b),
+        Value::Null => Ok(false),
+        v => error(format!("expected a bool, found {}", v.type_name())),
+    }
+}
+
+pub fn eval(expr: &Expression, scope: &Scope) -> Result<Value> {
+    Ok(match expr {
+        Expression::Identifier(id) => match scope.lookup(&id.name) {
+            Some(v) => v,
+            None => return error(format!("undefined identifier {}", id.name)),
+        },
+        Expression::Array(a) => Value::Array(Rc::new(
+            a.elements
+                .iter()
+                .map(|e| eval(e, scope))
+                .collect::<Result<Vec<Value>>>()?,
+        )),
+        Expression::Function(f) => Value::Function(Rc::new(Function::Flux {
+            expr: f.clone(),
+            scope: scope.clone(),
+        })),
+        Expression::Logical(l) => {
+            let left = truthy(&eval(&l.left, scope)?)?;
+            Value::Bool(match l.operator {
+                ast::LogicalOperator::AndOperator => left && truthy(&eval(&l.right, scope)?)?,
+                ast::LogicalOperator::OrOperator => left || truthy(&eval(&l.right, scope)?)?,
+            })
+        }
+        Expression::Object(o) => {
+            let mut r = match &o.with {
+                Some(with) => match scope.lookup(&with.name) {
+                    Some(Value::Record(r)) => r.as_ref().clone(),
+                    Some(v) => return error(format!("cannot extend a {}", v.type_name())),
+                    None => return error(format!("undefined identifier {}", with.name)),
+                },
+                None => Record::new(),
+            };
+            for p in &o.properties {
+                r.insert(p.key.name.clone(), eval(&p.value, scope)?);
+            }
+            Value::Record(Rc::new(r))
+        }
+        Expression::Member(m) => match eval(&m.object, scope)? {
+            // A record has null for the properties it does not have, as
+            // a row does for the columns it does not have.
+            Value::Record(r) => r.get(&m.property).cloned().unwrap_or(Value::Null),
+            Value::Package(p) => match p.member(&m.property) {
+                Some(v) => v,
+                None => return error(format!("package has no member {}", m.property)),
+            },
+            v => {
+                return error(format!(
+                    "cannot access property {} of a {}",
+                    m.property,
+                    v.type_name()
+                ))
+            }
+        },
+        Expression::Index(i) => match (eval(&i.array, scope)?, eval(&i.index, scope)?) {
+            (Value::Array(vs), Value::Int(n)) => match vs.get(n as usize) {
+                Some(v) if n >= 0 => v.clone(),
+                _ => {
+                    return error(format!(
+                        "index {} out of bounds of an array of length {}",
+                        n,
+                        vs.len()
+                    ))
+                }
+            },
+            (a, n) => {
+                return error(format!(
+                    "cannot index a {} by a {}",
+                    a.type_name(),
+                    n.type_name()
+                ))
+            }
+        },
+        Expression::Binary(b) => {
+            binary(&b.operator, eval(&b.left, scope)?, eval(&b.right, scope)?)?
+        }
+        Expression::Unary(u) => unary(&u.operator, eval(&u.argument, scope)?)?,
+        Expression::Call(c) => {
+            let f = eval(&c.callee, scope)?;
+            let mut args = Record::new();
+            for p in &c.arguments {
+                args.insert(p.key.name.clone(), eval(&p.value, scope)?);
+            }
+            let pipe = match &c.pipe {
+                Some(p) => Some(eval(p, scope)?),
+                None => None,
+            };
+            call(&f, args, pipe)?
+        }
+        Expression::Conditional(c) => {
+            if truthy(&eval(&c.test, scope)?)? {
+                eval(&c.consequent, scope)?
+            } else {
+                eval(&c.alternate, scope)?
+            }
+        }
+        Expression::StringExpr(s) => {
+            let mut out = String::new();
+            for part in &s.parts {
+                match part {
+                    StringExprPart::Text(t) => out.push_str(&t.value),
+                    StringExprPart::Interpolated(i) => {
+                        out.push_str(&eval(&i.expression, scope)?.to_string())
+                    }
+                }
+            }
+            Value::String(out)
+        }
+        Expression::Integer(lit) => Value::Int(lit.value),
+        Expression::Float(lit) => Value::Float(lit.value),
+        Expression::StringLit(lit) => Value::String(lit.value.clone()),
+        Expression::Duration(lit) => Value::Duration(Duration::from(&lit.value)),
+        Expression::Uint(lit) => Value::UInt(lit.value),
+        Expression::Boolean(lit) => Value::Bool(lit.value),
+        Expression::DateTime(lit) => Value::Time(timestamp(&lit.value.naive_utc())),
+        Expression::Regexp(lit) => match Regex::new(&lit.value) {
+            Ok(re) => Value::Regexp(Rc::new(re)),
+            Err(err) => return error(format!("invalid regular expression {}: {}", lit.value, err)),
+        },
+    })
+}
+
+/// Compares two values of the same type, or two numbers.
+pub fn compare(l: &Value, r: &Value) -> Option<Ordering> {
+    match (l, r) {
+        (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+        (Value::UInt(a), Value::UInt(b)) => Some(a.cmp(b)),
+        (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+        (Value::Int(a), Value::UInt(b)) => Some(i128::from(*a).cmp(&i128::from(*b))),
+        (Value::UInt(a), Value::Int(b)) => Some(i128::from(*a).cmp(&i128::from(*b))),
+        (Value::Int(a), Value::Float(b)) => (*a as f64).partial_cmp(b),
+        (Value::Float(a), Value::Int(b)) => a.partial_cmp(&(*b as f64)),
+        (Value::UInt(a), Value::Float(b)) => (*a as f64).partial_cmp(b),
+        (Value::Float(a), Value::UInt(b)) => a.partial_cmp(&(*b as f64)),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Time(a), Value::Time(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+fn binary(op: &ast::Operator, l: Value, r: Value) -> Result<Value> {
+    use ast::Operator::*;
+    // Operations on null are null.
+    if l.is_null() || r.is_null() {
+        return Ok(Value::Null);
+    }
+    let cmp = |want: &dyn Fn(Ordering) -> bool| match compare(&l, &r) {
+        Some(o) => Ok(Value::Bool(want(o))),
+        None if *op == EqualOperator => Ok(Value::Bool(l == r)),
+        None if *op == NotEqualOperator => Ok(Value::Bool(l != r)),
+        None => error(format!(
+            "cannot compare {} with {}",
+            l.type_name(),
+            r.type_name()
+        )),
+    };
+    match op {
+        EqualOperator => return cmp(&|o| o == Ordering::Equal),
+        NotEqualOperator => return cmp(&|o| o != Ordering::Equal),
+        LessThanOperator => return cmp(&|o| o == Ordering::Less),
+        LessThanEqualOperator => return cmp(&|o| o != Ordering::Greater),
+        GreaterThanOperator => return cmp(&|o| o == Ordering::Greater),
+        GreaterThanEqualOperator => return cmp(&|o| o != Ordering::Less),
+        _ => {}
+    }
+    Ok(match (op, &l, &r) {
+        (AdditionOperator, Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_add(*b)),
+        (SubtractionOperator, Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_sub(*b)),
+        (MultiplicationOperator, Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_mul(*b)),
+        (DivisionOperator, Value::Int(_), Value::Int(0))
+        | (ModuloOperator, Value::Int(_), Value::Int(0))
+        | (DivisionOperator, Value::UInt(_), Value::UInt(0))
+        | (ModuloOperator, Value::UInt(_), Value::UInt(0)) => {
+            return error(String::from("division by zero"))
+        }
+        (DivisionOperator, Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_div(*b)),
+        (ModuloOperator, Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_rem(*b)),
+        (PowerOperator, Value::Int(a), Value::Int(b)) => Value::Float((*a as f64).powf(*b as f64)),
+        (AdditionOperator, Value::UInt(a), Value::UInt(b)) => Value::UInt(a.wrapping_add(*b)),
+        (SubtractionOperator, Value::UInt(a), Value::UInt(b)) => Value::UInt(a.wrapping_sub(*b)),
+        (MultiplicationOperator, Value::UInt(a), Value::UInt(b)) => Value::UInt(a.wrapping_mul(*b)),
+        (DivisionOperator, Value::UInt(a), Value::UInt(b)) => Value::UInt(a / b),
+        (ModuloOperator, Value::UInt(a), Value::UInt(b)) => Value::UInt(a % b),
+        (PowerOperator, Value::UInt(a), Value::UInt(b)) => {
+            Value::Float((*a as f64).powf(*b as f64))
+        }
+        (AdditionOperator, Value::Float(a), Value::Float(b)) => Value::Float(a + b),
+        (SubtractionOperator, Value::Float(a), Value::Float(b)) => Value::Float(a - b),
+        (MultiplicationOperator, Value::Float(a), Value::Float(b)) => Value::Float(a * b),
+        (DivisionOperator, Value::Float(a), Value::Float(b)) => Value::Float(a / b),
+        (ModuloOperator, Value::Float(a), Value::Float(b)) => Value::Float(a % b),
+        (PowerOperator, Value::Float(a), Value::Float(b)) => Value::Float(a.powf(*b)),
+        (AdditionOperator, Value::String(a), Value::String(b)) => {
+            Value::String(format!("{}{}", a, b))
+        }
+        (RegexpMatchOperator, Value::String(s), Value::Regexp(re)) => Value::Bool(re.is_match(s)),
+        (NotRegexpMatchOperator, Value::String(s), Value::Regexp(re)) => {
+            Value::Bool(!re.is_match(s))
+        }
+        (op, l, r) => {
+            return error(format!(
+                "unsupported binary operation {} {} {}",
+                l.type_name(),
+                op.to_string(),
+                r.type_name()
+            ))
+        }
+    })
+}
+
+fn unary(op: &ast::Operator, v: Value) -> Result<Value> {
+    use ast::Operator::*;
+    Ok(match (op, v) {
+        (ExistsOperator, v) => Value::Bool(!v.is_null()),
+        (_, Value::Null) => Value::Null,
+        (NotOperator, Value::Bool(b)) => Value::Bool(!b),
+        (AdditionOperator, v) => v,
+        (SubtractionOperator, Value::Int(i)) => Value::Int(i.wrapping_neg()),
+        (SubtractionOperator, Value::Float(f)) => Value::Float(-f),
+        (SubtractionOperator, Value::Duration(d)) => Value::Duration(Duration {
+            months: -d.months,
+            nanoseconds: -d.nanoseconds,
+        }),
+        (op, v) => {
+            return error(format!(
+                "unsupported unary operation {} {}",
+                op.to_string(),
+                v.type_name()
+            ))
+        }
+    })
+}
@@ -0,0 +1,180 @@
+//! Discovery, checking and running of the `test` statements of Flux test
+//! files.
+//!
+//! A test statement binds a function that returns a test case, a record of
+//! the tables to test with, the tables wanted and the function to test:
+//!
+//! ```text
+//! test t = () => ({input: testing.loadStorage(csv: a), want: testing.loadMem(csv: b), fn: f})
+//! ```
+//!
+//! Each test of a file is checked on its own, against the rest of the file
+//! without the other tests, so that one broken test does not fail the others.
+//! A test passes its check if it type checks as a test case.
+//!
+//! A test that checks can be run: the function is applied to the input
+//! tables, decoded from their CSV, and the tables it returns are compared
+//! with the wanted tables. The evaluator that runs tests implements the
+//! builtins of the standard library natively, and only some of them; a
+//! test that calls one it does not implement is skipped.
+use std::path::Path;
+
+use flux::ast;
+use flux::parser::parse_string;
+use flux::semantic::nodes::Package;
+
+mod builtins;
+pub mod csv;
+pub mod diff;
+pub mod eval;
+pub mod value;
+
+use eval::{call, Interpreter};
+use value::{Record, Value};
+
+/// TestCase is a test statement of a file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TestCase {
+    pub name: String,
+    pub loc: ast::SourceLocation,
+}
+
+/// TestResult is the outcome of checking a test case. The error is the
+/// reason the test case does not check, if it does not.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TestResult {
+    pub case: TestCase,
+    pub error: Option<String>,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Returns the test statements of a file in the order they appear.
+pub fn find_tests(file: &ast::File) -> Vec<TestCase> {
+    file.body
+        .iter()
+        .filter_map(|stmt| match stmt {
+            ast::Statement::Test(t) => Some(TestCase {
+                name: t.assignment.id.name.clone(),
+                loc: t.base.location.clone(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Checks every test of a source file. A file with syntax errors has no
+/// tests to check and is reported by its first error.
+pub fn check_tests(name: &str, src: &str) -> Result<Vec<TestResult>, flux::Error> {
+    Ok(analyze_tests(name, src)?
+        .into_iter()
+        .map(|(case, pkg)| TestResult {
+            case,
+            error: pkg.err(),
+        })
+        .collect())
+}
+
+/// AnalyzedTest is a test case with the package of its file, or the reason
+/// that package does not check.
+type AnalyzedTest = (TestCase, Result<Package, String>);
+
+/// Returns each test of a source file with the package of the file without
+/// the other tests, or the reason that package does not check.
+fn analyze_tests(name: &str, src: &str) -> Result<Vec<AnalyzedTest>, flux::Error> {
+    let file = parse_string(name, src);
+    if let Some(err) = ast::check::check(ast::walk::Node::File(&file))
+        .into_iter()
+        .next()
+    {
+        return Err(flux::Error::from(err.to_string()));
+    }
+    Ok(find_tests(&file)
+        .into_iter()
+        .map(|case| {
+            let mut only = file.clone();
+            only.body.retain(|stmt| match stmt {
+                ast::Statement::Test(t) => t.assignment.id.name == case.name,
+                _ => true,
+            });
+            let pkg = crate::analyze(only.into()).map_err(|e| e.to_string());
+            (case, pkg)
+        })
+        .collect())
+}
+
+/// Outcome is the outcome of running a test case.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Outcome {
+    Passed,
+    /// The test got other tables than it wants. The diff lists the rows
+    /// that differ, as diff::diff does.
+    Failed(String),
+    /// The test does not check, or fails while it runs.
+    Error(String),
+    /// The test calls a builtin that the evaluator does not implement.
+    Skipped(String),
+}
+
+/// TestRun is a test case with the outcome of running it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TestRun {
+    pub case: TestCase,
+    pub outcome: Outcome,
+}
+
+/// Runs every test of a source file against the standard library in the
+/// stdlib directory. A file with syntax errors has no tests to run and is
+/// reported by its first error.
+pub fn run_tests(name: &str, src: &str, stdlib: &Path) -> Result<Vec<TestRun>, flux::Error> {
+    Ok(analyze_tests(name, src)?
+        .into_iter()
+        .map(|(case, pkg)| {
+            let outcome = match pkg {
+                Ok(pkg) => match run_test(&case.name, &pkg, stdlib) {
+                    Ok(None) => Outcome::Passed,
+                    Ok(Some(diff)) => Outcome::Failed(diff),
+                    Err(err @ eval::Error::Unsupported(_)) => Outcome::Skipped(err.to_string()),
+                    Err(err) => Outcome::Error(err.to_string()),
+                },
+                Err(err) => Outcome::Error(err),
+            };
+            TestRun { case, outcome }
+        })
+        .collect())
+}
+
+/// Runs the test of a package, returning the difference between the
+/// tables it wants and the tables it gets, if they differ.
+fn run_test(name: &str, pkg: &Package, stdlib: &Path) -> eval::Result<Option<String>> {
+    let mut interpreter = Interpreter::new(stdlib)?;
+    let scope = interpreter.scope();
+    for file in &pkg.files {
+        interpreter.eval_file(file, &scope, &pkg.package)?;
+    }
+    let case = match scope.lookup(name) {
+        Some(case) => call(&case, Record::new(), None)?,
+        None => return eval::error(format!("test {} is not defined", name)),
+    };
+    let field = |label: &str| match &case {
+        Value::Record(r) => r.get(label).cloned().unwrap_or(Value::Null),
+        _ => Value::Null,
+    };
+    let got = call(&field("fn"), Record::new(), Some(field("input")))?;
+    match (field("want"), got) {
+        (Value::Stream(want), Value::Stream(got)) => Ok(diff::diff(&want.tables, &got.tables)),
+        (want, got) => eval::error(format!(
+            "test {} must want and get tables, not {} and {}",
+            name,
+            want.type_name(),
+            got.type_name()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests;
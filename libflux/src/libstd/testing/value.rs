@@ -0,0 +1,408 @@
+//! The values a Flux program evaluates to, and the tables that streams of
+//! them are made of.
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::rc::Rc;
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use regex::Regex;
+
+use super::eval::Function;
+
+/// Record is the value of a record, a row of a table or the arguments of
+/// a call.
+pub type Record = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+    /// Time is the number of nanoseconds since the Unix epoch, in UTC.
+    Time(i64),
+    Duration(Duration),
+    Regexp(Rc<Regex>),
+    Array(Rc<Vec<Value>>),
+    Record(Rc<Record>),
+    Function(Rc<Function>),
+    Stream(Rc<Stream>),
+    Package(Scope),
+}
+
+impl PartialEq for Value {
+    // Values are equal if they are of the same type and hold the same
+    // value. Two nulls are equal. Functions, streams and packages are only
+    // equal to themselves.
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::UInt(a), Value::UInt(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Time(a), Value::Time(b)) => a == b,
+            (Value::Duration(a), Value::Duration(b)) => a == b,
+            (Value::Regexp(a), Value::Regexp(b)) => a.as_str() == b.as_str(),
+            (Value::Array(a), Value::Array(b)) => a == b,
+            (Value::Record(a), Value::Record(b)) => a == b,
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Stream(a), Value::Stream(b)) => Rc::ptr_eq(a, b),
+            (Value::Package(a), Value::Package(b)) => Rc::ptr_eq(&a.0, &b.0),
+            _ => false,
+        }
+    }
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        *self == Value::Null
+    }
+
+    /// Returns the name of the type of the value, as used in errors.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int",
+            Value::UInt(_) => "uint",
+            Value::Float(_) => "float",
+            Value::String(_) => "string",
+            Value::Time(_) => "time",
+            Value::Duration(_) => "duration",
+            Value::Regexp(_) => "regexp",
+            Value::Array(_) => "array",
+            Value::Record(_) => "record",
+            Value::Function(_) => "function",
+            Value::Stream(_) => "stream",
+            Value::Package(_) => "package",
+        }
+    }
+}
+
+// Values are displayed as they are converted to strings by string
+// interpolation.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(v) => write!(f, "{}", v),
+            Value::Int(v) => write!(f, "{}", v),
+            Value::UInt(v) => write!(f, "{}", v),
+            Value::Float(v) => write!(f, "{}", v),
+            Value::String(v) => f.write_str(v),
+            Value::Time(v) => f.write_str(&format_time(*v)),
+            Value::Duration(v) => write!(f, "{}", v),
+            Value::Regexp(v) => write!(f, "/{}/", v.as_str()),
+            Value::Array(vs) => {
+                f.write_str("[")?;
+                for (i, v) in vs.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                f.write_str("]")
+            }
+            Value::Record(r) => {
+                f.write_str("{")?;
+                for (i, (k, v)) in r.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", k, v)?;
+                }
+                f.write_str("}")
+            }
+            Value::Function(_) | Value::Stream(_) | Value::Package(_) => {
+                write!(f, "<{}>", self.type_name())
+            }
+        }
+    }
+}
+
+/// Formats a time as RFC3339 in UTC with as many fractional digits as it
+/// needs, as Go formats times.
+pub fn format_time(nanos: i64) -> String {
+    let secs = nanos.div_euclid(1_000_000_000);
+    let frac = nanos.rem_euclid(1_000_000_000);
+    match NaiveDateTime::from_timestamp_opt(secs, frac as u32) {
+        Some(t) => {
+            let mut s = t.format("%Y-%m-%dT%H:%M:%S").to_string();
+            if frac > 0 {
+                let digits = format!("{:09}", frac);
+                s.push('.');
+                s.push_str(digits.trim_end_matches('0'));
+            }
+            s.push('Z');
+            s
+        }
+        None => nanos.to_string(),
+    }
+}
+
+/// Returns a time in nanoseconds since the Unix epoch. A time that does
+/// not fit wraps around, as it does in Go, where chrono's timestamp_nanos
+/// would panic.
+pub fn timestamp(t: &NaiveDateTime) -> i64 {
+    let nanos = i128::from(t.timestamp()) * 1_000_000_000 + i128::from(t.timestamp_subsec_nanos());
+    nanos as i64
+}
+
+/// Formats a time as RFC3339 in UTC with all nine fractional digits, as
+/// string() converts times.
+pub fn format_time_nanos(nanos: i64) -> String {
+    let secs = nanos.div_euclid(1_000_000_000);
+    let frac = nanos.rem_euclid(1_000_000_000);
+    match NaiveDateTime::from_timestamp_opt(secs, frac as u32) {
+        Some(t) => format!("{}.{:09}Z", t.format("%Y-%m-%dT%H:%M:%S"), frac),
+        None => nanos.to_string(),
+    }
+}
+
+/// Adds a duration to a time. Months are added to the date and days past
+/// the end of the month carry over into the next, as Go adds them.
+pub fn add_duration(t: i64, d: Duration) -> i64 {
+    let mut t = t;
+    if d.months != 0 {
+        let secs = t.div_euclid(1_000_000_000);
+        let frac = t.rem_euclid(1_000_000_000);
+        if let Some(dt) = NaiveDateTime::from_timestamp_opt(secs, frac as u32) {
+            let months = i64::from(dt.year()) * 12 + i64::from(dt.month0()) + d.months;
+            let date = NaiveDate::from_ymd_opt(
+                months.div_euclid(12) as i32,
+                months.rem_euclid(12) as u32 + 1,
+                1,
+            )
+            .map(|first| first + chrono::Duration::days(i64::from(dt.day0())));
+            if let Some(date) = date {
+                t = timestamp(&date.and_time(dt.time()));
+            }
+        }
+    }
+    t.saturating_add(d.nanoseconds)
+}
+
+/// Duration is a signed length of time in months and nanoseconds.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Duration {
+    pub months: i64,
+    pub nanoseconds: i64,
+}
+
+impl Duration {
+    /// INF is the infinite duration, which windows every table as a whole.
+    pub const INF: Duration = Duration {
+        months: 0,
+        nanoseconds: i64::MAX,
+    };
+}
+
+impl From<&flux::semantic::nodes::Duration> for Duration {
+    fn from(d: &flux::semantic::nodes::Duration) -> Duration {
+        let sign = if d.negative { -1 } else { 1 };
+        Duration {
+            months: sign * d.months,
+            nanoseconds: sign * d.nanoseconds,
+        }
+    }
+}
+
+// Durations are displayed in the units of duration literals, largest first.
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.months == 0 && self.nanoseconds == 0 {
+            return f.write_str("0ns");
+        }
+        if self.months < 0 || self.nanoseconds < 0 {
+            f.write_str("-")?;
+        }
+        let months = self.months.unsigned_abs();
+        let (years, months) = (months / 12, months % 12);
+        if years > 0 {
+            write!(f, "{}y", years)?;
+        }
+        if months > 0 {
+            write!(f, "{}mo", months)?;
+        }
+        let mut ns = self.nanoseconds.unsigned_abs();
+        for (unit, size) in &[
+            ("w", 7 * 24 * 3_600_000_000_000),
+            ("d", 24 * 3_600_000_000_000),
+            ("h", 3_600_000_000_000),
+            ("m", 60_000_000_000),
+            ("s", 1_000_000_000),
+            ("ms", 1_000_000),
+            ("us", 1_000),
+            ("ns", 1),
+        ] {
+            if ns >= *size {
+                write!(f, "{}{}", ns / size, unit)?;
+                ns %= size;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Scope is a frame of variables. A variable that is not in the frame is
+/// looked up in its parent. Packages are the scope of their files.
+#[derive(Clone)]
+pub struct Scope(pub Rc<Frame>);
+
+// The functions of a scope refer back to it, so its variables are not
+// printed.
+impl fmt::Debug for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Scope")
+    }
+}
+
+pub struct Frame {
+    vars: RefCell<HashMap<String, Value>>,
+    parent: Option<Scope>,
+}
+
+impl Scope {
+    pub fn new(parent: Option<Scope>) -> Scope {
+        Scope(Rc::new(Frame {
+            vars: RefCell::new(HashMap::new()),
+            parent,
+        }))
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<Value> {
+        match self.0.vars.borrow().get(name) {
+            Some(v) => Some(v.clone()),
+            None => self.0.parent.as_ref().and_then(|p| p.lookup(name)),
+        }
+    }
+
+    /// Looks up a variable of this frame, not of its parents.
+    pub fn member(&self, name: &str) -> Option<Value> {
+        self.0.vars.borrow().get(name).cloned()
+    }
+
+    /// Returns the variables of this frame.
+    pub fn bindings(&self) -> Vec<(String, Value)> {
+        self.0
+            .vars
+            .borrow()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    pub fn set(&self, name: &str, v: Value) {
+        self.0.vars.borrow_mut().insert(name.to_string(), v);
+    }
+
+    /// Sets an option. An option declared by an enclosing scope, such as
+    /// now of the prelude, is set where it is declared so that the
+    /// functions of that scope see the new value.
+    pub fn set_option(&self, name: &str, v: Value) {
+        let mut scope = self;
+        loop {
+            if scope.0.vars.borrow().contains_key(name) {
+                return scope.set(name, v);
+            }
+            match &scope.0.parent {
+                Some(p) => scope = p,
+                None => return self.set(name, v),
+            }
+        }
+    }
+}
+
+/// Stream is a stream of tables. The bounds are the time range that range
+/// last limited the stream to, which window divides into windows.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Stream {
+    pub tables: Vec<Table>,
+    pub bounds: Option<(i64, i64)>,
+}
+
+/// Table is a table of rows. The group key are the columns whose value is
+/// the same in every row, sorted by label, with their value. Every key
+/// column is a column of the table.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Table {
+    pub key: Vec<(String, Value)>,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    pub fn new(mut key: Vec<(String, Value)>, columns: Vec<String>) -> Table {
+        key.sort_by(|a, b| a.0.cmp(&b.0));
+        Table {
+            key,
+            columns,
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn col(&self, label: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c == label)
+    }
+
+    pub fn in_key(&self, label: &str) -> bool {
+        self.key.iter().any(|(k, _)| k == label)
+    }
+
+    pub fn key_value(&self, label: &str) -> Option<&Value> {
+        self.key.iter().find(|(k, _)| k == label).map(|(_, v)| v)
+    }
+
+    /// Returns a row as a record of its columns.
+    pub fn record(&self, row: &[Value]) -> Record {
+        self.columns
+            .iter()
+            .cloned()
+            .zip(row.iter().cloned())
+            .collect()
+    }
+
+    /// Appends a record as a row. Columns the record does not have are
+    /// null and columns the table does not have are added, null in the
+    /// rows before.
+    pub fn push_record(&mut self, mut r: Record) {
+        let mut row: Vec<Value> = self
+            .columns
+            .iter()
+            .map(|c| r.remove(c).unwrap_or(Value::Null))
+            .collect();
+        for (label, v) in r {
+            self.columns.push(label);
+            for row in &mut self.rows {
+                row.push(Value::Null);
+            }
+            row.push(v);
+        }
+        self.rows.push(row);
+    }
+}
+
+/// Tables collects rows into tables by their group key, in the order the
+/// keys are first seen.
+#[derive(Debug, Default)]
+pub struct Tables(pub Vec<Table>);
+
+impl Tables {
+    /// Returns the table of a group key, adding it with the columns if it
+    /// is new.
+    pub fn table(&mut self, mut key: Vec<(String, Value)>, columns: &[String]) -> &mut Table {
+        key.sort_by(|a, b| a.0.cmp(&b.0));
+        match self.0.iter().position(|t| t.key == key) {
+            Some(i) => &mut self.0[i],
+            None => {
+                self.0.push(Table::new(key, columns.to_vec()));
+                self.0.last_mut().unwrap()
+            }
+        }
+    }
+}
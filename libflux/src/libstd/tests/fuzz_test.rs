@@ -0,0 +1,192 @@
+// Fuzzes parsing and analysis with mutations of the transpiled InfluxQL
+// queries, checking that no input makes either of them panic or overflow the
+// stack.
+use std::panic;
+use std::thread;
+
+const FRAGMENTS: &[&str] = &[
+    "(",
+    ")",
+    "[",
+    "]",
+    "{",
+    "}",
+    "a",
+    "r",
+    "1",
+    "1.5",
+    "\"s\"",
+    "\"${",
+    "/re/",
+    "2h",
+    "=",
+    "=>",
+    "|>",
+    "+",
+    "-",
+    "*",
+    "/",
+    "not",
+    "exists",
+    "and",
+    "or",
+    ",",
+    ":",
+    ".",
+    "with",
+    "if",
+    "then",
+    "else",
+    "return",
+    "import",
+    "package",
+    "option",
+    "builtin",
+    "test",
+    "<-",
+    "\n",
+    " ",
+    "//c\n",
+    "@",
+    "?",
+    "\0",
+    "é",
+    "2020-01-01T00:00:00Z",
+];
+
+// Rng is a linear congruential generator, so that every run tries the same
+// inputs.
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (self.0 >> 33) as usize % n.max(1)
+    }
+
+    // boundary picks a char boundary of s.
+    fn boundary(&mut self, s: &str) -> usize {
+        let mut i = self.below(s.len() + 1);
+        while !s.is_char_boundary(i) {
+            i -= 1;
+        }
+        i
+    }
+}
+
+fn mutate(rng: &mut Rng, src: &str) -> String {
+    let mut src = src.to_string();
+    for _ in 0..1 + rng.below(4) {
+        let i = rng.boundary(&src);
+        let j = i + rng.boundary(&src[i..]).min(16);
+        let j = (j..=src.len()).find(|&j| src.is_char_boundary(j)).unwrap();
+        match rng.below(4) {
+            0 => src.replace_range(i..j, ""),
+            1 => {
+                let dup = src[i..j].to_string();
+                src.insert_str(j, &dup);
+            }
+            2 => src.insert_str(i, FRAGMENTS[rng.below(FRAGMENTS.len())]),
+            // A fragment repeated many times nests deeply or makes a long
+            // chain of operators.
+            _ => {
+                let frag = FRAGMENTS[rng.below(FRAGMENTS.len())];
+                src.insert_str(i, &frag.repeat(1 + rng.below(5_000)));
+            }
+        }
+    }
+    src
+}
+
+// on_big_stack runs f on a thread with a stack of 8 MiB, the size the default
+// limits are meant for. A stack overflow aborts the test rather than failing
+// it, as it cannot be caught.
+fn on_big_stack<F: FnOnce() + Send + 'static>(f: F) {
+    thread::Builder::new()
+        .stack_size(8 << 20)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+fn parse_and_analyze(src: &str) {
+    let result = panic::catch_unwind(|| {
+        let file = flux::parser::parse_string("fuzz.flux", src);
+        let _ = libstd::analyze(file.into());
+    });
+    if result.is_err() {
+        panic!("panicked on input:\n{}", src);
+    }
+}
+
+#[test]
+fn parse_and_analyze_never_panic() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../flux/influxql/testdata");
+    let mut seeds = Vec::new();
+    for entry in std::fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|ext| ext == "flux") {
+            seeds.push(std::fs::read_to_string(&path).unwrap());
+        }
+    }
+    seeds.sort();
+    assert!(!seeds.is_empty(), "no seeds found in {}", dir.display());
+
+    on_big_stack(move || {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..3000 {
+            let seed = &seeds[rng.below(seeds.len())];
+            parse_and_analyze(&mutate(&mut rng, seed));
+        }
+    });
+}
+
+#[test]
+fn deep_nesting_and_long_chains_never_overflow() {
+    on_big_stack(|| {
+        let n = 50_000;
+        let nested = [
+            ("(", ")"),
+            ("[", "]"),
+            ("{a: ", "}"),
+            ("{r with a: ", "}"),
+            ("f(a: ", ")"),
+            ("() => ", ""),
+            ("(r) => {return ", "}"),
+            ("if true then ", " else 1"),
+            ("not ", ""),
+            ("-", ""),
+            ("exists ", ""),
+            ("\"${", "}\""),
+            ("a[", "]"),
+        ];
+        for (open, close) in nested.iter() {
+            parse_and_analyze(&format!("x = {}1{}", open.repeat(n), close.repeat(n)));
+            // Unclosed nesting is cut off at the limit as well.
+            parse_and_analyze(&format!("x = {}1", open.repeat(n)));
+        }
+        let chains = [
+            " + 1",
+            " * 1",
+            " == 1",
+            " and true",
+            " or false",
+            " |> f()",
+            ".a",
+            "[0]",
+            "()",
+            " =~ /a/",
+        ];
+        for link in chains.iter() {
+            parse_and_analyze(&format!("x = 1{}", link.repeat(n)));
+        }
+        // Chains within nesting, and nesting within chains.
+        parse_and_analyze(&format!("x = {}1{}", "(1 + ".repeat(n), ")".repeat(n)));
+        parse_and_analyze(&format!("x = 1{}", " + (1 + 1)".repeat(n)));
+        parse_and_analyze(&format!("x = a{}", ".b(c: d |> e())".repeat(n)));
+    });
+}
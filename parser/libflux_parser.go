@@ -26,6 +26,12 @@ func parseFile(f *token.File, src []byte) (*ast.File, error) {
 	file := pkg.Files[0]
 	file.Name = f.Name()
 
+	// Only the file keeps its raw source, the formatter
+	// slices the source of string literals out of it.
+	if l := file.Loc; l != nil && l.Span != nil && l.Span.End <= len(src) {
+		l.Source = string(src[l.Span.Start:l.Span.End])
+	}
+
 	// The go parser will not fill in the imports if there are
 	// none so we remove them here to retain compatibility.
 	if len(file.Imports) == 0 {
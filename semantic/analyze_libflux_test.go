@@ -21,7 +21,7 @@ func TestAnalyzeSource(t *testing.T) {
 		{
 			name: "failure",
 			flx:  `x = 10 + "foo"`,
-			err:  errors.New("cannot unify int with string"),
+			err:  errors.New("error at @1:5-1:15: expected int but found string"),
 		},
 	}
 	for _, tc := range tcs {
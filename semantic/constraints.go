@@ -433,7 +433,12 @@ func (v ConstraintGenerator) typeof(n Node) (PolyType, error) {
 				if err != nil {
 					return nil, err
 				}
-				v.cs.AddTypeConst(t, String, p.Location())
+				k := StringableKind{T: t, loc: p.Location()}
+				if tv, ok := t.(Tvar); ok {
+					v.cs.AddKindConst(tv, k)
+				} else if err := k.check(); err != nil {
+					return nil, err
+				}
 			}
 		}
 		return String, nil
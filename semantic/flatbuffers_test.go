@@ -873,7 +873,7 @@ func TestFlatBuffersRoundTrip(t *testing.T) {
 		{
 			name:    "exists operator",
 			fluxSrc: `e = exists {foo: 30}.bar`,
-			err:     errors.New("cannot unify {{}} with {bar:t0 | t1}"),
+			err:     errors.New("error at @1:12-1:25: missing field bar"),
 		},
 		{
 			name:    "exists operator with tvar",
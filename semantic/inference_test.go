@@ -2194,6 +2194,23 @@ foo.b
 			script:  `if 1 then 0.1 else 0.0`,
 			wantErr: errors.New(`type error 1:4-1:5: int != bool`),
 		},
+		{
+			name: "interpolated regexp",
+			script: `re = /a/
+"${re}"`,
+			wantErr: errors.New(`type error 2:2-2:7: regexp is not Stringable`),
+		},
+		{
+			name:    "interpolated array",
+			script:  `"${[1]}"`,
+			wantErr: errors.New(`type error 1:2-1:8: array is not Stringable`),
+		},
+		{
+			name: "interpolated record",
+			script: `r = {a: 1}
+"${r}"`,
+			wantErr: errors.New(`type error 2:2-2:6: object is not Stringable`),
+		},
 		{
 			name: "exists",
 			script: `b = 1
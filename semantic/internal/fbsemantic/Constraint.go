@@ -51,8 +51,16 @@ func (rcv *Constraint) MutateKind(n Kind) bool {
 	return rcv._tab.MutateByteSlot(6, n)
 }
 
+func (rcv *Constraint) Label() []byte {
+	o := flatbuffers.UOffsetT(rcv._tab.Offset(8))
+	if o != 0 {
+		return rcv._tab.ByteVector(o + rcv._tab.Pos)
+	}
+	return nil
+}
+
 func ConstraintStart(builder *flatbuffers.Builder) {
-	builder.StartObject(2)
+	builder.StartObject(3)
 }
 func ConstraintAddTvar(builder *flatbuffers.Builder, tvar flatbuffers.UOffsetT) {
 	builder.PrependUOffsetTSlot(0, flatbuffers.UOffsetT(tvar), 0)
@@ -60,6 +68,9 @@ func ConstraintAddTvar(builder *flatbuffers.Builder, tvar flatbuffers.UOffsetT)
 func ConstraintAddKind(builder *flatbuffers.Builder, kind byte) {
 	builder.PrependByteSlot(1, kind, 0)
 }
+func ConstraintAddLabel(builder *flatbuffers.Builder, label flatbuffers.UOffsetT) {
+	builder.PrependUOffsetTSlot(2, flatbuffers.UOffsetT(label), 0)
+}
 func ConstraintEnd(builder *flatbuffers.Builder) flatbuffers.UOffsetT {
 	return builder.EndObject()
 }
@@ -14,6 +14,9 @@ const (
 	KindNullable     Kind = 6
 	KindRow          Kind = 7
 	KindNegatable    Kind = 8
+	KindTimeable     Kind = 9
+	KindStringable   Kind = 10
+	KindLacks        Kind = 11
 )
 
 var EnumNamesKind = map[Kind]string{
@@ -26,4 +29,7 @@ var EnumNamesKind = map[Kind]string{
 	KindNullable:     "Nullable",
 	KindRow:          "Row",
 	KindNegatable:    "Negatable",
+	KindTimeable:     "Timeable",
+	KindStringable:   "Stringable",
+	KindLacks:        "Lacks",
 }
@@ -5,6 +5,7 @@ import (
 	"sort"
 	"strings"
 
+	"github.com/influxdata/flux/ast"
 	"github.com/influxdata/flux/codes"
 	"github.com/influxdata/flux/internal/errors"
 )
@@ -711,6 +712,9 @@ func (k ObjectKind) freeVars(c *Constraints) TvarSet {
 }
 
 func (l ObjectKind) unifyKind(kinds map[Tvar]Kind, k Kind) (Kind, Substitution, error) {
+	if sk, ok := k.(StringableKind); ok {
+		return sk.unifyKind(kinds, l)
+	}
 	r, ok := k.(ObjectKind)
 	if !ok {
 		return nil, nil, errors.Newf(codes.Invalid, "cannot unify record with %T", k)
@@ -876,6 +880,59 @@ func (n NullableKind) occurs(tv Tvar) bool {
 	return n.T.occurs(tv)
 }
 
+// StringableKind indicates that the type variable must be
+// of a type that can be interpolated into a string.
+// Records are rejected when the kinds are unified; any other
+// type is checked once the type variable is substituted.
+type StringableKind struct {
+	T   PolyType
+	loc ast.SourceLocation
+}
+
+func (k StringableKind) MonoType() (Type, bool) {
+	return k.T.MonoType()
+}
+func (StringableKind) freeVars(*Constraints) TvarSet { return nil }
+func (k StringableKind) resolveType(kinds map[Tvar]Kind) (Type, error) {
+	if tv, ok := k.T.(Tvar); ok {
+		return nil, errors.Newf(codes.Invalid, "type variable %q is not monomorphic", tv)
+	}
+	return k.T.resolveType(kinds)
+}
+func (k StringableKind) resolvePolyType(kinds map[Tvar]Kind) (PolyType, error) {
+	if tv, ok := k.T.(Tvar); ok {
+		return tv, nil
+	}
+	return k.T.resolvePolyType(kinds)
+}
+func (k StringableKind) substituteKind(tv Tvar, t PolyType) Kind {
+	if ptv, ok := k.T.(Tvar); ok && ptv == tv {
+		return StringableKind{T: t, loc: k.loc}
+	}
+	return k
+}
+func (k StringableKind) unifyKind(kinds map[Tvar]Kind, r Kind) (Kind, Substitution, error) {
+	if _, ok := r.(ObjectKind); ok {
+		return nil, nil, errors.Newf(codes.Invalid, "type error %v: %v is not Stringable", k.loc, Object)
+	}
+	// A nullable or stringable type variable stays stringable.
+	return k, nil, nil
+}
+func (k StringableKind) occurs(tv Tvar) bool {
+	return k.T.occurs(tv)
+}
+
+// check reports an error if the type variable has been
+// substituted with a type that cannot be interpolated.
+func (k StringableKind) check() error {
+	switch n := k.T.Nature(); n {
+	case Invalid, Nil, String, Int, UInt, Float, Bool, Time, Duration:
+		return nil
+	default:
+		return errors.Newf(codes.Invalid, "type error %v: %v is not Stringable", k.loc, n)
+	}
+}
+
 // NullableTvar is a type variable that might be null.
 // If a type variable is constrained to be nullable (via the NullableKind),
 // in order to preserve that constraint when resolving the type, we return
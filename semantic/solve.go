@@ -79,6 +79,11 @@ func (sol *Solution) solve() error {
 	sol.kinds = make(map[Tvar]Kind, len(kinds))
 	for tv, k := range kinds {
 		k = subst.ApplyKind(k)
+		if sk, ok := k.(StringableKind); ok {
+			if err := sk.check(); err != nil {
+				return err
+			}
+		}
 		tv = subst.ApplyTvar(tv)
 		sol.kinds[tv] = k
 	}
@@ -59,7 +59,7 @@ func (pt *PolyType) Constraint(i int) (*fbsemantic.Constraint, error) {
 
 }
 
-// SortedConstraints returns the constraints for this polytype sorted by type variable and constraint kind.
+// SortedConstraints returns the constraints for this polytype sorted by type variable, constraint kind and label.
 func (pt *PolyType) SortedConstraints() ([]*fbsemantic.Constraint, error) {
 	ncs := pt.NumConstraints()
 	cs := make([]*fbsemantic.Constraint, ncs)
@@ -73,6 +73,9 @@ func (pt *PolyType) SortedConstraints() ([]*fbsemantic.Constraint, error) {
 	sort.Slice(cs, func(i, j int) bool {
 		tvi, tvj := cs[i].Tvar(nil).I(), cs[j].Tvar(nil).I()
 		if tvi == tvj {
+			if cs[i].Kind() == cs[j].Kind() {
+				return string(cs[i].Label()) < string(cs[j].Label())
+			}
 			return cs[i].Kind() < cs[j].Kind()
 		}
 		return tvi < tvj
@@ -146,6 +149,11 @@ func (pt *PolyType) String() string {
 		sb.WriteString(mtv.String())
 		sb.WriteString(": ")
 		sb.WriteString(fbsemantic.EnumNamesKind[k])
+		if k == fbsemantic.KindLacks {
+			sb.WriteString("(")
+			sb.Write(cons.Label())
+			sb.WriteString(")")
+		}
 
 		if i < pt.NumConstraints()-1 {
 			sb.WriteString(", ")
@@ -4,9 +4,7 @@ import (
 	"context"
 	"regexp"
 	"strconv"
-	"strings"
 	"time"
-	"unicode/utf8"
 
 	"github.com/influxdata/flux"
 	"github.com/influxdata/flux/codes"
@@ -95,43 +93,15 @@ func (c *stringConv) HasSideEffect() bool {
 }
 
 func (c *stringConv) Call(ctx context.Context, args values.Object) (values.Value, error) {
-	var str string
 	v, ok := args.Get(conversionArg)
 	if !ok {
 		return nil, errMissingArg
 	} else if v.IsNull() {
 		return values.Null, nil
 	}
-	switch v.Type().Nature() {
-	case semantic.String:
-		str = v.Str()
-	case semantic.Int:
-		str = strconv.FormatInt(v.Int(), 10)
-	case semantic.UInt:
-		str = strconv.FormatUint(v.UInt(), 10)
-	case semantic.Float:
-		str = strconv.FormatFloat(v.Float(), 'f', -1, 64)
-	case semantic.Bool:
-		str = strconv.FormatBool(v.Bool())
-	case semantic.Time:
-		str = v.Time().String()
-	case semantic.Duration:
-		str = v.Duration().String()
-	case semantic.Bytes:
-		var sb strings.Builder
-		var vB = v.Bytes()
-		for len(vB) > 0 {
-			r, size := utf8.DecodeRune(vB)
-			if r == utf8.RuneError && size == 1 {
-				return nil, errors.Newf(codes.Invalid, "invalid utf8 response")
-			}
-			vB = vB[size:]
-
-			sb.WriteRune(r)
-		}
-		str = sb.String()
-	default:
-		return nil, errors.Newf(codes.Invalid, "cannot convert %v to string", v.Type())
+	str, err := values.Stringify(v)
+	if err != nil {
+		return nil, err
 	}
 	return values.NewString(str), nil
 }
@@ -7,6 +7,8 @@ import (
 	"regexp"
 	"runtime/debug"
 	"strconv"
+	"strings"
+	"unicode/utf8"
 
 	"github.com/influxdata/flux/codes"
 	"github.com/influxdata/flux/internal/errors"
@@ -322,6 +324,46 @@ func AssignableTo(V, T semantic.Type) bool {
 	}
 }
 
+// Stringify formats a value of a basic type as a string,
+// the same way the string conversion function and string
+// interpolation do.
+func Stringify(v Value) (string, error) {
+	if v.IsNull() {
+		return "", errors.New(codes.Invalid, "cannot convert a null value to string")
+	}
+	switch v.Type().Nature() {
+	case semantic.String:
+		return v.Str(), nil
+	case semantic.Int:
+		return strconv.FormatInt(v.Int(), 10), nil
+	case semantic.UInt:
+		return strconv.FormatUint(v.UInt(), 10), nil
+	case semantic.Float:
+		return strconv.FormatFloat(v.Float(), 'f', -1, 64), nil
+	case semantic.Bool:
+		return strconv.FormatBool(v.Bool()), nil
+	case semantic.Time:
+		return v.Time().String(), nil
+	case semantic.Duration:
+		return v.Duration().String(), nil
+	case semantic.Bytes:
+		var sb strings.Builder
+		var vB = v.Bytes()
+		for len(vB) > 0 {
+			r, size := utf8.DecodeRune(vB)
+			if r == utf8.RuneError && size == 1 {
+				return "", errors.Newf(codes.Invalid, "invalid utf8 response")
+			}
+			vB = vB[size:]
+
+			sb.WriteRune(r)
+		}
+		return sb.String(), nil
+	default:
+		return "", errors.Newf(codes.Invalid, "cannot convert %v to string", v.Type())
+	}
+}
+
 func UnexpectedKind(got, exp semantic.Nature) error {
 	return errors.Newf(codes.Internal, "unexpected kind: got %q expected %q, trace: %s", got, exp, string(debug.Stack()))
 }